@@ -1 +1,30 @@
+pub mod async_channel;
+pub mod borrowed_buf;
 pub mod bounded_buf;
+pub mod bounded_deque;
+pub mod bounded_heap;
+pub mod bounded_interner;
+pub mod bounded_list;
+pub mod bounded_map;
+pub mod bounded_mpmc;
+pub mod bounded_ring;
+pub mod bounded_str;
+pub mod broadcast_ring;
+pub mod bucket_queue;
+pub(crate) mod cache_padded;
+pub mod concurrent_pool;
+pub mod double_buf;
+pub mod error;
+pub mod inline_buf;
+pub mod pod;
+pub(crate) mod raw_buf;
+pub mod seq_cell;
+pub mod slab;
+pub mod small_buf;
+pub mod spsc;
+pub mod sync_channel;
+#[cfg(test)]
+pub(crate) mod test_util;
+pub mod top_k;
+pub mod triple_buf;
+pub mod work_steal;
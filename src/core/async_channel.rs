@@ -0,0 +1,830 @@
+//! An async multi-producer single-consumer channel with a capacity fixed at
+//! construction.
+//!
+//! Elements are queued in a [`BoundedRing`] allocated once up front, so the
+//! channel never holds more than its capacity. The receiver's waker lives in
+//! a single slot; blocked senders wait in a FIFO list that grows to the
+//! largest number of simultaneously blocked sends and is reused from then
+//! on, so steady-state sending and receiving don't allocate.
+//!
+//! Both [`Sender::send`] and [`Receiver::recv`] are cancellation safe.
+//! Dropping a pending `send` future drops its element without it ever
+//! having been queued, and hands its place in line to the next waiting
+//! sender. Dropping a pending `recv` future loses nothing; the next
+//! element stays queued.
+//!
+//! With the `futures` feature, [`Receiver`] implements `Stream` and
+//! [`Sender`] implements `Sink`.
+//!
+//! ```
+//! use fixed_buf::async_channel::bounded_async;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let (tx, mut rx) = bounded_async(2);
+//! let producer = tokio::spawn(async move {
+//!     for i in 0..10 {
+//!         tx.send(i).await.unwrap();
+//!     }
+//! });
+//! let mut received = Vec::new();
+//! while let Ok(i) = rx.recv().await {
+//!     received.push(i);
+//! }
+//! producer.await.unwrap();
+//! assert!(received.into_iter().eq(0..10));
+//! # });
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::poll_fn,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+use crate::core::{
+    bounded_ring::BoundedRing,
+    error::{RecvError, SendError, TryNewError, TryRecvError, TrySendError},
+};
+
+/// Creates a channel that queues at most `cap` elements and returns its two
+/// halves. The [`Sender`] can be cloned to send from several tasks.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero or the allocation fails, like
+/// [`BoundedBuffer::new`](crate::BoundedBuffer::new).
+#[track_caller]
+pub fn bounded_async<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    match try_bounded_async(cap) {
+        Ok(halves) => halves,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+/// Like [`bounded_async`], but returns an error instead of panicking if the
+/// allocation fails.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero.
+#[track_caller]
+pub fn try_bounded_async<T>(cap: usize) -> Result<(Sender<T>, Receiver<T>), TryNewError> {
+    assert!(cap > 0, "channel capacity must be positive");
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            ring: BoundedRing::try_new(cap)?,
+            reserved: 0,
+            senders: 1,
+            receiver: true,
+            recv_waker: None,
+            send_waiters: VecDeque::new(),
+            next_ticket: 0,
+        }),
+    });
+    let sender = Sender {
+        shared: shared.clone(),
+        waiter: None,
+        reserved: false,
+    };
+    Ok((sender, Receiver { shared }))
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    ring: BoundedRing<T>,
+    // Slots promised to senders by `poll_reserve` but not yet filled.
+    reserved: usize,
+    senders: usize,
+    receiver: bool,
+    recv_waker: Option<Waker>,
+    // Blocked senders in arrival order, keyed by ticket. A waiter is
+    // removed when it is woken, so a ticket that is no longer listed
+    // belongs to a sender that was handed a free slot.
+    send_waiters: VecDeque<(u64, Waker)>,
+    next_ticket: u64,
+}
+
+impl<T> Shared<T> {
+    // No user code runs while the lock is held. Wakers are only woken after
+    // it is released, since waking may run arbitrary code.
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> State<T> {
+    fn has_room(&self) -> bool {
+        self.ring.len() + self.reserved < self.ring.capacity()
+    }
+
+    // Takes the waker of the first blocked sender, if there's a slot for it.
+    fn next_sender(&mut self) -> Option<Waker> {
+        if !self.has_room() {
+            return None;
+        }
+        self.send_waiters.pop_front().map(|(_, waker)| waker)
+    }
+
+    fn push(&mut self, elem: T) -> Option<Waker> {
+        let pushed = self.ring.try_push_back(elem);
+        debug_assert!(pushed.is_ok());
+        self.recv_waker.take()
+    }
+
+    // Enters `ticket` in the waiting list, or refreshes its waker if it is
+    // already there.
+    fn wait(&mut self, ticket: &mut Option<u64>, cx: &Context<'_>) {
+        if let Some(t) = *ticket {
+            if let Some((_, waker)) = self.send_waiters.iter_mut().find(|(w, _)| *w == t) {
+                waker.clone_from(cx.waker());
+                return;
+            }
+        }
+        let t = self.next_ticket;
+        self.next_ticket += 1;
+        self.send_waiters.push_back((t, cx.waker().clone()));
+        *ticket = Some(t);
+    }
+
+    // Takes `ticket` off the waiting list. If it was already taken off, the
+    // sender was woken for a slot it won't use, so the next sender is woken
+    // in its place.
+    fn leave(&mut self, ticket: &mut Option<u64>) -> Option<Waker> {
+        let t = ticket.take()?;
+        match self.send_waiters.iter().position(|(w, _)| *w == t) {
+            Some(i) => {
+                self.send_waiters.remove(i);
+                None
+            }
+            None => self.next_sender(),
+        }
+    }
+}
+
+fn wake(waker: Option<Waker>) {
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+// A sender's place in the waiting list, owned by a `send` future. Leaves
+// the list when dropped, so a cancelled send doesn't strand the wakeup it
+// may have been given.
+struct Waiter<'a, T> {
+    shared: &'a Shared<T>,
+    ticket: Option<u64>,
+}
+
+impl<T> Waiter<'_, T> {
+    fn poll_send(
+        &mut self,
+        cx: &Context<'_>,
+        elem: &mut Option<T>,
+    ) -> Poll<Result<(), SendError<T>>> {
+        let mut state = self.shared.lock();
+        if !state.receiver {
+            self.ticket = None;
+            return Poll::Ready(Err(SendError(elem.take().unwrap())));
+        }
+        if !state.has_room() {
+            state.wait(&mut self.ticket, cx);
+            return Poll::Pending;
+        }
+        let receiver = state.push(elem.take().unwrap());
+        // Leaving after the push means a stale wakeup is only passed on if
+        // another slot is still free.
+        let sender = state.leave(&mut self.ticket);
+        drop(state);
+        wake(receiver);
+        wake(sender);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Drop for Waiter<'_, T> {
+    fn drop(&mut self) {
+        if self.ticket.is_some() {
+            let waker = self.shared.lock().leave(&mut self.ticket);
+            wake(waker);
+        }
+    }
+}
+
+/// The sending half of a channel, created by [`bounded_async`].
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+    // The waiting-list ticket of a pending `poll_reserve`.
+    waiter: Option<u64>,
+    // Whether `poll_reserve` has set a slot aside for this sender.
+    reserved: bool,
+}
+
+impl<T> Sender<T> {
+    /// Sends `elem`, waiting while the channel is full. Fails with the
+    /// element if the receiver is dropped, even while waiting.
+    ///
+    /// Cancellation safe: if the future is dropped before it completes, the
+    /// element was not sent and is dropped along with the future.
+    pub async fn send(&self, elem: T) -> Result<(), SendError<T>> {
+        let mut waiter = Waiter {
+            shared: &self.shared,
+            ticket: None,
+        };
+        let mut elem = Some(elem);
+        poll_fn(|cx| waiter.poll_send(cx, &mut elem)).await
+    }
+
+    /// Sends `elem` if there is room right now.
+    pub fn try_send(&self, elem: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.lock();
+        if !state.receiver {
+            return Err(TrySendError::Disconnected(elem));
+        }
+        if !state.has_room() {
+            return Err(TrySendError::Full(elem));
+        }
+        let receiver = state.push(elem);
+        drop(state);
+        wake(receiver);
+        Ok(())
+    }
+
+    /// Sets a slot aside for this sender, registering the task to be woken
+    /// if the channel is full. Once this returns `Ready(Ok(()))`, the next
+    /// [`send_reserved`](Self::send_reserved) cannot fail for lack of room.
+    ///
+    /// Calling it again while holding a reservation returns immediately.
+    pub fn poll_reserve(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError<()>>> {
+        let mut state = self.shared.lock();
+        if !state.receiver {
+            let waker = state.leave(&mut self.waiter);
+            drop(state);
+            wake(waker);
+            return Poll::Ready(Err(SendError(())));
+        }
+        if self.reserved {
+            return Poll::Ready(Ok(()));
+        }
+        if !state.has_room() {
+            state.wait(&mut self.waiter, cx);
+            return Poll::Pending;
+        }
+        state.reserved += 1;
+        self.reserved = true;
+        let waker = state.leave(&mut self.waiter);
+        drop(state);
+        wake(waker);
+        Poll::Ready(Ok(()))
+    }
+
+    /// Sends `elem` into the slot set aside by
+    /// [`poll_reserve`](Self::poll_reserve). Fails with the element if the
+    /// receiver has been dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this sender holds no reservation.
+    #[track_caller]
+    pub fn send_reserved(&mut self, elem: T) -> Result<(), SendError<T>> {
+        assert!(self.reserved, "send_reserved called without a reservation");
+        self.reserved = false;
+        let mut state = self.shared.lock();
+        state.reserved -= 1;
+        if !state.receiver {
+            return Err(SendError(elem));
+        }
+        let receiver = state.push(elem);
+        drop(state);
+        wake(receiver);
+        Ok(())
+    }
+
+    /// Gives back a slot set aside by [`poll_reserve`](Self::poll_reserve)
+    /// without sending anything. Does nothing without a reservation.
+    pub fn release(&mut self) {
+        if !std::mem::take(&mut self.reserved) {
+            return;
+        }
+        let mut state = self.shared.lock();
+        state.reserved -= 1;
+        let waker = state.next_sender();
+        drop(state);
+        wake(waker);
+    }
+
+    /// Returns `true` if the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        !self.shared.lock().receiver
+    }
+
+    /// Returns the number of queued elements.
+    pub fn len(&self) -> usize {
+        self.shared.lock().ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.lock().ring.capacity()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+            waiter: None,
+            reserved: false,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        let passed_on = state.leave(&mut self.waiter);
+        let released = match std::mem::take(&mut self.reserved) {
+            true => {
+                state.reserved -= 1;
+                state.next_sender()
+            }
+            false => None,
+        };
+        state.senders -= 1;
+        let receiver = match state.senders {
+            0 => state.recv_waker.take(),
+            _ => None,
+        };
+        drop(state);
+        wake(passed_on);
+        wake(released);
+        wake(receiver);
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("reserved", &self.reserved)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a channel, created by [`bounded_async`].
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the oldest element, waiting while the channel is empty.
+    /// Fails once every sender is dropped and the queue is drained.
+    ///
+    /// Cancellation safe: if the future is dropped before it completes, no
+    /// element was taken.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receives the oldest element, or registers the task to be woken when
+    /// one arrives or the last sender is dropped.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        let mut state = self.shared.lock();
+        if let Some(elem) = state.ring.pop_front() {
+            let waker = state.next_sender();
+            drop(state);
+            wake(waker);
+            return Poll::Ready(Ok(elem));
+        }
+        if state.senders == 0 {
+            return Poll::Ready(Err(RecvError));
+        }
+        match &mut state.recv_waker {
+            Some(waker) => waker.clone_from(cx.waker()),
+            slot => *slot = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+
+    /// Receives the oldest element if one is queued right now.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+        match state.ring.pop_front() {
+            Some(elem) => {
+                let waker = state.next_sender();
+                drop(state);
+                wake(waker);
+                Ok(elem)
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Returns the number of queued elements.
+    pub fn len(&self) -> usize {
+        self.shared.lock().ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.lock().ring.capacity()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.receiver = false;
+        let waiters = std::mem::take(&mut state.send_waiters);
+        drop(state);
+        waiters.into_iter().for_each(|(_, waker)| waker.wake());
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> futures_core::Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx).map(Result::ok)
+    }
+}
+
+/// Each item takes a slot reserved by `poll_ready`. Errors once the
+/// receiver is dropped; the item passed to a failing `start_send` is
+/// dropped.
+#[cfg(feature = "futures")]
+impl<T> futures_sink::Sink<T> for Sender<T> {
+    type Error = SendError<()>;
+
+    fn poll_ready(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_reserve(cx)
+    }
+
+    fn start_send(self: std::pin::Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.get_mut()
+            .send_reserved(item)
+            .map_err(|_| SendError(()))
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().release();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Wake, Waker},
+        thread,
+    };
+
+    use super::bounded_async;
+    use crate::core::error::{RecvError, SendError, TryRecvError, TrySendError};
+    use crate::core::test_util::{DropCount, Drops};
+
+    #[derive(Default)]
+    struct WakeCount(AtomicUsize);
+
+    impl Wake for WakeCount {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    impl WakeCount {
+        fn get(&self) -> usize {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    fn waker() -> (Arc<WakeCount>, Waker) {
+        let count = Arc::new(WakeCount::default());
+        (count.clone(), Waker::from(count))
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(fut)
+    }
+
+    #[test]
+    fn test_try_send_recv() {
+        let (tx, mut rx) = bounded_async(2);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!((rx.len(), tx.capacity()), (2, 2));
+        assert_eq!(rx.try_recv(), Ok(1));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_send_waits_for_room() {
+        let (tx, mut rx) = bounded_async(1);
+        let (wakes, waker) = waker();
+        let mut cx = Context::from_waker(&waker);
+
+        tx.try_send(1).unwrap();
+        let mut send = pin!(tx.send(2));
+        assert!(send.as_mut().poll(&mut cx).is_pending());
+        assert!(send.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(wakes.get(), 0);
+
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(wakes.get(), 1);
+        assert_eq!(send.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(rx.try_recv(), Ok(2));
+    }
+
+    #[test]
+    fn test_recv_waits_and_disconnects() {
+        let (tx, mut rx) = bounded_async::<u32>(1);
+        let (wakes, waker) = waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(rx.poll_recv(&mut cx).is_pending());
+        tx.try_send(5).unwrap();
+        assert_eq!(wakes.get(), 1);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Ok(5)));
+
+        assert!(rx.poll_recv(&mut cx).is_pending());
+        let tx2 = tx.clone();
+        drop(tx);
+        assert_eq!(wakes.get(), 1);
+        drop(tx2);
+        assert_eq!(wakes.get(), 2);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Err(RecvError)));
+    }
+
+    #[test]
+    fn test_receiver_drop_wakes_senders() {
+        let (tx, rx) = bounded_async(1);
+        let (wakes, waker) = waker();
+        let mut cx = Context::from_waker(&waker);
+
+        tx.try_send(0).unwrap();
+        let mut first = pin!(tx.send(1));
+        let mut second = pin!(tx.send(2));
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+        drop(rx);
+        assert_eq!(wakes.get(), 2);
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Ready(Err(SendError(1))));
+        assert_eq!(
+            second.as_mut().poll(&mut cx),
+            Poll::Ready(Err(SendError(2)))
+        );
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    fn test_cancelled_send_is_not_sent() {
+        let drops = Drops::default();
+        let (tx, mut rx) = bounded_async(1);
+        let (_, waker) = waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(tx.try_send(DropCount(drops.clone())).is_ok());
+        {
+            let mut send = pin!(tx.send(DropCount(drops.clone())));
+            assert!(send.as_mut().poll(&mut cx).is_pending());
+        }
+        // The pending send's element went with it and nothing was queued.
+        assert_eq!(drops.get(), 1);
+        assert_eq!(rx.len(), 1);
+        drop(rx.try_recv());
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn test_cancelled_send_passes_wakeup_on() {
+        let (tx, mut rx) = bounded_async(1);
+        let (first_wakes, first_waker) = waker();
+        let (second_wakes, second_waker) = waker();
+
+        tx.try_send(0).unwrap();
+        let mut first = Box::pin(tx.send(1));
+        let mut second = pin!(tx.send(2));
+        assert!(first
+            .as_mut()
+            .poll(&mut Context::from_waker(&first_waker))
+            .is_pending());
+        assert!(second
+            .as_mut()
+            .poll(&mut Context::from_waker(&second_waker))
+            .is_pending());
+
+        // The freed slot goes to the first sender in line...
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!((first_wakes.get(), second_wakes.get()), (1, 0));
+        // ...and when it gives up without sending, to the next.
+        drop(first);
+        assert_eq!(second_wakes.get(), 1);
+        let mut cx = Context::from_waker(&second_waker);
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_cancelled_recv_loses_nothing() {
+        let (tx, mut rx) = bounded_async(2);
+        let (_, waker) = waker();
+        let mut cx = Context::from_waker(&waker);
+        {
+            let mut recv = pin!(rx.recv());
+            assert!(recv.as_mut().poll(&mut cx).is_pending());
+            tx.try_send(1).unwrap();
+        }
+        tx.try_send(2).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+    }
+
+    #[test]
+    fn test_reserve_holds_a_slot() {
+        let (mut tx, mut rx) = bounded_async(2);
+        let mut other = tx.clone();
+        let (wakes, waker) = waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert_eq!(tx.poll_reserve(&mut cx), Poll::Ready(Ok(())));
+        assert_eq!(tx.poll_reserve(&mut cx), Poll::Ready(Ok(())));
+        other.try_send(1).unwrap();
+        // The reserved slot is not available to anyone else.
+        assert_eq!(other.try_send(2), Err(TrySendError::Full(2)));
+        assert!(other.poll_reserve(&mut cx).is_pending());
+        tx.send_reserved(3).unwrap();
+
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(wakes.get(), 1);
+        assert_eq!(other.poll_reserve(&mut cx), Poll::Ready(Ok(())));
+        // Releasing unused reservations frees their slots again.
+        other.release();
+        drop(other);
+        tx.try_send(4).unwrap();
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "send_reserved called without a reservation")]
+    fn test_send_reserved_without_reservation() {
+        let (mut tx, _rx) = bounded_async(1);
+        let _ = tx.send_reserved(1);
+    }
+
+    #[test]
+    fn test_runtime_round_trip() {
+        let n = if cfg!(miri) { 20 } else { 10_000 };
+        let (tx, mut rx) = bounded_async(4);
+        let producers: Vec<_> = (0..2)
+            .map(|p| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    block_on(async {
+                        for i in 0..n {
+                            tx.send(p * n + i).await.unwrap();
+                        }
+                    })
+                })
+            })
+            .collect();
+        drop(tx);
+        let mut seen = block_on(async {
+            let mut seen = Vec::new();
+            while let Ok(i) = rx.recv().await {
+                seen.push(i);
+            }
+            seen
+        });
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        seen.sort_unstable();
+        assert!(seen.into_iter().eq(0..2 * n));
+    }
+
+    #[test]
+    fn test_cancellation_stress() {
+        // Each sender awaits its even-numbered sends and polls each
+        // odd-numbered one once before dropping it, cancelling it if it
+        // had to wait for room. Every element must be either received once
+        // or dropped unsent.
+        let n = if cfg!(miri) { 20 } else { 2_000 };
+        let drops = Drops::default();
+        let received = Arc::new(AtomicUsize::new(0));
+        let (tx, mut rx) = bounded_async(2);
+        let senders: Vec<_> = (0..3)
+            .map(|_| {
+                let tx = tx.clone();
+                let drops = drops.clone();
+                thread::spawn(move || {
+                    block_on(async {
+                        for i in 0..n {
+                            let send = tx.send(DropCount(drops.clone()));
+                            if i % 2 == 0 {
+                                let _ = send.await;
+                            } else {
+                                // Poll once, then cancel if it had to wait.
+                                let mut send = pin!(send);
+                                std::future::poll_fn(|cx| {
+                                    let _ = send.as_mut().poll(cx);
+                                    Poll::Ready(())
+                                })
+                                .await;
+                            }
+                        }
+                    })
+                })
+            })
+            .collect();
+        drop(tx);
+        block_on(async {
+            while let Ok(elem) = rx.recv().await {
+                received.fetch_add(1, Ordering::Relaxed);
+                drop(elem);
+            }
+        });
+        for sender in senders {
+            sender.join().unwrap();
+        }
+        assert_eq!(drops.get(), 3 * n);
+        assert!(received.load(Ordering::Relaxed) >= 3 * n / 2);
+        assert_eq!(drops.handles(), 1);
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_stream_and_sink() {
+        use futures_core::Stream;
+        use futures_sink::Sink;
+
+        let (mut tx, mut rx) = bounded_async(1);
+        let (_, waker) = waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut sink = std::pin::Pin::new(&mut tx);
+        assert_eq!(sink.as_mut().poll_ready(&mut cx), Poll::Ready(Ok(())));
+        sink.as_mut().start_send(1).unwrap();
+        assert!(sink.as_mut().poll_ready(&mut cx).is_pending());
+
+        let mut stream = std::pin::Pin::new(&mut rx);
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+        assert!(stream.as_mut().poll_next(&mut cx).is_pending());
+
+        let mut sink = std::pin::Pin::new(&mut tx);
+        assert_eq!(sink.as_mut().poll_ready(&mut cx), Poll::Ready(Ok(())));
+        sink.as_mut().start_send(2).unwrap();
+        assert_eq!(sink.as_mut().poll_close(&mut cx), Poll::Ready(Ok(())));
+        drop(tx);
+
+        let mut stream = std::pin::Pin::new(&mut rx);
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(2)));
+        assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(None));
+    }
+}
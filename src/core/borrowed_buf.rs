@@ -0,0 +1,367 @@
+use std::{
+    fmt,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr, slice,
+};
+
+use crate::core::{error::CapacityError, raw_buf::RawBuf};
+
+/// A buffer over storage the caller owns, such as a stack array, a static
+/// or a slice of an arena, created by [`new`](Self::new).
+///
+/// It tracks its own length over the borrowed slots and otherwise behaves
+/// like [`BoundedBuffer`](crate::BoundedBuffer) with a capacity of
+/// `storage.len()`. Dropping it drops the elements it holds but leaves the
+/// storage alone; [`into_parts`](Self::into_parts) hands the storage back
+/// with the elements still in it.
+///
+/// ```
+/// use std::mem::MaybeUninit;
+///
+/// use fixed_buf::BorrowedBuffer;
+///
+/// let mut storage = [const { MaybeUninit::<String>::uninit() }; 2];
+/// let mut buf = BorrowedBuffer::new(&mut storage);
+/// assert!(buf.try_push("a".to_owned()));
+/// assert!(buf.try_insert(0, "b".to_owned()));
+/// assert!(!buf.try_push("c".to_owned()));
+/// assert_eq!(buf.as_slice(), ["b", "a"]);
+///
+/// let (len, storage) = buf.into_parts();
+/// assert_eq!(len, 2);
+/// unsafe { storage[..len].assume_init_drop() };
+/// ```
+pub struct BorrowedBuffer<'a, T> {
+    // The first `len` slots are initialized.
+    storage: &'a mut [MaybeUninit<T>],
+    len: usize,
+}
+
+impl<'a, T> BorrowedBuffer<'a, T> {
+    /// Creates an empty buffer over `storage`. Whatever the slots hold is
+    /// treated as uninitialized and never dropped.
+    pub fn new(storage: &'a mut [MaybeUninit<T>]) -> Self {
+        Self { storage, len: 0 }
+    }
+
+    /// Creates a buffer over `storage` whose first `len` slots already hold
+    /// elements, which the buffer takes ownership of.
+    ///
+    /// # Safety
+    ///
+    /// `len` must be at most `storage.len()`, and the first `len` slots must
+    /// be initialized.
+    pub unsafe fn from_parts(len: usize, storage: &'a mut [MaybeUninit<T>]) -> Self {
+        debug_assert!(len <= storage.len(), "length exceeds capacity");
+        Self { storage, len }
+    }
+
+    /// Gives the storage back along with the number of slots at its start
+    /// that hold elements. Those elements are not dropped; they now belong
+    /// to the caller.
+    pub fn into_parts(self) -> (usize, &'a mut [MaybeUninit<T>]) {
+        let mut this = ManuallyDrop::new(self);
+        (this.len, mem::take(&mut this.storage))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.storage.len()
+    }
+
+    fn slot(&mut self, index: usize) -> *mut T {
+        self.storage.as_mut_ptr().wrapping_add(index) as *mut T
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.storage.as_ptr() as *const T, self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.slot(0), self.len) }
+    }
+
+    /// Returns the uninitialized slots past `len()`. Write into them and then
+    /// call [`set_len`](Self::set_len) to make the written elements part of
+    /// the buffer.
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        &mut self.storage[self.len..]
+    }
+
+    /// Sets the length of the buffer without dropping or initializing
+    /// anything.
+    ///
+    /// # Safety
+    ///
+    /// `new_len` must be at most `capacity()`, and every element below
+    /// `new_len` must be initialized.
+    #[track_caller]
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.capacity(), "length exceeds capacity");
+        self.len = new_len;
+    }
+
+    fn raw(&mut self) -> RawBuf<'_, T> {
+        let cap = self.storage.len();
+        // SAFETY: the first `len` slots of `storage` are initialized.
+        unsafe { RawBuf::new(self.storage.as_mut_ptr().cast(), &mut self.len, cap) }
+    }
+
+    pub fn try_push(&mut self, elem: T) -> bool {
+        self.raw().try_push(elem)
+    }
+
+    pub fn try_insert(&mut self, index: usize, elem: T) -> bool {
+        self.raw().try_insert(index, elem)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.raw().pop()
+    }
+
+    #[track_caller]
+    pub fn remove(&mut self, index: usize) -> T {
+        self.raw().remove(index)
+    }
+
+    pub fn try_remove(&mut self, index: usize) -> Option<T> {
+        self.raw().try_remove(index)
+    }
+
+    /// Removes and returns the element at `index` in O(1) by moving the last
+    /// element into its place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len()`.
+    #[track_caller]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        self.raw().swap_remove(index)
+    }
+
+    /// Drops the elements from `new_len` on. Does nothing if `new_len` is not
+    /// less than the length.
+    pub fn truncate(&mut self, new_len: usize) {
+        self.raw().truncate(new_len)
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Appends clones of all of `src`, or fails without writing anything if
+    /// they do not all fit.
+    pub fn try_extend_from_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        self.raw().try_extend_from_slice(src)
+    }
+}
+
+impl<T> Drop for BorrowedBuffer<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+/// Formats the initialized elements as a list, the same way `Vec` does. The
+/// capacity is not shown.
+impl<T: fmt::Debug> fmt::Debug for BorrowedBuffer<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+impl<T: PartialEq<U>, U> PartialEq<[U]> for BorrowedBuffer<'_, T> {
+    fn eq(&self, other: &[U]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize> PartialEq<[U; N]> for BorrowedBuffer<'_, T> {
+    fn eq(&self, other: &[U; N]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+/// Appends every element of the iterator.
+///
+/// # Panics
+///
+/// Panics if the buffer fills up before the iterator is exhausted, keeping
+/// the elements that fit.
+impl<T> Extend<T> for BorrowedBuffer<'_, T> {
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.raw().extend(iter);
+    }
+}
+
+impl<T> Deref for BorrowedBuffer<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for BorrowedBuffer<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T> AsRef<[T]> for BorrowedBuffer<'_, T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> AsMut<[T]> for BorrowedBuffer<'_, T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<'b, T> IntoIterator for &'b BorrowedBuffer<'_, T> {
+    type Item = &'b T;
+    type IntoIter = slice::Iter<'b, T>;
+
+    fn into_iter(self) -> slice::Iter<'b, T> {
+        self.iter()
+    }
+}
+
+impl<'b, T> IntoIterator for &'b mut BorrowedBuffer<'_, T> {
+    type Item = &'b mut T;
+    type IntoIter = slice::IterMut<'b, T>;
+
+    fn into_iter(self) -> slice::IterMut<'b, T> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BorrowedBuffer;
+    use crate::core::error::CapacityError;
+    use crate::core::test_util::{DropCount, Drops};
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn test_borrowed_over_stack_array() {
+        let mut storage = [MaybeUninit::<u32>::uninit(); 4];
+        let mut buf = BorrowedBuffer::new(&mut storage);
+        assert_eq!(buf.capacity(), 4);
+        assert_eq!(buf.pop(), None);
+        for i in 0..4 {
+            assert!(buf.try_push(i));
+        }
+        assert!(!buf.try_push(4));
+        assert!(!buf.try_insert(0, 4));
+        assert_eq!(buf.remove(0), 0);
+        assert!(buf.try_insert(1, 9));
+        assert_eq!(buf, [1, 9, 2, 3]);
+        assert_eq!(buf.swap_remove(0), 1);
+        assert_eq!(buf.try_remove(3), None);
+        assert_eq!(buf, [3, 9, 2]);
+        for elem in &mut buf {
+            *elem += 1;
+        }
+        buf.sort();
+        assert_eq!(format!("{buf:?}"), "[3, 4, 10]");
+        assert_eq!(
+            buf.try_extend_from_slice(&[0, 0]),
+            Err(CapacityError::new(()))
+        );
+        buf.try_extend_from_slice(&[7]).unwrap();
+        buf.truncate(2);
+        let (len, storage) = buf.into_parts();
+        assert_eq!(len, 2);
+        assert_eq!(unsafe { storage[1].assume_init() }, 4);
+
+        // Picks the elements back up where it left off.
+        let mut buf = unsafe { BorrowedBuffer::from_parts(len, storage) };
+        assert!(buf.try_push(5));
+        assert_eq!(buf, [3, 4, 5]);
+        buf.clear();
+        assert!(buf.is_empty());
+        assert_eq!(buf.spare_capacity_mut().len(), 4);
+    }
+
+    #[test]
+    fn test_borrowed_drops_elements_not_storage() {
+        let drops = Drops::default();
+        let mut storage = [const { MaybeUninit::<DropCount>::uninit() }; 5];
+        let mut buf = BorrowedBuffer::new(&mut storage);
+        for _ in 0..5 {
+            assert!(buf.try_push(DropCount(drops.clone())));
+        }
+        assert!(!buf.try_push(DropCount(drops.clone())));
+        assert_eq!(drops.get(), 1);
+        drop(buf.pop());
+        drop(buf.remove(1));
+        drop(buf.swap_remove(0));
+        assert_eq!(drops.get(), 4);
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+
+        // Reusing the same storage after the buffer is gone.
+        let mut buf = BorrowedBuffer::new(&mut storage);
+        buf.extend((0..3).map(|_| DropCount(drops.clone())));
+        let (len, storage) = buf.into_parts();
+        assert_eq!(drops.get(), 6);
+        unsafe { storage[..len].assume_init_drop() };
+        assert_eq!(drops.get(), 9);
+    }
+
+    #[test]
+    fn test_borrowed_over_leaked_heap_slice() {
+        let storage: &'static mut [MaybeUninit<String>] = Box::leak(Box::new_uninit_slice(3));
+        let mut buf = BorrowedBuffer::new(storage);
+        buf.extend(["x", "y", "z"].map(str::to_owned));
+        assert_eq!(buf.concat(), "xyz");
+        assert_eq!(buf.remove(1), "y");
+        let (len, storage) = buf.into_parts();
+        assert_eq!(len, 2);
+
+        // Reclaim the leaked allocation so Miri sees nothing leak.
+        unsafe {
+            storage[..len].assume_init_drop();
+            drop(Box::from_raw(storage));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "extended past capacity (is 1)")]
+    fn test_borrowed_extend_past_capacity() {
+        let mut storage = [MaybeUninit::uninit(); 1];
+        BorrowedBuffer::new(&mut storage).extend([1, 2]);
+    }
+
+    #[test]
+    fn test_borrowed_zsts_and_empty_storage() {
+        let mut storage = [MaybeUninit::<()>::uninit(); 3];
+        let mut buf = BorrowedBuffer::new(&mut storage);
+        while buf.try_push(()) {}
+        assert_eq!(buf.len(), 3);
+
+        let mut buf = BorrowedBuffer::<u8>::new(&mut []);
+        assert!(buf.is_full());
+        assert!(!buf.try_push(1));
+        assert_eq!(buf.spare_capacity_mut().len(), 0);
+    }
+}
@@ -1,279 +1,7491 @@
+#[cfg(feature = "borsh")]
+mod borsh;
+mod bounded_cursor;
+#[cfg(feature = "bytemuck")]
+mod bytemuck;
+#[cfg(feature = "bytes")]
+mod bytes;
+mod checkpoint;
+mod cursor;
+mod drain;
+mod extract_if;
+mod ffi;
+mod format;
+mod hex;
+mod into_iter;
+mod io;
+mod map;
+mod pop_while;
+#[cfg(feature = "proptest")]
+mod proptest;
+#[cfg(feature = "quickcheck")]
+mod quickcheck;
+#[cfg(feature = "rand")]
+mod rand;
+#[cfg(feature = "rayon")]
+mod rayon;
+mod reader;
+#[cfg(feature = "rkyv")]
+mod rkyv;
+mod search;
+#[cfg(feature = "serde")]
+mod serde;
+mod snapshot;
+mod splice;
+#[cfg(feature = "tokio")]
+mod tokio;
+#[cfg(feature = "zeroize")]
+mod zeroize;
+
 use std::{
     alloc::{alloc, dealloc, Layout},
-    isize,
-    ops::{Deref, DerefMut},
+    borrow::{Borrow, BorrowMut},
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Bound, Deref, DerefMut, Range, RangeBounds},
     ptr::NonNull,
+    slice::GetDisjointMutError,
+};
+
+use crate::core::{
+    error::{CapacityError, CastError, TryNewError},
+    pod::Pod,
+    raw_buf::{RawBuf, SetLenOnDrop},
+};
+
+#[cfg(feature = "proptest")]
+pub use self::proptest::{bounded_buffer, BoundedBufferStrategy, BoundedBufferValueTree};
+#[cfg(feature = "serde")]
+pub use self::serde::BoundedBufferSeed;
+#[cfg(feature = "zeroize")]
+pub use self::zeroize::ZeroizingBuffer;
+pub use self::{
+    bounded_cursor::BoundedCursor,
+    checkpoint::Checkpoint,
+    cursor::CursorMut,
+    drain::Drain,
+    extract_if::ExtractIf,
+    ffi::{BufferHeader, ForeignBuffer},
+    into_iter::IntoIter,
+    pop_while::PopWhile,
+    reader::BoundedReader,
+    splice::Splice,
 };
 
-#[derive(Debug, Clone)]
+// The most a length read from the input may make the deserializers allocate
+// up front. Longer sequences still deserialize, growing as their elements
+// arrive.
+#[cfg(any(feature = "serde", feature = "borsh"))]
+const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+
+/// A heap-allocated buffer with a capacity fixed at construction. It never
+/// reallocates; pushing into a full buffer fails instead.
+///
+/// ```
+/// use fixed_buf::BoundedBuffer;
+///
+/// let mut buf = BoundedBuffer::new(2);
+/// assert!(buf.try_push(1));
+/// assert!(buf.try_push(2));
+/// assert!(!buf.try_push(3));
+/// assert_eq!(buf.as_slice(), [1, 2]);
+/// assert_eq!(buf.pop(), Some(2));
+/// ```
 pub struct BoundedBuffer<T> {
     ptr: NonNull<T>,
     len: usize,
     cap: usize,
+    // Tells dropck that the buffer owns and drops `T` values.
+    _marker: PhantomData<T>,
 }
 
 impl<T> BoundedBuffer<T> {
+    /// Creates an empty buffer that holds at most `size` elements.
+    ///
+    /// Nothing is allocated when `size` is zero or `T` is zero-sized. A buffer
+    /// of zero-sized elements still reports `size` as its capacity and fills
+    /// up like any other buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows `isize::MAX` bytes or the
+    /// allocator fails. See [`try_new`](Self::try_new) for a fallible version.
+    #[track_caller]
     pub fn new(size: usize) -> Self {
-        assert!(size <= isize::MAX as usize, "size is too large");
-        unsafe {
-            let layout = Layout::array::<T>(size).unwrap_unchecked();
-            let ptr = alloc(layout);
+        match Self::try_new(size) {
+            Ok(buf) => buf,
+            Err(err) => panic!("{err}"),
+        }
+    }
 
-            assert!(!ptr.is_null(), "could not allocate");
-            Self {
-                ptr: NonNull::new_unchecked(ptr as *mut T),
+    /// Like [`new`](Self::new), but returns an error instead of panicking:
+    /// [`TryNewError::CapacityOverflow`] if `size` elements would take more
+    /// than `isize::MAX` bytes, or [`TryNewError::AllocError`] with the
+    /// requested layout if the allocator fails.
+    pub fn try_new(size: usize) -> Result<Self, TryNewError> {
+        let layout = Layout::array::<T>(size).map_err(|_| TryNewError::CapacityOverflow)?;
+        if layout.size() == 0 {
+            return Ok(Self {
+                ptr: NonNull::dangling(),
                 len: 0,
                 cap: size,
+                _marker: PhantomData,
+            });
+        }
+
+        let ptr = unsafe { alloc(layout) };
+        let ptr = NonNull::new(ptr as *mut T).ok_or(TryNewError::AllocError { layout })?;
+        Ok(Self {
+            ptr,
+            len: 0,
+            cap: size,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Creates a full buffer of `n` clones of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`new`](Self::new) if the allocation fails.
+    #[track_caller]
+    pub fn from_elem(value: T, n: usize) -> Self
+    where
+        T: Clone,
+    {
+        Self::with_capacity_from_elem(n, value, n)
+    }
+
+    /// Creates a buffer of capacity `cap` holding `n` clones of `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n > cap`, or like [`new`](Self::new) if the allocation
+    /// fails.
+    #[track_caller]
+    pub fn with_capacity_from_elem(cap: usize, value: T, n: usize) -> Self
+    where
+        T: Clone,
+    {
+        assert!(n <= cap, "length (is {n}) should be <= capacity (is {cap})");
+        let mut buf = Self::new(cap);
+        unsafe {
+            buf.extend_with(n, value);
+        }
+        buf
+    }
+
+    /// Creates a full buffer of `n` elements, where the element at each
+    /// index is `f(index)`. If `f` panics, the elements built so far are
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`new`](Self::new) if the allocation fails.
+    #[track_caller]
+    pub fn from_fn<F>(n: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        let mut buf = Self::new(n);
+        let mut index = 0;
+        unsafe {
+            buf.extend_with_fn(n, || {
+                let elem = f(index);
+                index += 1;
+                elem
+            });
+        }
+        buf
+    }
+
+    /// Rebuilds a buffer from the parts returned by
+    /// [`into_raw_parts`](Self::into_raw_parts).
+    ///
+    /// # Safety
+    ///
+    /// - `ptr` must have been allocated by the global allocator with
+    ///   `Layout::array::<T>(cap)`, as `new` does, or be dangling and
+    ///   well-aligned if that layout has size zero.
+    /// - `len` must be at most `cap`, and the first `len` elements must be
+    ///   initialized.
+    /// - Ownership of the allocation moves to the returned buffer, which will
+    ///   drop the elements and free it. Nothing else may use or free it.
+    pub unsafe fn from_raw_parts(ptr: NonNull<T>, len: usize, cap: usize) -> Self {
+        debug_assert!(len <= cap, "length exceeds capacity");
+        Self {
+            ptr,
+            len,
+            cap,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Decomposes the buffer into its pointer, length and capacity without
+    /// dropping anything. The caller becomes responsible for the elements and
+    /// the allocation, typically by passing the parts back to
+    /// [`from_raw_parts`](Self::from_raw_parts).
+    pub fn into_raw_parts(self) -> (NonNull<T>, usize, usize) {
+        let this = std::mem::ManuallyDrop::new(self);
+        (this.ptr, this.len, this.cap)
+    }
+
+    /// Creates a buffer with capacity `cap` holding clones of `src`, leaving
+    /// room for `cap - src.len()` more elements. Gives `src` back if it does
+    /// not fit in `cap`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`new`](Self::new).
+    #[track_caller]
+    pub fn from_slice_with_capacity(src: &[T], cap: usize) -> Result<Self, CapacityError<&[T]>>
+    where
+        T: Clone,
+    {
+        if src.len() > cap {
+            return Err(CapacityError::new(src));
+        }
+
+        let mut buf = Self::new(cap);
+        unsafe {
+            buf.extend_from_slice_unchecked(src);
+        }
+        Ok(buf)
+    }
+
+    /// Creates a buffer of capacity `cap` from the elements of `iter`, or
+    /// fails if it yields more than `cap` of them.
+    ///
+    /// Allocates `cap` slots up front and ignores the iterator's size hint.
+    /// Detecting an iterator that is too long pulls one element past `cap`;
+    /// on failure everything pulled is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`new`](Self::new) if the allocation fails.
+    #[track_caller]
+    pub fn try_from_iter<I>(iter: I, cap: usize) -> Result<Self, CapacityError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut buf = Self::new(cap);
+        for elem in iter {
+            if buf.len == buf.cap {
+                return Err(CapacityError::new(()));
+            }
+            unsafe {
+                buf.push_unchecked(elem);
             }
         }
+        Ok(buf)
+    }
+
+    /// Adopts the allocation of `vec` without copying. The capacity of the
+    /// buffer is the capacity of the `Vec`.
+    pub fn from_vec(vec: Vec<T>) -> Self {
+        let mut vec = std::mem::ManuallyDrop::new(vec);
+        // `Vec` allocates with `Layout::array::<T>(capacity)` from the global
+        // allocator, exactly like `new`.
+        unsafe {
+            Self::from_raw_parts(
+                NonNull::new_unchecked(vec.as_mut_ptr()),
+                vec.len(),
+                vec.capacity(),
+            )
+        }
+    }
+
+    /// Hands the allocation over to a `Vec` without copying.
+    pub fn into_vec(self) -> Vec<T> {
+        let (ptr, len, cap) = self.into_raw_parts();
+        unsafe { Vec::from_raw_parts(ptr.as_ptr(), len, cap) }
+    }
+
+    /// Returns a pointer to the first slot. It stays valid until the buffer
+    /// is dropped, and must not be written through.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns a pointer to the first slot that can be written through for
+    /// all `capacity()` slots, e.g. by foreign code.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    // Every element access goes through this pointer, which is derived from
+    // the allocation itself rather than from an intermediate reference, so
+    // reads and writes all share the same provenance. Collectors built on a
+    // buffer that write to its slots through `&self` use it too.
+    #[inline]
+    pub(crate) unsafe fn slot(&self, index: usize) -> *mut T {
+        self.ptr.as_ptr().add(index)
+    }
+
+    #[inline]
+    fn raw(&mut self) -> RawBuf<'_, T> {
+        // SAFETY: the allocation has `cap` slots and the first `len` are
+        // initialized.
+        unsafe { RawBuf::new(self.ptr.as_ptr(), &mut self.len, self.cap) }
     }
 
     pub fn len(&self) -> usize {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn capacity(&self) -> usize {
         self.cap
     }
 
+    /// Moves the elements into a new allocation of `new_cap` slots and frees
+    /// the old one. Does nothing if the capacity is already at least
+    /// `new_cap`.
+    ///
+    /// This is the only way a buffer's capacity ever grows, so it must be
+    /// asked for explicitly. On error the buffer is left untouched.
+    pub fn grow_to(&mut self, new_cap: usize) -> Result<(), TryNewError> {
+        if new_cap <= self.cap {
+            return Ok(());
+        }
+        self.reallocate(new_cap)
+    }
+
+    /// Moves the elements into a new allocation of `max(new_cap, len)` slots
+    /// and frees the old one. Like `Vec::shrink_to`, it never drops elements:
+    /// call [`truncate`](Self::truncate) first to shrink below the current
+    /// length. Does nothing if the capacity is already at most `new_cap`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocator fails.
+    #[track_caller]
+    pub fn shrink_to(&mut self, new_cap: usize) {
+        let new_cap = usize::max(new_cap, self.len);
+        if new_cap >= self.cap {
+            return;
+        }
+        if let Err(err) = self.reallocate(new_cap) {
+            panic!("{err}");
+        }
+    }
+
+    fn reallocate(&mut self, new_cap: usize) -> Result<(), TryNewError> {
+        debug_assert!(self.len <= new_cap, "length exceeds capacity");
+        let mut new = Self::try_new(new_cap)?;
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.slot(0), new.slot(0), self.len);
+        }
+        new.len = self.len;
+        // The elements now belong to `new`; the old buffer only frees its
+        // allocation when it is dropped.
+        let mut old = std::mem::replace(self, new);
+        old.len = 0;
+        Ok(())
+    }
+
     pub fn get(&self, index: usize) -> Option<&T> {
-        if index > self.len {
+        if index >= self.len {
+            return None;
+        }
+
+        unsafe { Some(self.get_unchecked(index)) }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
             return None;
         }
 
-        unsafe { Some(self.ptr.add(index).as_ref()) }
+        unsafe { Some(self.get_unchecked_mut(index)) }
     }
 
     pub fn as_slice(&self) -> &[T] {
-        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+        unsafe { std::slice::from_raw_parts(self.slot(0), self.len) }
     }
 
-    pub fn as_mut_slice(&self) -> &mut [T] {
-        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    /// Only one mutable view of the elements can exist at a time:
+    ///
+    /// ```compile_fail
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let mut buf = BoundedBuffer::<u8>::new(4);
+    /// let a = buf.as_mut_slice();
+    /// let b = buf.as_mut_slice();
+    /// a[0] = b[0];
+    /// ```
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { self.as_mut_slice_unchecked() }
     }
 
-    pub fn try_push(&mut self, elem: T) -> bool {
-        if self.len == self.cap {
-            return false;
-        }
+    /// Returns a mutable view of the elements through a shared reference.
+    ///
+    /// # Safety
+    ///
+    /// For the lifetime of the returned slice no other reference to the
+    /// elements (shared or mutable) may be used, and the buffer must not be
+    /// modified through any other path.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn as_mut_slice_unchecked(&self) -> &mut [T] {
+        std::slice::from_raw_parts_mut(self.slot(0), self.len)
+    }
 
-        unsafe {
-            self.push_unchecked(elem);
-        }
-        true
+    /// Views the elements as arrays of `N`, followed by the remainder that
+    /// does not fill a whole array. Same as [`slice::as_chunks`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn as_chunks<const N: usize>(&self) -> (&[[T; N]], &[T]) {
+        self.as_slice().as_chunks()
     }
 
-    pub fn try_insert(&mut self, index: usize, elem: T) -> bool {
-        if self.len == self.cap || index > self.len {
-            return false;
+    /// Like [`as_chunks`](Self::as_chunks), but mutable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn as_chunks_mut<const N: usize>(&mut self) -> (&mut [[T; N]], &mut [T]) {
+        self.as_mut_slice().as_chunks_mut()
+    }
+
+    /// Regroups the elements into arrays of `N`, reusing the allocation.
+    /// Gives the buffer back unchanged if the length is not a multiple of
+    /// `N`.
+    ///
+    /// The new capacity is `capacity() / N`. If the capacity is not a multiple
+    /// of `N`, the elements are first moved to an allocation that is, since
+    /// an allocation can only be freed with the layout it was made with.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let buf = BoundedBuffer::from_fn(6, |i| i);
+    /// let pairs = buf.into_chunks::<2>().unwrap();
+    /// assert_eq!(pairs.as_slice(), [[0, 1], [2, 3], [4, 5]]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero, or if the allocator fails while moving to a
+    /// smaller allocation.
+    #[track_caller]
+    pub fn into_chunks<const N: usize>(mut self) -> Result<BoundedBuffer<[T; N]>, Self> {
+        assert!(N != 0, "chunk size must be non-zero");
+        if !self.len.is_multiple_of(N) {
+            return Err(self);
+        }
+        if !self.cap.is_multiple_of(N) {
+            self.shrink_to(self.cap - self.cap % N);
         }
 
-        unsafe {
-            self.insert_unchecked(index, elem);
+        let (ptr, len, cap) = self.into_raw_parts();
+        unsafe { Ok(BoundedBuffer::from_raw_parts(ptr.cast(), len / N, cap / N)) }
+    }
+
+    /// Reinterprets the elements as `U` without copying, handing over the
+    /// allocation. Gives the buffer back if the cast is not possible.
+    ///
+    /// An allocation has to be freed with the exact layout it was made with,
+    /// so the cast only succeeds when `T` and `U` have the same alignment and
+    /// both the length and the capacity in bytes divide evenly into `U`s.
+    /// Zero-sized types are never cast. In particular a `BoundedBuffer<u8>`
+    /// cannot become a `BoundedBuffer<u32>`, but it can become a
+    /// `BoundedBuffer<[u8; 4]>`:
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let buf = BoundedBuffer::from_fn(8, |i| i as u8);
+    /// let words = buf.try_cast::<[u8; 4]>().unwrap();
+    /// assert_eq!(words.as_slice(), [[0, 1, 2, 3], [4, 5, 6, 7]]);
+    /// assert!(words.try_cast::<u32>().is_err());
+    /// ```
+    pub fn try_cast<U: Pod>(self) -> Result<BoundedBuffer<U>, CastError<T>>
+    where
+        T: Pod,
+    {
+        let (from, to) = (size_of::<T>(), size_of::<U>());
+        if from == 0
+            || to == 0
+            || align_of::<T>() != align_of::<U>()
+            || !(self.len * from).is_multiple_of(to)
+            || !(self.cap * from).is_multiple_of(to)
+        {
+            return Err(CastError::new(self));
         }
-        true
+
+        let (ptr, len, cap) = self.into_raw_parts();
+        let (len, cap) = (len * from / to, cap * from / to);
+        debug_assert_eq!(
+            Layout::array::<T>(cap * to / from).ok(),
+            Layout::array::<U>(cap).ok()
+        );
+        unsafe { Ok(BoundedBuffer::from_raw_parts(ptr.cast(), len, cap)) }
+    }
+
+    /// Returns mutable references to several elements at once, failing if any
+    /// index is out of bounds or two indices are equal. Same as
+    /// [`slice::get_disjoint_mut`].
+    pub fn get_disjoint_mut<const N: usize>(
+        &mut self,
+        indices: [usize; N],
+    ) -> Result<[&mut T; N], GetDisjointMutError> {
+        self.as_mut_slice().get_disjoint_mut(indices)
+    }
+
+    /// Like [`get_disjoint_mut`](Self::get_disjoint_mut) without the checks.
+    ///
+    /// # Safety
+    ///
+    /// Every index must be less than `len()` and no two indices may be equal.
+    pub unsafe fn get_disjoint_unchecked_mut<const N: usize>(
+        &mut self,
+        indices: [usize; N],
+    ) -> [&mut T; N] {
+        self.as_mut_slice().get_disjoint_unchecked_mut(indices)
     }
 
-    pub fn insert_lossy(&mut self, index: usize, elem: T) {
+    /// Returns the uninitialized slots past `len()`. Write into them and then
+    /// call [`set_len`](Self::set_len) to make the written elements part of
+    /// the buffer.
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
         unsafe {
-            std::ptr::copy(
-                self.ptr.add(index).as_ptr(),
-                self.ptr.add(index + 1).as_ptr(),
-                self.len() - index,
-            );
-            self.ptr.add(index).write(elem);
-            self.len = usize::min(self.cap, self.len + 1);
+            std::slice::from_raw_parts_mut(
+                self.slot(self.len) as *mut MaybeUninit<T>,
+                self.cap - self.len,
+            )
         }
     }
 
-    pub fn remove(&mut self, index: usize) -> T {
-        assert!(index < self.len, "index out of bounds");
-        self.len -= 1;
+    /// Returns the initialized elements and the uninitialized spare slots at
+    /// the same time, so results can be computed from the former straight
+    /// into the latter and then committed with [`set_len`](Self::set_len).
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let mut buf = BoundedBuffer::new(6);
+    /// buf.try_extend_from_slice(&[1, 2, 3]).unwrap();
+    ///
+    /// let (init, spare) = buf.split_at_spare_mut();
+    /// let written = init.len().min(spare.len());
+    /// for (src, dst) in init.iter().zip(spare.iter_mut()) {
+    ///     dst.write(src * 10);
+    /// }
+    /// unsafe {
+    ///     buf.set_len(buf.len() + written);
+    /// }
+    /// assert_eq!(buf.as_slice(), [1, 2, 3, 10, 20, 30]);
+    /// ```
+    pub fn split_at_spare_mut(&mut self) -> (&mut [T], &mut [MaybeUninit<T>]) {
+        // Both halves come from the one allocation pointer so neither borrow
+        // is derived from the other.
         unsafe {
-            let val = std::ptr::read(self.ptr.add(index).as_ptr());
-            std::ptr::copy(
-                self.ptr.add(index + 1).as_ptr(),
-                self.ptr.add(index).as_ptr(),
-                self.len - index,
+            let ptr = self.slot(0);
+            let init = std::slice::from_raw_parts_mut(ptr, self.len);
+            let spare = std::slice::from_raw_parts_mut(
+                ptr.add(self.len) as *mut MaybeUninit<T>,
+                self.cap - self.len,
             );
-            val
+            (init, spare)
         }
     }
 
-    pub fn clear(&mut self) {
-        let slice = self.as_mut_slice();
-        unsafe {
-            std::ptr::drop_in_place(slice);
-            self.len = 0;
-        }
+    /// Sets the length of the buffer without dropping or initializing
+    /// anything.
+    ///
+    /// # Safety
+    ///
+    /// `new_len` must be at most `capacity()`, and every element below
+    /// `new_len` must be initialized. Shrinking this way leaks the elements
+    /// past `new_len` instead of dropping them. The capacity bound is checked
+    /// with `debug_assert!` in debug builds.
+    #[track_caller]
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.cap, "length exceeds capacity");
+        self.len = new_len;
     }
 
-    pub fn pop(&mut self) -> Option<T> {
-        if self.len == 0 {
+    pub fn try_push(&mut self, elem: T) -> bool {
+        self.raw().try_push(elem)
+    }
+
+    pub fn try_insert(&mut self, index: usize, elem: T) -> bool {
+        self.raw().try_insert(index, elem)
+    }
+
+    /// Inserts `elem` at `index`, evicting the last element if the buffer is
+    /// full. The evicted element is returned; inserting at `len()` into a full
+    /// buffer hands `elem` straight back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len()`.
+    #[track_caller]
+    pub fn insert_lossy(&mut self, index: usize, elem: T) -> Option<T> {
+        assert!(index <= self.len, "index out of bounds");
+        if self.len < self.cap {
+            unsafe {
+                self.insert_unchecked(index, elem);
+            }
             return None;
         }
 
-        self.len -= 1;
-        unsafe { Some(std::ptr::read(self.ptr.add(self.len).as_ptr())) }
+        if index == self.cap {
+            return Some(elem);
+        }
+
+        unsafe {
+            let evicted = std::ptr::read(self.slot(self.cap - 1));
+            std::ptr::copy(self.slot(index), self.slot(index + 1), self.cap - index - 1);
+            self.slot(index).write(elem);
+            Some(evicted)
+        }
     }
 
-    pub unsafe fn get_unchecked(&self, index: usize) -> &T {
-        self.ptr.add(index).as_ref()
+    #[track_caller]
+    pub fn remove(&mut self, index: usize) -> T {
+        self.raw().remove(index)
     }
 
-    pub unsafe fn push_unchecked(&mut self, elem: T) {
-        self.ptr.add(self.len).write(elem);
-        self.len += 1;
+    pub fn try_remove(&mut self, index: usize) -> Option<T> {
+        self.raw().try_remove(index)
     }
 
-    pub unsafe fn insert_unchecked(&mut self, index: usize, elem: T) {
-        std::ptr::copy(
-            self.ptr.add(index).as_ptr(),
-            self.ptr.add(index + 1).as_ptr(),
-            self.len() - index,
-        );
-        self.ptr.add(index).write(elem);
-        self.len += 1;
+    /// Removes and returns the element at `index` in O(1) by moving the last
+    /// element into its place.
+    ///
+    /// This does not preserve order: unlike [`remove`](Self::remove), the
+    /// element that was last now sits at `index`, and every other element
+    /// keeps its index. Use `remove` when order matters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len()`.
+    #[track_caller]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        self.raw().swap_remove(index)
     }
-}
 
-impl<T> Drop for BoundedBuffer<T> {
-    fn drop(&mut self) {
-        let elem_size = std::mem::size_of::<T>();
-        if self.cap != 0 && elem_size != 0 {
-            unsafe {
-                dealloc(
-                    self.ptr.as_ptr() as *mut u8,
-                    Layout::array::<T>(self.cap).unwrap_unchecked(),
-                );
-            }
-        }
+    /// Like [`swap_remove`](Self::swap_remove), but returns `None` if
+    /// `index` is out of bounds.
+    pub fn try_swap_remove(&mut self, index: usize) -> Option<T> {
+        self.raw().try_swap_remove(index)
     }
-}
 
-impl<T> Deref for BoundedBuffer<T> {
-    type Target = [T];
+    /// Swaps `elem` into slot `index` and returns the previous element,
+    /// without shifting anything.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len()`.
+    #[track_caller]
+    pub fn replace(&mut self, index: usize, elem: T) -> T {
+        assert!(index < self.len, "index out of bounds");
+        unsafe { std::mem::replace(self.get_unchecked_mut(index), elem) }
+    }
 
-    fn deref(&self) -> &Self::Target {
-        self.as_slice()
+    /// Like [`replace`](Self::replace), but returns `None` (dropping `elem`)
+    /// if `index` is out of bounds.
+    pub fn try_replace(&mut self, index: usize, elem: T) -> Option<T> {
+        self.get_mut(index)
+            .map(|slot| std::mem::replace(slot, elem))
     }
-}
 
-impl<T> DerefMut for BoundedBuffer<T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.as_mut_slice()
+    /// Moves the element at `index` through `f` and stores the result back in
+    /// its slot. If `f` panics, the element is gone and the tail is shifted
+    /// down over its slot, as if it had been removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len()`.
+    #[track_caller]
+    pub fn map_in_place<F>(&mut self, index: usize, f: F)
+    where
+        F: FnOnce(T) -> T,
+    {
+        // Closes the hole left by the moved-out element if `f` unwinds.
+        struct RemoveOnUnwind<'a, T> {
+            buf: &'a mut BoundedBuffer<T>,
+            index: usize,
+        }
+
+        impl<T> Drop for RemoveOnUnwind<'_, T> {
+            fn drop(&mut self) {
+                let tail = self.buf.len - self.index - 1;
+                unsafe {
+                    std::ptr::copy(
+                        self.buf.slot(self.index + 1),
+                        self.buf.slot(self.index),
+                        tail,
+                    );
+                }
+                self.buf.len -= 1;
+            }
+        }
+
+        assert!(index < self.len, "index out of bounds");
+        unsafe {
+            let slot = self.slot(index);
+            let guard = RemoveOnUnwind { buf: self, index };
+            let elem = f(std::ptr::read(slot));
+            std::mem::forget(guard);
+            slot.write(elem);
+        }
     }
-}
 
-impl<T> AsRef<[T]> for BoundedBuffer<T> {
-    fn as_ref(&self) -> &[T] {
-        self.as_slice()
+    pub fn clear(&mut self) {
+        self.truncate(0);
     }
-}
 
-impl<T> AsMut<[T]> for BoundedBuffer<T> {
-    fn as_mut(&mut self) -> &mut [T] {
-        self.as_mut_slice()
+    /// Drops the elements past `new_len`. Does nothing if `new_len >= len()`.
+    pub fn truncate(&mut self, new_len: usize) {
+        self.raw().truncate(new_len);
     }
-}
 
-impl<T> AsRef<BoundedBuffer<T>> for BoundedBuffer<T> {
-    fn as_ref(&self) -> &Self {
-        self
+    /// Drops the first `n` elements and moves the rest down with a single
+    /// copy. Clears the buffer if `n >= len()`.
+    ///
+    /// If a destructor panics, the remaining front elements are still
+    /// dropped and the rest still moved down.
+    pub fn truncate_front(&mut self, n: usize) {
+        self.drain_front(n);
     }
-}
 
-impl<T> AsMut<BoundedBuffer<T>> for BoundedBuffer<T> {
-    fn as_mut(&mut self) -> &mut BoundedBuffer<T> {
-        self
+    /// Removes the first `n` elements (or all of them if `n >= len()`) and
+    /// returns them as an iterator. The rest are moved down when the
+    /// iterator is dropped; see [`Drain`].
+    pub fn drain_front(&mut self, n: usize) -> Drain<'_, T> {
+        let n = usize::min(n, self.len);
+        self.drain(..n)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::BoundedBuffer;
+    /// Resizes the buffer to `new_len`, dropping excess elements or filling
+    /// new slots with clones of `value`. Fails without modifying the buffer
+    /// if `new_len` exceeds the capacity.
+    pub fn resize(&mut self, new_len: usize, value: T) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        if new_len > self.cap {
+            return Err(CapacityError::new(()));
+        }
 
-    #[test]
-    fn test_bounded_array() {
-        let n = 10_000;
-        let mut arr1 = BoundedBuffer::new(n);
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return Ok(());
+        }
 
-        let now = std::time::Instant::now();
-        for i in 0..n {
-            arr1.try_push(i);
+        unsafe {
+            self.extend_with(new_len - self.len, value);
         }
-        let elapsed = now.elapsed().as_nanos();
-        println!("BoundedBuffer Len: {}", arr1.len());
-        println!("BoundedBuffer Push: {} ns/op", elapsed / n as u128);
+        Ok(())
+    }
 
-        let mut arr2 = Vec::with_capacity(n);
+    /// Resizes the buffer to `new_len`, dropping excess elements or filling
+    /// new slots with values returned by `f`. Fails without modifying the
+    /// buffer if `new_len` exceeds the capacity. If `f` panics, the elements
+    /// written so far are kept.
+    pub fn resize_with<F>(&mut self, new_len: usize, f: F) -> Result<(), CapacityError>
+    where
+        F: FnMut() -> T,
+    {
+        if new_len > self.cap {
+            return Err(CapacityError::new(()));
+        }
 
-        let now = std::time::Instant::now();
-        for i in 0..n {
-            arr2.push(i);
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return Ok(());
         }
 
-        let elapsed = now.elapsed().as_nanos();
-        println!("Vec Len: {}", arr2.len());
-        println!("Vec Push: {} ns/op", elapsed / n as u128);
+        unsafe {
+            self.extend_with_fn(new_len - self.len, f);
+        }
+        Ok(())
+    }
 
-        for i in 0..n {
-            let val1 = arr1.get(i);
-            let val2 = arr2.get(i);
-            assert!(val1.is_some());
-            assert!(val2.is_some());
+    /// Clones `value` into every remaining slot until the buffer is full. Use
+    /// the slice method [`fill`](slice::fill) to overwrite the existing
+    /// elements instead.
+    pub fn fill_to_capacity(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        unsafe {
+            self.extend_with(self.cap - self.len, value);
+        }
+    }
 
-            let val1 = val1.unwrap();
-            let val2 = val2.unwrap();
+    /// Fills every remaining slot with values returned by `f` until the buffer
+    /// is full. If `f` panics, the elements written so far are kept.
+    pub fn fill_with_to_capacity<F>(&mut self, f: F)
+    where
+        F: FnMut() -> T,
+    {
+        unsafe {
+            self.extend_with_fn(self.cap - self.len, f);
+        }
+    }
 
-            assert_eq!(val1, val2);
+    // Appends `n` clones of `value`, moving `value` itself into the last slot.
+    // Like `extend_from_slice_unchecked`, the length is only written back at
+    // the end so the loop lowers to a `memset` for `Copy` types.
+    unsafe fn extend_with(&mut self, n: usize, value: T)
+    where
+        T: Clone,
+    {
+        debug_assert!(n <= self.cap - self.len);
+        if n == 0 {
+            return;
         }
 
-        let slice = arr1.as_slice();
+        let dst = self.slot(self.len);
+        let mut len = SetLenOnDrop::new(&mut self.len);
+        for i in 0..n - 1 {
+            dst.add(i).write(value.clone());
+            len.increment();
+        }
+        dst.add(n - 1).write(value);
+        len.increment();
+    }
+
+    unsafe fn extend_with_fn<F>(&mut self, n: usize, mut f: F)
+    where
+        F: FnMut() -> T,
+    {
+        debug_assert!(n <= self.cap - self.len);
+        let dst = self.slot(self.len);
+        let mut len = SetLenOnDrop::new(&mut self.len);
         for i in 0..n {
-            let val1 = slice[i];
-            let val2 = arr2[i];
-            assert_eq!(val1, val2);
+            dst.add(i).write(f());
+            len.increment();
         }
+    }
 
-        for (val1, val2) in arr1.iter().zip(arr2.iter()) {
-            assert_eq!(val1, val2);
+    /// Appends clones of all of `src`, or fails without writing anything if
+    /// they do not all fit.
+    pub fn try_extend_from_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        if src.len() > self.cap - self.len {
+            return Err(CapacityError::new(()));
         }
 
-        let mut sum = 0;
-        let now = std::time::Instant::now();
-        for i in 0..n {
-            sum += arr1.get(i).unwrap();
+        unsafe {
+            self.extend_from_slice_unchecked(src);
         }
-        let elapsed = now.elapsed().as_nanos();
-        println!("BoundedBuffer Get: {} ns/op", elapsed / n as u128);
-        println!("Sum: {}", sum);
+        Ok(())
+    }
 
-        let mut sum = 0;
-        let now = std::time::Instant::now();
-        for i in 0..n {
-            sum += arr2.get(i).unwrap();
+    /// Like [`try_extend_from_slice`](Self::try_extend_from_slice), but for
+    /// `Copy` elements, which are appended with a single `memcpy` rather than
+    /// one `clone` call each.
+    pub fn try_extend_from_copy_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Copy,
+    {
+        if src.len() > self.cap - self.len {
+            return Err(CapacityError::new(()));
         }
 
-        let elapsed = now.elapsed().as_nanos();
-        println!("Vec Get: {} ns/op", elapsed / n as u128);
-        println!("Sum: {}", sum);
+        // `src` is borrowed while `self` is borrowed mutably, so it can't
+        // alias the spare slots it is copied into.
+        unsafe {
+            std::ptr::copy_nonoverlapping(src.as_ptr(), self.slot(self.len), src.len());
+        }
+        self.len += src.len();
+        Ok(())
+    }
 
-        arr1.clear();
-        arr2.clear();
+    /// Appends clones of as many elements of `src` as fit and returns how
+    /// many were appended.
+    pub fn extend_from_slice_truncating(&mut self, src: &[T]) -> usize
+    where
+        T: Clone,
+    {
+        let count = usize::min(src.len(), self.cap - self.len);
+        unsafe {
+            self.extend_from_slice_unchecked(&src[..count]);
+        }
+        count
+    }
 
-        let now = std::time::Instant::now();
-        for i in 0..n {
-            arr1.try_insert(0, i);
+    /// Appends clones of the elements in `src` to the end of the buffer, or
+    /// fails without writing anything if they do not all fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing does.
+    #[track_caller]
+    pub fn extend_from_within<R>(&mut self, src: R) -> Result<(), CapacityError>
+    where
+        T: Clone,
+        R: RangeBounds<usize>,
+    {
+        let Range { start, end } = slice_range(src, self.len);
+        if end - start > self.cap - self.len {
+            return Err(CapacityError::new(()));
         }
-        let elapsed = now.elapsed().as_nanos();
-        println!("BoundedBuffer Try Insert: {} ns/op", elapsed / n as u128);
 
-        let now = std::time::Instant::now();
-        for i in 0..n {
-            arr2.insert(0, i);
+        // The source lies entirely below `len` and the writes entirely above
+        // it, so the two never overlap and the same bulk path as
+        // `try_extend_from_slice` applies (a `copy_nonoverlapping` for `Copy`
+        // types once the loop is lowered).
+        unsafe {
+            let src = std::slice::from_raw_parts(self.slot(start), end - start);
+            self.extend_from_slice_unchecked(src);
         }
-        let elapsed = now.elapsed().as_nanos();
-        println!("Vec Insert: {} ns/op", elapsed / n as u128);
+        Ok(())
+    }
+
+    /// Appends the elements of `iter` and returns how many were appended, or
+    /// fails at the first element that does not fit. Elements appended
+    /// before that stay in the buffer; the one that did not fit is dropped
+    /// and the rest of the iterator is never pulled.
+    ///
+    /// The iterator's lower size hint, where it fits, lets that many
+    /// elements be written without a capacity check each. A size hint that
+    /// is wrong only costs that fast path: it is never trusted for safety.
+    pub fn try_extend<I>(&mut self, iter: I) -> Result<usize, CapacityError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter();
+        let old_len = self.len;
+        let fast = usize::min(iter.size_hint().0, self.cap - self.len);
+        unsafe {
+            let dst = self.slot(self.len);
+            let mut len = SetLenOnDrop::new(&mut self.len);
+            for i in 0..fast {
+                let Some(elem) = iter.next() else {
+                    drop(len);
+                    return Ok(self.len - old_len);
+                };
+                dst.add(i).write(elem);
+                len.increment();
+            }
+        }
+
+        for elem in iter {
+            if self.len == self.cap {
+                return Err(CapacityError::new(()));
+            }
+            unsafe {
+                self.push_unchecked(elem);
+            }
+        }
+        Ok(self.len - old_len)
+    }
+
+    // Leaves the `memcpy` to the optimizer like `RawBuf` does;
+    // `try_extend_from_copy_slice` is the guaranteed bulk copy.
+    unsafe fn extend_from_slice_unchecked(&mut self, src: &[T])
+    where
+        T: Clone,
+    {
+        self.raw().extend_from_slice_unchecked(src);
+    }
+
+    /// Removes `range` from the buffer and returns its elements as an
+    /// iterator. See [`Drain`] for what happens to the buffer when the
+    /// iterator is dropped or leaked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing does.
+    #[track_caller]
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        let Range { start, end } = slice_range(range, self.len);
+        let len = self.len;
+        self.len = start;
+        unsafe {
+            let drained = std::slice::from_raw_parts(self.slot(start), end - start);
+            Drain {
+                iter: drained.iter(),
+                tail_start: end,
+                tail_len: len - end,
+                buf: NonNull::from(self),
+            }
+        }
+    }
+
+    /// Keeps only the elements for which `f` returns `true`, preserving their
+    /// order. Runs in a single pass over the buffer.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_mut(|elem| f(elem));
+    }
+
+    /// Like [`retain`](Self::retain), but `f` may mutate the elements.
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let original_len = self.len;
+        self.len = 0;
+        let mut guard = BackshiftOnDrop {
+            buf: self,
+            processed: 0,
+            deleted: 0,
+            original_len,
+        };
+
+        while guard.processed != original_len {
+            unsafe {
+                let cur = guard.buf.slot(guard.processed);
+                if !f(&mut *cur) {
+                    guard.processed += 1;
+                    guard.deleted += 1;
+                    std::ptr::drop_in_place(cur);
+                    continue;
+                }
+                if guard.deleted > 0 {
+                    let hole = guard.buf.slot(guard.processed - guard.deleted);
+                    std::ptr::copy_nonoverlapping(cur, hole, 1);
+                }
+                guard.processed += 1;
+            }
+        }
+    }
+
+    /// Keeps only the elements for which `f` returns `true`, filling each
+    /// hole with the current last element instead of shifting the tail.
+    ///
+    /// This does O(removed) moves rather than O(len), but does not preserve
+    /// order. `f` is called exactly once per element and may mutate it. If
+    /// `f` or a destructor panics, the elements not yet rejected stay in
+    /// the buffer.
+    pub fn retain_unordered<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        // The element moved into a hole comes from the untested end, so the
+        // same index is tested again without advancing.
+        let mut i = 0;
+        while i < self.len {
+            unsafe {
+                if f(&mut *self.slot(i)) {
+                    i += 1;
+                } else {
+                    drop(self.raw().swap_remove_unchecked(i));
+                }
+            }
+        }
+    }
+
+    /// Keeps the elements for which `pred` returns `true` and moves the rest
+    /// to the end of `other`, preserving their relative order in both
+    /// buffers.
+    ///
+    /// If `other` runs out of room, the pass stops at the first element that
+    /// no longer fits and an error is returned. Everything moved up to that
+    /// point stays in `other`; this buffer keeps the elements accepted so
+    /// far followed by every element not yet examined, still in order.
+    pub fn partition_into<F>(&mut self, mut pred: F, other: &mut Self) -> Result<(), CapacityError>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        // Moved elements are counted as deleted.
+        let original_len = self.len;
+        self.len = 0;
+        let mut guard = BackshiftOnDrop {
+            buf: self,
+            processed: 0,
+            deleted: 0,
+            original_len,
+        };
+
+        while guard.processed != original_len {
+            unsafe {
+                let cur = guard.buf.slot(guard.processed);
+                if !pred(&mut *cur) {
+                    if other.len == other.cap {
+                        return Err(CapacityError::new(()));
+                    }
+                    other.push_unchecked(std::ptr::read(cur));
+                    guard.processed += 1;
+                    guard.deleted += 1;
+                    continue;
+                }
+                if guard.deleted > 0 {
+                    let hole = guard.buf.slot(guard.processed - guard.deleted);
+                    std::ptr::copy_nonoverlapping(cur, hole, 1);
+                }
+                guard.processed += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes consecutive repeated elements, keeping the first of each run.
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b);
+    }
+
+    /// Removes consecutive elements that map to the same key.
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        F: FnMut(&mut T) -> K,
+        K: PartialEq,
+    {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements for which `same_bucket(current,
+    /// previous)` returns `true`, keeping the first of each run.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        // Shifts the unprocessed tail over the gap and fixes the length if
+        // `same_bucket` or a destructor panics part way through.
+        struct FillGapOnDrop<'a, T> {
+            read: usize,
+            write: usize,
+            buf: &'a mut BoundedBuffer<T>,
+        }
+
+        impl<T> Drop for FillGapOnDrop<'_, T> {
+            fn drop(&mut self) {
+                let len = self.buf.len;
+                unsafe {
+                    std::ptr::copy(
+                        self.buf.slot(self.read),
+                        self.buf.slot(self.write),
+                        len - self.read,
+                    );
+                }
+                self.buf.len = len - (self.read - self.write);
+            }
+        }
+
+        let len = self.len;
+        if len <= 1 {
+            return;
+        }
+
+        let mut gap = FillGapOnDrop {
+            read: 1,
+            write: 1,
+            buf: self,
+        };
+
+        unsafe {
+            while gap.read < len {
+                let read = gap.buf.slot(gap.read);
+                let prev = gap.buf.slot(gap.write - 1);
+                if same_bucket(&mut *read, &mut *prev) {
+                    gap.read += 1;
+                    std::ptr::drop_in_place(read);
+                } else {
+                    std::ptr::copy(read, gap.buf.slot(gap.write), 1);
+                    gap.write += 1;
+                    gap.read += 1;
+                }
+            }
+
+            gap.buf.len = gap.write;
+            std::mem::forget(gap);
+        }
+    }
+
+    /// Moves the elements from `at` onwards into a new buffer whose capacity
+    /// is exactly the number of elements moved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len()`.
+    #[track_caller]
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let cap = self.len.saturating_sub(at);
+        self.split_off_with_capacity(at, cap)
+    }
+
+    /// Moves the elements from `at` onwards into a new buffer with capacity
+    /// `cap`. Pass `self.capacity()` to get a buffer of the same size, e.g.
+    /// for returning it to a pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len()`, if `cap` is smaller than the number of elements
+    /// moved, or if the new buffer cannot be allocated. Nothing is moved in
+    /// any of these cases.
+    #[track_caller]
+    pub fn split_off_with_capacity(&mut self, at: usize, cap: usize) -> Self {
+        assert!(
+            at <= self.len,
+            "`at` split index (is {at}) should be <= len (is {})",
+            self.len
+        );
+        let other_len = self.len - at;
+        assert!(
+            other_len <= cap,
+            "capacity (is {cap}) should be >= split length (is {other_len})"
+        );
+
+        let mut other = Self::new(cap);
+        unsafe {
+            self.len = at;
+            std::ptr::copy_nonoverlapping(self.slot(at), other.slot(0), other_len);
+            other.len = other_len;
+        }
+        other
+    }
+
+    /// Moves all elements of `other` to the end of this buffer, leaving
+    /// `other` empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the elements do not fit, in which case neither buffer is
+    /// modified. See [`try_append`](Self::try_append).
+    #[track_caller]
+    pub fn append(&mut self, other: &mut Self) {
+        if self.try_append(other).is_err() {
+            panic!(
+                "appended length (is {}) should be <= spare capacity (is {})",
+                other.len,
+                self.cap - self.len
+            );
+        }
+    }
+
+    /// Moves all elements of `other` to the end of this buffer, leaving
+    /// `other` empty, or fails without modifying either buffer if they do not
+    /// fit.
+    pub fn try_append(&mut self, other: &mut Self) -> Result<(), CapacityError> {
+        if other.len > self.cap - self.len {
+            return Err(CapacityError::new(()));
+        }
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(other.slot(0), self.slot(self.len), other.len);
+        }
+        self.len += other.len;
+        other.len = 0;
+        Ok(())
+    }
+
+    /// Creates a buffer of capacity `cap` holding clones of the elements of
+    /// every buffer in `parts`, in order. Fails if they add up to more than
+    /// `cap`.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`new`](Self::new) if the allocation fails.
+    #[track_caller]
+    pub fn concat<'a, I>(parts: I, cap: usize) -> Result<Self, CapacityError>
+    where
+        T: Clone + 'a,
+        I: IntoIterator<Item = &'a Self>,
+    {
+        let mut out = Self::new(cap);
+        for part in parts {
+            out.try_extend_from_slice(part)?;
+        }
+        Ok(out)
+    }
+
+    /// Creates a buffer of capacity `cap` by moving the elements of every
+    /// buffer in `parts` into it, in order, leaving the parts empty but
+    /// keeping their allocations. Fails without touching any part if they
+    /// add up to more than `cap`.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`new`](Self::new) if the allocation fails.
+    #[track_caller]
+    pub fn concat_owned(parts: &mut [Self], cap: usize) -> Result<Self, CapacityError> {
+        let total = parts
+            .iter()
+            .try_fold(0usize, |total, part| total.checked_add(part.len));
+        if total.is_none_or(|total| total > cap) {
+            return Err(CapacityError::new(()));
+        }
+
+        let mut out = Self::new(cap);
+        for part in parts {
+            out.append(part);
+        }
+        Ok(out)
+    }
+
+    /// Inserts `elem` into an already sorted buffer, after any equal
+    /// elements, and returns its index. Gives `elem` back if the buffer is
+    /// full.
+    pub fn insert_sorted(&mut self, elem: T) -> Result<usize, CapacityError<T>>
+    where
+        T: Ord,
+    {
+        if self.len == self.cap {
+            return Err(CapacityError::new(elem));
+        }
+
+        let index = self.partition_point(|probe| probe <= &elem);
+        unsafe {
+            self.insert_unchecked(index, elem);
+        }
+        Ok(index)
+    }
+
+    /// Like [`insert_sorted`](Self::insert_sorted) for a buffer sorted by the
+    /// key `f` extracts.
+    pub fn insert_sorted_by_key<K, F>(
+        &mut self,
+        elem: T,
+        mut f: F,
+    ) -> Result<usize, CapacityError<T>>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        if self.len == self.cap {
+            return Err(CapacityError::new(elem));
+        }
+
+        let key = f(&elem);
+        let index = self.partition_point(|probe| f(probe) <= key);
+        unsafe {
+            self.insert_unchecked(index, elem);
+        }
+        Ok(index)
+    }
+
+    /// Searches a sorted buffer with `cmp` (as in [`slice::binary_search_by`])
+    /// and returns the matching element, or inserts the one built by `make`
+    /// at the position that keeps the buffer sorted. Hands `make` back if an
+    /// insert is needed and the buffer is full.
+    pub fn find_or_insert_sorted_with<C, F>(
+        &mut self,
+        cmp: C,
+        make: F,
+    ) -> Result<&mut T, CapacityError<F>>
+    where
+        C: FnMut(&T) -> std::cmp::Ordering,
+        F: FnOnce() -> T,
+    {
+        let index = match self.binary_search_by(cmp) {
+            Ok(index) => index,
+            Err(index) => {
+                if self.len == self.cap {
+                    return Err(CapacityError::new(make));
+                }
+                unsafe {
+                    self.insert_unchecked(index, make());
+                }
+                index
+            }
+        };
+        unsafe { Ok(self.get_unchecked_mut(index)) }
+    }
+
+    /// Drops the elements in `range` and closes the gap with a single copy,
+    /// returning how many were removed. The same as dropping
+    /// [`drain`](Self::drain) straight away: if a destructor panics, the
+    /// rest of the range is still dropped and the tail still moved down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing does.
+    #[track_caller]
+    pub fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) -> usize {
+        let range = slice_range(range, self.len);
+        let removed = range.len();
+        self.drain(range);
+        removed
+    }
+
+    /// Replaces `range` with the elements of `replace_with` and returns the
+    /// removed elements as an iterator. See [`Splice`] for when the
+    /// replacement is written.
+    ///
+    /// The replacement length is taken from its `ExactSizeIterator` impl up
+    /// front: if the buffer would not have room for it, nothing is modified
+    /// and the iterator is handed back. If it then yields fewer elements than
+    /// it reported, the buffer simply ends up shorter; any extra elements are
+    /// never pulled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing does.
+    #[track_caller]
+    pub fn splice<R, I>(
+        &mut self,
+        range: R,
+        replace_with: I,
+    ) -> Result<Splice<'_, I::IntoIter>, CapacityError<I::IntoIter>>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let range = slice_range(range, self.len);
+        let replace_with = replace_with.into_iter();
+        let replace_len = replace_with.len();
+        if replace_len > self.cap - (self.len - range.len()) {
+            return Err(CapacityError::new(replace_with));
+        }
+
+        Ok(Splice {
+            drain: self.drain(range),
+            replace_with,
+            replace_len,
+        })
+    }
+
+    /// Inserts the elements of `iter` at `index`, shifting the tail once to
+    /// make room for all of them.
+    ///
+    /// As with [`splice`](Self::splice), the length comes from the
+    /// `ExactSizeIterator` impl: if the elements would not fit, nothing is
+    /// modified and the iterator is handed back. If it then yields fewer
+    /// elements than it reported, or panics, the tail is moved back to close
+    /// the gap; extra elements are never pulled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len()`.
+    #[track_caller]
+    pub fn insert_many<I>(
+        &mut self,
+        index: usize,
+        iter: I,
+    ) -> Result<(), CapacityError<I::IntoIter>>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        assert!(index <= self.len, "index out of bounds");
+        self.splice(index..index, iter).map(drop)
+    }
+
+    /// Returns an iterator that removes and yields every element for which
+    /// `filter` returns `true`, keeping the rest in order. See [`ExtractIf`]
+    /// for what happens to the buffer when the iterator is dropped early or
+    /// leaked.
+    pub fn extract_if<F>(&mut self, filter: F) -> ExtractIf<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let old_len = self.len;
+        self.len = 0;
+        ExtractIf {
+            buf: self,
+            idx: 0,
+            del: 0,
+            old_len,
+            pred: filter,
+        }
+    }
+
+    /// Records the current length and returns a guard that truncates the
+    /// buffer back to it when dropped, unless
+    /// [`Checkpoint::commit`] is called first.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let mut buf = BoundedBuffer::new(8);
+    /// buf.try_extend_from_slice(b"ok").unwrap();
+    ///
+    /// let mut cp = buf.checkpoint();
+    /// cp.try_extend_from_slice(b"bad").unwrap();
+    /// drop(cp);
+    /// assert_eq!(buf.as_slice(), b"ok");
+    ///
+    /// let mut cp = buf.checkpoint();
+    /// cp.try_extend_from_slice(b"!").unwrap();
+    /// cp.commit();
+    /// assert_eq!(buf.as_slice(), b"ok!");
+    /// ```
+    pub fn checkpoint(&mut self) -> Checkpoint<'_, T> {
+        let len = self.len;
+        Checkpoint { buf: self, len }
+    }
+
+    /// Returns a cursor pointing at the first element, or at the ghost
+    /// position if the buffer is empty. See [`CursorMut`].
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            buf: self,
+            index: 0,
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.raw().pop()
+    }
+
+    /// Moves up to `out.len()` elements off the end of the buffer into the
+    /// front of `out`, keeping their order, and returns how many were moved.
+    /// `out[..n]` then owns those elements; the rest of `out` is untouched.
+    pub fn pop_many(&mut self, out: &mut [MaybeUninit<T>]) -> usize {
+        let count = usize::min(out.len(), self.len);
+        self.len -= count;
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.slot(self.len), out.as_mut_ptr() as *mut T, count);
+        }
+        count
+    }
+
+    /// Copies up to `dst.len()` elements off the end of the buffer into the
+    /// front of `dst`, keeping their order, and returns how many were moved.
+    pub fn pop_copy_into(&mut self, dst: &mut [T]) -> usize
+    where
+        T: Copy,
+    {
+        let count = usize::min(dst.len(), self.len);
+        self.len -= count;
+        dst[..count]
+            .copy_from_slice(unsafe { std::slice::from_raw_parts(self.slot(self.len), count) });
+        count
+    }
+
+    /// Removes and returns the last element if `f` returns `true` for it.
+    /// The element stays in place when `f` returns `false`, including any
+    /// changes `f` made to it.
+    pub fn pop_if<F>(&mut self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut T) -> bool,
+    {
+        let last = self.len.checked_sub(1)?;
+        if f(unsafe { &mut *self.slot(last) }) {
+            self.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator that pops elements off the end for as long as `f`
+    /// returns `true`. Elements are only popped as the iterator is advanced,
+    /// so dropping it early leaves the rest in place.
+    pub fn pop_while<F>(&mut self, f: F) -> PopWhile<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        PopWhile {
+            buf: self,
+            pred: f,
+            done: false,
+        }
+    }
+
+    /// Returns a reference to the element at `index` without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be less than `len()`. Reading past `len()` observes
+    /// uninitialized memory. Checked with `debug_assert!` in debug builds.
+    #[track_caller]
+    pub unsafe fn get_unchecked(&self, index: usize) -> &T {
+        debug_assert!(index < self.len, "index out of bounds");
+        &*self.slot(index)
+    }
+
+    /// Returns a mutable reference to the element at `index` without bounds
+    /// checking.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be less than `len()`. Checked with `debug_assert!` in
+    /// debug builds.
+    #[track_caller]
+    pub unsafe fn get_unchecked_mut(&mut self, index: usize) -> &mut T {
+        debug_assert!(index < self.len, "index out of bounds");
+        &mut *self.slot(index)
+    }
+
+    /// Appends `elem` without checking for spare capacity.
+    ///
+    /// # Safety
+    ///
+    /// `len()` must be less than `capacity()`, otherwise the write lands past
+    /// the end of the allocation. Checked with `debug_assert!` in debug
+    /// builds.
+    #[track_caller]
+    pub unsafe fn push_unchecked(&mut self, elem: T) {
+        self.raw().push_unchecked(elem);
+    }
+
+    /// Inserts `elem` at `index`, shifting the tail right, without checking
+    /// the index or the capacity.
+    ///
+    /// # Safety
+    ///
+    /// `len()` must be less than `capacity()` and `index` must be at most
+    /// `len()`. Both are checked with `debug_assert!` in debug builds.
+    #[track_caller]
+    pub unsafe fn insert_unchecked(&mut self, index: usize, elem: T) {
+        self.raw().insert_unchecked(index, elem);
+    }
+
+    /// Removes and returns the element at `index`, shifting the tail left,
+    /// without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be less than `len()`. Checked with `debug_assert!` in
+    /// debug builds.
+    #[track_caller]
+    pub unsafe fn remove_unchecked(&mut self, index: usize) -> T {
+        self.raw().remove_unchecked(index)
+    }
+}
+
+// The buffer owns its elements exactly like a `Vec<T>` does, so it is as
+// thread-safe as `T` itself.
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<fixed_buf::BoundedBuffer<std::rc::Rc<u8>>>();
+/// ```
+unsafe impl<T: Send> Send for BoundedBuffer<T> {}
+
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<fixed_buf::BoundedBuffer<std::cell::Cell<u8>>>();
+/// ```
+unsafe impl<T: Sync> Sync for BoundedBuffer<T> {}
+
+impl<T: Clone> Clone for BoundedBuffer<T> {
+    fn clone(&self) -> Self {
+        let mut buf = Self::new(self.cap);
+        unsafe {
+            buf.extend_from_slice_unchecked(self.as_slice());
+        }
+        buf
+    }
+
+    /// Reuses this buffer's allocation, and keeps its capacity, whenever it
+    /// can hold all of `source`'s elements. The overlapping prefix is updated
+    /// with `T::clone_from`. Otherwise the buffer is replaced by a fresh clone
+    /// with `source`'s capacity.
+    fn clone_from(&mut self, source: &Self) {
+        if self.cap < source.len {
+            *self = source.clone();
+            return;
+        }
+
+        self.truncate(source.len);
+        let (init, tail) = source.as_slice().split_at(self.len);
+        self.as_mut_slice().clone_from_slice(init);
+        unsafe {
+            self.extend_from_slice_unchecked(tail);
+        }
+    }
+}
+
+// Resolves `range` against a buffer of length `len`, panicking on the same
+// conditions (and with the same messages) as slice indexing.
+#[track_caller]
+pub(crate) fn slice_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start
+            .checked_add(1)
+            .expect("attempted to index slice from after maximum usize"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end
+            .checked_add(1)
+            .expect("attempted to index slice up to maximum usize"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+
+    assert!(
+        start <= end,
+        "slice index starts at {start} but ends at {end}"
+    );
+    assert!(
+        end <= len,
+        "range end index {end} out of range for slice of length {len}"
+    );
+    start..end
+}
+
+// Closes the gap left by the elements taken out of a single in-order pass
+// (`retain_mut`, `partition_into`) once it completes or unwinds. Until then
+// the buffer reports itself empty so a panic in a callback or in a
+// destructor can never lead to a double drop.
+struct BackshiftOnDrop<'a, T> {
+    buf: &'a mut BoundedBuffer<T>,
+    processed: usize,
+    deleted: usize,
+    original_len: usize,
+}
+
+impl<T> Drop for BackshiftOnDrop<'_, T> {
+    fn drop(&mut self) {
+        if self.deleted > 0 {
+            unsafe {
+                std::ptr::copy(
+                    self.buf.slot(self.processed),
+                    self.buf.slot(self.processed - self.deleted),
+                    self.original_len - self.processed,
+                );
+            }
+        }
+        self.buf.len = self.original_len - self.deleted;
+    }
+}
+
+impl<T> Drop for BoundedBuffer<T> {
+    fn drop(&mut self) {
+        // Frees the allocation even if one of the element destructors panics.
+        struct Dealloc<T> {
+            ptr: NonNull<T>,
+            cap: usize,
+        }
+
+        impl<T> Drop for Dealloc<T> {
+            fn drop(&mut self) {
+                let elem_size = std::mem::size_of::<T>();
+                if self.cap != 0 && elem_size != 0 {
+                    unsafe {
+                        dealloc(
+                            self.ptr.as_ptr() as *mut u8,
+                            Layout::array::<T>(self.cap).unwrap_unchecked(),
+                        );
+                    }
+                }
+            }
+        }
+
+        let _dealloc = Dealloc {
+            ptr: self.ptr,
+            cap: self.cap,
+        };
+        unsafe {
+            std::ptr::drop_in_place(self.as_mut_slice());
+        }
+    }
+}
+
+/// Formats the initialized elements as a list, the same way `Vec` does. The
+/// capacity is not shown.
+impl<T: fmt::Debug> fmt::Debug for BoundedBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+// Equality, ordering and hashing only look at the elements, never the
+// capacity, and agree with the same operations on `as_slice()`.
+//
+// Arrays only compare against the buffer's own element type. With a free
+// `U`, `buf == []` has nothing to infer the element type from and stops
+// compiling as soon as any crate in the build adds a `PartialEq<_>` impl
+// for the element (serde_json does for every primitive).
+macro_rules! impl_slice_eq {
+    ([$($vars:tt)*]; $lhs:ty, $rhs:ty) => {
+        impl<T, U, $($vars)*> PartialEq<$rhs> for $lhs
+        where
+            T: PartialEq<U>,
+        {
+            fn eq(&self, other: &$rhs) -> bool {
+                self[..] == other[..]
+            }
+        }
+    };
+    ([$($vars:tt)*]; $lhs:ty, $rhs:ty, same) => {
+        impl<T: PartialEq, $($vars)*> PartialEq<$rhs> for $lhs {
+            fn eq(&self, other: &$rhs) -> bool {
+                self[..] == other[..]
+            }
+        }
+    };
+}
+
+impl_slice_eq!([]; BoundedBuffer<T>, BoundedBuffer<U>);
+impl_slice_eq!([]; BoundedBuffer<T>, [U]);
+impl_slice_eq!([]; BoundedBuffer<T>, &[U]);
+impl_slice_eq!([]; BoundedBuffer<T>, &mut [U]);
+impl_slice_eq!([]; BoundedBuffer<T>, Vec<U>);
+impl_slice_eq!([const N: usize]; BoundedBuffer<T>, [T; N], same);
+impl_slice_eq!([const N: usize]; BoundedBuffer<T>, &[T; N], same);
+impl_slice_eq!([]; [T], BoundedBuffer<U>);
+impl_slice_eq!([]; &[T], BoundedBuffer<U>);
+impl_slice_eq!([]; &mut [T], BoundedBuffer<U>);
+impl_slice_eq!([]; Vec<T>, BoundedBuffer<U>);
+impl_slice_eq!([const N: usize]; [T; N], BoundedBuffer<T>, same);
+
+impl<T: Eq> Eq for BoundedBuffer<T> {}
+
+impl<T: PartialOrd> PartialOrd for BoundedBuffer<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.as_slice().partial_cmp(other.as_slice())
+    }
+}
+
+impl<T: Ord> Ord for BoundedBuffer<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl<T: Hash> Hash for BoundedBuffer<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+/// Clones the slice into a buffer of exactly its length. Fails only if the
+/// allocation does.
+impl<T: Clone> TryFrom<&[T]> for BoundedBuffer<T> {
+    type Error = TryNewError;
+
+    fn try_from(src: &[T]) -> Result<Self, Self::Error> {
+        let mut buf = Self::try_new(src.len())?;
+        unsafe {
+            buf.extend_from_slice_unchecked(src);
+        }
+        Ok(buf)
+    }
+}
+
+/// Moves the array into a buffer of capacity `N`.
+impl<T, const N: usize> From<[T; N]> for BoundedBuffer<T> {
+    fn from(arr: [T; N]) -> Self {
+        let mut buf = Self::new(N);
+        let arr = std::mem::ManuallyDrop::new(arr);
+        unsafe {
+            std::ptr::copy_nonoverlapping(arr.as_ptr(), buf.slot(0), N);
+            buf.len = N;
+        }
+        buf
+    }
+}
+
+/// Adopts the allocation of the `Vec`; see [`BoundedBuffer::from_vec`].
+impl<T> From<Vec<T>> for BoundedBuffer<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_vec(vec)
+    }
+}
+
+impl<T> From<BoundedBuffer<T>> for Vec<T> {
+    fn from(buf: BoundedBuffer<T>) -> Self {
+        buf.into_vec()
+    }
+}
+
+/// Collects into a `Vec` and adopts its allocation, so the capacity is
+/// whatever the `Vec` ended up with: exactly the length for iterators with an
+/// exact size hint, possibly more otherwise. Use
+/// [`BoundedBuffer::try_from_iter`] to choose the capacity.
+impl<T> FromIterator<T> for BoundedBuffer<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_vec(iter.into_iter().collect())
+    }
+}
+
+/// Appends every element of the iterator.
+///
+/// # Panics
+///
+/// Panics if the buffer fills up before the iterator is exhausted, keeping
+/// the elements that fit. Use [`BoundedBuffer::try_extend`] to handle that
+/// case instead.
+impl<T> Extend<T> for BoundedBuffer<T> {
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        if self.try_extend(iter).is_err() {
+            panic!("extended past capacity (is {})", self.cap);
+        }
+    }
+}
+
+/// Copies every element of the iterator, with the same overflow behavior as
+/// `Extend<T>`.
+impl<'a, T: Copy + 'a> Extend<&'a T> for BoundedBuffer<T> {
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T> IntoIterator for BoundedBuffer<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        let (ptr, len, cap) = self.into_raw_parts();
+        IntoIter {
+            ptr,
+            cap,
+            start: 0,
+            end: len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BoundedBuffer<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut BoundedBuffer<T> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> Deref for BoundedBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for BoundedBuffer<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<T> AsRef<[T]> for BoundedBuffer<T> {
+    fn as_ref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> AsMut<[T]> for BoundedBuffer<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+/// Lets map and set keys of type `BoundedBuffer<T>` be looked up by `&[T]`.
+/// This relies on `Eq`, `Ord` and `Hash` for the buffer behaving exactly as
+/// they do for its elements as a slice, which they do.
+impl<T> Borrow<[T]> for BoundedBuffer<T> {
+    fn borrow(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> BorrowMut<[T]> for BoundedBuffer<T> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T> AsRef<BoundedBuffer<T>> for BoundedBuffer<T> {
+    fn as_ref(&self) -> &Self {
+        self
+    }
+}
+
+impl<T> AsMut<BoundedBuffer<T>> for BoundedBuffer<T> {
+    fn as_mut(&mut self) -> &mut BoundedBuffer<T> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BoundedBuffer, BoundedCursor};
+    use crate::core::error::{CapacityError, TryNewError};
+    use crate::core::test_util::{xorshift, DropCount, Drops};
+
+    #[test]
+    fn test_get_out_of_bounds() {
+        let mut buf = BoundedBuffer::<u32>::new(4);
+        assert_eq!(buf.get(0), None);
+        assert_eq!(buf.get_mut(0), None);
+
+        buf.try_push(1);
+        buf.try_push(2);
+        assert_eq!(buf.get(1), Some(&2));
+        assert_eq!(buf.get(buf.len()), None);
+        assert_eq!(buf.get_mut(buf.len()), None);
+
+        buf.try_push(3);
+        buf.try_push(4);
+        assert_eq!(buf.get(3), Some(&4));
+        assert_eq!(buf.get(buf.len()), None);
+        assert_eq!(buf.get_mut(buf.len()), None);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(String::from("a"));
+        buf.try_push(String::from("b"));
+
+        buf.get_mut(1).unwrap().push('c');
+        unsafe {
+            buf.get_unchecked_mut(0).push('d');
+        }
+        assert_eq!(buf.as_slice(), ["ad", "bc"]);
+    }
+
+    #[test]
+    fn test_as_mut_slice() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        buf.try_push(2);
+
+        buf.as_mut_slice()[0] = 10;
+        AsMut::<[i32]>::as_mut(&mut buf)[1] = 20;
+        buf[1] += 1;
+        assert_eq!(buf.as_slice(), [10, 21]);
+
+        unsafe {
+            buf.as_mut_slice_unchecked()[0] = 11;
+        }
+        assert_eq!(buf.as_slice(), [11, 21]);
+    }
+
+    #[test]
+    fn test_insert_lossy() {
+        let mut buf = BoundedBuffer::new(4);
+        assert_eq!(buf.insert_lossy(0, String::from("b")), None);
+        assert_eq!(buf.insert_lossy(0, String::from("a")), None);
+        assert_eq!(buf.insert_lossy(2, String::from("d")), None);
+        assert_eq!(buf.insert_lossy(2, String::from("c")), None);
+        assert_eq!(buf.as_slice(), ["a", "b", "c", "d"]);
+
+        assert_eq!(buf.insert_lossy(0, String::from("z")).as_deref(), Some("d"));
+        assert_eq!(buf.as_slice(), ["z", "a", "b", "c"]);
+
+        assert_eq!(buf.insert_lossy(2, String::from("y")).as_deref(), Some("c"));
+        assert_eq!(buf.as_slice(), ["z", "a", "y", "b"]);
+
+        assert_eq!(buf.insert_lossy(3, String::from("x")).as_deref(), Some("b"));
+        assert_eq!(buf.as_slice(), ["z", "a", "y", "x"]);
+
+        assert_eq!(buf.insert_lossy(4, String::from("w")).as_deref(), Some("w"));
+        assert_eq!(buf.as_slice(), ["z", "a", "y", "x"]);
+        assert_eq!(buf.len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_insert_lossy_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        buf.insert_lossy(2, 2);
+    }
+
+    #[test]
+    fn test_zero_sized() {
+        let mut buf = BoundedBuffer::<()>::new(1000);
+        assert_eq!(buf.capacity(), 1000);
+        for _ in 0..1000 {
+            assert!(buf.try_push(()));
+        }
+        assert!(!buf.try_push(()));
+        assert_eq!(buf.len(), 1000);
+
+        assert_eq!(buf.remove(500), ());
+        assert!(buf.try_insert(0, ()));
+        assert_eq!(buf.insert_lossy(0, ()), Some(()));
+        assert_eq!(buf.pop(), Some(()));
+        assert_eq!(buf.len(), 999);
+        assert_eq!(buf.as_slice().len(), 999);
+
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Zst;
+
+        impl Drop for Zst {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(3);
+        for _ in 0..4 {
+            buf.try_push(Zst);
+        }
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        drop(buf.pop());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut buf = BoundedBuffer::<u64>::new(0);
+        assert_eq!(buf.capacity(), 0);
+        assert!(buf.is_empty());
+        assert!(!buf.try_push(1));
+        assert!(!buf.try_insert(0, 1));
+        assert_eq!(buf.insert_lossy(0, 1), Some(1));
+        assert_eq!(buf.pop(), None);
+        assert_eq!(buf.get(0), None);
+        assert!(buf.as_slice().is_empty());
+        buf.clear();
+
+        let cloned = buf.clone();
+        assert_eq!(cloned.capacity(), 0);
+    }
+
+    #[test]
+    fn test_try_new() {
+        let buf = BoundedBuffer::<u64>::try_new(16).unwrap();
+        assert_eq!(buf.capacity(), 16);
+        assert!(buf.is_empty());
+
+        assert_eq!(
+            BoundedBuffer::<u64>::try_new(usize::MAX).unwrap_err(),
+            TryNewError::CapacityOverflow
+        );
+        assert_eq!(
+            BoundedBuffer::<u16>::try_new(isize::MAX as usize / 2 + 1).unwrap_err(),
+            TryNewError::CapacityOverflow
+        );
+
+        let buf = BoundedBuffer::<()>::try_new(usize::MAX).unwrap();
+        assert_eq!(buf.capacity(), usize::MAX);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_try_new_alloc_error() {
+        let err = BoundedBuffer::<u64>::try_new(isize::MAX as usize / 8).unwrap_err();
+        assert!(matches!(err, TryNewError::AllocError { .. }));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn test_new_overflow() {
+        BoundedBuffer::<u64>::new(usize::MAX);
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<BoundedBuffer<u64>>();
+        assert_sync::<BoundedBuffer<u64>>();
+        assert_send::<BoundedBuffer<std::cell::Cell<u64>>>();
+        assert_sync::<BoundedBuffer<std::sync::MutexGuard<'static, u64>>>();
+
+        let mut buf = BoundedBuffer::new(100);
+        for i in 0..100u64 {
+            buf.try_push(i);
+        }
+        let sum = std::thread::spawn(move || buf.iter().sum::<u64>())
+            .join()
+            .unwrap();
+        assert_eq!(sum, 4950);
+
+        let shared = std::sync::Arc::new(std::sync::Mutex::new(BoundedBuffer::new(8)));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    shared.lock().unwrap().try_push(i);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(shared.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut buf = BoundedBuffer::new(8);
+        assert_eq!(format!("{buf:?}"), "[]");
+        assert_eq!(format!("{buf:#?}"), "[]");
+
+        buf.try_push(1);
+        buf.try_push(2);
+        buf.try_push(3);
+        assert_eq!(format!("{buf:?}"), "[1, 2, 3]");
+        assert_eq!(format!("{buf:#?}"), "[\n    1,\n    2,\n    3,\n]");
+        assert_eq!(format!("{buf:?}"), format!("{:?}", vec![1, 2, 3]));
+
+        let mut buf = BoundedBuffer::new(2);
+        buf.try_push("a");
+        assert_eq!(format!("{buf:?}"), r#"["a"]"#);
+    }
+
+    #[test]
+    fn test_clear_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 1 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(4);
+        for i in 0..4 {
+            buf.try_push(PanicOnDrop(i));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| buf.clear()));
+        assert!(result.is_err());
+        assert!(buf.is_empty());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_try_remove() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for i in 0..4 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        assert_eq!(buf.try_remove(0).map(|(i, _)| i), Some(0));
+        assert_eq!(buf.try_remove(buf.len() - 1).map(|(i, _)| i), Some(3));
+        assert!(buf.try_remove(buf.len()).is_none());
+        assert_eq!(drops.get(), 2);
+        assert_eq!(buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2]);
+
+        assert_eq!(unsafe { buf.remove_unchecked(1) }.0, 2);
+        assert_eq!(buf.remove(0).0, 1);
+        assert!(buf.try_remove(0).is_none());
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_remove_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        buf.remove(1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_get_unchecked_debug_assert() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        unsafe {
+            buf.get_unchecked(1);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_get_unchecked_mut_debug_assert() {
+        let mut buf = BoundedBuffer::<u8>::new(4);
+        unsafe {
+            buf.get_unchecked_mut(0);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "buffer is full")]
+    fn test_push_unchecked_debug_assert() {
+        let mut buf = BoundedBuffer::new(1);
+        buf.try_push(1);
+        unsafe {
+            buf.push_unchecked(2);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "buffer is full")]
+    fn test_insert_unchecked_full_debug_assert() {
+        let mut buf = BoundedBuffer::new(1);
+        buf.try_push(1);
+        unsafe {
+            buf.insert_unchecked(0, 2);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_insert_unchecked_index_debug_assert() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        unsafe {
+            buf.insert_unchecked(2, 2);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_remove_unchecked_debug_assert() {
+        let mut buf = BoundedBuffer::<u8>::new(4);
+        unsafe {
+            buf.remove_unchecked(0);
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for _ in 0..6 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+
+        buf.truncate(10);
+        assert_eq!(buf.len(), 6);
+        buf.truncate(6);
+        assert_eq!(buf.len(), 6);
+        assert_eq!(drops.get(), 0);
+
+        buf.truncate(4);
+        assert_eq!(buf.len(), 4);
+        assert_eq!(drops.get(), 2);
+
+        buf.truncate(0);
+        assert!(buf.is_empty());
+        assert_eq!(drops.get(), 6);
+
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_truncate_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 3 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push(PanicOnDrop(i));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| buf.truncate(2)));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 2);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut buf = BoundedBuffer::new(5);
+        assert_eq!(buf.resize(0, String::from("x")), Ok(()));
+        assert!(buf.is_empty());
+
+        assert_eq!(buf.resize(5, String::from("a")), Ok(()));
+        assert_eq!(buf.as_slice(), ["a"; 5]);
+
+        assert_eq!(
+            buf.resize(6, String::from("b")),
+            Err(CapacityError::new(()))
+        );
+        assert_eq!(buf.len(), 5);
+
+        assert_eq!(buf.resize(5, String::from("c")), Ok(()));
+        assert_eq!(buf.as_slice(), ["a"; 5]);
+
+        assert_eq!(buf.resize(2, String::from("d")), Ok(()));
+        assert_eq!(buf.as_slice(), ["a", "a"]);
+
+        assert_eq!(buf.resize(3, String::from("e")), Ok(()));
+        assert_eq!(buf.as_slice(), ["a", "a", "e"]);
+    }
+
+    #[test]
+    fn test_resize_with() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        let mut next = 0;
+        let mut counter = || {
+            next += 1;
+            (next, DropCount(drops.clone()))
+        };
+
+        assert_eq!(buf.resize_with(3, &mut counter), Ok(()));
+        assert_eq!(buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(
+            buf.resize_with(5, &mut counter),
+            Err(CapacityError::new(()))
+        );
+        assert_eq!(buf.len(), 3);
+        assert_eq!(buf.resize_with(1, &mut counter), Ok(()));
+        assert_eq!(drops.get(), 2);
+        assert_eq!(buf.resize_with(4, &mut counter), Ok(()));
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 4, 5, 6]
+        );
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_resize_with_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        let mut calls = 0;
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.resize_with(6, || {
+                calls += 1;
+                if calls == 4 {
+                    panic!("fill failed");
+                }
+                DropCount(drops.clone())
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 3);
+        assert_eq!(drops.get(), 0);
+
+        drop(buf);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn test_drop_elements() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for _ in 0..6 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for _ in 0..6 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+        buf.clear();
+        assert_eq!(drops.get(), 6);
+        assert!(buf.is_empty());
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for _ in 0..8 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+        drop(buf.pop());
+        drop(buf.remove(0));
+        drop(buf.remove(3));
+        drop(buf.pop());
+        assert_eq!(drops.get(), 4);
+        assert_eq!(buf.len(), 4);
+        drop(buf);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_drop_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 2 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(5);
+        for i in 0..5 {
+            buf.try_push(PanicOnDrop(i));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(buf)));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..5 {
+            buf.try_push(i.to_string());
+        }
+
+        let mut cloned = buf.clone();
+        assert_eq!(cloned.capacity(), buf.capacity());
+        assert_eq!(cloned.as_slice(), buf.as_slice());
+        assert_ne!(cloned.as_slice().as_ptr(), buf.as_slice().as_ptr());
+
+        cloned.as_mut_slice()[0].push_str("-changed");
+        cloned.try_push("5".to_string());
+        assert_eq!(buf.as_slice(), ["0", "1", "2", "3", "4"]);
+        assert_eq!(cloned.as_slice(), ["0-changed", "1", "2", "3", "4", "5"]);
+
+        drop(buf);
+        drop(cloned);
+    }
+
+    #[test]
+    fn test_clone_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CLONES: AtomicUsize = AtomicUsize::new(0);
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnClone(usize);
+
+        impl Clone for PanicOnClone {
+            fn clone(&self) -> Self {
+                if self.0 == 3 {
+                    panic!("clone failed");
+                }
+                CLONES.fetch_add(1, Ordering::SeqCst);
+                Self(self.0)
+            }
+        }
+
+        impl Drop for PanicOnClone {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(5);
+        for i in 0..5 {
+            buf.try_push(PanicOnClone(i));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| buf.clone()));
+        assert!(result.is_err());
+        assert_eq!(CLONES.load(Ordering::SeqCst), 3);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_covariance() {
+        fn shorten<'a>(buf: BoundedBuffer<&'static str>) -> BoundedBuffer<&'a str> {
+            buf
+        }
+
+        let mut buf = BoundedBuffer::new(1);
+        buf.try_push("a");
+        assert_eq!(shorten(buf).as_slice(), ["a"]);
+    }
+
+    #[test]
+    fn test_bounded_array() {
+        let n = if cfg!(miri) { 100 } else { 10_000 };
+        let mut arr1 = BoundedBuffer::new(n);
+
+        let now = std::time::Instant::now();
+        for i in 0..n {
+            arr1.try_push(i);
+        }
+        let elapsed = now.elapsed().as_nanos();
+        println!("BoundedBuffer Len: {}", arr1.len());
+        println!("BoundedBuffer Push: {} ns/op", elapsed / n as u128);
+
+        let mut arr2 = Vec::with_capacity(n);
+
+        let now = std::time::Instant::now();
+        for i in 0..n {
+            arr2.push(i);
+        }
+
+        let elapsed = now.elapsed().as_nanos();
+        println!("Vec Len: {}", arr2.len());
+        println!("Vec Push: {} ns/op", elapsed / n as u128);
+
+        for i in 0..n {
+            let val1 = arr1.get(i);
+            let val2 = arr2.get(i);
+            assert!(val1.is_some());
+            assert!(val2.is_some());
+
+            let val1 = val1.unwrap();
+            let val2 = val2.unwrap();
+
+            assert_eq!(val1, val2);
+        }
+
+        let slice = arr1.as_slice();
+        for i in 0..n {
+            let val1 = slice[i];
+            let val2 = arr2[i];
+            assert_eq!(val1, val2);
+        }
+
+        for (val1, val2) in arr1.iter().zip(arr2.iter()) {
+            assert_eq!(val1, val2);
+        }
+
+        let mut sum = 0;
+        let now = std::time::Instant::now();
+        for i in 0..n {
+            sum += arr1.get(i).unwrap();
+        }
+        let elapsed = now.elapsed().as_nanos();
+        println!("BoundedBuffer Get: {} ns/op", elapsed / n as u128);
+        println!("Sum: {}", sum);
+
+        let mut sum = 0;
+        let now = std::time::Instant::now();
+        for i in 0..n {
+            sum += arr2.get(i).unwrap();
+        }
+
+        let elapsed = now.elapsed().as_nanos();
+        println!("Vec Get: {} ns/op", elapsed / n as u128);
+        println!("Sum: {}", sum);
+
+        arr1.clear();
+        arr2.clear();
+
+        let now = std::time::Instant::now();
+        for i in 0..n {
+            arr1.try_insert(0, i);
+        }
+        let elapsed = now.elapsed().as_nanos();
+        println!("BoundedBuffer Try Insert: {} ns/op", elapsed / n as u128);
+
+        let now = std::time::Instant::now();
+        for i in 0..n {
+            arr2.insert(0, i);
+        }
+        let elapsed = now.elapsed().as_nanos();
+        println!("Vec Insert: {} ns/op", elapsed / n as u128);
+    }
+
+    #[test]
+    fn test_try_extend_from_slice() {
+        let mut buf = BoundedBuffer::new(4);
+        assert_eq!(buf.try_extend_from_slice(&[]), Ok(()));
+        assert!(buf.is_empty());
+
+        let src = [String::from("a"), String::from("b"), String::from("c")];
+        assert_eq!(buf.try_extend_from_slice(&src[..1]), Ok(()));
+        assert_eq!(buf.try_extend_from_slice(&src), Ok(()));
+        assert_eq!(buf.as_slice(), ["a", "a", "b", "c"]);
+
+        assert_eq!(buf.try_extend_from_slice(&[]), Ok(()));
+        assert_eq!(
+            buf.try_extend_from_slice(&src[..1]),
+            Err(CapacityError::new(()))
+        );
+        assert_eq!(buf.len(), 4);
+
+        let mut buf = BoundedBuffer::new(2);
+        assert_eq!(
+            buf.try_extend_from_slice(&[1, 2, 3]),
+            Err(CapacityError::new(()))
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_try_extend_from_copy_slice() {
+        let mut buf = BoundedBuffer::new(4);
+        assert_eq!(buf.try_extend_from_copy_slice(&[]), Ok(()));
+        assert_eq!(buf.try_extend_from_copy_slice(&[1u32, 2]), Ok(()));
+        assert_eq!(buf.try_extend_from_copy_slice(&[3]), Ok(()));
+        assert_eq!(buf.as_slice(), [1, 2, 3]);
+
+        assert_eq!(
+            buf.try_extend_from_copy_slice(&[4, 5]),
+            Err(CapacityError::new(()))
+        );
+        assert_eq!(buf.as_slice(), [1, 2, 3]);
+        assert_eq!(buf.try_extend_from_copy_slice(&[4]), Ok(()));
+        assert_eq!(buf.as_slice(), [1, 2, 3, 4]);
+
+        let mut zst = BoundedBuffer::new(3);
+        assert_eq!(zst.try_extend_from_copy_slice(&[(); 3]), Ok(()));
+        assert_eq!(zst.len(), 3);
+    }
+
+    #[test]
+    fn test_extend_from_slice_truncating() {
+        let mut buf = BoundedBuffer::new(5);
+        assert_eq!(buf.extend_from_slice_truncating(&[]), 0);
+        assert_eq!(buf.extend_from_slice_truncating(&[1, 2, 3]), 3);
+        assert_eq!(buf.extend_from_slice_truncating(&[4, 5, 6]), 2);
+        assert_eq!(buf.extend_from_slice_truncating(&[7]), 0);
+        assert_eq!(buf.as_slice(), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_extend_from_slice_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnClone(usize);
+
+        impl Clone for PanicOnClone {
+            fn clone(&self) -> Self {
+                if self.0 == 2 {
+                    panic!("clone failed");
+                }
+                Self(self.0)
+            }
+        }
+
+        impl Drop for PanicOnClone {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let src: Vec<_> = (0..4).map(PanicOnClone).collect();
+        let mut buf = BoundedBuffer::new(4);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.try_extend_from_slice(&src)
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 2);
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_extend_from_slice_timing() {
+        let n = if cfg!(miri) { 100 } else { 10_000 };
+        let src: Vec<u64> = (0..n as u64).collect();
+
+        let mut arr1 = BoundedBuffer::new(n);
+        let now = std::time::Instant::now();
+        for &i in &src {
+            arr1.try_push(i);
+        }
+        let elapsed = now.elapsed().as_nanos();
+        println!("BoundedBuffer Push Loop: {} ns/op", elapsed / n as u128);
+
+        let mut arr2 = BoundedBuffer::new(n);
+        let now = std::time::Instant::now();
+        arr2.try_extend_from_slice(&src).unwrap();
+        let elapsed = now.elapsed().as_nanos();
+        println!(
+            "BoundedBuffer Extend From Slice: {} ns/op",
+            elapsed / n as u128
+        );
+
+        assert_eq!(arr1.as_slice(), arr2.as_slice());
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..6 {
+            buf.try_push(i.to_string());
+        }
+
+        let drained: Vec<_> = buf.drain(..).collect();
+        assert_eq!(drained, ["0", "1", "2", "3", "4", "5"]);
+        assert!(buf.is_empty());
+
+        for i in 0..6 {
+            buf.try_push(i.to_string());
+        }
+        let mut drain = buf.drain(1..4);
+        assert_eq!(drain.len(), 3);
+        assert_eq!(drain.next_back().as_deref(), Some("3"));
+        assert_eq!(drain.next().as_deref(), Some("1"));
+        assert_eq!(drain.as_slice(), ["2"]);
+        assert_eq!(drain.next().as_deref(), Some("2"));
+        assert_eq!(drain.next(), None);
+        drop(drain);
+        assert_eq!(buf.as_slice(), ["0", "4", "5"]);
+
+        let drained: Vec<_> = buf.drain(1..=1).collect();
+        assert_eq!(drained, ["4"]);
+        assert_eq!(buf.as_slice(), ["0", "5"]);
+
+        assert_eq!(buf.drain(2..).count(), 0);
+        assert_eq!(buf.drain(..0).count(), 0);
+        assert_eq!(buf.as_slice(), ["0", "5"]);
+    }
+
+    #[test]
+    fn test_drain_partial() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..8 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let mut drain = buf.drain(2..6);
+        assert_eq!(drain.next().map(|(i, _)| i), Some(2));
+        assert_eq!(drops.get(), 1);
+        drop(drain);
+        assert_eq!(drops.get(), 4);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 1, 6, 7]
+        );
+
+        drop(buf);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_drain_forget() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Counted through a static so the leaked elements don't leak heap
+        // memory, which Miri would report.
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted(usize);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..8 {
+            buf.try_push(Counted(i));
+        }
+
+        let mut drain = buf.drain(3..5);
+        drain.next();
+        std::mem::forget(drain);
+        assert_eq!(buf.iter().map(|elem| elem.0).collect::<Vec<_>>(), [0, 1, 2]);
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_drain_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 2 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push(PanicOnDrop(i));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.drain(1..4);
+        }));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+        assert_eq!(buf.iter().map(|elem| elem.0).collect::<Vec<_>>(), [0, 4, 5]);
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "range end index 5 out of range for slice of length 4")]
+    fn test_drain_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(8);
+        buf.resize(4, 0).unwrap();
+        buf.drain(2..5);
+    }
+
+    #[test]
+    #[should_panic(expected = "slice index starts at 3 but ends at 2")]
+    fn test_drain_inverted_range() {
+        let mut buf = BoundedBuffer::new(8);
+        buf.resize(4, 0).unwrap();
+        #[allow(clippy::reversed_empty_ranges)]
+        buf.drain(3..2);
+    }
+
+    #[test]
+    fn test_retain() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(10);
+        for i in 0..10 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        buf.retain(|(i, _)| i % 3 != 0);
+        assert_eq!(drops.get(), 4);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2, 4, 5, 7, 8]
+        );
+
+        buf.retain(|_| true);
+        assert_eq!(buf.len(), 6);
+        buf.retain(|_| false);
+        assert!(buf.is_empty());
+        assert_eq!(drops.get(), 10);
+
+        buf.retain(|_| unreachable!());
+    }
+
+    #[test]
+    fn test_retain_mut() {
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push(i.to_string());
+        }
+
+        buf.retain_mut(|s| {
+            s.push('!');
+            s != "2!" && s != "5!"
+        });
+        assert_eq!(buf.as_slice(), ["0!", "1!", "3!", "4!"]);
+    }
+
+    #[test]
+    fn test_retain_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..8 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.retain(|(i, _)| {
+                if *i == 5 {
+                    panic!("predicate failed");
+                }
+                i % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 2);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 2, 4, 5, 6, 7]
+        );
+
+        drop(buf);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_retain_drop_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 3 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push(PanicOnDrop(i));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.retain(|elem| elem.0 % 2 == 0)
+        }));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            buf.iter().map(|elem| elem.0).collect::<Vec<_>>(),
+            [0, 2, 4, 5]
+        );
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_dedup() {
+        fn strings(items: &[&str]) -> BoundedBuffer<String> {
+            let mut buf = BoundedBuffer::new(items.len());
+            for item in items {
+                buf.try_push(item.to_string());
+            }
+            buf
+        }
+
+        let mut buf = strings(&[]);
+        buf.dedup();
+        assert!(buf.is_empty());
+
+        let mut buf = strings(&["a"]);
+        buf.dedup();
+        assert_eq!(buf.as_slice(), ["a"]);
+
+        let mut buf = strings(&["a", "a", "a", "b", "c", "c", "b", "d", "d"]);
+        buf.dedup();
+        assert_eq!(buf.as_slice(), ["a", "b", "c", "b", "d"]);
+
+        let mut buf = strings(&["a", "b", "a", "b"]);
+        buf.dedup();
+        assert_eq!(buf.as_slice(), ["a", "b", "a", "b"]);
+
+        let mut buf = strings(&["x", "x", "x"]);
+        buf.dedup();
+        assert_eq!(buf.as_slice(), ["x"]);
+    }
+
+    #[test]
+    fn test_dedup_by_key() {
+        let mut buf = BoundedBuffer::new(8);
+        for s in [
+            "apple",
+            "avocado",
+            "banana",
+            "blueberry",
+            "cherry",
+            "apricot",
+        ] {
+            buf.try_push(s.to_string());
+        }
+        buf.dedup_by_key(|s| s.chars().next());
+        assert_eq!(buf.as_slice(), ["apple", "banana", "cherry", "apricot"]);
+
+        let mut buf = BoundedBuffer::new(8);
+        for s in ["Foo", "foo", "BAR", "Bar", "baz"] {
+            buf.try_push(s.to_string());
+        }
+        buf.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        assert_eq!(buf.as_slice(), ["Foo", "BAR", "baz"]);
+    }
+
+    #[test]
+    fn test_dedup_drops() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in [1, 1, 2, 3, 3, 3, 4, 4] {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        buf.dedup_by_key(|(i, _)| *i);
+        assert_eq!(drops.get(), 4);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+
+        drop(buf);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_dedup_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in [1, 1, 2, 2, 3, 3, 4, 4] {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.dedup_by(|a, b| {
+                if a.0 == 3 {
+                    panic!("comparison failed");
+                }
+                a.0 == b.0
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 2);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2, 3, 3, 4, 4]
+        );
+
+        drop(buf);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_split_off() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..6 {
+            buf.try_push((i.to_string(), DropCount(drops.clone())));
+        }
+
+        let other = buf.split_off(4);
+        assert_eq!(other.capacity(), 2);
+        assert_eq!(buf.capacity(), 8);
+        assert_eq!(
+            buf.iter().map(|(s, _)| s.as_str()).collect::<Vec<_>>(),
+            ["0", "1", "2", "3"]
+        );
+        assert_eq!(
+            other.iter().map(|(s, _)| s.as_str()).collect::<Vec<_>>(),
+            ["4", "5"]
+        );
+        assert_eq!(drops.get(), 0);
+
+        let all = buf.split_off_with_capacity(0, buf.capacity());
+        assert_eq!(all.capacity(), 8);
+        assert_eq!(all.len(), 4);
+        assert!(buf.is_empty());
+
+        let empty = buf.split_off(0);
+        assert_eq!(empty.capacity(), 0);
+        assert!(empty.is_empty());
+
+        drop(buf);
+        drop(other);
+        assert_eq!(drops.get(), 2);
+        drop(all);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_split_off_at_len() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(String::from("a"));
+        let other = buf.split_off_with_capacity(1, 4);
+        assert_eq!(buf.as_slice(), ["a"]);
+        assert!(other.is_empty());
+        assert_eq!(other.capacity(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "`at` split index (is 2) should be <= len (is 1)")]
+    fn test_split_off_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        buf.split_off(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity (is 1) should be >= split length (is 3)")]
+    fn test_split_off_small_capacity() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.resize(4, 1).unwrap();
+        buf.split_off_with_capacity(1, 1);
+    }
+
+    #[test]
+    fn test_append() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        let mut other = BoundedBuffer::new(4);
+        for i in 0..2 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+        for i in 2..6 {
+            other.try_push((i, DropCount(drops.clone())));
+        }
+
+        buf.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(other.capacity(), 4);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(drops.get(), 0);
+
+        buf.append(&mut other);
+        assert_eq!(buf.len(), 6);
+
+        drop(other);
+        assert_eq!(drops.get(), 0);
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_try_append_overflow() {
+        let mut buf = BoundedBuffer::new(3);
+        let mut other = BoundedBuffer::new(3);
+        buf.try_push(String::from("a"));
+        buf.try_push(String::from("b"));
+        other.try_push(String::from("c"));
+        other.try_push(String::from("d"));
+
+        assert_eq!(buf.try_append(&mut other), Err(CapacityError::new(())));
+        assert_eq!(buf.as_slice(), ["a", "b"]);
+        assert_eq!(other.as_slice(), ["c", "d"]);
+
+        other.pop();
+        assert_eq!(buf.try_append(&mut other), Ok(()));
+        assert_eq!(buf.as_slice(), ["a", "b", "c"]);
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "appended length (is 2) should be <= spare capacity (is 1)")]
+    fn test_append_overflow() {
+        let mut buf = BoundedBuffer::new(2);
+        let mut other = BoundedBuffer::new(2);
+        buf.try_push(1);
+        other.resize(2, 2).unwrap();
+        buf.append(&mut other);
+    }
+
+    #[test]
+    fn test_insert_sorted() {
+        let mut buf = BoundedBuffer::new(5);
+        assert_eq!(buf.insert_sorted(3), Ok(0));
+        assert_eq!(buf.insert_sorted(1), Ok(0));
+        assert_eq!(buf.insert_sorted(5), Ok(2));
+        assert_eq!(buf.insert_sorted(3), Ok(2));
+        assert_eq!(buf.insert_sorted(4), Ok(3));
+        assert_eq!(buf.as_slice(), [1, 3, 3, 4, 5]);
+        assert_eq!(buf.insert_sorted(2), Err(CapacityError::new(2)));
+        assert_eq!(buf.as_slice(), [1, 3, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_insert_sorted_by_key() {
+        let mut buf = BoundedBuffer::new(4);
+        for (key, name) in [(2, "b"), (1, "a"), (2, "c"), (0, "z")] {
+            buf.insert_sorted_by_key((key, name.to_string()), |(key, _)| *key)
+                .unwrap();
+        }
+        assert_eq!(
+            buf.iter()
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["z", "a", "b", "c"]
+        );
+
+        let err = buf
+            .insert_sorted_by_key((1, String::from("y")), |(key, _)| *key)
+            .unwrap_err();
+        assert_eq!(err.element(), (1, String::from("y")));
+    }
+
+    #[test]
+    fn test_find_or_insert_sorted_with() {
+        let mut buf: BoundedBuffer<(u32, u32)> = BoundedBuffer::new(3);
+        for key in [5, 1, 5, 3, 1, 5] {
+            let entry = buf
+                .find_or_insert_sorted_with(|probe| probe.0.cmp(&key), || (key, 0))
+                .unwrap();
+            entry.1 += 1;
+        }
+        assert_eq!(buf.as_slice(), [(1, 2), (3, 1), (5, 3)]);
+
+        let entry = buf
+            .find_or_insert_sorted_with(|probe| probe.0.cmp(&3), || unreachable!())
+            .unwrap();
+        assert_eq!(*entry, (3, 1));
+
+        assert!(buf
+            .find_or_insert_sorted_with(|probe| probe.0.cmp(&4), || (4, 0))
+            .is_err());
+        assert_eq!(buf.len(), 3);
+    }
+
+    #[test]
+    fn test_insert_sorted_random() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..50 {
+            let cap = (xorshift(&mut state) % 32) as usize;
+            let mut buf = BoundedBuffer::new(cap);
+            let mut model = Vec::new();
+            for _ in 0..40 {
+                let value = xorshift(&mut state) % 16;
+                match buf.insert_sorted(value) {
+                    Ok(index) => {
+                        assert_eq!(buf[index], value);
+                        model.push(value);
+                    }
+                    Err(err) => {
+                        assert_eq!(buf.len(), cap);
+                        assert_eq!(err.element(), value);
+                    }
+                }
+                assert!(buf.windows(2).all(|pair| pair[0] <= pair[1]));
+            }
+            model.sort();
+            assert_eq!(buf.as_slice(), model);
+        }
+    }
+
+    #[test]
+    fn test_fill_to_capacity() {
+        let mut buf = BoundedBuffer::new(5);
+        buf.try_push(String::from("a"));
+        buf.fill_to_capacity(String::from("b"));
+        assert_eq!(buf.as_slice(), ["a", "b", "b", "b", "b"]);
+
+        buf.fill_to_capacity(String::from("c"));
+        assert_eq!(buf.len(), 5);
+
+        buf.fill(String::from("d"));
+        assert_eq!(buf.as_slice(), ["d"; 5]);
+
+        let mut buf = BoundedBuffer::<u8>::new(0);
+        buf.fill_to_capacity(1);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_fill_with_to_capacity() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(0);
+        let mut next = 0;
+        buf.fill_with_to_capacity(|| {
+            next += 10;
+            next
+        });
+        assert_eq!(buf.as_slice(), [0, 10, 20, 30]);
+
+        buf.fill_with_to_capacity(|| unreachable!());
+        assert_eq!(buf.len(), 4);
+    }
+
+    #[test]
+    fn test_fill_with_to_capacity_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        buf.try_push(DropCount(drops.clone()));
+        let mut calls = 0;
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.fill_with_to_capacity(|| {
+                calls += 1;
+                if calls == 3 {
+                    panic!("fill failed");
+                }
+                DropCount(drops.clone())
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 3);
+
+        drop(buf);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn test_fill_to_capacity_timing() {
+        fn time<T: Copy + std::fmt::Debug + PartialEq>(name: &str, n: usize, value: T) {
+            // Touch every page up front so neither loop pays for page faults.
+            let mut arr = BoundedBuffer::new(n);
+            arr.fill_to_capacity(value);
+            arr.clear();
+
+            let now = std::time::Instant::now();
+            while arr.try_push(value) {}
+            let elapsed = now.elapsed().as_nanos();
+            println!(
+                "BoundedBuffer<{name}> Push Loop: {:.2} ns/op",
+                elapsed as f64 / n as f64
+            );
+            assert_eq!(arr.len(), n);
+            arr.clear();
+
+            let now = std::time::Instant::now();
+            arr.fill_to_capacity(value);
+            let elapsed = now.elapsed().as_nanos();
+            println!(
+                "BoundedBuffer<{name}> Fill To Capacity: {:.2} ns/op",
+                elapsed as f64 / n as f64
+            );
+            assert!(arr.iter().all(|elem| *elem == value));
+        }
+
+        let n = if cfg!(miri) { 100 } else { 100_000 };
+        time("u8", n, 7u8);
+        time("u64", n, 7u64);
+    }
+
+    #[test]
+    fn test_spare_capacity_mut() {
+        use std::io::Read;
+
+        let mut reader: &[u8] = b"hello, world";
+        let mut buf = BoundedBuffer::<u8>::new(8);
+        buf.try_push(b'>');
+
+        let spare = buf.spare_capacity_mut();
+        assert_eq!(spare.len(), 7);
+        for slot in spare.iter_mut() {
+            slot.write(0);
+        }
+        let spare = unsafe { &mut *(spare as *mut [std::mem::MaybeUninit<u8>] as *mut [u8]) };
+        let read = reader.read(spare).unwrap();
+        assert_eq!(read, 7);
+        unsafe {
+            buf.set_len(buf.len() + read);
+        }
+        assert_eq!(buf.as_slice(), b">hello, ");
+        assert!(buf.spare_capacity_mut().is_empty());
+    }
+
+    #[test]
+    fn test_spare_capacity_mut_strings() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(String::from("a"));
+
+        let spare = buf.spare_capacity_mut();
+        spare[0].write(String::from("b"));
+        spare[1].write(String::from("c"));
+        unsafe {
+            buf.set_len(3);
+        }
+        assert_eq!(buf.as_slice(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "length exceeds capacity")]
+    fn test_set_len_debug_assert() {
+        let mut buf = BoundedBuffer::<u8>::new(4);
+        unsafe {
+            buf.set_len(5);
+        }
+    }
+
+    #[test]
+    fn test_raw_parts() {
+        extern "C" fn sum(ptr: *const u32, len: usize) -> u32 {
+            unsafe { std::slice::from_raw_parts(ptr, len).iter().sum() }
+        }
+
+        extern "C" fn fill(ptr: *mut u32, len: usize, cap: usize) -> usize {
+            for i in len..cap {
+                unsafe {
+                    ptr.add(i).write(i as u32 * 10);
+                }
+            }
+            cap
+        }
+
+        let mut buf = BoundedBuffer::new(6);
+        buf.try_push(1u32);
+        buf.try_push(2);
+        assert_eq!(sum(buf.as_ptr(), buf.len()), 3);
+
+        let (len, cap) = (buf.len(), buf.capacity());
+        let new_len = fill(buf.as_mut_ptr(), len, cap);
+        unsafe {
+            buf.set_len(new_len);
+        }
+        assert_eq!(buf.as_slice(), [1, 2, 20, 30, 40, 50]);
+
+        let (ptr, len, cap) = buf.into_raw_parts();
+        assert_eq!((len, cap), (6, 6));
+        let buf = unsafe { BoundedBuffer::from_raw_parts(ptr, len, cap) };
+        assert_eq!(buf.as_ptr(), ptr.as_ptr() as *const u32);
+        assert_eq!(buf.as_slice(), [1, 2, 20, 30, 40, 50]);
+    }
+
+    #[test]
+    fn test_raw_parts_drops() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for _ in 0..3 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+
+        let (ptr, len, cap) = buf.into_raw_parts();
+        assert_eq!(drops.get(), 0);
+        let buf = unsafe { BoundedBuffer::from_raw_parts(ptr, len, cap) };
+        assert_eq!(buf.len(), 3);
+        drop(buf);
+        assert_eq!(drops.get(), 3);
+
+        let (ptr, len, cap) = BoundedBuffer::<()>::new(3).into_raw_parts();
+        drop(unsafe { BoundedBuffer::from_raw_parts(ptr, len, cap) });
+        let (ptr, len, cap) = BoundedBuffer::<u64>::new(0).into_raw_parts();
+        drop(unsafe { BoundedBuffer::from_raw_parts(ptr, len, cap) });
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for _ in 0..5 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+        let ptr = buf.as_ptr();
+
+        let vec = buf.into_vec();
+        assert_eq!(vec.as_ptr(), ptr);
+        assert_eq!(vec.len(), 5);
+        assert_eq!(vec.capacity(), 8);
+        assert_eq!(drops.get(), 0);
+
+        let buf = BoundedBuffer::from_vec(vec);
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(buf.len(), 5);
+        assert_eq!(buf.capacity(), 8);
+
+        let vec: Vec<_> = buf.into();
+        assert_eq!(vec.as_ptr(), ptr);
+        drop(vec);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn test_from_vec() {
+        let mut vec = Vec::with_capacity(10);
+        vec.extend(["a", "b", "c"].map(String::from));
+        let ptr = vec.as_ptr();
+
+        let mut buf = BoundedBuffer::from_vec(vec);
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(buf.capacity(), 10);
+        assert!(buf.try_push(String::from("d")));
+        assert_eq!(buf.as_slice(), ["a", "b", "c", "d"]);
+
+        let buf = BoundedBuffer::from_vec(Vec::<u64>::new());
+        assert_eq!(buf.capacity(), 0);
+        assert!(buf.into_vec().is_empty());
+
+        let buf = BoundedBuffer::from_vec(vec![(); 3]);
+        assert_eq!(buf.len(), 3);
+        assert_eq!(buf.into_vec().len(), 3);
+    }
+
+    #[test]
+    fn test_from_slice() {
+        let src = ["a", "b", "c"].map(String::from);
+
+        let buf = BoundedBuffer::try_from(&src[..]).unwrap();
+        assert_eq!(buf.capacity(), 3);
+        assert_eq!(buf.as_slice(), src);
+
+        let mut buf = BoundedBuffer::from_slice_with_capacity(&src, 5).unwrap();
+        assert_eq!(buf.capacity(), 5);
+        assert!(buf.try_push(String::from("d")));
+        assert_eq!(buf.as_slice(), ["a", "b", "c", "d"]);
+
+        let err = BoundedBuffer::from_slice_with_capacity(&src, 2).unwrap_err();
+        assert_eq!(err.element(), &src[..]);
+
+        let buf = BoundedBuffer::<String>::try_from(&[][..]).unwrap();
+        assert_eq!(buf.capacity(), 0);
+    }
+
+    #[test]
+    fn test_from_array() {
+        let drops = Drops::default();
+        let arr = [(); 4].map(|_| DropCount(drops.clone()));
+        let buf = BoundedBuffer::from(arr);
+        assert_eq!(buf.len(), 4);
+        assert_eq!(buf.capacity(), 4);
+        assert_eq!(drops.get(), 0);
+        drop(buf);
+        assert_eq!(drops.get(), 4);
+
+        let buf = BoundedBuffer::from(["x", "y"].map(String::from));
+        assert_eq!(buf.as_slice(), ["x", "y"]);
+
+        let buf = BoundedBuffer::from([0u8; 0]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_vec_round_trip() {
+        let vec: Vec<_> = (0..4).map(|i| i.to_string()).collect();
+        let buf = BoundedBuffer::from(vec.clone());
+        let back = Vec::from(buf);
+        assert_eq!(back, vec);
+
+        let buf = BoundedBuffer::try_from(back.as_slice()).unwrap();
+        let again: Vec<String> = buf.into();
+        assert_eq!(again, vec);
+    }
+
+    #[test]
+    fn test_get_disjoint_mut() {
+        use std::slice::GetDisjointMutError;
+
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..5 {
+            buf.try_push(i.to_string());
+        }
+
+        let [a, b] = buf.get_disjoint_mut([3, 1]).unwrap();
+        std::mem::swap(a, b);
+        assert_eq!(buf.as_slice(), ["0", "3", "2", "1", "4"]);
+
+        let [a, b, c] = buf.get_disjoint_mut([4, 0, 2]).unwrap();
+        a.push('a');
+        b.push('b');
+        c.push('c');
+        assert_eq!(buf.as_slice(), ["0b", "3", "2c", "1", "4a"]);
+
+        assert_eq!(
+            buf.get_disjoint_mut([1, 1]).unwrap_err(),
+            GetDisjointMutError::OverlappingIndices
+        );
+        assert_eq!(
+            buf.get_disjoint_mut([0, 5]).unwrap_err(),
+            GetDisjointMutError::IndexOutOfBounds
+        );
+
+        let [] = buf.get_disjoint_mut([]).unwrap();
+
+        let [a, b] = unsafe { buf.get_disjoint_unchecked_mut([0, 1]) };
+        std::mem::swap(a, b);
+        assert_eq!(buf.as_slice(), ["3", "0b", "2c", "1", "4a"]);
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let mut buf = BoundedBuffer::new(10);
+        for i in 0..10 {
+            buf.try_push(i.to_string());
+        }
+
+        let extracted: Vec<_> = buf
+            .extract_if(|s| s.parse::<u32>().unwrap() % 3 == 0)
+            .collect();
+        assert_eq!(extracted, ["0", "3", "6", "9"]);
+        assert_eq!(buf.as_slice(), ["1", "2", "4", "5", "7", "8"]);
+
+        let extracted: Vec<_> = buf.extract_if(|_| false).collect();
+        assert!(extracted.is_empty());
+        assert_eq!(buf.len(), 6);
+
+        let extracted: Vec<_> = buf
+            .extract_if(|s| {
+                s.push('!');
+                s.len() > 5
+            })
+            .collect();
+        assert!(extracted.is_empty());
+        assert_eq!(buf.as_slice(), ["1!", "2!", "4!", "5!", "7!", "8!"]);
+
+        assert_eq!(buf.extract_if(|_| true).count(), 6);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_extract_if_early_drop() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..8 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let mut iter = buf.extract_if(|(i, _)| *i % 2 == 1);
+        assert_eq!(iter.next().map(|(i, _)| i), Some(1));
+        assert_eq!(iter.next().map(|(i, _)| i), Some(3));
+        drop(iter);
+        assert_eq!(drops.get(), 2);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 2, 4, 5, 6, 7]
+        );
+
+        drop(buf);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_extract_if_forget() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted(usize);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..8 {
+            buf.try_push(Counted(i));
+        }
+
+        let mut iter = buf.extract_if(|elem| elem.0 == 2);
+        assert_eq!(iter.next().map(|elem| elem.0), Some(2));
+        std::mem::forget(iter);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        assert_eq!(buf.iter().map(|elem| elem.0).collect::<Vec<_>>(), [0, 1]);
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_extract_if_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.extract_if(|(i, _)| {
+                if *i == 3 {
+                    panic!("predicate failed");
+                }
+                *i == 1
+            })
+            .for_each(drop)
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 1);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 2, 3, 4, 5]
+        );
+
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_splice_shrink() {
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..6 {
+            buf.try_push(i.to_string());
+        }
+
+        let removed: Vec<_> = buf.splice(1..4, [String::from("x")]).unwrap().collect();
+        assert_eq!(removed, ["1", "2", "3"]);
+        assert_eq!(buf.as_slice(), ["0", "x", "4", "5"]);
+
+        let removed: Vec<_> = buf.splice(.., []).unwrap().collect();
+        assert_eq!(removed, ["0", "x", "4", "5"]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_splice_grow() {
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..4 {
+            buf.try_push(i.to_string());
+        }
+
+        let removed: Vec<_> = buf
+            .splice(1..2, ["a", "b", "c"].map(String::from))
+            .unwrap()
+            .collect();
+        assert_eq!(removed, ["1"]);
+        assert_eq!(buf.as_slice(), ["0", "a", "b", "c", "2", "3"]);
+
+        let removed: Vec<_> = buf.splice(6.., []).unwrap().collect();
+        assert!(removed.is_empty());
+        assert_eq!(buf.len(), 6);
+    }
+
+    #[test]
+    fn test_splice_overflow() {
+        let mut buf = BoundedBuffer::new(5);
+        for i in 0..4 {
+            buf.try_push(i.to_string());
+        }
+
+        let err = buf
+            .splice(1..2, ["a", "b", "c"].map(String::from))
+            .unwrap_err();
+        assert_eq!(err.element().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(buf.as_slice(), ["0", "1", "2", "3"]);
+
+        let removed: Vec<_> = buf
+            .splice(1..2, ["a", "b"].map(String::from))
+            .unwrap()
+            .collect();
+        assert_eq!(removed, ["1"]);
+        assert_eq!(buf.as_slice(), ["0", "a", "b", "2", "3"]);
+    }
+
+    #[test]
+    fn test_splice_early_drop() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..6 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let replacement: Vec<_> = (10..14).map(|i| (i, DropCount(drops.clone()))).collect();
+        let mut splice = buf.splice(2..4, replacement).unwrap();
+        assert_eq!(splice.next().map(|(i, _)| i), Some(2));
+        drop(splice);
+        assert_eq!(drops.get(), 2);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 1, 10, 11, 12, 13, 4, 5]
+        );
+
+        drop(buf);
+        assert_eq!(drops.get(), 10);
+    }
+
+    #[test]
+    fn test_splice_short_replacement() {
+        // Reports four elements but only yields two.
+        struct Liar(std::vec::IntoIter<String>);
+
+        impl Iterator for Liar {
+            type Item = String;
+
+            fn next(&mut self) -> Option<String> {
+                self.0.next()
+            }
+        }
+
+        impl ExactSizeIterator for Liar {
+            fn len(&self) -> usize {
+                4
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..4 {
+            buf.try_push(i.to_string());
+        }
+        let replacement = Liar(vec![String::from("a"), String::from("b")].into_iter());
+        drop(buf.splice(1..2, replacement).unwrap());
+        assert_eq!(buf.as_slice(), ["0", "a", "b", "2", "3"]);
+    }
+
+    #[test]
+    fn test_splice_panic() {
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..4 {
+            buf.try_push(i.to_string());
+        }
+
+        let replacement = (0..3).map(|i| {
+            if i == 1 {
+                panic!("replacement failed");
+            }
+            String::from("r")
+        });
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            drop(buf.splice(1..3, replacement).unwrap());
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.as_slice(), ["0", "r", "3"]);
+    }
+
+    #[test]
+    fn test_replace() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for i in 0..3 {
+            buf.try_push((i.to_string(), DropCount(drops.clone())));
+        }
+
+        let old = buf.replace(1, (String::from("x"), DropCount(drops.clone())));
+        assert_eq!(old.0, "1");
+        drop(old);
+        assert_eq!(drops.get(), 1);
+
+        let old = buf.try_replace(2, (String::from("y"), DropCount(drops.clone())));
+        assert_eq!(old.map(|(s, _)| s).as_deref(), Some("2"));
+        assert!(buf
+            .try_replace(3, (String::from("z"), DropCount(drops.clone())))
+            .is_none());
+        assert_eq!(drops.get(), 3);
+
+        assert_eq!(
+            buf.iter().map(|(s, _)| s.as_str()).collect::<Vec<_>>(),
+            ["0", "x", "y"]
+        );
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_replace_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        buf.replace(1, 2);
+    }
+
+    #[test]
+    fn test_map_in_place() {
+        let mut buf = BoundedBuffer::new(4);
+        for s in ["a", "b", "c"] {
+            buf.try_push(String::from(s));
+        }
+
+        buf.map_in_place(1, |s| s + "!");
+        buf.map_in_place(0, |s| s.repeat(3));
+        assert_eq!(buf.as_slice(), ["aaa", "b!", "c"]);
+    }
+
+    #[test]
+    fn test_map_in_place_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for i in 0..4 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.map_in_place(1, |_| panic!("map failed"));
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 1);
+        assert_eq!(buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 2, 3]);
+
+        drop(buf);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_clone_from() {
+        let mut source = BoundedBuffer::new(4);
+        for s in ["a", "b", "c"] {
+            source.try_push(String::from(s));
+        }
+
+        let mut dest = BoundedBuffer::new(8);
+        dest.try_push(String::from("x"));
+        let ptr = dest.as_ptr();
+        dest.clone_from(&source);
+        assert_eq!(dest.as_ptr(), ptr);
+        assert_eq!(dest.capacity(), 8);
+        assert_eq!(dest.as_slice(), ["a", "b", "c"]);
+
+        source.pop();
+        dest.clone_from(&source);
+        assert_eq!(dest.as_ptr(), ptr);
+        assert_eq!(dest.as_slice(), ["a", "b"]);
+
+        let mut small = BoundedBuffer::new(1);
+        small.try_push(String::from("y"));
+        source.try_push(String::from("z"));
+        small.clone_from(&source);
+        assert_eq!(small.capacity(), 4);
+        assert_eq!(small.as_slice(), ["a", "b", "z"]);
+    }
+
+    #[test]
+    fn test_clone_from_drops() {
+        let drops = Drops::default();
+        let mut source = BoundedBuffer::new(4);
+        for _ in 0..2 {
+            source.try_push(DropCount(drops.clone()));
+        }
+        let mut dest = BoundedBuffer::new(4);
+        for _ in 0..4 {
+            dest.try_push(DropCount(drops.clone()));
+        }
+
+        // Two elements truncated away, two overwritten by `clone_from`.
+        dest.clone_from(&source);
+        assert_eq!(drops.get(), 4);
+        assert_eq!(dest.len(), 2);
+
+        drop(dest);
+        drop(source);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_split_at_spare_mut() {
+        let mut buf = BoundedBuffer::new(5);
+        buf.try_push(String::from("a"));
+        buf.try_push(String::from("b"));
+
+        let (init, spare) = buf.split_at_spare_mut();
+        assert_eq!(spare.len(), 3);
+        for (src, dst) in init.iter_mut().zip(spare.iter_mut()) {
+            src.push('!');
+            dst.write(src.repeat(2));
+        }
+        unsafe {
+            buf.set_len(4);
+        }
+        assert_eq!(buf.as_slice(), ["a!", "b!", "a!a!", "b!b!"]);
+
+        let (init, spare) = buf.split_at_spare_mut();
+        assert_eq!(init.len(), 4);
+        spare[0].write(String::from("c"));
+        unsafe {
+            buf.set_len(5);
+        }
+        let (init, spare) = buf.split_at_spare_mut();
+        assert_eq!(init.len(), 5);
+        assert!(spare.is_empty());
+    }
+
+    #[test]
+    fn test_extend_from_within() {
+        let mut buf = BoundedBuffer::new(8);
+        buf.try_extend_from_slice(&[1, 2, 3]).unwrap();
+
+        buf.extend_from_within(1..).unwrap();
+        assert_eq!(buf.as_slice(), [1, 2, 3, 2, 3]);
+        buf.extend_from_within(2..2).unwrap();
+        assert_eq!(buf.as_slice(), [1, 2, 3, 2, 3]);
+        buf.extend_from_within(..=2).unwrap();
+        assert_eq!(buf.as_slice(), [1, 2, 3, 2, 3, 1, 2, 3]);
+
+        assert!(buf.extend_from_within(..1).is_err());
+        buf.extend_from_within(..0).unwrap();
+        assert_eq!(buf.len(), 8);
+    }
+
+    #[test]
+    fn test_extend_from_within_clone() {
+        let mut buf = BoundedBuffer::new(5);
+        buf.try_push(String::from("a"));
+        buf.try_push(String::from("b"));
+        buf.try_push(String::from("c"));
+
+        assert!(buf.extend_from_within(..).is_err());
+        assert_eq!(buf.as_slice(), ["a", "b", "c"]);
+        buf.extend_from_within(1..3).unwrap();
+        assert_eq!(buf.as_slice(), ["a", "b", "c", "b", "c"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "range end index 4 out of range for slice of length 3")]
+    fn test_extend_from_within_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(8);
+        buf.try_extend_from_slice(&[1, 2, 3]).unwrap();
+        let _ = buf.extend_from_within(2..4);
+    }
+
+    #[test]
+    fn test_find_byte() {
+        // Lengths straddle several word boundaries so every position is hit
+        // in the leading chunks, the trailing remainder and at the seams.
+        for len in 0..40 {
+            let mut buf = BoundedBuffer::new(len);
+            buf.fill_to_capacity(b'a');
+            assert_eq!(buf.find_byte(b'\n'), None);
+            assert_eq!(buf.rfind_byte(b'\n'), None);
+
+            for pos in 0..len {
+                buf[pos] = b'\n';
+                assert_eq!(buf.find_byte(b'\n'), Some(pos));
+                assert_eq!(buf.rfind_byte(b'\n'), Some(pos));
+                buf[pos] = b'a';
+            }
+            if len > 1 {
+                buf[0] = b'\n';
+                buf[len - 1] = b'\n';
+                assert_eq!(buf.find_byte(b'\n'), Some(0));
+                assert_eq!(buf.rfind_byte(b'\n'), Some(len - 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_byte_high_bytes() {
+        // Bytes just above and below the target catch carry mistakes in the
+        // word-at-a-time test.
+        let mut buf = BoundedBuffer::new(32);
+        for i in 0..32u8 {
+            buf.try_push(0x7f + (i % 3));
+        }
+        let naive = |b: u8| buf.iter().position(|&x| x == b);
+        let naive_rev = |b: u8| buf.iter().rposition(|&x| x == b);
+        for b in [0x00, 0x7e, 0x7f, 0x80, 0x81, 0x82, 0xff] {
+            assert_eq!(buf.find_byte(b), naive(b), "byte {b:#x}");
+            assert_eq!(buf.rfind_byte(b), naive_rev(b), "byte {b:#x}");
+        }
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let mut buf = BoundedBuffer::new(64);
+        buf.try_extend_from_slice(b"GET / HTTP/1.1\r\nHost: a\r\n\r\nbody")
+            .unwrap();
+
+        assert_eq!(buf.find_bytes(b""), Some(0));
+        assert_eq!(buf.find_bytes(b"GET"), Some(0));
+        assert_eq!(buf.find_bytes(b"\r\n"), Some(14));
+        assert_eq!(buf.find_bytes(b"\r\n\r\n"), Some(23));
+        assert_eq!(buf.find_bytes(b"body"), Some(27));
+        assert_eq!(buf.find_bytes(b"bodyx"), None);
+        assert_eq!(buf.find_bytes(b"\n\n"), None);
+        assert_eq!(buf.find_bytes(&[b'a'; 65]), None);
+
+        let empty = BoundedBuffer::<u8>::new(0);
+        assert_eq!(empty.find_bytes(b""), Some(0));
+        assert_eq!(empty.find_bytes(b"a"), None);
+
+        let mut buf = BoundedBuffer::new(20);
+        buf.try_extend_from_slice(b"aaaaaaaaaaaaaaaaaaab").unwrap();
+        assert_eq!(buf.find_bytes(b"aab"), Some(17));
+        assert_eq!(buf.find_bytes(b"ab"), Some(18));
+        assert_eq!(buf.find_bytes(b"b"), Some(19));
+    }
+
+    #[test]
+    fn test_find_byte_timing() {
+        let n = if cfg!(miri) { 100 } else { 1 << 20 };
+        let mut buf = BoundedBuffer::new(n);
+        buf.fill_to_capacity(b'a');
+        buf[n - 1] = b'\n';
+
+        let now = std::time::Instant::now();
+        let naive = std::hint::black_box(&buf).iter().position(|&b| b == b'\n');
+        let elapsed = now.elapsed().as_nanos();
+        println!(
+            "BoundedBuffer<u8> Naive Position: {:.3} ns/byte",
+            elapsed as f64 / n as f64
+        );
+
+        let now = std::time::Instant::now();
+        let found = std::hint::black_box(&buf).find_byte(b'\n');
+        let elapsed = now.elapsed().as_nanos();
+        println!(
+            "BoundedBuffer<u8> Find Byte: {:.3} ns/byte",
+            elapsed as f64 / n as f64
+        );
+
+        let now = std::time::Instant::now();
+        let found_rev = std::hint::black_box(&buf).rfind_byte(b'\n');
+        let elapsed = now.elapsed().as_nanos();
+        println!(
+            "BoundedBuffer<u8> Rfind Byte (hit at end): {:.3} ns/byte",
+            elapsed as f64 / n as f64
+        );
+
+        let now = std::time::Instant::now();
+        let found_bytes = std::hint::black_box(&buf).find_bytes(b"a\n");
+        let elapsed = now.elapsed().as_nanos();
+        println!(
+            "BoundedBuffer<u8> Find Bytes: {:.3} ns/byte",
+            elapsed as f64 / n as f64
+        );
+
+        assert_eq!(naive, Some(n - 1));
+        assert_eq!(found, Some(n - 1));
+        assert_eq!(found_rev, Some(n - 1));
+        assert_eq!(found_bytes, Some(n - 2));
+    }
+
+    #[test]
+    fn test_grow_to() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(2);
+        buf.try_push(DropCount(drops.clone()));
+        buf.try_push(DropCount(drops.clone()));
+        assert!(!buf.try_push(DropCount(drops.clone())));
+        assert_eq!(drops.get(), 1);
+
+        buf.grow_to(1).unwrap();
+        assert_eq!(buf.capacity(), 2);
+        buf.grow_to(4).unwrap();
+        assert_eq!(buf.capacity(), 4);
+        assert_eq!(buf.len(), 2);
+        assert_eq!(drops.get(), 1);
+        assert!(buf.try_push(DropCount(drops.clone())));
+        assert!(buf.try_push(DropCount(drops.clone())));
+
+        assert_eq!(buf.grow_to(usize::MAX), Err(TryNewError::CapacityOverflow));
+        assert_eq!(buf.capacity(), 4);
+        assert_eq!(buf.len(), 4);
+
+        drop(buf);
+        assert_eq!(drops.get(), 5);
+
+        let mut buf = BoundedBuffer::<String>::new(0);
+        buf.grow_to(3).unwrap();
+        assert!(buf.try_push(String::from("a")));
+        assert_eq!(buf.capacity(), 3);
+    }
+
+    #[test]
+    fn test_shrink_to() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for _ in 0..3 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+
+        buf.shrink_to(10);
+        assert_eq!(buf.capacity(), 8);
+        buf.shrink_to(5);
+        assert_eq!(buf.capacity(), 5);
+        buf.shrink_to(1);
+        assert_eq!(buf.capacity(), 3);
+        assert_eq!(buf.len(), 3);
+        assert_eq!(drops.get(), 0);
+
+        buf.truncate(1);
+        buf.shrink_to(0);
+        assert_eq!(buf.capacity(), 1);
+        assert_eq!(drops.get(), 2);
+        buf.clear();
+        buf.shrink_to(0);
+        assert_eq!(buf.capacity(), 0);
+        assert!(!buf.try_push(DropCount(drops.clone())));
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_grow_shrink_zst() {
+        let mut buf = BoundedBuffer::new(2);
+        buf.try_push(());
+        buf.try_push(());
+        buf.grow_to(usize::MAX).unwrap();
+        assert_eq!(buf.capacity(), usize::MAX);
+        assert_eq!(buf.len(), 2);
+        buf.shrink_to(0);
+        assert_eq!(buf.capacity(), 2);
+        assert_eq!(buf.len(), 2);
+        assert!(!buf.try_push(()));
+    }
+
+    #[test]
+    fn test_from_elem() {
+        let buf = BoundedBuffer::from_elem(0u8, 5);
+        assert_eq!(buf.as_slice(), [0; 5]);
+        assert_eq!(buf.capacity(), 5);
+
+        let buf = BoundedBuffer::from_elem(String::from("ab"), 3);
+        assert_eq!(buf.as_slice(), ["ab", "ab", "ab"]);
+
+        let buf = BoundedBuffer::from_elem(String::new(), 0);
+        assert!(buf.is_empty());
+        assert_eq!(buf.capacity(), 0);
+
+        let drops = Drops::default();
+        let buf = BoundedBuffer::from_elem(DropCount(drops.clone()), 0);
+        assert_eq!(drops.get(), 1);
+        drop(buf);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn test_with_capacity_from_elem() {
+        let mut buf = BoundedBuffer::with_capacity_from_elem(4, 7, 2);
+        assert_eq!(buf.as_slice(), [7, 7]);
+        assert_eq!(buf.capacity(), 4);
+        assert!(buf.try_push(8));
+
+        let buf = BoundedBuffer::with_capacity_from_elem(3, 'x', 3);
+        assert_eq!(buf.as_slice(), ['x'; 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "length (is 3) should be <= capacity (is 2)")]
+    fn test_with_capacity_from_elem_too_long() {
+        BoundedBuffer::with_capacity_from_elem(2, 0, 3);
+    }
+
+    #[test]
+    fn test_from_fn() {
+        let buf = BoundedBuffer::from_fn(5, |i| i * i);
+        assert_eq!(buf.as_slice(), [0, 1, 4, 9, 16]);
+        assert_eq!(buf.capacity(), 5);
+
+        let buf = BoundedBuffer::<String>::from_fn(0, |_| unreachable!());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_from_fn_panic() {
+        let drops = Drops::default();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            BoundedBuffer::from_fn(6, |i| {
+                if i == 4 {
+                    panic!("generator failed");
+                }
+                DropCount(drops.clone())
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_from_elem_timing() {
+        fn time<T: Copy + std::fmt::Debug + PartialEq>(name: &str, n: usize, value: T) {
+            // Warm the allocator with a buffer of the same size first.
+            drop(BoundedBuffer::from_elem(value, n));
+
+            let now = std::time::Instant::now();
+            let mut arr = BoundedBuffer::new(n);
+            while arr.try_push(value) {}
+            let arr = std::hint::black_box(arr);
+            let elapsed = now.elapsed().as_nanos();
+            println!(
+                "BoundedBuffer<{name}> New + Push Loop: {:.2} ns/op",
+                elapsed as f64 / n as f64
+            );
+            assert_eq!(arr.len(), n);
+            drop(arr);
+
+            let now = std::time::Instant::now();
+            let arr = std::hint::black_box(BoundedBuffer::from_elem(value, n));
+            let elapsed = now.elapsed().as_nanos();
+            println!(
+                "BoundedBuffer<{name}> From Elem: {:.2} ns/op",
+                elapsed as f64 / n as f64
+            );
+            assert!(arr.iter().all(|elem| *elem == value));
+        }
+
+        let n = if cfg!(miri) { 100 } else { 100_000 };
+        time("u8", n, 7u8);
+        time("u64", n, 7u64);
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        buf.try_push(DropCount(drops.clone()));
+        buf.try_push(DropCount(drops.clone()));
+
+        let mut cp = buf.checkpoint();
+        assert_eq!(cp.checkpoint_len(), 2);
+        cp.try_push(DropCount(drops.clone()));
+        cp.try_push(DropCount(drops.clone()));
+        assert_eq!(cp.len(), 4);
+        drop(cp);
+        assert_eq!(buf.len(), 2);
+        assert_eq!(drops.get(), 2);
+
+        let mut cp = buf.checkpoint();
+        cp.try_push(DropCount(drops.clone()));
+        cp.rollback();
+        assert_eq!(buf.len(), 2);
+        assert_eq!(drops.get(), 3);
+
+        let mut cp = buf.checkpoint();
+        cp.try_push(DropCount(drops.clone()));
+        cp.commit();
+        assert_eq!(buf.len(), 3);
+        assert_eq!(drops.get(), 3);
+
+        // Elements popped from below the checkpoint stay gone.
+        let mut cp = buf.checkpoint();
+        cp.pop();
+        cp.pop();
+        cp.try_push(DropCount(drops.clone()));
+        drop(cp);
+        assert_eq!(buf.len(), 2);
+        assert_eq!(drops.get(), 5);
+
+        drop(buf);
+        assert_eq!(drops.get(), 7);
+    }
+
+    #[test]
+    fn test_checkpoint_nested() {
+        let mut buf = BoundedBuffer::new(8);
+        buf.try_push(String::from("a"));
+
+        let mut outer = buf.checkpoint();
+        outer.try_push(String::from("b"));
+        {
+            let mut inner = outer.checkpoint();
+            inner.try_push(String::from("c"));
+            inner.commit();
+        }
+        {
+            let mut inner = outer.checkpoint();
+            inner.try_push(String::from("d"));
+        }
+        assert_eq!(outer.as_slice(), ["a", "b", "c"]);
+        drop(outer);
+        assert_eq!(buf.as_slice(), ["a"]);
+    }
+
+    #[test]
+    fn test_checkpoint_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        buf.try_push(DropCount(drops.clone()));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut cp = buf.checkpoint();
+            cp.try_push(DropCount(drops.clone()));
+            cp.try_push(DropCount(drops.clone()));
+            if cp.len() == 3 {
+                panic!("validation failed");
+            }
+            cp.commit();
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 1);
+        assert_eq!(drops.get(), 2);
+
+        drop(buf);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn test_pop_if() {
+        let mut buf = BoundedBuffer::<i32>::new(4);
+        assert_eq!(buf.pop_if(|_| unreachable!()), None);
+
+        buf.try_extend_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(buf.pop_if(|x| *x == 3), Some(3));
+        assert_eq!(buf.pop_if(|x| *x == 3), None);
+
+        // Changes made by a rejecting predicate stick.
+        assert_eq!(
+            buf.pop_if(|x| {
+                *x += 10;
+                false
+            }),
+            None
+        );
+        assert_eq!(buf.as_slice(), [1, 12]);
+    }
+
+    #[test]
+    fn test_pop_while() {
+        let mut buf = BoundedBuffer::<i32>::new(8);
+        assert_eq!(buf.pop_while(|_| true).next(), None);
+
+        buf.try_extend_from_slice(&[1, 5, 2, 4, 6]).unwrap();
+        let popped: Vec<_> = buf.pop_while(|x| *x % 2 == 0).collect();
+        assert_eq!(popped, [6, 4, 2]);
+        assert_eq!(buf.as_slice(), [1, 5]);
+
+        // Dropping the iterator early leaves the rest in place.
+        buf.try_extend_from_slice(&[8, 10]).unwrap();
+        assert_eq!(buf.pop_while(|x| *x % 2 == 0).next(), Some(10));
+        assert_eq!(buf.as_slice(), [1, 5, 8]);
+
+        let mut iter = buf.pop_while(|x| {
+            *x *= 2;
+            *x < 12
+        });
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+        assert_eq!(buf.as_slice(), [1, 5, 16]);
+
+        assert_eq!(buf.pop_while(|_| true).count(), 3);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_pop_while_drops() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for _ in 0..4 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+        buf.pop_while(|_| true).for_each(drop);
+        assert!(buf.is_empty());
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_truncate_front() {
+        let mut buf = BoundedBuffer::new(6);
+        buf.try_extend_from_slice(&[1, 2, 3, 4, 5]).unwrap();
+
+        buf.truncate_front(0);
+        assert_eq!(buf.as_slice(), [1, 2, 3, 4, 5]);
+        buf.truncate_front(2);
+        assert_eq!(buf.as_slice(), [3, 4, 5]);
+        assert!(buf.try_push(6));
+        buf.truncate_front(10);
+        assert!(buf.is_empty());
+        buf.truncate_front(1);
+        assert!(buf.is_empty());
+
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for _ in 0..4 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+        buf.truncate_front(3);
+        assert_eq!(buf.len(), 1);
+        assert_eq!(drops.get(), 3);
+        drop(buf);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_drain_front() {
+        let mut buf = BoundedBuffer::new(6);
+        buf.try_push(String::from("a"));
+        buf.try_push(String::from("b"));
+        buf.try_push(String::from("c"));
+
+        let front: Vec<_> = buf.drain_front(2).collect();
+        assert_eq!(front, ["a", "b"]);
+        assert_eq!(buf.as_slice(), ["c"]);
+        assert_eq!(buf.drain_front(0).count(), 0);
+        assert_eq!(buf.drain_front(5).collect::<Vec<_>>(), ["c"]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_truncate_front_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 1 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(5);
+        for i in 0..5 {
+            buf.try_push(PanicOnDrop(i));
+        }
+
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| buf.truncate_front(3)));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+        assert_eq!(buf.len(), 2);
+        assert_eq!(buf[0].0, 3);
+        assert_eq!(buf[1].0, 4);
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_truncate_front_timing() {
+        let n = if cfg!(miri) { 100 } else { 20_000 };
+        let batch = 8;
+
+        let mut buf = BoundedBuffer::from_fn(n, |i| i as u64);
+        let now = std::time::Instant::now();
+        while buf.len() >= batch {
+            for _ in 0..batch {
+                buf.remove(0);
+            }
+        }
+        let elapsed = now.elapsed().as_nanos();
+        println!(
+            "BoundedBuffer<u64> Remove(0) Loop: {:.2} ns/elem",
+            elapsed as f64 / n as f64
+        );
+
+        let mut buf = BoundedBuffer::from_fn(n, |i| i as u64);
+        let now = std::time::Instant::now();
+        while buf.len() >= batch {
+            buf.truncate_front(batch);
+        }
+        let elapsed = now.elapsed().as_nanos();
+        println!(
+            "BoundedBuffer<u64> Truncate Front: {:.2} ns/elem",
+            elapsed as f64 / n as f64
+        );
+        assert!(buf.len() < batch);
+    }
+
+    #[test]
+    fn test_pop_many() {
+        use std::mem::MaybeUninit;
+
+        let mut buf = BoundedBuffer::new(5);
+        for s in ["a", "b", "c", "d", "e"] {
+            buf.try_push(String::from(s));
+        }
+
+        let mut out: [MaybeUninit<String>; 3] = [const { MaybeUninit::uninit() }; 3];
+        assert_eq!(buf.pop_many(&mut out[..2]), 2);
+        assert_eq!(buf.as_slice(), ["a", "b", "c"]);
+        let moved = unsafe { [out[0].assume_init_read(), out[1].assume_init_read()] };
+        assert_eq!(moved, ["d", "e"]);
+
+        assert_eq!(buf.pop_many(&mut out), 3);
+        assert!(buf.is_empty());
+        let moved = unsafe { out.map(|slot| slot.assume_init()) };
+        assert_eq!(moved, ["a", "b", "c"]);
+
+        let mut out = [MaybeUninit::new(7)];
+        assert_eq!(BoundedBuffer::<i32>::new(2).pop_many(&mut out), 0);
+        assert_eq!(unsafe { out[0].assume_init() }, 7);
+    }
+
+    #[test]
+    fn test_pop_many_drops() {
+        use std::mem::MaybeUninit;
+
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for _ in 0..4 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+
+        let mut out: [MaybeUninit<DropCount>; 3] = [const { MaybeUninit::uninit() }; 3];
+        assert_eq!(buf.pop_many(&mut out), 3);
+        drop(buf);
+        assert_eq!(drops.get(), 1);
+        for slot in &mut out {
+            unsafe { slot.assume_init_drop() };
+        }
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_pop_copy_into() {
+        let mut buf = BoundedBuffer::new(8);
+        buf.try_extend_from_slice(&[1, 2, 3, 4, 5]).unwrap();
+
+        let mut dst = [0; 3];
+        assert_eq!(buf.pop_copy_into(&mut dst), 3);
+        assert_eq!(dst, [3, 4, 5]);
+        assert_eq!(buf.as_slice(), [1, 2]);
+
+        assert_eq!(buf.pop_copy_into(&mut dst), 2);
+        assert_eq!(dst, [1, 2, 5]);
+        assert!(buf.is_empty());
+
+        assert_eq!(buf.pop_copy_into(&mut dst), 0);
+        assert_eq!(dst, [1, 2, 5]);
+        assert_eq!(buf.pop_copy_into(&mut []), 0);
+    }
+
+    #[test]
+    fn test_concat() {
+        let a = BoundedBuffer::from_elem(String::from("a"), 2);
+        let b = BoundedBuffer::<String>::new(3);
+        let mut c = BoundedBuffer::new(3);
+        c.try_push(String::from("c"));
+
+        let out = BoundedBuffer::concat([&a, &b, &c], 3).unwrap();
+        assert_eq!(out.as_slice(), ["a", "a", "c"]);
+        assert_eq!(out.capacity(), 3);
+        assert_eq!(a.len(), 2);
+
+        let out = BoundedBuffer::<String>::concat([], 4).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(out.capacity(), 4);
+
+        assert!(BoundedBuffer::concat([&a, &c], 2).is_err());
+
+        let mut parts = vec![BoundedBuffer::new(2); 3];
+        for (i, part) in parts.iter_mut().enumerate() {
+            part.try_push(i);
+        }
+        let out = BoundedBuffer::concat(&parts, 3).unwrap();
+        assert_eq!(out.as_slice(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_concat_owned() {
+        let drops = Drops::default();
+        let mut parts: Vec<_> = (0..3).map(|_| BoundedBuffer::new(2)).collect();
+        for part in &mut parts {
+            part.try_push(DropCount(drops.clone()));
+        }
+        parts[1].try_push(DropCount(drops.clone()));
+
+        assert!(BoundedBuffer::concat_owned(&mut parts, 3).is_err());
+        assert_eq!(parts.iter().map(|p| p.len()).collect::<Vec<_>>(), [1, 2, 1]);
+        assert_eq!(drops.get(), 0);
+
+        let out = BoundedBuffer::concat_owned(&mut parts, 4).unwrap();
+        assert_eq!(out.len(), 4);
+        assert!(parts.iter().all(|p| p.is_empty() && p.capacity() == 2));
+        assert_eq!(drops.get(), 0);
+
+        drop(parts);
+        assert_eq!(drops.get(), 0);
+        drop(out);
+        assert_eq!(drops.get(), 4);
+
+        let out = BoundedBuffer::<String>::concat_owned(&mut [], 0).unwrap();
+        assert!(out.is_empty());
+
+        let mut parts = [BoundedBuffer::new(usize::MAX), BoundedBuffer::new(1)];
+        // Unit lengths can overflow the running total.
+        unsafe {
+            parts[0].set_len(usize::MAX);
+        }
+        parts[1].try_push(());
+        assert!(BoundedBuffer::concat_owned(&mut parts, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_map_same_layout() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_extend_from_slice(&[1u32, 2, 3]).unwrap();
+        let ptr = buf.as_ptr() as usize;
+
+        let buf = buf.map(|x| x as f32 * 0.5);
+        assert_eq!(buf.as_slice(), [0.5, 1.0, 1.5]);
+        assert_eq!(buf.capacity(), 4);
+        assert_eq!(buf.as_ptr() as usize, ptr);
+
+        let mut buf = BoundedBuffer::new(3);
+        buf.try_push(String::from("a"));
+        buf.try_push(String::from("bc"));
+        let buf = buf.map(|s| s.into_bytes());
+        assert_eq!(buf.as_slice(), [b"a".to_vec(), b"bc".to_vec()]);
+        assert_eq!(buf.capacity(), 3);
+    }
+
+    #[test]
+    fn test_map_new_layout() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_extend_from_slice(&[1u8, 2, 3]).unwrap();
+        let mut buf = buf.map(|x| (x as u64) << 40);
+        assert_eq!(buf.as_slice(), [1 << 40, 2 << 40, 3 << 40]);
+        assert_eq!(buf.capacity(), 4);
+        assert!(buf.try_push(0));
+
+        let buf = BoundedBuffer::from_elem(7u16, 2).map(|_| ());
+        assert_eq!(buf.len(), 2);
+        assert_eq!(buf.capacity(), 2);
+    }
+
+    #[test]
+    fn test_try_map() {
+        let mut buf = BoundedBuffer::new(4);
+        for s in ["1", "2", "3"] {
+            buf.try_push(String::from(s));
+        }
+        let parsed = buf.clone().try_map(|s| s.parse::<usize>()).unwrap();
+        assert_eq!(parsed.as_slice(), [1, 2, 3]);
+
+        buf[1].push('x');
+        assert!(buf.clone().try_map(|s| s.parse::<usize>()).is_err());
+        assert!(buf.try_map(|s| s.parse::<u8>()).is_err());
+    }
+
+    #[test]
+    fn test_map_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static OLD: AtomicUsize = AtomicUsize::new(0);
+        static NEW: AtomicUsize = AtomicUsize::new(0);
+
+        struct Old(#[allow(dead_code)] u64);
+        struct New(#[allow(dead_code)] u64);
+        struct Wide(#[allow(dead_code)] [u64; 2]);
+
+        impl Drop for Old {
+            fn drop(&mut self) {
+                OLD.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        impl Drop for New {
+            fn drop(&mut self) {
+                NEW.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        impl Drop for Wide {
+            fn drop(&mut self) {
+                NEW.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let buf = BoundedBuffer::from_fn(5, |i| Old(i as u64));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.map(|old| {
+                if old.0 == 2 {
+                    panic!("conversion failed");
+                }
+                New(old.0)
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(OLD.load(Ordering::SeqCst), 5);
+        assert_eq!(NEW.load(Ordering::SeqCst), 2);
+
+        OLD.store(0, Ordering::SeqCst);
+        NEW.store(0, Ordering::SeqCst);
+        let buf = BoundedBuffer::from_fn(5, |i| Old(i as u64));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.map(|old| {
+                if old.0 == 3 {
+                    panic!("conversion failed");
+                }
+                Wide([old.0; 2])
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(OLD.load(Ordering::SeqCst), 5);
+        assert_eq!(NEW.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_partition_into() {
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push(i.to_string());
+        }
+        let mut slow = BoundedBuffer::new(4);
+        slow.try_push(String::from("x"));
+
+        buf.partition_into(|s| s.parse::<u32>().unwrap() % 2 == 0, &mut slow)
+            .unwrap();
+        assert_eq!(buf.as_slice(), ["0", "2", "4"]);
+        assert_eq!(slow.as_slice(), ["x", "1", "3", "5"]);
+
+        // Nothing needs to move, so a full target is fine.
+        buf.partition_into(|_| true, &mut slow).unwrap();
+        assert_eq!(buf.len(), 3);
+
+        let mut empty = BoundedBuffer::<String>::new(0);
+        let mut none = BoundedBuffer::new(0);
+        empty.partition_into(|_| false, &mut none).unwrap();
+    }
+
+    #[test]
+    fn test_partition_into_full() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+        let mut other = BoundedBuffer::new(2);
+
+        let result = buf.partition_into(|(i, _)| *i == 2, &mut other);
+        assert!(result.is_err());
+        assert_eq!(other.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [2, 3, 4, 5]
+        );
+        assert_eq!(drops.get(), 0);
+
+        drop(buf);
+        drop(other);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_partition_into_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(5);
+        for i in 0..5 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+        let mut other = BoundedBuffer::new(5);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.partition_into(
+                |(i, _)| {
+                    if *i == 3 {
+                        panic!("predicate failed");
+                    }
+                    *i == 0
+                },
+                &mut other,
+            )
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 3, 4]);
+        assert_eq!(other.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(drops.get(), 0);
+
+        drop(buf);
+        drop(other);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn test_as_chunks() {
+        let mut buf = BoundedBuffer::from_fn(7, |i| i as u32);
+
+        let (chunks, rest) = buf.as_chunks::<1>();
+        assert_eq!(chunks.len(), 7);
+        assert!(rest.is_empty());
+
+        let (chunks, rest) = buf.as_chunks::<3>();
+        assert_eq!(chunks, [[0, 1, 2], [3, 4, 5]]);
+        assert_eq!(rest, [6]);
+
+        let (chunks, rest) = buf.as_chunks::<8>();
+        assert!(chunks.is_empty());
+        assert_eq!(rest.len(), 7);
+
+        let (chunks, rest) = buf.as_chunks_mut::<2>();
+        for chunk in chunks {
+            chunk.swap(0, 1);
+        }
+        rest[0] = 60;
+        assert_eq!(buf.as_slice(), [1, 0, 3, 2, 5, 4, 60]);
+    }
+
+    #[test]
+    fn test_into_chunks() {
+        let buf = BoundedBuffer::from_fn(5, |i| i.to_string());
+        let buf = buf.into_chunks::<2>().unwrap_err();
+        assert_eq!(buf.len(), 5);
+
+        let buf = buf.into_chunks::<1>().unwrap();
+        assert_eq!(buf.capacity(), 5);
+        assert_eq!(buf[4], ["4"]);
+
+        let mut buf = BoundedBuffer::new(7);
+        for i in 0..4 {
+            buf.try_push(i.to_string());
+        }
+        let mut pairs = buf.into_chunks::<2>().unwrap();
+        assert_eq!(pairs.capacity(), 3);
+        pairs[1][0].push('!');
+        assert!(pairs.try_push([String::from("a"), String::from("b")]));
+        assert_eq!(pairs.as_slice(), [["0", "1"], ["2!", "3"], ["a", "b"]]);
+
+        let empty = BoundedBuffer::<u8>::new(3).into_chunks::<4>().unwrap();
+        assert_eq!(empty.capacity(), 0);
+        assert!(empty.is_empty());
+
+        let units = BoundedBuffer::from_elem((), 4).into_chunks::<2>().unwrap();
+        assert_eq!(units.len(), 2);
+    }
+
+    #[test]
+    fn test_try_cast() {
+        let mut buf = BoundedBuffer::new(12);
+        buf.try_extend_from_slice(&[1u8, 2, 3, 4, 5, 6, 7, 8])
+            .unwrap();
+        let mut words = buf.try_cast::<[u8; 4]>().unwrap();
+        assert_eq!(words.as_slice(), [[1, 2, 3, 4], [5, 6, 7, 8]]);
+        assert_eq!(words.capacity(), 3);
+        assert!(words.try_push([9; 4]));
+        words[0][0] = 0;
+
+        let bytes = words.try_cast::<u8>().unwrap();
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(bytes.capacity(), 12);
+        assert_eq!(bytes[..5], [0, 2, 3, 4, 5]);
+
+        let floats = BoundedBuffer::from_elem(1.0f32.to_bits(), 3)
+            .try_cast::<f32>()
+            .unwrap();
+        assert_eq!(floats.as_slice(), [1.0; 3]);
+        let pairs = BoundedBuffer::from_elem(7u64, 2)
+            .try_cast::<[u64; 2]>()
+            .unwrap();
+        assert_eq!(pairs.as_slice(), [[7, 7]]);
+    }
+
+    #[test]
+    fn test_try_cast_rejected() {
+        // Different alignment.
+        let buf = BoundedBuffer::from_elem(0u8, 8);
+        let buf = buf.try_cast::<u32>().unwrap_err().into_inner();
+        assert_eq!(buf.len(), 8);
+
+        // Length or capacity not a whole number of `U`s.
+        let buf = BoundedBuffer::from_elem(0u8, 6);
+        assert!(buf.try_cast::<[u8; 4]>().is_err());
+        let mut buf = BoundedBuffer::with_capacity_from_elem(10, 0u8, 8);
+        assert!(buf.try_push(1));
+        let buf = buf.try_cast::<[u8; 4]>().unwrap_err().into_inner();
+        assert_eq!(buf.len(), 9);
+
+        // Zero-sized targets.
+        assert!(buf.try_cast::<[u8; 0]>().is_err());
+
+        let empty = BoundedBuffer::<u16>::new(0).try_cast::<i16>().unwrap();
+        assert_eq!(empty.capacity(), 0);
+    }
+
+    #[test]
+    fn test_cursor_mut() {
+        let mut buf = BoundedBuffer::new(6);
+        buf.try_extend_from_slice(&[1, 2, 3, 4]).unwrap();
+
+        let mut cursor = buf.cursor_mut();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.peek_prev(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 2));
+
+        // Drop the even elements and double the odd ones in one walk.
+        while let Some(x) = cursor.current() {
+            if *x % 2 == 0 {
+                cursor.remove_current();
+            } else {
+                *x *= 10;
+                cursor.move_next();
+            }
+        }
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 10));
+        assert_eq!(cursor.peek_prev(), Some(&mut 30));
+        assert_eq!(cursor.remove_current(), None);
+
+        // From the ghost, moving wraps around to either end.
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 10));
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 30));
+        assert_eq!(buf.as_slice(), [10, 30]);
+    }
+
+    #[test]
+    fn test_cursor_mut_insert() {
+        let mut buf = BoundedBuffer::new(5);
+        buf.try_extend_from_slice(&[2, 4]).unwrap();
+
+        let mut cursor = buf.cursor_mut();
+        cursor.insert_before(1).unwrap();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        cursor.insert_after(3).unwrap();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 4));
+        cursor.move_next();
+        cursor.insert_before(5).unwrap();
+        assert_eq!(cursor.index(), None);
+
+        // A full buffer gives the element back and leaves the cursor alone.
+        assert_eq!(cursor.insert_after(0).unwrap_err().element(), 0);
+        assert_eq!(cursor.insert_before(6).unwrap_err().element(), 6);
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.remove_current(), Some(5));
+        assert_eq!(cursor.index(), None);
+        cursor.insert_after(0).unwrap();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(buf.as_slice(), [0, 1, 2, 3, 4]);
+
+        let mut empty = BoundedBuffer::new(1);
+        let mut cursor = empty.cursor_mut();
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        cursor.insert_after(String::from("a")).unwrap();
+        assert_eq!(cursor.peek_next().map(|s| s.as_str()), Some("a"));
+        assert_eq!(empty.as_slice(), ["a"]);
+    }
+
+    #[test]
+    fn test_swap_remove() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(5);
+        for i in 0..5 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        assert_eq!(buf.swap_remove(1).0, 1);
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 4, 2, 3]
+        );
+        assert_eq!(buf.swap_remove(3).0, 3);
+        assert_eq!(buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 4, 2]);
+        assert_eq!(drops.get(), 2);
+
+        assert!(buf.try_swap_remove(3).is_none());
+        while !buf.is_empty() {
+            assert!(buf.try_swap_remove(0).is_some());
+        }
+        assert_eq!(drops.get(), 5);
+        assert!(buf.try_swap_remove(0).is_none());
+
+        buf.try_push((9, DropCount(drops.clone())));
+        assert_eq!(buf.swap_remove(0).0, 9);
+        assert!(buf.is_empty());
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_swap_remove_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        buf.swap_remove(1);
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let mut buf = BoundedBuffer::new(8);
+        buf.try_extend_from_slice(&[1, 5]).unwrap();
+
+        buf.insert_many(1, [2, 3, 4]).unwrap();
+        assert_eq!(buf.as_slice(), [1, 2, 3, 4, 5]);
+        buf.insert_many(0, [0]).unwrap();
+        buf.insert_many(6, vec![6, 7]).unwrap();
+        buf.insert_many(3, []).unwrap();
+        assert_eq!(buf.as_slice(), [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        let iter = buf.insert_many(2, [9]).unwrap_err().element();
+        assert_eq!(iter.collect::<Vec<_>>(), [9]);
+        assert_eq!(buf.len(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_insert_many_out_of_bounds() {
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(1);
+        let _ = buf.insert_many(2, [2]);
+    }
+
+    #[test]
+    fn test_insert_many_lying_iterator() {
+        // Claims more elements than it yields, or fewer.
+        struct Lying {
+            claimed: usize,
+            actual: std::ops::Range<usize>,
+        }
+
+        impl Iterator for Lying {
+            type Item = String;
+
+            fn next(&mut self) -> Option<String> {
+                self.actual.next().map(|i| i.to_string())
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.claimed, Some(self.claimed))
+            }
+        }
+
+        impl ExactSizeIterator for Lying {}
+
+        let mut buf = BoundedBuffer::new(8);
+        for s in ["a", "b", "c"] {
+            buf.try_push(String::from(s));
+        }
+
+        let short = Lying {
+            claimed: 4,
+            actual: 0..2,
+        };
+        buf.insert_many(1, short).unwrap();
+        assert_eq!(buf.as_slice(), ["a", "0", "1", "b", "c"]);
+
+        let long = Lying {
+            claimed: 1,
+            actual: 5..9,
+        };
+        buf.insert_many(5, long).unwrap();
+        assert_eq!(buf.as_slice(), ["a", "0", "1", "b", "c", "5"]);
+
+        let too_long = Lying {
+            claimed: 3,
+            actual: 0..3,
+        };
+        assert!(buf.insert_many(0, too_long).is_err());
+        assert_eq!(buf.len(), 6);
+    }
+
+    #[test]
+    fn test_insert_many_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(8);
+        for i in 0..3 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let drops = drops.clone();
+            let iter = (10..14).map(move |i| {
+                if i == 12 {
+                    panic!("iterator failed");
+                }
+                (i, DropCount(drops.clone()))
+            });
+            buf.insert_many(1, iter)
+        }));
+        assert!(result.is_err());
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [0, 10, 11, 1, 2]
+        );
+        assert_eq!(drops.get(), 0);
+
+        drop(buf);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn test_remove_range() {
+        let mut buf = BoundedBuffer::from_fn(8, |i| i);
+        assert_eq!(buf.remove_range(2..5), 3);
+        assert_eq!(buf.as_slice(), [0, 1, 5, 6, 7]);
+        assert_eq!(buf.remove_range(3..3), 0);
+        assert_eq!(buf.remove_range(3..), 2);
+        assert_eq!(buf.as_slice(), [0, 1, 5]);
+        assert_eq!(buf.remove_range(..=0), 1);
+        assert_eq!(buf.remove_range(..), 2);
+        assert!(buf.is_empty());
+
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(4);
+        for i in 0..4 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+        assert_eq!(buf.remove_range(1..3), 2);
+        assert_eq!(drops.get(), 2);
+        assert_eq!(buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "slice index starts at 3 but ends at 2")]
+    fn test_remove_range_inverted() {
+        let mut buf = BoundedBuffer::from_fn(4, |i| i);
+        #[allow(clippy::reversed_empty_ranges)]
+        buf.remove_range(3..2);
+    }
+
+    #[test]
+    fn test_remove_range_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 2 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut buf = BoundedBuffer::from_fn(6, PanicOnDrop);
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| buf.remove_range(1..4)));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+        assert_eq!(buf.iter().map(|e| e.0).collect::<Vec<_>>(), [0, 4, 5]);
+
+        drop(buf);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn test_remove_range_timing() {
+        let n = if cfg!(miri) { 100 } else { 100_000 };
+        let rounds = if cfg!(miri) { 2 } else { 100 };
+
+        let mut elapsed = 0;
+        for _ in 0..rounds {
+            let mut buf = BoundedBuffer::from_fn(n, |i| i as u64);
+            let now = std::time::Instant::now();
+            buf.drain(n / 4..n / 2).for_each(drop);
+            elapsed += now.elapsed().as_nanos();
+            assert_eq!(std::hint::black_box(buf).len(), n - n / 4);
+        }
+        println!(
+            "BoundedBuffer<u64> Drain For Each: {:.2} ns/call",
+            elapsed as f64 / rounds as f64
+        );
+
+        let mut elapsed = 0;
+        for _ in 0..rounds {
+            let mut buf = BoundedBuffer::from_fn(n, |i| i as u64);
+            let now = std::time::Instant::now();
+            buf.remove_range(n / 4..n / 2);
+            elapsed += now.elapsed().as_nanos();
+            assert_eq!(std::hint::black_box(buf).len(), n - n / 4);
+        }
+        println!(
+            "BoundedBuffer<u64> Remove Range: {:.2} ns/call",
+            elapsed as f64 / rounds as f64
+        );
+    }
+
+    #[test]
+    fn test_retain_unordered() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for len in [0, 1, 2, 7, 64] {
+            let values: Vec<u64> = (0..len).map(|_| xorshift(&mut state) % 10).collect();
+            let mut ordered = BoundedBuffer::from_fn(len, |i| values[i]);
+            let mut unordered = ordered.clone();
+
+            ordered.retain(|x| x % 3 != 0);
+            let mut calls = 0;
+            unordered.retain_unordered(|x| {
+                calls += 1;
+                *x % 3 != 0
+            });
+            assert_eq!(calls, len);
+
+            let mut ordered = ordered.into_vec();
+            let mut unordered = unordered.into_vec();
+            ordered.sort_unstable();
+            unordered.sort_unstable();
+            assert_eq!(ordered, unordered);
+        }
+
+        // Every element rejected, including each one moved into a hole.
+        let mut buf = BoundedBuffer::from_fn(5, |i| i);
+        buf.retain_unordered(|_| false);
+        assert!(buf.is_empty());
+
+        let mut buf = BoundedBuffer::from_fn(5, |i| i);
+        buf.retain_unordered(|x| {
+            *x *= 10;
+            *x >= 30
+        });
+        assert_eq!(buf.as_slice(), [40, 30]);
+    }
+
+    #[test]
+    fn test_retain_unordered_drops() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+        buf.retain_unordered(|(i, _)| *i % 2 == 0);
+        assert_eq!(buf.len(), 3);
+        assert_eq!(drops.get(), 3);
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_retain_unordered_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        for i in 0..6 {
+            buf.try_push((i, DropCount(drops.clone())));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            buf.retain_unordered(|(i, _)| {
+                if *i == 3 {
+                    panic!("predicate failed");
+                }
+                *i != 0 && *i != 4
+            })
+        }));
+        assert!(result.is_err());
+        // 0 was rejected and replaced by 5; the panic came at 3.
+        assert_eq!(
+            buf.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [5, 1, 2, 3, 4]
+        );
+        assert_eq!(drops.get(), 1);
+
+        drop(buf);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_retain_unordered_timing() {
+        fn time<T: Copy>(name: &str, n: usize, make: impl Fn(u64) -> T, key: impl Fn(&T) -> u64) {
+            let mut state = 0x9e37_79b9_7f4a_7c15;
+            let values: Vec<T> = (0..n).map(|_| make(xorshift(&mut state))).collect();
+
+            let mut buf = BoundedBuffer::from_fn(n, |i| values[i]);
+            let now = std::time::Instant::now();
+            buf.retain(|x| key(x).is_multiple_of(2));
+            let elapsed = now.elapsed().as_nanos();
+            println!(
+                "BoundedBuffer<{name}> Retain (50%): {:.2} ns/op",
+                elapsed as f64 / n as f64
+            );
+            let kept = buf.len();
+
+            let mut buf = BoundedBuffer::from_fn(n, |i| values[i]);
+            let now = std::time::Instant::now();
+            buf.retain_unordered(|x| key(x).is_multiple_of(2));
+            let elapsed = now.elapsed().as_nanos();
+            println!(
+                "BoundedBuffer<{name}> Retain Unordered (50%): {:.2} ns/op",
+                elapsed as f64 / n as f64
+            );
+            assert_eq!(buf.len(), kept);
+        }
+
+        let n = if cfg!(miri) { 100 } else { 100_000 };
+        time("u64", n, |x| x, |x| *x);
+        time("[u64; 16]", n, |x| [x; 16], |x| x[0]);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut buf = BoundedBuffer::new(6);
+        for s in ["a", "b", "c", "d", "e"] {
+            buf.try_push(String::from(s));
+        }
+
+        for s in &mut buf {
+            s.push('!');
+        }
+        let mut joined = String::new();
+        for s in &buf {
+            joined += s;
+        }
+        assert_eq!(joined, "a!b!c!d!e!");
+
+        let mut iter = buf.into_iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next().as_deref(), Some("a!"));
+        assert_eq!(iter.next_back().as_deref(), Some("e!"));
+        assert_eq!(iter.as_slice(), ["b!", "c!", "d!"]);
+        iter.as_mut_slice()[0].push('?');
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.collect::<Vec<_>>(), ["b!?", "c!", "d!"]);
+
+        let mut iter = BoundedBuffer::<u8>::new(0).into_iter();
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let units: Vec<()> = BoundedBuffer::from_elem((), 3).into_iter().rev().collect();
+        assert_eq!(units.len(), 3);
+    }
+
+    #[test]
+    fn test_into_iter_drop() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        for _ in 0..5 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+
+        let mut iter = buf.into_iter();
+        drop(iter.next());
+        drop(iter.next_back());
+        assert_eq!(drops.get(), 2);
+        drop(iter);
+        assert_eq!(drops.get(), 5);
+
+        let mut iter = BoundedBuffer::from_elem(DropCount(drops.clone()), 2).into_iter();
+        assert_eq!(drops.get(), 5);
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.next().map(|_| ()), None);
+        drop(iter);
+        assert_eq!(drops.get(), 7);
+    }
+
+    #[test]
+    fn test_into_iter_forget() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        // Zero-sized, so forgetting the iterator leaks no allocation.
+        struct Counted;
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(4);
+        for _ in 0..4 {
+            buf.try_push(Counted);
+        }
+        let mut iter = buf.into_iter();
+        drop(iter.next());
+        std::mem::forget(iter);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_into_iter_drop_panic() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(usize);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 == 2 {
+                    panic!("drop failed");
+                }
+            }
+        }
+
+        let mut iter = BoundedBuffer::from_fn(5, PanicOnDrop).into_iter();
+        drop(iter.next());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(iter)));
+        assert!(result.is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let buf: BoundedBuffer<_> = (0..5).map(|i| i * 2).collect();
+        assert_eq!(buf.as_slice(), [0, 2, 4, 6, 8]);
+        assert_eq!(buf.capacity(), 5);
+
+        let buf: BoundedBuffer<_> = (0..10).filter(|i| i % 3 == 0).collect();
+        assert_eq!(buf.as_slice(), [0, 3, 6, 9]);
+        assert!(buf.capacity() >= 4);
+
+        let buf: BoundedBuffer<String> = std::iter::empty().collect();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_try_from_iter() {
+        let buf = BoundedBuffer::try_from_iter(0..3, 5).unwrap();
+        assert_eq!(buf.as_slice(), [0, 1, 2]);
+        assert_eq!(buf.capacity(), 5);
+
+        let buf = BoundedBuffer::try_from_iter(0..5, 5).unwrap();
+        assert_eq!(buf.len(), 5);
+
+        assert!(BoundedBuffer::try_from_iter(0..6, 5).is_err());
+        assert!(BoundedBuffer::try_from_iter(0..1, 0).is_err());
+        assert!(BoundedBuffer::<u8>::try_from_iter(None, 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_try_from_iter_lying_size_hint() {
+        struct Lying {
+            range: std::ops::Range<usize>,
+            hint: usize,
+            drops: Drops,
+        }
+
+        impl Iterator for Lying {
+            type Item = DropCount;
+
+            fn next(&mut self) -> Option<DropCount> {
+                self.range.next().map(|_| DropCount(self.drops.clone()))
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.hint, Some(self.hint))
+            }
+        }
+
+        let drops = Drops::default();
+        let lying = |range, hint| Lying {
+            range,
+            hint,
+            drops: drops.clone(),
+        };
+
+        let buf = BoundedBuffer::try_from_iter(lying(0..4, 100), 4).unwrap();
+        assert_eq!(buf.len(), 4);
+        drop(buf);
+        assert_eq!(drops.get(), 4);
+
+        // One element past the capacity is pulled and dropped.
+        assert!(BoundedBuffer::try_from_iter(lying(0..6, 0), 4).is_err());
+        assert_eq!(drops.get(), 9);
+
+        let buf: BoundedBuffer<_> = lying(0..3, 1).collect();
+        assert_eq!(buf.len(), 3);
+    }
+
+    #[test]
+    fn test_try_extend() {
+        let mut buf = BoundedBuffer::new(5);
+        assert_eq!(buf.try_extend(0..2), Ok(2));
+        assert_eq!(buf.try_extend(std::iter::empty()), Ok(0));
+        assert_eq!(buf.try_extend((2..8).filter(|i| i % 2 == 0)), Ok(3));
+        assert_eq!(buf.as_slice(), [0, 1, 2, 4, 6]);
+        assert_eq!(buf.try_extend(None), Ok(0));
+        assert!(buf.try_extend(Some(7)).is_err());
+
+        let mut buf = BoundedBuffer::new(4);
+        assert!(buf.try_extend(0..5).is_err());
+        assert_eq!(buf.as_slice(), [0, 1, 2, 3]);
+
+        // Exactly at the boundary.
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_push(0);
+        assert_eq!(buf.try_extend(1..4), Ok(3));
+        assert_eq!(buf.try_extend(0..0), Ok(0));
+        assert_eq!(buf.as_slice(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_try_extend_lying_size_hint() {
+        struct Lying {
+            range: std::ops::Range<usize>,
+            hint: usize,
+        }
+
+        impl Iterator for Lying {
+            type Item = String;
+
+            fn next(&mut self) -> Option<String> {
+                self.range.next().map(|i| i.to_string())
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.hint, None)
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(4);
+        let short = Lying {
+            range: 0..2,
+            hint: 10,
+        };
+        assert_eq!(buf.try_extend(short), Ok(2));
+        let long = Lying {
+            range: 2..6,
+            hint: 0,
+        };
+        assert!(buf.try_extend(long).is_err());
+        assert_eq!(buf.as_slice(), ["0", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_try_extend_panic() {
+        let drops = Drops::default();
+        let mut buf = BoundedBuffer::new(6);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let iter = (0..6).map(|i| {
+                if i == 3 {
+                    panic!("iterator failed");
+                }
+                DropCount(drops.clone())
+            });
+            buf.try_extend(iter)
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 3);
+        drop(buf);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut buf = BoundedBuffer::new(6);
+        buf.extend([1, 2]);
+        buf.extend(&[3, 4]);
+        buf.extend([5, 6].iter());
+        assert_eq!(buf.as_slice(), [1, 2, 3, 4, 5, 6]);
+        buf.extend(std::iter::empty::<i32>());
+    }
+
+    #[test]
+    #[should_panic(expected = "extended past capacity (is 3)")]
+    fn test_extend_overflow() {
+        let mut buf = BoundedBuffer::new(3);
+        buf.extend(0..4);
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = BoundedBuffer::with_capacity_from_elem(8, 1, 3);
+        let b = BoundedBuffer::from_elem(1, 3);
+        assert_eq!(a, b);
+        assert_ne!(a.capacity(), b.capacity());
+        assert_ne!(a, BoundedBuffer::from_elem(1, 2));
+
+        let v = vec![1, 1, 1];
+        assert_eq!(a, v);
+        assert_eq!(v, a);
+        assert_eq!(a, [1, 1, 1]);
+        assert_eq!([1, 1, 1], a);
+        assert_eq!(a, &[1, 1, 1]);
+        assert_eq!(a, v[..]);
+        assert_eq!(v[..], a);
+        assert_eq!(a, &v[..]);
+        assert_eq!(&v[..], a);
+        assert_ne!(a, [1, 1]);
+
+        // Different element types that compare with each other.
+        let strings = BoundedBuffer::from_elem(String::from("x"), 2);
+        assert_eq!(strings, ["x", "x"][..]);
+        assert_eq!(strings, &["x", "x"][..]);
+        assert_eq!(strings, vec!["x"; 2]);
+
+        let empty = BoundedBuffer::<u8>::new(4);
+        assert_eq!(empty, BoundedBuffer::<u8>::new(0));
+        assert_eq!(empty, []);
+        assert_eq!([], empty);
+    }
+
+    #[test]
+    fn test_ord() {
+        let buf = |s: &[i32]| BoundedBuffer::try_from(s).unwrap();
+        assert!(buf(&[1, 2]) < buf(&[1, 3]));
+        assert!(buf(&[1, 2]) < buf(&[1, 2, 0]));
+        assert!(buf(&[]) < buf(&[0]));
+        assert_eq!(buf(&[4]).cmp(&buf(&[4])), std::cmp::Ordering::Equal);
+
+        let mut bufs = vec![buf(&[3]), buf(&[1, 5]), buf(&[1])];
+        bufs.sort();
+        assert_eq!(bufs, [buf(&[1]), buf(&[1, 5]), buf(&[3])]);
+
+        let nan = BoundedBuffer::from_elem(f64::NAN, 1);
+        assert_eq!(nan.partial_cmp(&nan), None);
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashMap;
+        use std::hash::{BuildHasher, RandomState};
+
+        let state = RandomState::new();
+        let buf = BoundedBuffer::with_capacity_from_elem(10, 7u32, 4);
+        assert_eq!(state.hash_one(&buf), state.hash_one(buf.as_slice()));
+        assert_eq!(
+            state.hash_one(&buf),
+            state.hash_one(BoundedBuffer::from_elem(7u32, 4))
+        );
+
+        let mut map = HashMap::new();
+        map.insert(buf, "sevens");
+        assert_eq!(map.get(&BoundedBuffer::from_elem(7u32, 4)), Some(&"sevens"));
+        assert_eq!(map.get(&BoundedBuffer::from_elem(7u32, 3)), None);
+    }
+
+    #[test]
+    fn test_borrow() {
+        use std::borrow::BorrowMut;
+        use std::collections::{BTreeSet, HashMap};
+
+        let mut map = HashMap::new();
+        map.insert(BoundedBuffer::try_from(&b"GET"[..]).unwrap(), 1);
+        map.insert(BoundedBuffer::with_capacity_from_elem(16, b'x', 2), 2);
+        assert_eq!(map.get(&b"GET"[..]), Some(&1));
+        assert_eq!(map.get(&b"xx"[..]), Some(&2));
+        assert_eq!(map.get(&b"PUT"[..]), None);
+
+        let mut set = BTreeSet::new();
+        set.insert(BoundedBuffer::from_elem(3, 1));
+        set.insert(BoundedBuffer::from_elem(1, 2));
+        assert!(set.contains(&[1, 1][..]));
+        assert!(!set.contains(&[1][..]));
+
+        let mut buf = BoundedBuffer::from_elem(0, 2);
+        let slice: &mut [i32] = buf.borrow_mut();
+        slice[1] = 5;
+        assert_eq!(buf, [0, 5]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Tick {
+            symbol: String,
+            prices: BoundedBuffer<u32>,
+        }
+
+        let mut ticks = BoundedBuffer::new(4);
+        for (symbol, n) in [("A", 2), ("BC", 0), ("D", 3)] {
+            ticks.try_push(Tick {
+                symbol: symbol.to_string(),
+                prices: BoundedBuffer::from_fn(n, |i| i as u32 * 100),
+            });
+        }
+
+        let json = serde_json::to_string(&ticks).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"symbol":"A","prices":[0,100]},{"symbol":"BC","prices":[]},{"symbol":"D","prices":[0,100,200]}]"#
+        );
+        let back: BoundedBuffer<Tick> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, ticks);
+        assert_eq!(back.capacity(), 3);
+
+        let bytes = bincode::serialize(&ticks).unwrap();
+        let back: BoundedBuffer<Tick> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(back, ticks);
+        assert_eq!(back.capacity(), 3);
+
+        // Same wire format as `Vec<T>` both ways.
+        let json = serde_json::to_string(&ticks[2].prices).unwrap();
+        let vec: Vec<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(vec, [0, 100, 200]);
+        let buf: BoundedBuffer<u32> =
+            bincode::deserialize(&bincode::serialize(&vec![7u32; 5]).unwrap()).unwrap();
+        assert_eq!(buf, [7; 5]);
+
+        let empty: BoundedBuffer<String> = serde_json::from_str("[]").unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.capacity(), 0);
+        assert_eq!(serde_json::to_string(&empty).unwrap(), "[]");
+        assert!(serde_json::from_str::<BoundedBuffer<u8>>("{}").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_hostile_size_hint() {
+        use serde::de::{
+            value::U16Deserializer, DeserializeSeed, IntoDeserializer, SeqAccess, Visitor,
+        };
+
+        // A sequence that claims to hold `usize::MAX` elements but only has a
+        // few.
+        struct Lying(std::ops::Range<u16>);
+
+        impl<'de> SeqAccess<'de> for Lying {
+            type Error = serde::de::value::Error;
+
+            fn next_element_seed<S: DeserializeSeed<'de>>(
+                &mut self,
+                seed: S,
+            ) -> Result<Option<S::Value>, Self::Error> {
+                self.0
+                    .next()
+                    .map(|x| {
+                        seed.deserialize::<U16Deserializer<Self::Error>>(x.into_deserializer())
+                    })
+                    .transpose()
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(usize::MAX)
+            }
+        }
+
+        impl<'de> serde::Deserializer<'de> for Lying {
+            type Error = serde::de::value::Error;
+
+            fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.visit_seq(self)
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map struct enum identifier ignored_any
+            }
+        }
+
+        let buf: BoundedBuffer<u16> = serde::Deserialize::deserialize(Lying(0..3)).unwrap();
+        assert_eq!(buf, [0, 1, 2]);
+        assert_eq!(buf.capacity(), 3);
+
+        // A huge length prefix on the wire fails on the missing elements
+        // instead of allocating for all of them up front.
+        let mut bytes = bincode::serialize(&vec![1u64, 2, 3]).unwrap();
+        bytes[..8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        assert!(bincode::deserialize::<BoundedBuffer<u64>>(&bytes).is_err());
+
+        // Growing past the preallocation limit still works.
+        let n = if cfg!(miri) { 100 } else { 300_000 };
+        let big: Vec<u64> = (0..n).collect();
+        let buf: BoundedBuffer<u64> =
+            bincode::deserialize(&bincode::serialize(&big).unwrap()).unwrap();
+        assert_eq!(buf, big);
+        assert_eq!(buf.capacity(), n as usize);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_seed() {
+        use super::BoundedBufferSeed;
+        use bincode::Options;
+        use serde::de::DeserializeSeed;
+
+        let mut buf = BoundedBuffer::new(3);
+        buf.try_push(String::from("old"));
+        let ptr = buf.as_ptr();
+
+        for frame in [vec!["a", "b"], vec![], vec!["c", "d", "e"]] {
+            let bytes = bincode::options().serialize(&frame).unwrap();
+            let mut de = bincode::Deserializer::from_slice(&bytes, bincode::options());
+            BoundedBufferSeed(&mut buf).deserialize(&mut de).unwrap();
+            assert_eq!(buf, frame);
+        }
+        assert_eq!(buf.capacity(), 3);
+        assert_eq!(buf.as_ptr() as usize, ptr as usize);
+
+        let mut de = serde_json::Deserializer::from_str(r#"["f","g","h","i"]"#);
+        let err = BoundedBufferSeed(&mut buf)
+            .deserialize(&mut de)
+            .unwrap_err();
+        assert!(err.to_string().contains("insufficient capacity"));
+        assert_eq!(buf, ["f", "g", "h"][..]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_seed_drops() {
+        use super::BoundedBufferSeed;
+        use serde::de::DeserializeSeed;
+
+        thread_local! {
+            static DROPS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Counted(#[allow(dead_code)] u8);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.set(DROPS.get() + 1);
+            }
+        }
+
+        let mut buf = BoundedBuffer::<Counted>::new(4);
+        let mut de = serde_json::Deserializer::from_str("[1,2,3]");
+        BoundedBufferSeed(&mut buf).deserialize(&mut de).unwrap();
+        assert_eq!(buf.len(), 3);
+        assert_eq!(DROPS.get(), 0);
+
+        let mut de = serde_json::Deserializer::from_str("[4]");
+        BoundedBufferSeed(&mut buf).deserialize(&mut de).unwrap();
+        assert_eq!(buf.len(), 1);
+        assert_eq!(DROPS.get(), 3);
+
+        // The element that did not fit is dropped too.
+        let mut de = serde_json::Deserializer::from_str("[5,6,7,8,9]");
+        assert!(BoundedBufferSeed(&mut buf).deserialize(&mut de).is_err());
+        assert_eq!(buf.len(), 4);
+        assert_eq!(DROPS.get(), 5);
+
+        drop(buf);
+        assert_eq!(DROPS.get(), 9);
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_borsh_matches_vec() {
+        #[derive(Debug, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize)]
+        struct Order {
+            id: u64,
+            tags: BoundedBuffer<String>,
+        }
+
+        let mut tags = BoundedBuffer::new(3);
+        tags.try_push("ioc".to_string());
+        tags.try_push("post-only".to_string());
+        let order = Order { id: 7, tags };
+        let bytes = borsh::to_vec(&order).unwrap();
+        let vec_bytes = borsh::to_vec(&(7u64, vec!["ioc", "post-only"])).unwrap();
+        assert_eq!(bytes, vec_bytes);
+
+        let back: Order = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(back, order);
+        assert_eq!(back.tags.capacity(), 2);
+
+        let empty = BoundedBuffer::<u32>::new(8);
+        let bytes = borsh::to_vec(&empty).unwrap();
+        assert_eq!(bytes, borsh::to_vec(&Vec::<u32>::new()).unwrap());
+        let back: BoundedBuffer<u32> = borsh::from_slice(&bytes).unwrap();
+        assert!(back.is_empty());
+        assert_eq!(back.capacity(), 0);
+
+        // A full buffer round-trips through `Vec<T>` in both directions.
+        let full = BoundedBuffer::from_fn(5, |i| i as u16 * 3);
+        let bytes = borsh::to_vec(&full).unwrap();
+        let vec: Vec<u16> = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(vec, full);
+        let back: BoundedBuffer<u16> = borsh::from_slice(&borsh::to_vec(&vec).unwrap()).unwrap();
+        assert_eq!(back, full);
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_borsh_length_prefix() {
+        // Longer than the preallocation limit: grows as elements arrive and
+        // still ends with the capacity equal to the length.
+        let vec: Vec<[u8; 4096]> = (0..300).map(|i| [i as u8; 4096]).collect();
+        let bytes = borsh::to_vec(&vec).unwrap();
+        let buf: BoundedBuffer<[u8; 4096]> = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(buf, vec);
+        assert_eq!(buf.capacity(), 300);
+
+        // A prefix claiming `u32::MAX` elements fails on the short input
+        // instead of allocating for it up front.
+        let mut bytes = u32::MAX.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[1, 0, 2, 0]);
+        assert!(borsh::from_slice::<BoundedBuffer<u16>>(&bytes).is_err());
+
+        // Trailing bytes are rejected like they are for `Vec<T>`.
+        let mut bytes = borsh::to_vec(&vec![1u8, 2]).unwrap();
+        bytes.push(3);
+        assert!(borsh::from_slice::<BoundedBuffer<u8>>(&bytes).is_err());
+
+        let zst = BoundedBuffer::from_elem((), 3);
+        assert!(borsh::to_vec(&zst).is_err());
+        assert!(borsh::from_slice::<BoundedBuffer<()>>(&[3, 0, 0, 0]).is_err());
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn test_rkyv_zero_copy() {
+        use rkyv::{rancor::Error, vec::ArchivedVec};
+
+        #[derive(Debug, PartialEq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+        struct Tick {
+            seq: u64,
+            prices: BoundedBuffer<u32>,
+        }
+
+        let mut ticks = BoundedBuffer::new(4);
+        for seq in 0..3 {
+            let prices = BoundedBuffer::from_fn(seq as usize + 1, |i| 100 + i as u32);
+            ticks.try_push(Tick { seq, prices });
+        }
+        let bytes = rkyv::to_bytes::<Error>(&ticks).unwrap();
+
+        // Same bytes as the equivalent `Vec`s.
+        let as_vecs: Vec<(u64, Vec<u32>)> =
+            ticks.iter().map(|t| (t.seq, t.prices.to_vec())).collect();
+        assert_eq!(bytes[..], rkyv::to_bytes::<Error>(&as_vecs).unwrap()[..]);
+
+        let archived = rkyv::access::<ArchivedVec<ArchivedTick>, Error>(&bytes).unwrap();
+        assert_eq!(archived.len(), 3);
+        assert_eq!(archived[2].seq, 2);
+        assert_eq!(archived[2].prices, [100, 101, 102]);
+        assert_eq!(archived[1].prices, ticks[1].prices);
+
+        let back: BoundedBuffer<Tick> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(back, ticks);
+        assert_eq!(back.capacity(), 3);
+        assert_eq!(back[0].prices.capacity(), 1);
+
+        let empty = BoundedBuffer::<u64>::new(8);
+        let bytes = rkyv::to_bytes::<Error>(&empty).unwrap();
+        let archived = rkyv::access::<ArchivedVec<rkyv::Archived<u64>>, Error>(&bytes).unwrap();
+        let back: BoundedBuffer<u64> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert!(back.is_empty());
+        assert_eq!(back.capacity(), 0);
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn test_rkyv_rejects_bad_length() {
+        use rkyv::{rancor::Error, vec::ArchivedVec, Archived};
+
+        let buf = BoundedBuffer::from_fn(4, |i| i as u32);
+        let mut bytes = rkyv::to_bytes::<Error>(&buf).unwrap();
+        assert!(rkyv::access::<ArchivedVec<Archived<u32>>, Error>(&bytes).is_ok());
+
+        // The root `ArchivedVec` sits at the end: an offset, then the length.
+        let at = bytes.len() - 4;
+        bytes[at..].copy_from_slice(&5u32.to_le_bytes());
+        assert!(rkyv::access::<ArchivedVec<Archived<u32>>, Error>(&bytes).is_err());
+        bytes[at..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(rkyv::access::<ArchivedVec<Archived<u32>>, Error>(&bytes).is_err());
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_bytemuck_bytes() {
+        #[repr(C)]
+        #[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+        struct Tick {
+            seq: u64,
+            price: u32,
+            qty: u32,
+        }
+
+        let mut ticks = BoundedBuffer::from_fn(3, |i| Tick {
+            seq: i as u64,
+            price: 100 + i as u32,
+            qty: 10,
+        });
+        assert_eq!(ticks.as_bytes().len(), 3 * 16);
+        assert_eq!(ticks.as_bytes()[16..24], 1u64.to_ne_bytes());
+
+        ticks.as_bytes_mut()[40..44].copy_from_slice(&7u32.to_ne_bytes());
+        assert_eq!(ticks[2].price, 7);
+
+        let back = BoundedBuffer::<Tick>::from_byte_slice(ticks.as_bytes(), 5).unwrap();
+        assert_eq!(back, ticks);
+        assert_eq!(back.capacity(), 5);
+
+        // Unaligned input is copied into an aligned allocation.
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(ticks.as_bytes());
+        let back = BoundedBuffer::<Tick>::from_byte_slice(&shifted[1..], 3).unwrap();
+        assert_eq!(back, ticks);
+
+        let empty = BoundedBuffer::<Tick>::from_byte_slice(&[], 2).unwrap();
+        assert!(empty.is_empty());
+        assert!(empty.as_bytes().is_empty());
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_bytemuck_from_byte_slice_errors() {
+        use bytemuck::PodCastError;
+
+        let bytes = [0u8; 12];
+        assert_eq!(
+            BoundedBuffer::<u64>::from_byte_slice(&bytes, 4).unwrap_err(),
+            PodCastError::OutputSliceWouldHaveSlop
+        );
+        assert_eq!(
+            BoundedBuffer::<u32>::from_byte_slice(&bytes[1..], 4).unwrap_err(),
+            PodCastError::OutputSliceWouldHaveSlop
+        );
+        assert_eq!(
+            BoundedBuffer::<u32>::from_byte_slice(&bytes, 2).unwrap_err(),
+            PodCastError::SizeMismatch
+        );
+        assert_eq!(
+            BoundedBuffer::<()>::from_byte_slice(&[], 2).unwrap_err(),
+            PodCastError::SizeMismatch
+        );
+        assert_eq!(
+            BoundedBuffer::<u32>::from_byte_slice(&bytes, 3).unwrap(),
+            [0; 3]
+        );
+    }
+
+    #[test]
+    fn test_io_write() {
+        use std::io::{ErrorKind, IoSlice, Write};
+
+        let mut buf = BoundedBuffer::new(8);
+        assert_eq!(buf.write(b"abc").unwrap(), 3);
+        buf.write_all(b"defgh").unwrap();
+        assert_eq!(buf, *b"abcdefgh");
+        assert_eq!(buf.write(b"i").unwrap(), 0);
+        assert_eq!(buf.write(b"").unwrap(), 0);
+        buf.write_all(b"").unwrap();
+        buf.flush().unwrap();
+
+        // Short write: only what fits is copied.
+        let mut buf = BoundedBuffer::new(4);
+        assert_eq!(buf.write(b"abcdef").unwrap(), 4);
+        assert_eq!(buf, *b"abcd");
+
+        // `write_all` is all or nothing.
+        let mut buf = BoundedBuffer::new(4);
+        buf.write_all(b"ab").unwrap();
+        let err = buf.write_all(b"cde").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(buf, *b"ab");
+        assert!(write!(buf, "{}", 123).is_err());
+
+        let mut buf = BoundedBuffer::new(5);
+        let bufs = [
+            IoSlice::new(b"ab"),
+            IoSlice::new(b""),
+            IoSlice::new(b"cde"),
+            IoSlice::new(b"f"),
+        ];
+        assert_eq!(buf.write_vectored(&bufs).unwrap(), 5);
+        assert_eq!(buf, *b"abcde");
+        assert_eq!(buf.write_vectored(&bufs).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_io_write_serde_json() {
+        use std::collections::BTreeMap;
+
+        let fields = BTreeMap::from([("px", 101), ("qty", 5)]);
+        let mut buf = BoundedBuffer::new(64);
+        serde_json::to_writer(&mut buf, &fields).unwrap();
+        assert_eq!(buf, *br#"{"px":101,"qty":5}"#);
+
+        let mut exact = BoundedBuffer::new(buf.len());
+        serde_json::to_writer(&mut exact, &fields).unwrap();
+        assert_eq!(exact, buf);
+
+        let mut small = BoundedBuffer::new(buf.len() - 1);
+        assert!(serde_json::to_writer(&mut small, &fields).is_err());
+    }
+
+    #[test]
+    fn test_reader_chunks() {
+        use std::io::Read;
+
+        let buf = BoundedBuffer::from_fn(20, |i| i as u8);
+        let mut reader = buf.reader();
+        let mut out = Vec::new();
+        let mut chunk = [0; 7];
+        loop {
+            let n = reader.read(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&chunk[..n]);
+            assert_eq!(reader.position() + reader.remaining(), 20);
+        }
+        assert_eq!(out, buf);
+        assert_eq!(reader.position(), 20);
+        assert_eq!(reader.remaining(), 0);
+
+        let mut reader = buf.reader();
+        let mut head = [0; 3];
+        reader.read_exact(&mut head).unwrap();
+        assert_eq!(head, [0, 1, 2]);
+        let mut big = [0; 18];
+        assert!(reader.read_exact(&mut big).is_err());
+        let mut rest = Vec::new();
+        assert_eq!(reader.read_to_end(&mut rest).unwrap(), 17);
+        assert_eq!(rest, buf[3..]);
+
+        let empty = BoundedBuffer::<u8>::new(4);
+        assert_eq!(empty.reader().read(&mut chunk).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_reader_buf_read() {
+        use std::io::BufRead;
+
+        let buf = BoundedBuffer::from_vec(b"one\ntwo\nthree".to_vec());
+        let mut reader = buf.reader();
+        let mut line = String::new();
+        let mut lines = Vec::new();
+        while reader.read_line(&mut line).unwrap() != 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        assert_eq!(lines, ["one\n", "two\n", "three"]);
+
+        // `fill_buf` borrows from the buffer itself.
+        let mut reader = buf.reader();
+        assert_eq!(reader.fill_buf().unwrap().as_ptr(), buf.as_ptr());
+        reader.consume(4);
+        assert_eq!(reader.fill_buf().unwrap(), b"two\nthree");
+        reader.consume(100);
+        assert_eq!(reader.position(), buf.len());
+        assert!(reader.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bounded_cursor_seek_and_write() {
+        use std::io::{Read, Seek, SeekFrom, Write};
+
+        let mut file = BoundedCursor::new(BoundedBuffer::new(10));
+        file.write_all(b"abc").unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(3)).unwrap(), 6);
+        file.write_all(b"xy").unwrap();
+        assert_eq!(file.get_ref(), b"abc\0\0\0xy");
+
+        // Overwrite across the end.
+        assert_eq!(file.seek(SeekFrom::End(-1)).unwrap(), 7);
+        file.write_all(b"zw").unwrap();
+        assert_eq!(file.get_ref(), b"abc\0\0\0xzw");
+        assert_eq!(file.stream_position().unwrap(), 9);
+
+        file.seek(SeekFrom::Start(1)).unwrap();
+        file.write_all(b"B").unwrap();
+        let mut out = [0; 4];
+        file.read_exact(&mut out).unwrap();
+        assert_eq!(out, *b"c\0\0\0");
+
+        assert!(file.seek(SeekFrom::End(-10)).is_err());
+        assert!(file.seek(SeekFrom::Current(-7)).is_err());
+        assert_eq!(file.position(), 6);
+        assert_eq!(file.seek(SeekFrom::End(0)).unwrap(), 9);
+        let mut rest = Vec::new();
+        assert_eq!(file.read_to_end(&mut rest).unwrap(), 0);
+
+        // Reading from past the end finds nothing.
+        file.set_position(20);
+        assert_eq!(file.read(&mut out).unwrap(), 0);
+        assert_eq!(file.into_inner(), *b"aBc\0\0\0xzw");
+    }
+
+    #[test]
+    fn test_bounded_cursor_capacity() {
+        use std::io::{ErrorKind, Seek, SeekFrom, Write};
+
+        let mut file = BoundedCursor::new(BoundedBuffer::new(4));
+        assert_eq!(file.write(b"abcdef").unwrap(), 4);
+        assert_eq!(file.write(b"g").unwrap_err().kind(), ErrorKind::WriteZero);
+        assert_eq!(file.write(b"").unwrap(), 0);
+
+        file.seek(SeekFrom::Start(2)).unwrap();
+        let err = file.write_all(b"xyz").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(file.get_ref(), b"abcd");
+        assert_eq!(file.position(), 2);
+        file.write_all(b"xy").unwrap();
+        assert_eq!(file.get_ref(), b"abxy");
+
+        // A gap past the capacity is never filled.
+        let mut file = BoundedCursor::new(BoundedBuffer::new(4));
+        file.seek(SeekFrom::Start(6)).unwrap();
+        assert!(file.write(b"a").is_err());
+        assert!(file.write_all(b"a").is_err());
+        assert!(file.get_ref().is_empty());
+        file.seek(SeekFrom::Start(3)).unwrap();
+        assert_eq!(file.write(b"ab").unwrap(), 1);
+        assert_eq!(file.into_inner(), *b"\0\0\0a");
+    }
+
+    #[test]
+    fn test_fmt_write() {
+        use std::fmt::Write;
+
+        // Exactly fills the buffer.
+        let mut buf = BoundedBuffer::new(10);
+        let (sym, px) = ("ESZ5", 5012);
+        write!(buf, "{sym}:{px}").unwrap();
+        buf.write_char('!').unwrap();
+        assert_eq!(buf, *b"ESZ5:5012!");
+        assert!(buf.write_str("").is_ok());
+        assert!(buf.write_char('x').is_err());
+
+        // Overflows mid-argument: the literal before it stays, none of the
+        // argument is written.
+        let mut buf = BoundedBuffer::new(8);
+        let px = 123456;
+        assert!(write!(buf, "px={px}").is_err());
+        assert_eq!(buf, *b"px=");
+
+        // Padding is written a character at a time, and stops at capacity.
+        let mut buf = BoundedBuffer::new(4);
+        assert!(write!(buf, "{:>8}", 1).is_err());
+        assert_eq!(buf, *b"    ");
+
+        let mut buf = BoundedBuffer::new(6);
+        buf.push_str("héllo").unwrap();
+        assert_eq!(buf.push_str("!"), Err(CapacityError::new(())));
+        assert_eq!(std::str::from_utf8(&buf).unwrap(), "héllo");
+    }
+
+    #[test]
+    fn test_push_fmt() {
+        let (qty, px) = (5, 7);
+        let mut buf = BoundedBuffer::new(13);
+        assert_eq!(buf.push_fmt(format_args!("qty={qty}")), Ok(5));
+        assert_eq!(buf.push_fmt(format_args!("")), Ok(0));
+        assert_eq!(buf.push_fmt(format_args!(";px={px:04}")), Ok(8));
+        assert!(buf.push_fmt(format_args!("x")).is_err());
+        assert_eq!(buf, *b"qty=5;px=0007");
+
+        // A failed write leaves nothing behind, even pieces that fit.
+        let mut buf = BoundedBuffer::new(8);
+        buf.push_str("ab").unwrap();
+        let id = 12345;
+        assert!(buf.push_fmt(format_args!("cd{id}")).is_err());
+        assert_eq!(buf, *b"ab");
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_prost() {
+        use bytes::{Buf, BufMut};
+        use prost::Message;
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct Quote {
+            #[prost(string, tag = "1")]
+            sym: String,
+            #[prost(uint64, tag = "2")]
+            px: u64,
+        }
+
+        let quote = Quote {
+            sym: "ESZ5".to_string(),
+            px: 501275,
+        };
+        let len = quote.encoded_len();
+        let mut buf = BoundedBuffer::new(len);
+        quote.encode(&mut buf).unwrap();
+        assert_eq!(buf, quote.encode_to_vec());
+        assert_eq!(buf.remaining_mut(), 0);
+        assert!(quote.encode(&mut buf).is_err());
+        assert_eq!(buf.len(), len);
+
+        let mut reader = buf.reader();
+        assert_eq!(Quote::decode(&mut reader).unwrap(), quote);
+        assert!(!reader.has_remaining());
+
+        let mut reader = buf.reader();
+        reader.advance(2);
+        assert_eq!(reader.chunk(), &buf[2..]);
+        assert_eq!(Buf::remaining(&reader), len - 2);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_length_delimited() {
+        use bytes::{BufMut, Bytes, BytesMut};
+        use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+        // Frames laid out by hand match what the codec produces.
+        let mut buf = BoundedBuffer::new(32);
+        for frame in [&b"hello"[..], b"", b"bounded"] {
+            buf.put_u32(frame.len() as u32);
+            buf.put_slice(frame);
+        }
+        let mut codec = LengthDelimitedCodec::new();
+        let mut expected = BytesMut::new();
+        for frame in ["hello", "", "bounded"] {
+            codec.encode(Bytes::from(frame), &mut expected).unwrap();
+        }
+        assert_eq!(buf, expected[..]);
+
+        let mut wire = BytesMut::from(buf.as_slice());
+        assert_eq!(codec.decode(&mut wire).unwrap().unwrap(), "hello");
+        assert_eq!(codec.decode(&mut wire).unwrap().unwrap(), "");
+        assert_eq!(codec.decode(&mut wire).unwrap().unwrap(), "bounded");
+        assert!(codec.decode(&mut wire).unwrap().is_none());
+
+        // Writing through the chunk and advancing by hand.
+        let mut buf = BoundedBuffer::new(4);
+        let chunk = buf.chunk_mut();
+        assert_eq!(chunk.len(), 4);
+        chunk.copy_from_slice(b"ab\0\0");
+        unsafe {
+            buf.advance_mut(2);
+        }
+        assert_eq!(buf, *b"ab");
+        assert_eq!(buf.chunk_mut().len(), 2);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    #[should_panic(expected = "cannot advance past `remaining_mut`")]
+    fn test_bytes_advance_mut_past_capacity() {
+        use bytes::BufMut;
+
+        let mut buf = BoundedBuffer::<u8>::new(4);
+        buf.put_u8(1);
+        unsafe {
+            buf.advance_mut(4);
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    #[should_panic(expected = "buffer overflow")]
+    fn test_bytes_put_past_capacity() {
+        use bytes::BufMut;
+
+        let mut buf = BoundedBuffer::<u8>::new(4);
+        buf.put_u8(1);
+        buf.put_u32(2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_tokio_read_from_duplex() {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut client, mut server) = tokio::io::duplex(8);
+        let writer = async move {
+            for chunk in [&b"abc"[..], b"defgh", b"ijklmn"] {
+                client.write_all(chunk).await.unwrap();
+            }
+        };
+        let reader = async {
+            let mut buf = BoundedBuffer::new(10);
+            let mut reads = 0;
+            while buf.len() < buf.capacity() {
+                assert_ne!(buf.read_from_async(&mut server).await.unwrap(), 0);
+                reads += 1;
+            }
+            assert!(reads >= 2);
+            // Full: returns without touching the stream.
+            assert_eq!(buf.read_from_async(&mut server).await.unwrap(), 0);
+            buf
+        };
+        let ((), buf) = tokio::join!(writer, reader);
+        assert_eq!(buf, *b"abcdefghij");
+
+        // The rest is still in the stream, followed by end of stream.
+        let mut rest = BoundedBuffer::new(10);
+        assert_eq!(rest.read_from_async(&mut server).await.unwrap(), 4);
+        assert_eq!(rest.read_from_async(&mut server).await.unwrap(), 0);
+        assert_eq!(rest, *b"klmn");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_tokio_read_in_place() {
+        use std::{
+            pin::Pin,
+            task::{Context, Poll},
+        };
+        use tokio::io::{AsyncRead, ReadBuf};
+
+        // Checks it is handed the buffer's own tail, then fills part of it.
+        struct Probe(*const u8);
+
+        impl AsyncRead for Probe {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut ReadBuf<'_>,
+            ) -> Poll<std::io::Result<()>> {
+                assert_eq!(buf.filled().as_ptr(), self.0);
+                assert_eq!(buf.remaining(), 6);
+                buf.put_slice(b"xyz");
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(8);
+        buf.push_str("ab").unwrap();
+        let mut probe = Probe(unsafe { buf.as_ptr().add(2) });
+        assert_eq!(buf.read_from_async(&mut probe).await.unwrap(), 3);
+        assert_eq!(buf, *b"abxyz");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_tokio_async_write() {
+        use std::io::ErrorKind;
+        use tokio::io::AsyncWriteExt;
+
+        let mut buf = BoundedBuffer::new(6);
+        buf.write_all(b"abc").await.unwrap();
+        assert_eq!(buf.write(b"defg").await.unwrap(), 3);
+        assert_eq!(buf.write(b"h").await.unwrap(), 0);
+        buf.flush().await.unwrap();
+        assert_eq!(buf, *b"abcdef");
+
+        // Copying a longer stream fails once the buffer is full.
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(b"0123456789").await.unwrap();
+        drop(client);
+        let mut buf = BoundedBuffer::new(4);
+        let err = tokio::io::copy(&mut server, &mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(buf, *b"0123");
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_rayon_matches_sequential() {
+        use rayon::prelude::*;
+
+        let n = if cfg!(miri) { 64 } else { 100_000 };
+        let mut buf = BoundedBuffer::from_fn(n, |i| i as u64);
+        let sum: u64 = buf.par_iter().sum();
+        assert_eq!(sum, buf.iter().sum::<u64>());
+
+        buf.par_iter_mut().for_each(|x| *x = *x * 3 + 1);
+        assert!(buf.iter().enumerate().all(|(i, &x)| x == i as u64 * 3 + 1));
+
+        let evens: Vec<u64> = buf.clone().into_par_iter().filter(|x| x % 2 == 0).collect();
+        let expected: Vec<u64> = buf.iter().copied().filter(|x| x % 2 == 0).collect();
+        assert_eq!(evens, expected);
+
+        let collected: BoundedBuffer<u64> = (0..n as u64).into_par_iter().collect();
+        assert_eq!(collected, BoundedBuffer::from_fn(n, |i| i as u64));
+
+        let mut out = BoundedBuffer::new(2 * n);
+        out.par_extend(buf.par_iter());
+        out.par_extend((0..n as u32).into_par_iter().rev().map(u64::from));
+        let expected: Vec<u64> = buf.iter().copied().chain((0..n as u64).rev()).collect();
+        assert_eq!(out, expected);
+
+        // All or nothing past capacity.
+        assert_eq!(
+            out.try_par_extend(rayon::iter::once(0)),
+            Err(CapacityError::new(()))
+        );
+        assert_eq!(out.len(), 2 * n);
+        let mut empty = BoundedBuffer::<u64>::new(0);
+        assert_eq!(empty.try_par_extend(rayon::iter::empty()), Ok(0));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_rayon_panic_drops() {
+        use rayon::prelude::*;
+        use std::{
+            panic::{catch_unwind, AssertUnwindSafe},
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked(usize);
+
+        impl Tracked {
+            fn new(i: usize) -> Self {
+                CREATED.fetch_add(1, Ordering::Relaxed);
+                Tracked(i)
+            }
+        }
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let n = if cfg!(miri) { 32 } else { 10_000 };
+
+        // A panic mid-drive drops every element, yielded or not.
+        let buf = BoundedBuffer::from_fn(n, Tracked::new);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            buf.into_par_iter().for_each(|t| assert!(t.0 != n / 2));
+        }));
+        assert!(result.is_err());
+        assert_eq!(DROPPED.load(Ordering::Relaxed), n);
+
+        // A panic while producing elements leaves the buffer as it was.
+        let mut buf = BoundedBuffer::new(2 * n);
+        buf.try_push(Tracked::new(0));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            buf.par_extend((0..n).into_par_iter().map(|i| {
+                assert!(i != n / 3);
+                Tracked::new(i)
+            }));
+        }));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 1);
+        drop(buf);
+        assert_eq!(
+            CREATED.load(Ordering::Relaxed),
+            DROPPED.load(Ordering::Relaxed)
+        );
+
+        // Overflow drops everything that was produced.
+        let mut buf = BoundedBuffer::new(n / 2);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            buf.par_extend((0..n).into_par_iter().map(Tracked::new));
+        }));
+        assert!(result.is_err());
+        assert!(buf.is_empty());
+        assert_eq!(
+            CREATED.load(Ordering::Relaxed),
+            DROPPED.load(Ordering::Relaxed)
+        );
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(if cfg!(miri) { 4 } else { 256 }))]
+
+        #[test]
+        fn test_proptest_push_remove(
+            mut buf in super::bounded_buffer(proptest::num::u16::ANY, 0..16),
+            x: u16,
+            at: proptest::sample::Index,
+        ) {
+            let (len, cap) = (buf.len(), buf.capacity());
+            proptest::prop_assert!(len <= cap);
+            proptest::prop_assert_eq!(buf.try_push(x), len < cap);
+            if len < cap {
+                proptest::prop_assert_eq!(buf.pop(), Some(x));
+            }
+            if !buf.is_empty() {
+                let i = at.index(buf.len());
+                let expected = buf[i];
+                proptest::prop_assert_eq!(buf.remove(i), expected);
+                proptest::prop_assert_eq!(buf.len(), len - 1);
+            }
+            proptest::prop_assert_eq!(buf.capacity(), cap);
+        }
+
+        #[test]
+        fn test_proptest_arbitrary(buf: BoundedBuffer<u8>) {
+            proptest::prop_assert!(buf.capacity() < 100);
+            proptest::prop_assert!(buf.len() <= buf.capacity());
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn test_proptest_shrinks_length_first() {
+        use proptest::test_runner::{Config, TestError, TestRunner};
+
+        let strategy = super::bounded_buffer(0u32..1000, 4..32);
+        let mut runner = TestRunner::new(Config {
+            cases: if cfg!(miri) { 8 } else { 256 },
+            max_shrink_iters: 10_000,
+            ..Config::default()
+        });
+        let result = runner.run(&strategy, |buf| {
+            assert!(buf.len() <= buf.capacity());
+            assert!((4..32).contains(&buf.capacity()));
+            proptest::prop_assert!(buf.len() < 3);
+            Ok(())
+        });
+        match result {
+            Err(TestError::Fail(_, minimal)) => {
+                assert_eq!(minimal, [0, 0, 0]);
+                assert!(minimal.capacity() >= 3);
+            }
+            other => panic!("expected a failure, got {other:?}"),
+        }
+    }
+
+    #[cfg(feature = "quickcheck")]
+    #[test]
+    fn test_quickcheck_arbitrary() {
+        use quickcheck::{Arbitrary, Gen, QuickCheck};
+
+        fn push_pop(mut buf: BoundedBuffer<u8>, x: u8) -> bool {
+            let (len, cap) = (buf.len(), buf.capacity());
+            let pushed = buf.try_push(x);
+            len <= cap && pushed == (len < cap) && (!pushed || buf.pop() == Some(x))
+        }
+        QuickCheck::new()
+            .tests(if cfg!(miri) { 4 } else { 100 })
+            .quickcheck(push_pop as fn(BoundedBuffer<u8>, u8) -> bool);
+
+        let mut g = Gen::new(20);
+        for _ in 0..if cfg!(miri) { 2 } else { 50 } {
+            let buf = BoundedBuffer::<u16>::arbitrary(&mut g);
+            assert!(buf.capacity() <= 20);
+            assert!(buf.len() <= buf.capacity());
+            for shrunk in buf.shrink().take(50) {
+                assert_eq!(shrunk.capacity(), buf.capacity());
+                assert!(shrunk.len() <= buf.len());
+            }
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize_wipes_allocation() {
+        use zeroize::Zeroize;
+
+        let mut buf = BoundedBuffer::from_elem(0xaau8, 8);
+        buf.pop();
+        buf.truncate(5);
+        let ptr = buf.as_ptr();
+        buf.zeroize();
+        assert!(buf.is_empty());
+        assert_eq!(buf.capacity(), 8);
+        // The whole allocation, including the stale popped bytes, is zero.
+        let raw = unsafe { std::slice::from_raw_parts(ptr, 8) };
+        assert_eq!(raw, [0; 8]);
+
+        let mut keys = BoundedBuffer::new(3);
+        keys.try_push(String::from("secret"));
+        keys.zeroize();
+        assert!(keys.is_empty());
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroizing_buffer_vacated_slots() {
+        use super::ZeroizingBuffer;
+
+        // Stale bytes in the spare capacity are wiped on construction.
+        let mut buf = BoundedBuffer::from_elem(0xaau8, 8);
+        buf.truncate(2);
+        let mut buf = ZeroizingBuffer::new(buf);
+        let raw = |buf: &ZeroizingBuffer<u8>| unsafe {
+            std::slice::from_raw_parts(buf.as_buffer().as_ptr(), buf.capacity()).to_vec()
+        };
+        assert_eq!(raw(&buf), [0xaa, 0xaa, 0, 0, 0, 0, 0, 0]);
+
+        buf.try_extend_from_slice(&[1, 2, 3, 4, 5]).unwrap();
+        assert!(buf.try_push(6));
+        assert_eq!(raw(&buf), [0xaa, 0xaa, 1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(buf.pop(), Some(6));
+        assert_eq!(raw(&buf), [0xaa, 0xaa, 1, 2, 3, 4, 5, 0]);
+        assert_eq!(buf.remove(0), 0xaa);
+        assert_eq!(raw(&buf), [0xaa, 1, 2, 3, 4, 5, 0, 0]);
+        assert_eq!(buf.swap_remove(1), 1);
+        assert_eq!(raw(&buf), [0xaa, 5, 2, 3, 4, 0, 0, 0]);
+        buf.truncate(2);
+        assert_eq!(raw(&buf), [0xaa, 5, 0, 0, 0, 0, 0, 0]);
+        buf[1] = 9;
+        assert_eq!(&buf[..], [0xaa, 9]);
+        buf.clear();
+        assert_eq!(raw(&buf), [0; 8]);
+        assert_eq!(buf.pop(), None);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroizing_buffer_drop() {
+        use super::ZeroizingBuffer;
+        use zeroize::Zeroize;
+
+        thread_local! {
+            static WIPES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        }
+
+        struct Secret(u64);
+
+        impl Zeroize for Secret {
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+                WIPES.set(WIPES.get() + 1);
+            }
+        }
+
+        let mut buf = ZeroizingBuffer::new(BoundedBuffer::new(4));
+        for i in 0..4 {
+            assert!(buf.try_push(Secret(i)));
+        }
+        buf.truncate(3);
+        assert_eq!(WIPES.get(), 1);
+        // Popped elements belong to the caller and are not wiped.
+        assert_eq!(buf.pop().map(|s| s.0), Some(2));
+        assert_eq!(WIPES.get(), 1);
+        drop(buf);
+        assert_eq!(WIPES.get(), 3);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_rand_fill() {
+        use rand::{distr::Uniform, rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut buf = BoundedBuffer::<u64>::new(64);
+        buf.try_push(7);
+        buf.fill_random_to_capacity(&mut rng);
+        assert_eq!(buf.len(), 64);
+        assert_eq!(buf[0], 7);
+        assert!(buf[1..].iter().any(|&x| x > u64::from(u32::MAX)));
+
+        // Same seed, same values.
+        let mut again = BoundedBuffer::<u64>::new(64);
+        again.try_push(7);
+        again.fill_random_to_capacity(&mut StdRng::seed_from_u64(42));
+        assert_eq!(again, buf);
+
+        let mut bytes = BoundedBuffer::<u8>::new(256);
+        bytes.fill_random_to_capacity(&mut rng);
+        assert!(bytes.iter().any(|&b| b != bytes[0]));
+        bytes.fill_random_to_capacity(&mut rng);
+        assert_eq!(bytes.len(), 256);
+
+        let mut dice = BoundedBuffer::<u32>::new(100);
+        dice.fill_random_to_capacity_with(&mut rng, Uniform::new_inclusive(1, 6).unwrap());
+        assert_eq!(dice.len(), 100);
+        assert!(dice.iter().all(|d| (1..=6).contains(d)));
+        assert!((1..=6).all(|d| dice.contains(&d)));
+
+        let mut flags = BoundedBuffer::<bool>::new(2);
+        assert!(flags.try_push_random(&mut rng));
+        assert!(flags.try_push_random(&mut rng));
+        assert!(!flags.try_push_random(&mut rng));
+        assert_eq!(flags.len(), 2);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_rand_choose_shuffle() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let empty = BoundedBuffer::<u32>::new(4);
+        assert_eq!(empty.choose(&mut rng), None);
+
+        let mut buf = BoundedBuffer::from_fn(50, |i| i as u32);
+        for _ in 0..20 {
+            assert!(*buf.choose(&mut rng).unwrap() < 50);
+        }
+
+        buf.shuffle(&mut rng);
+        assert_ne!(buf, BoundedBuffer::from_fn(50, |i| i as u32));
+        let mut sorted = buf.clone();
+        sorted.sort();
+        assert_eq!(sorted, BoundedBuffer::from_fn(50, |i| i as u32));
+
+        let (picked, rest) = buf.partial_shuffle(&mut rng, 10);
+        assert_eq!((picked.len(), rest.len()), (10, 40));
+        let (picked, rest) = buf.partial_shuffle(&mut rng, 80);
+        assert_eq!((picked.len(), rest.len()), (50, 0));
+        assert_eq!(buf.len(), 50);
+    }
+
+    // Hands out at most `step` bytes per read, is interrupted every third
+    // call, and fails for good once `fail_at` bytes have been read.
+    struct ShortReader {
+        data: Vec<u8>,
+        pos: usize,
+        step: usize,
+        calls: usize,
+        fail_at: usize,
+    }
+
+    impl ShortReader {
+        fn new(len: usize, step: usize, fail_at: usize) -> Self {
+            ShortReader {
+                data: (0..len).map(|i| i as u8).collect(),
+                pos: 0,
+                step,
+                calls: 0,
+                fail_at,
+            }
+        }
+    }
+
+    impl std::io::Read for ShortReader {
+        fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+            use std::io::{Error, ErrorKind};
+
+            self.calls += 1;
+            if self.calls.is_multiple_of(3) {
+                return Err(ErrorKind::Interrupted.into());
+            }
+            if self.pos >= self.fail_at {
+                return Err(Error::other("device gone"));
+            }
+            let n = out.len().min(self.step).min(self.data.len() - self.pos);
+            out[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_from_short_reads() {
+        use std::io::ErrorKind;
+
+        let mut r = ShortReader::new(100, 7, usize::MAX);
+        let mut buf = BoundedBuffer::new(64);
+        assert_eq!(buf.read_from(&mut r).unwrap(), 7);
+        assert_eq!(buf.read_from(&mut r).unwrap(), 7);
+        assert_eq!(
+            buf.read_from(&mut r).unwrap_err().kind(),
+            ErrorKind::Interrupted
+        );
+        assert_eq!(buf.len(), 14);
+
+        buf.read_exact_from(&mut r, 20).unwrap();
+        assert_eq!(buf.len(), 34);
+        assert_eq!(buf.read_to_capacity(&mut r).unwrap(), 30);
+        assert_eq!(buf, (0..64).collect::<Vec<u8>>());
+        assert_eq!(buf.read_from(&mut r).unwrap(), 0);
+
+        let err = buf.read_exact_from(&mut r, 1).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        buf.read_exact_from(&mut r, 0).unwrap();
+
+        // Running out of input.
+        let mut r = ShortReader::new(10, 4, usize::MAX);
+        let mut buf = BoundedBuffer::new(32);
+        let err = buf.read_exact_from(&mut r, 12).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(buf.len(), 10);
+        assert_eq!(buf.read_to_capacity(&mut r).unwrap(), 0);
+
+        // Larger than one read window.
+        let data: Vec<u8> = (0..20_000).map(|i| (i % 251) as u8).collect();
+        let mut buf = BoundedBuffer::new(data.len() + 5);
+        assert_eq!(buf.read_to_capacity(&mut &data[..]).unwrap(), data.len());
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn test_read_from_error_midway() {
+        use std::io::ErrorKind;
+
+        let mut r = ShortReader::new(100, 5, 12);
+        let mut buf = BoundedBuffer::new(50);
+        let err = buf.read_to_capacity(&mut r).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert_eq!(buf.len(), 15);
+        assert_eq!(buf, (0..15).collect::<Vec<u8>>());
+
+        let mut r = ShortReader::new(100, 5, 12);
+        let mut buf = BoundedBuffer::new(50);
+        assert!(buf.read_exact_from(&mut r, 30).is_err());
+        assert_eq!(buf, (0..15).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "reader returned more bytes than it was given room for")]
+    fn test_read_from_lying_reader() {
+        struct Liar;
+
+        impl std::io::Read for Liar {
+            fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+                Ok(out.len() + 1)
+            }
+        }
+
+        let mut buf = BoundedBuffer::<u8>::new(4);
+        let _ = buf.read_from(&mut Liar);
+    }
+
+    // Accepts at most `step` bytes per vectored write, spread over the
+    // slices, and is interrupted every fourth call.
+    struct TrickleWriter {
+        out: Vec<u8>,
+        step: usize,
+        calls: usize,
+    }
+
+    impl std::io::Write for TrickleWriter {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.write_vectored(&[std::io::IoSlice::new(data)])
+        }
+
+        fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> std::io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(4) {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            let mut budget = self.step;
+            for buf in bufs {
+                let n = buf.len().min(budget);
+                self.out.extend_from_slice(&buf[..n]);
+                budget -= n;
+            }
+            Ok(self.step - budget)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_all_vectored_to() {
+        let header = BoundedBuffer::from_vec(b"HDR:".to_vec());
+        let empty = BoundedBuffer::<u8>::new(4);
+        let payload = BoundedBuffer::from_fn(40, |i| b'a' + (i % 26) as u8);
+        let trailer = BoundedBuffer::from_vec(b";END".to_vec());
+        let bufs = [&header, &empty, &payload, &empty, &trailer];
+        let expected = [&header[..], &payload[..], &trailer[..]].concat();
+
+        for step in [1, 3, 4, 5, 7, 100] {
+            let mut w = TrickleWriter {
+                out: Vec::new(),
+                step,
+                calls: 0,
+            };
+            BoundedBuffer::write_all_vectored_to(&bufs, &mut w).unwrap();
+            assert_eq!(w.out, expected, "step {step}");
+        }
+
+        // More buffers than go into one call.
+        let many: Vec<_> = (0..150u8).map(|i| BoundedBuffer::from_elem(i, 2)).collect();
+        let refs: Vec<_> = many.iter().collect();
+        let mut out = Vec::new();
+        BoundedBuffer::write_all_vectored_to(&refs, &mut out).unwrap();
+        assert_eq!(out, (0..150u8).flat_map(|i| [i, i]).collect::<Vec<_>>());
+
+        BoundedBuffer::write_all_vectored_to(&[], &mut out).unwrap();
+        BoundedBuffer::write_all_vectored_to(&[&empty], &mut std::io::sink()).unwrap();
+
+        let mut full = [0u8; 6];
+        let err = BoundedBuffer::write_all_vectored_to(&bufs, &mut &mut full[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        assert_eq!(&full, b"HDR:ab");
+    }
+
+    #[test]
+    fn test_io_slices() {
+        use std::io::{Read, Write};
+
+        let head = BoundedBuffer::from_vec(b"ab".to_vec());
+        let body = BoundedBuffer::from_vec(b"cde".to_vec());
+        let mut out = Vec::new();
+        let n = out
+            .write_vectored(&[head.as_io_slice(), body.as_io_slice()])
+            .unwrap();
+        assert_eq!(n, 5);
+        assert_eq!(out, b"abcde");
+
+        let mut a = BoundedBuffer::new(3);
+        a.try_push(b'x');
+        let mut b = BoundedBuffer::new(4);
+        let mut src = &b"12345"[..];
+        let n = src
+            .read_vectored(&mut [a.spare_as_io_slice_mut(), b.spare_as_io_slice_mut()])
+            .unwrap();
+        assert_eq!(n, 5);
+        unsafe {
+            a.set_len(3);
+            b.set_len(4);
+        }
+        assert_eq!(a, *b"x12");
+        assert_eq!(b, *b"345\0");
+    }
+
+    #[test]
+    fn test_push_str_at_capacity() {
+        // "aé€😀" is 1 + 2 + 3 + 4 bytes.
+        let text = "aé€😀";
+        for cap in 0..=text.len() {
+            let mut buf = BoundedBuffer::new(cap);
+            let mut pushed = String::new();
+            for c in text.chars() {
+                if buf.push_char(c).is_ok() {
+                    pushed.push(c);
+                }
+            }
+            assert_eq!(buf.as_str(), Ok(pushed.as_str()), "cap {cap}");
+            assert!(buf.len() <= cap);
+
+            let mut buf = BoundedBuffer::new(cap);
+            buf.push_str("x").ok();
+            let before = buf.len();
+            if cap < text.len() + before {
+                assert_eq!(buf.push_str(text), Err(CapacityError::new(())));
+                assert_eq!(buf.len(), before);
+            }
+        }
+
+        let mut buf = BoundedBuffer::new(6);
+        buf.push_str("ab").unwrap();
+        buf.push_str("€").unwrap();
+        assert_eq!(buf.push_char('😀').unwrap_err().element(), '😀');
+        assert_eq!(buf.push_str("é"), Err(CapacityError::new(())));
+        buf.push_char('z').unwrap();
+        assert_eq!(buf.as_str(), Ok("ab€z"));
+        assert_eq!(buf.push_char('z').unwrap_err().element(), 'z');
+    }
+
+    #[test]
+    fn test_as_str_invalid() {
+        let buf = BoundedBuffer::from_vec(b"ok\xffok".to_vec());
+        let err = buf.as_str().unwrap_err();
+        assert_eq!(err.valid_up_to(), 2);
+        assert_eq!(buf.as_str_lossy(), "ok\u{fffd}ok");
+
+        // A multi-byte character cut off at the end.
+        let mut buf = BoundedBuffer::new(8);
+        buf.try_extend_from_slice(&"a€".as_bytes()[..3]).unwrap();
+        let err = buf.as_str().unwrap_err();
+        assert_eq!((err.valid_up_to(), err.error_len()), (1, None));
+        assert_eq!(buf.as_str_lossy(), "a\u{fffd}");
+
+        let buf = BoundedBuffer::from_vec("ünï".as_bytes().to_vec());
+        assert!(matches!(
+            buf.as_str_lossy(),
+            std::borrow::Cow::Borrowed("ünï")
+        ));
+    }
+
+    #[test]
+    fn test_bounded_string_from_utf8() {
+        use crate::BoundedString;
+
+        let mut buf = BoundedBuffer::new(10);
+        buf.push_str("日本").unwrap();
+        let s = BoundedString::from_utf8(buf).unwrap();
+        assert_eq!(s, "日本");
+        assert_eq!((s.len(), s.capacity()), (6, 10));
+        assert_eq!(s.chars().count(), 2);
+        assert_eq!(format!("{s} {s:?}"), "日本 \"日本\"");
+        let buf = s.into_bytes();
+        assert_eq!(buf.capacity(), 10);
+
+        let mut bad = BoundedBuffer::new(10);
+        bad.push_str("日").unwrap();
+        bad.try_push(0x9f);
+        let (err, back) = BoundedString::from_utf8(bad).unwrap_err();
+        assert_eq!(err.valid_up_to(), 3);
+        assert_eq!(back, [0xe6, 0x97, 0xa5, 0x9f]);
+        assert_eq!(back.capacity(), 10);
+    }
+
+    mod c_side {
+        //! Stands in for a C library on the other side of an FFI boundary.
+        use super::super::BufferHeader;
+        use std::alloc::{alloc, dealloc, Layout};
+
+        pub extern "C" fn sum(header: *const BufferHeader, data: *const u32) -> u64 {
+            let header = unsafe { &*header };
+            assert_eq!(header.elem_size, 4);
+            (0..header.len)
+                .map(|i| u64::from(unsafe { *data.add(i) }))
+                .sum()
+        }
+
+        pub extern "C" fn alloc_u32(cap: usize) -> *mut u32 {
+            unsafe { alloc(Layout::array::<u32>(cap).unwrap()).cast() }
+        }
+
+        pub extern "C" fn free_u32(data: *mut u32, cap: usize) {
+            unsafe { dealloc(data.cast(), Layout::array::<u32>(cap).unwrap()) }
+        }
+
+        // Writes `i * 10` into each of the `cap` slots and returns how many
+        // it wrote.
+        pub extern "C" fn fill(data: *mut u32, cap: usize) -> usize {
+            for i in 0..cap {
+                unsafe { data.add(i).write(i as u32 * 10) };
+            }
+            cap
+        }
+    }
+
+    #[test]
+    fn test_export_to_foreign() {
+        use super::BufferHeader;
+
+        let mut buf = BoundedBuffer::<u32>::new(8);
+        buf.try_extend_from_slice(&[1, 2, 3, 4]).unwrap();
+        let header = buf.header();
+        assert_eq!(
+            header,
+            BufferHeader {
+                len: 4,
+                cap: 8,
+                elem_size: 4
+            }
+        );
+        let (data, len) = buf.as_raw_slice();
+        assert_eq!(len, 4);
+        assert_eq!(c_side::sum(&header, data), 10);
+
+        let (data, len) = buf.as_raw_slice_mut();
+        let written = c_side::fill(unsafe { data.add(len) }, 8 - len);
+        unsafe { buf.set_len(len + written) };
+        assert_eq!(buf, [1, 2, 3, 4, 0, 10, 20, 30]);
+        assert_eq!(
+            std::mem::size_of::<BufferHeader>(),
+            3 * std::mem::size_of::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_foreign_buffer_borrowed_memory() {
+        use super::ForeignBuffer;
+        use std::ptr::NonNull;
+
+        let data = c_side::alloc_u32(6);
+        let mut buf =
+            unsafe { ForeignBuffer::from_foreign_parts(NonNull::new(data).unwrap(), 0, 6, false) };
+        assert!(!buf.owns_allocation());
+        buf.try_extend_from_slice(&[5, 6, 7]).unwrap();
+        assert!(buf.try_push(8));
+        assert_eq!(buf.pop(), Some(8));
+        buf[0] = 4;
+        assert_eq!(c_side::sum(&buf.header(), buf.as_buffer().as_ptr()), 17);
+        assert_eq!(
+            buf.try_extend_from_slice(&[0; 4]),
+            Err(CapacityError::new(()))
+        );
+        let buf = buf.into_buffer().unwrap_err();
+        drop(buf);
+
+        // The memory outlives the buffer and is freed by its owner.
+        assert_eq!(unsafe { *data.add(2) }, 7);
+        c_side::free_u32(data, 6);
+    }
+
+    #[test]
+    fn test_foreign_buffer_drops_elements_only() {
+        use super::ForeignBuffer;
+        use std::{mem::MaybeUninit, ptr::NonNull};
+
+        let drops = Drops::default();
+        let mut slots: [MaybeUninit<DropCount>; 4] = [const { MaybeUninit::uninit() }; 4];
+        slots[0].write(DropCount(drops.clone()));
+        let ptr = NonNull::new(slots.as_mut_ptr().cast::<DropCount>()).unwrap();
+        let mut buf = unsafe { ForeignBuffer::from_foreign_parts(ptr, 1, 4, false) };
+        assert!(buf.try_push(DropCount(drops.clone())));
+        assert!(buf.try_push(DropCount(drops.clone())));
+        buf.truncate(2);
+        assert_eq!(drops.get(), 1);
+        drop(buf);
+        assert_eq!(drops.get(), 3);
+
+        // Released without dropping anything.
+        slots[0].write(DropCount(drops.clone()));
+        let buf = unsafe { ForeignBuffer::from_foreign_parts(ptr, 1, 4, false) };
+        assert_eq!(buf.into_raw_parts(), (ptr, 1, 4));
+        assert_eq!(drops.get(), 3);
+        unsafe { slots[0].assume_init_drop() };
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_foreign_buffer_owned_memory() {
+        use super::ForeignBuffer;
+        use std::ptr::NonNull;
+
+        let data = c_side::alloc_u32(4);
+        let len = c_side::fill(data, 3);
+        let buf =
+            unsafe { ForeignBuffer::from_foreign_parts(NonNull::new(data).unwrap(), len, 4, true) };
+        assert!(buf.owns_allocation());
+        assert_eq!(&buf[..], [0, 10, 20]);
+        drop(buf);
+
+        let data = c_side::alloc_u32(4);
+        let len = c_side::fill(data, 4);
+        let buf =
+            unsafe { ForeignBuffer::from_foreign_parts(NonNull::new(data).unwrap(), len, 4, true) };
+        let mut buf = buf.into_buffer().unwrap();
+        buf.truncate(1);
+        buf.grow_to(16).unwrap();
+        assert_eq!(buf, [0]);
+
+        // Zero capacity never frees.
+        let buf =
+            unsafe { ForeignBuffer::<u32>::from_foreign_parts(NonNull::dangling(), 0, 0, true) };
+        assert!(buf.is_empty());
+        assert_eq!(
+            format!("{buf:?}"),
+            "ForeignBuffer { buf: [], dealloc: true }"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut buf = BoundedBuffer::<[u32; 3]>::new(16);
+        for i in 0..10 {
+            buf.try_push([i, i * i, u32::MAX - i]);
+        }
+        let mut file = Vec::new();
+        buf.write_snapshot(&mut file).unwrap();
+        assert_eq!(file.len(), 24 + 10 * 12);
+
+        let restored = BoundedBuffer::<[u32; 3]>::read_snapshot(&mut &file[..], 16).unwrap();
+        assert_eq!(restored, buf);
+        assert_eq!(restored.capacity(), 16);
+        let restored = BoundedBuffer::<[u32; 3]>::read_snapshot(&mut &file[..], 10).unwrap();
+        assert_eq!(restored, buf);
+
+        // Two snapshots back to back in one stream.
+        let small = BoundedBuffer::from_vec(vec![[7u32; 3]]);
+        small.write_snapshot(&mut file).unwrap();
+        let mut src = &file[..];
+        BoundedBuffer::<[u32; 3]>::read_snapshot(&mut src, 10).unwrap();
+        assert_eq!(
+            BoundedBuffer::<[u32; 3]>::read_snapshot(&mut src, 1).unwrap(),
+            small
+        );
+        assert!(src.is_empty());
+
+        let mut file = Vec::new();
+        BoundedBuffer::<i16>::new(0)
+            .write_snapshot(&mut file)
+            .unwrap();
+        let empty = BoundedBuffer::<i16>::read_snapshot(&mut &file[..], 0).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_rejects_bad_input() {
+        use std::io::ErrorKind;
+
+        let buf = BoundedBuffer::from_vec(vec![1u32, 2, 3, 4]);
+        let mut file = Vec::new();
+        buf.write_snapshot(&mut file).unwrap();
+
+        let err = BoundedBuffer::<u32>::read_snapshot(&mut &file[..], 3).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "snapshot holds 4 elements, more than the 3 allowed"
+        );
+
+        let err = BoundedBuffer::<u16>::read_snapshot(&mut &file[..], 8).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "snapshot elements are 4 bytes, expected 2");
+        let err = BoundedBuffer::<[u32; 2]>::read_snapshot(&mut &file[..], 8).unwrap_err();
+        assert_eq!(err.to_string(), "snapshot elements are 4 bytes, expected 8");
+
+        for cut in [0, 10, 23, 24, 30, file.len() - 1] {
+            let err = BoundedBuffer::<u32>::read_snapshot(&mut &file[..cut], 8).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof, "cut at {cut}");
+        }
+
+        let mut wrong_magic = file.clone();
+        wrong_magic[0] ^= 1;
+        let err = BoundedBuffer::<u32>::read_snapshot(&mut &wrong_magic[..], 8).unwrap_err();
+        assert_eq!(err.to_string(), "not a buffer snapshot");
+
+        let mut swapped = file.clone();
+        swapped[8..12].reverse();
+        let err = BoundedBuffer::<u32>::read_snapshot(&mut &swapped[..], 8).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "snapshot was written with the other byte order"
+        );
+
+        // A corrupt length is rejected before allocating anything.
+        let mut huge = file.clone();
+        huge[16..24].copy_from_slice(&u64::MAX.to_ne_bytes());
+        let err = BoundedBuffer::<u32>::read_snapshot(&mut &huge[..], 1 << 20).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = BoundedBuffer::<u32>::read_snapshot(&mut &file[..], usize::MAX).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+    }
+
+    #[test]
+    fn test_hex_dump_layout() {
+        let mut buf = BoundedBuffer::new(64);
+        buf.push_str("Hello, world!\n").unwrap();
+        buf.try_extend_from_slice(&[0x00, 0x7f, 0x80, 0xff, b' ', b'~'])
+            .unwrap();
+        assert_eq!(
+            buf.hex_dump().to_string(),
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 7f  |Hello, world!...|\n\
+             00000010  80 ff 20 7e                                       |.. ~|"
+        );
+
+        // Exactly two full lines: no padding and no empty third line.
+        let buf = BoundedBuffer::from_fn(32, |i| i as u8 + b'0');
+        assert_eq!(
+            buf.hex_dump().to_string(),
+            "00000000  30 31 32 33 34 35 36 37  38 39 3a 3b 3c 3d 3e 3f  |0123456789:;<=>?|\n\
+             00000010  40 41 42 43 44 45 46 47  48 49 4a 4b 4c 4d 4e 4f  |@ABCDEFGHIJKLMNO|"
+        );
+
+        assert_eq!(
+            buf.hex_dump().bytes_per_line(4).max_lines(2).to_string(),
+            "00000000  30 31 32 33  |0123|\n\
+             00000004  34 35 36 37  |4567|\n\
+             ... 24 more bytes"
+        );
+        assert_eq!(buf.hex_dump().max_lines(2).to_string().lines().count(), 2);
+        assert_eq!(buf.hex_dump().max_lines(0).to_string(), "... 32 more bytes");
+        assert_eq!(
+            buf.hex_dump().bytes_per_line(10).max_lines(1).to_string(),
+            "00000000  30 31 32 33 34 35 36 37  38 39  |0123456789|\n... 22 more bytes"
+        );
+
+        let empty = BoundedBuffer::<u8>::new(4);
+        assert_eq!(empty.hex_dump().to_string(), "");
+        assert_eq!(empty.hex_dump().max_lines(0).to_string(), "");
+    }
+
+    #[test]
+    #[should_panic(expected = "bytes per line must be positive")]
+    fn test_hex_dump_zero_width() {
+        BoundedBuffer::<u8>::new(1).hex_dump().bytes_per_line(0);
+    }
+
+    #[test]
+    fn test_hex_inline() {
+        let buf = BoundedBuffer::from_vec(vec![0xde, 0xad, 0x0b, 0xef]);
+        assert_eq!(format!("{buf:x}"), "dead0bef");
+        assert_eq!(format!("{buf:X}"), "DEAD0BEF");
+        assert_eq!(format!("{buf:#x} {buf:#X}"), "0xdead0bef 0xDEAD0BEF");
+        assert_eq!(format!("{:x}", BoundedBuffer::<u8>::new(0)), "");
     }
 }
@@ -0,0 +1,58 @@
+use borsh::{
+    error::ERROR_ZST_FORBIDDEN,
+    io::{Error, ErrorKind, Read, Result, Write},
+    BorshDeserialize, BorshSerialize,
+};
+
+use super::{BoundedBuffer, MAX_PREALLOC_BYTES};
+
+/// Serializes the elements as a `u32` length followed by each element, the
+/// same bytes `Vec<T>` produces.
+///
+/// Fails with [`ErrorKind::InvalidData`] for zero-sized elements, which
+/// borsh refuses in collections, and for buffers longer than `u32::MAX`.
+impl<T: BorshSerialize> BorshSerialize for BoundedBuffer<T> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        check_zst::<T>()?;
+        self.as_slice().serialize(writer)
+    }
+}
+
+/// Deserializes anything `Vec<T>` would into a buffer whose capacity equals
+/// its length.
+///
+/// Like the serde impl, the length prefix is only trusted up to about 1 MiB
+/// of memory (bytes, not elements); past that the buffer is grown by moving
+/// to a larger allocation as elements arrive, so a bogus prefix fails at the
+/// end of the input rather than with a huge allocation.
+impl<T: BorshDeserialize> BorshDeserialize for BoundedBuffer<T> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        check_zst::<T>()?;
+        let len = u32::deserialize_reader(reader)? as usize;
+        let max_prealloc = MAX_PREALLOC_BYTES / size_of::<T>();
+        let mut buf = BoundedBuffer::try_new(len.min(max_prealloc)).map_err(invalid_data)?;
+
+        for _ in 0..len {
+            if buf.len() == buf.capacity() {
+                let new_cap = buf.capacity().saturating_mul(2).max(4).min(len);
+                buf.grow_to(new_cap).map_err(invalid_data)?;
+            }
+            let elem = T::deserialize_reader(reader)?;
+            unsafe {
+                buf.push_unchecked(elem);
+            }
+        }
+        Ok(buf)
+    }
+}
+
+fn check_zst<T>() -> Result<()> {
+    if size_of::<T>() == 0 {
+        return Err(Error::new(ErrorKind::InvalidData, ERROR_ZST_FORBIDDEN));
+    }
+    Ok(())
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> Error {
+    Error::new(ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,161 @@
+use std::io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use super::BoundedBuffer;
+use crate::core::error::CapacityError;
+
+/// An in-memory file over an owned [`BoundedBuffer<u8>`], like
+/// `io::Cursor<Vec<u8>>` but never growing past the capacity.
+///
+/// Reads start at the position and stop at the length. Writes overwrite
+/// from the position and extend the buffer as needed; seeking past the end
+/// and then writing fills the gap with zeroes. A write that cannot place a
+/// single byte because the position is at or past the capacity fails with
+/// [`ErrorKind::WriteZero`], and [`write_all`](Write::write_all) fails
+/// without writing anything if the data does not fit.
+///
+/// ```
+/// use std::io::{Seek, SeekFrom, Write};
+///
+/// use fixed_buf::{core::bounded_buf::BoundedCursor, BoundedBuffer};
+///
+/// let mut file = BoundedCursor::new(BoundedBuffer::new(8));
+/// file.seek(SeekFrom::Start(2)).unwrap();
+/// file.write_all(b"hdr").unwrap();
+/// assert_eq!(file.into_inner(), *b"\0\0hdr");
+/// ```
+#[derive(Debug)]
+pub struct BoundedCursor {
+    buf: BoundedBuffer<u8>,
+    pos: u64,
+}
+
+impl BoundedCursor {
+    /// Wraps `buf`, positioned at the start.
+    pub fn new(buf: BoundedBuffer<u8>) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Gives the buffer back.
+    pub fn into_inner(self) -> BoundedBuffer<u8> {
+        self.buf
+    }
+
+    /// Returns the buffer.
+    pub fn get_ref(&self) -> &BoundedBuffer<u8> {
+        &self.buf
+    }
+
+    /// Returns the buffer mutably. Shrinking it leaves the position alone.
+    pub fn get_mut(&mut self) -> &mut BoundedBuffer<u8> {
+        &mut self.buf
+    }
+
+    /// Returns the position, which may be past the length.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Moves to `pos`, which may be past the length or the capacity.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    fn start(&self) -> usize {
+        usize::try_from(self.pos).unwrap_or(usize::MAX)
+    }
+
+    // `data` must fit between the position and the capacity.
+    fn write_fitting(&mut self, start: usize, data: &[u8]) {
+        let buf = &mut self.buf;
+        if start > buf.len() {
+            let _ = buf.resize(start, 0);
+        }
+        let overlap = usize::min(buf.len() - start, data.len());
+        buf[start..start + overlap].copy_from_slice(&data[..overlap]);
+        buf.extend_from_slice_truncating(&data[overlap..]);
+        self.pos = (start + data.len()) as u64;
+    }
+}
+
+fn full() -> io::Error {
+    io::Error::new(ErrorKind::WriteZero, CapacityError::new(()))
+}
+
+impl Read for BoundedCursor {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(out)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl BufRead for BoundedCursor {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let start = self.start().min(self.buf.len());
+        Ok(&self.buf[start..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl Write for BoundedCursor {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let start = self.start();
+        let room = self.buf.capacity().saturating_sub(start);
+        if room == 0 {
+            return Err(full());
+        }
+        let n = usize::min(data.len(), room);
+        self.write_fitting(start, &data[..n]);
+        Ok(n)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        let start = self.start();
+        if data.len() > self.buf.capacity().saturating_sub(start) {
+            return Err(full());
+        }
+        if !data.is_empty() {
+            self.write_fitting(start, data);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Seeks like `io::Cursor`: [`SeekFrom::End`] is relative to the length,
+/// and seeking before the start fails with [`ErrorKind::InvalidInput`].
+impl Seek for BoundedCursor {
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match style {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.buf.len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
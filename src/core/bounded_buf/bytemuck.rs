@@ -0,0 +1,75 @@
+use std::ptr;
+
+use bytemuck::{AnyBitPattern, NoUninit, Pod, PodCastError};
+
+use super::BoundedBuffer;
+
+impl<T: NoUninit> BoundedBuffer<T> {
+    /// Returns the elements as their raw bytes, in memory order.
+    ///
+    /// `NoUninit` rules out padding, so every byte is initialized.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let buf = BoundedBuffer::from_fn(2, |i| i as u16 + 1);
+    /// assert_eq!(buf.as_bytes(), [1u16.to_ne_bytes(), 2u16.to_ne_bytes()].concat());
+    /// ```
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self.as_slice())
+    }
+}
+
+impl<T: Pod> BoundedBuffer<T> {
+    /// Returns the elements as their raw bytes, mutably.
+    ///
+    /// Needs `Pod` rather than `NoUninit`: whatever is written through the
+    /// view must also be a valid `T`.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        bytemuck::cast_slice_mut(self.as_mut_slice())
+    }
+}
+
+impl<T: AnyBitPattern> BoundedBuffer<T> {
+    /// Creates a buffer with capacity `cap` holding a copy of the elements
+    /// encoded in `bytes`, the inverse of [`as_bytes`](Self::as_bytes).
+    ///
+    /// `bytes` is copied, so it need not be aligned for `T`. Fails with
+    /// [`PodCastError::OutputSliceWouldHaveSlop`] if its length is not a
+    /// multiple of `size_of::<T>()`, and with [`PodCastError::SizeMismatch`]
+    /// if `T` is zero-sized or the elements do not fit in `cap`.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let bytes = [1u32.to_ne_bytes(), 2u32.to_ne_bytes()].concat();
+    /// let buf = BoundedBuffer::<u32>::from_byte_slice(&bytes, 4).unwrap();
+    /// assert_eq!(buf, [1, 2]);
+    /// assert!(BoundedBuffer::<u32>::from_byte_slice(&bytes[1..], 4).is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`new`](Self::new) if the allocation fails.
+    #[track_caller]
+    pub fn from_byte_slice(bytes: &[u8], cap: usize) -> Result<Self, PodCastError> {
+        let size = size_of::<T>();
+        if size == 0 {
+            return Err(PodCastError::SizeMismatch);
+        }
+        if !bytes.len().is_multiple_of(size) {
+            return Err(PodCastError::OutputSliceWouldHaveSlop);
+        }
+        let len = bytes.len() / size;
+        if len > cap {
+            return Err(PodCastError::SizeMismatch);
+        }
+
+        let mut buf = Self::new(cap);
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), buf.as_mut_ptr().cast(), bytes.len());
+            buf.set_len(len);
+        }
+        Ok(buf)
+    }
+}
@@ -0,0 +1,81 @@
+use bytes::{buf::UninitSlice, Buf, BufMut};
+
+use super::{BoundedBuffer, BoundedReader};
+
+/// Writes into the spare capacity, so encoders written against `BufMut`
+/// fill the buffer in place.
+///
+/// [`remaining_mut`](BufMut::remaining_mut) is the spare capacity and never
+/// grows. Like every `BufMut`, the `put_*` methods panic if the data does
+/// not fit.
+///
+/// ```
+/// use bytes::BufMut;
+/// use fixed_buf::BoundedBuffer;
+///
+/// let mut buf = BoundedBuffer::new(6);
+/// buf.put_u16(0xbeef);
+/// buf.put_slice(b"abc");
+/// assert_eq!(buf, *b"\xbe\xefabc");
+/// assert_eq!(buf.remaining_mut(), 1);
+/// ```
+unsafe impl BufMut for BoundedBuffer<u8> {
+    fn remaining_mut(&self) -> usize {
+        self.cap - self.len
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `cnt` is more than the spare capacity.
+    #[track_caller]
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        let remaining = self.cap - self.len;
+        assert!(
+            cnt <= remaining,
+            "cannot advance past `remaining_mut`: {cnt} <= {remaining}"
+        );
+        self.len += cnt;
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        UninitSlice::uninit(self.spare_capacity_mut())
+    }
+
+    #[track_caller]
+    fn put_slice(&mut self, src: &[u8]) {
+        let remaining = self.cap - self.len;
+        assert!(
+            src.len() <= remaining,
+            "buffer overflow; remaining = {remaining}; src = {}",
+            src.len()
+        );
+        unsafe {
+            self.extend_from_slice_unchecked(src);
+        }
+    }
+}
+
+/// Reads the bytes that are left without copying them; the chunk is the
+/// rest of the buffer.
+impl Buf for BoundedReader<'_> {
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn chunk(&self) -> &[u8] {
+        &self.buf.as_slice()[self.pos..]
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `cnt` is more than the bytes that are left.
+    #[track_caller]
+    fn advance(&mut self, cnt: usize) {
+        let remaining = self.buf.len() - self.pos;
+        assert!(
+            cnt <= remaining,
+            "cannot advance past `remaining`: {cnt} <= {remaining}"
+        );
+        self.pos += cnt;
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    fmt, mem,
+    ops::{Deref, DerefMut},
+};
+
+use super::BoundedBuffer;
+
+/// A guard that rolls a [`BoundedBuffer`] back to an earlier length, created
+/// by [`BoundedBuffer::checkpoint`].
+///
+/// The guard dereferences to the buffer, so elements can be pushed through
+/// it. Dropping it (including during a panic) truncates the buffer back to
+/// the length it had at the checkpoint, dropping everything appended since;
+/// [`commit`](Self::commit) keeps them instead. Elements removed from below
+/// the checkpoint while it is held are not restored.
+pub struct Checkpoint<'a, T> {
+    pub(super) buf: &'a mut BoundedBuffer<T>,
+    pub(super) len: usize,
+}
+
+impl<T> Checkpoint<'_, T> {
+    /// Returns the length the buffer will be truncated to on rollback.
+    pub fn checkpoint_len(&self) -> usize {
+        self.len
+    }
+
+    /// Keeps everything appended since the checkpoint.
+    pub fn commit(self) {
+        mem::forget(self);
+    }
+
+    /// Drops everything appended since the checkpoint. This is what dropping
+    /// the guard does; calling it spells the intent out.
+    pub fn rollback(self) {}
+}
+
+impl<T: fmt::Debug> fmt::Debug for Checkpoint<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Checkpoint")
+            .field("len", &self.len)
+            .field("buf", &self.buf)
+            .finish()
+    }
+}
+
+impl<T> Deref for Checkpoint<'_, T> {
+    type Target = BoundedBuffer<T>;
+
+    fn deref(&self) -> &BoundedBuffer<T> {
+        self.buf
+    }
+}
+
+impl<T> DerefMut for Checkpoint<'_, T> {
+    fn deref_mut(&mut self) -> &mut BoundedBuffer<T> {
+        self.buf
+    }
+}
+
+impl<T> Drop for Checkpoint<'_, T> {
+    fn drop(&mut self) {
+        self.buf.truncate(self.len);
+    }
+}
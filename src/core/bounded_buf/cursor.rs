@@ -0,0 +1,116 @@
+use std::fmt;
+
+use super::BoundedBuffer;
+use crate::core::error::CapacityError;
+
+/// A cursor over a [`BoundedBuffer`] that can remove and insert elements at
+/// its position, created by [`BoundedBuffer::cursor_mut`].
+///
+/// Like `LinkedList`'s cursors, it points either at an element or at a
+/// "ghost" position that sits after the last element and before the first.
+/// Moving forward from the last element or backward from the first lands on
+/// the ghost, and moving on from the ghost wraps around to the other end. An
+/// empty buffer only has the ghost.
+pub struct CursorMut<'a, T> {
+    pub(super) buf: &'a mut BoundedBuffer<T>,
+    // `buf.len` stands for the ghost.
+    pub(super) index: usize,
+}
+
+impl<T> CursorMut<'_, T> {
+    /// Returns the index of the current element, or `None` at the ghost.
+    pub fn index(&self) -> Option<usize> {
+        (self.index < self.buf.len).then_some(self.index)
+    }
+
+    /// Returns the current element, or `None` at the ghost.
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.buf.get_mut(self.index)
+    }
+
+    /// Returns the element after the current one, or the first element when
+    /// at the ghost.
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = if self.index == self.buf.len {
+            0
+        } else {
+            self.index + 1
+        };
+        self.buf.get_mut(next)
+    }
+
+    /// Returns the element before the current one, or the last element when
+    /// at the ghost.
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        let prev = self.index.checked_sub(1)?;
+        self.buf.get_mut(prev)
+    }
+
+    /// Moves to the next element, or to the ghost from the last element, or
+    /// to the first element from the ghost.
+    pub fn move_next(&mut self) {
+        if self.index == self.buf.len {
+            self.index = 0;
+        } else {
+            self.index += 1;
+        }
+    }
+
+    /// Moves to the previous element, or to the ghost from the first element,
+    /// or to the last element from the ghost.
+    pub fn move_prev(&mut self) {
+        self.index = self.index.checked_sub(1).unwrap_or(self.buf.len);
+    }
+
+    /// Removes and returns the current element, moving the cursor to the
+    /// element that followed it (or the ghost). Returns `None` at the ghost.
+    pub fn remove_current(&mut self) -> Option<T> {
+        if self.index == self.buf.len {
+            return None;
+        }
+        unsafe { Some(self.buf.remove_unchecked(self.index)) }
+    }
+
+    /// Inserts `elem` before the current element, or at the end when at the
+    /// ghost. The cursor keeps pointing at the same element. Gives `elem`
+    /// back if the buffer is full.
+    pub fn insert_before(&mut self, elem: T) -> Result<(), CapacityError<T>> {
+        if self.buf.len == self.buf.cap {
+            return Err(CapacityError::new(elem));
+        }
+        unsafe {
+            self.buf.insert_unchecked(self.index, elem);
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Inserts `elem` after the current element, or at the front when at the
+    /// ghost. The cursor keeps pointing at the same element. Gives `elem`
+    /// back if the buffer is full.
+    pub fn insert_after(&mut self, elem: T) -> Result<(), CapacityError<T>> {
+        if self.buf.len == self.buf.cap {
+            return Err(CapacityError::new(elem));
+        }
+        if self.index == self.buf.len {
+            unsafe {
+                self.buf.insert_unchecked(0, elem);
+            }
+            self.index = self.buf.len;
+        } else {
+            unsafe {
+                self.buf.insert_unchecked(self.index + 1, elem);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CursorMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CursorMut")
+            .field(&self.buf)
+            .field(&self.index())
+            .finish()
+    }
+}
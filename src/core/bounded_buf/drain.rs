@@ -0,0 +1,104 @@
+use std::{fmt, iter::FusedIterator, mem, ptr, ptr::NonNull, slice};
+
+use super::BoundedBuffer;
+
+/// A draining iterator over a range of a [`BoundedBuffer`], created by
+/// [`BoundedBuffer::drain`].
+///
+/// The buffer is shortened to the start of the range as soon as the `Drain`
+/// is created. Dropping it drops any elements that were not yielded and moves
+/// the tail down to close the gap; leaking it leaves the buffer at that
+/// shorter length, with the drained range and the tail leaked.
+pub struct Drain<'a, T> {
+    pub(super) iter: slice::Iter<'a, T>,
+    pub(super) tail_start: usize,
+    pub(super) tail_len: usize,
+    pub(super) buf: NonNull<BoundedBuffer<T>>,
+}
+
+unsafe impl<T: Send> Send for Drain<'_, T> {}
+unsafe impl<T: Sync> Sync for Drain<'_, T> {}
+
+impl<T> Drain<'_, T> {
+    /// Returns the elements that have not been yielded yet.
+    pub fn as_slice(&self) -> &[T] {
+        self.iter.as_slice()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Drain<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Drain").field(&self.iter.as_slice()).finish()
+    }
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.iter
+            .next()
+            .map(|elem| unsafe { ptr::read(elem as *const T) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.iter
+            .next_back()
+            .map(|elem| unsafe { ptr::read(elem as *const T) })
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        // Moves the tail back even if dropping one of the remaining elements
+        // panics.
+        struct MoveTail<'r, 'a, T>(&'r mut Drain<'a, T>);
+
+        impl<T> Drop for MoveTail<'_, '_, T> {
+            fn drop(&mut self) {
+                if self.0.tail_len == 0 {
+                    return;
+                }
+
+                unsafe {
+                    let buf = self.0.buf.as_mut();
+                    let start = buf.len;
+                    if self.0.tail_start != start {
+                        ptr::copy(
+                            buf.slot(self.0.tail_start),
+                            buf.slot(start),
+                            self.0.tail_len,
+                        );
+                    }
+                    buf.len = start + self.0.tail_len;
+                }
+            }
+        }
+
+        let iter = mem::take(&mut self.iter);
+        let remaining = iter.len();
+        let guard = MoveTail(self);
+        if remaining == 0 {
+            return;
+        }
+
+        // Rebuild the pointer from the buffer so the drop happens with the
+        // allocation's provenance rather than through the shared slice.
+        unsafe {
+            let buf = guard.0.buf.as_ref();
+            let offset = iter.as_slice().as_ptr().offset_from(buf.slot(0)) as usize;
+            let remaining = ptr::slice_from_raw_parts_mut(buf.slot(offset), remaining);
+            ptr::drop_in_place(remaining);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::{fmt, iter::FusedIterator, ptr};
+
+use super::BoundedBuffer;
+
+/// An iterator that removes and yields the elements matching a predicate,
+/// created by [`BoundedBuffer::extract_if`].
+///
+/// Elements the predicate rejects are shifted down as the iterator advances,
+/// and dropping the iterator compacts whatever was not examined yet. If the
+/// iterator is leaked, the buffer keeps the elements that were examined and
+/// kept so far, and the unexamined tail is leaked.
+pub struct ExtractIf<'a, T, F> {
+    pub(super) buf: &'a mut BoundedBuffer<T>,
+    pub(super) idx: usize,
+    pub(super) del: usize,
+    pub(super) old_len: usize,
+    pub(super) pred: F,
+}
+
+impl<T, F> fmt::Debug for ExtractIf<'_, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf").finish_non_exhaustive()
+    }
+}
+
+impl<T, F> Iterator for ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        unsafe {
+            while self.idx < self.old_len {
+                let i = self.idx;
+                let cur = self.buf.slot(i);
+                let extract = (self.pred)(&mut *cur);
+                // Advance only after the predicate returns, so a panic leaves
+                // the current element in the unexamined tail.
+                self.idx += 1;
+                if extract {
+                    self.del += 1;
+                    self.buf.len = self.idx - self.del;
+                    return Some(ptr::read(cur));
+                }
+                if self.del > 0 {
+                    ptr::copy_nonoverlapping(cur, self.buf.slot(i - self.del), 1);
+                }
+                self.buf.len = self.idx - self.del;
+            }
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old_len - self.idx))
+    }
+}
+
+impl<T, F> FusedIterator for ExtractIf<'_, T, F> where F: FnMut(&mut T) -> bool {}
+
+impl<T, F> Drop for ExtractIf<'_, T, F> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.idx < self.old_len && self.del > 0 {
+                ptr::copy(
+                    self.buf.slot(self.idx),
+                    self.buf.slot(self.idx - self.del),
+                    self.old_len - self.idx,
+                );
+            }
+        }
+        self.buf.len = self.old_len - self.del;
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    fmt,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+use super::BoundedBuffer;
+use crate::core::error::CapacityError;
+
+/// Describes a buffer's contents to foreign code, e.g. as the first thing in
+/// a shared-memory segment with the elements following it.
+///
+/// The layout is that of the C struct
+///
+/// ```c
+/// struct buffer_header {
+///     size_t len;
+///     size_t cap;
+///     size_t elem_size;
+/// };
+/// ```
+///
+/// The header is a multiple of `size_t` in size, so elements aligned to at
+/// most `align_of::<usize>()` can start right after it. Anything more
+/// strictly aligned needs padding up to its alignment first.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferHeader {
+    /// Number of initialized elements.
+    pub len: usize,
+    /// Number of element slots in the allocation.
+    pub cap: usize,
+    /// `size_of` one element, in bytes.
+    pub elem_size: usize,
+}
+
+impl BufferHeader {
+    /// Describes `len` initialized out of `cap` elements of type `T`.
+    pub const fn new<T>(len: usize, cap: usize) -> Self {
+        Self {
+            len,
+            cap,
+            elem_size: mem::size_of::<T>(),
+        }
+    }
+}
+
+impl<T> BoundedBuffer<T> {
+    /// Returns a header describing the buffer to foreign code.
+    pub fn header(&self) -> BufferHeader {
+        BufferHeader::new::<T>(self.len, self.cap)
+    }
+
+    /// Returns a pointer to the first element and the number of initialized
+    /// elements, for handing to foreign code as a pointer and a length.
+    ///
+    /// The pointer is valid for reads of `len` elements until the buffer is
+    /// next mutated, moved to another allocation, or dropped.
+    pub fn as_raw_slice(&self) -> (*const T, usize) {
+        (self.ptr.as_ptr(), self.len)
+    }
+
+    /// Like [`as_raw_slice`](Self::as_raw_slice), but the pointer may also be
+    /// written through, for all `capacity()` slots.
+    pub fn as_raw_slice_mut(&mut self) -> (*mut T, usize) {
+        (self.ptr.as_ptr(), self.len)
+    }
+}
+
+/// A [`BoundedBuffer`] over memory that may belong to someone else, such as
+/// a shared-memory segment or an allocation made by C code, created by
+/// [`from_foreign_parts`](Self::from_foreign_parts).
+///
+/// Its elements are owned and dropped like a buffer's, but the allocation
+/// is only freed if it was handed over with `dealloc` set. Since the memory
+/// cannot be moved, nothing can change the capacity: the wrapper
+/// dereferences to the elements as a slice and to the buffer only through
+/// [`as_buffer`](Self::as_buffer), and offers the length-changing methods
+/// that stay within the allocation.
+///
+/// ```
+/// use std::{mem::MaybeUninit, ptr::NonNull};
+///
+/// use fixed_buf::core::bounded_buf::ForeignBuffer;
+///
+/// let mut segment = [MaybeUninit::<u32>::uninit(); 4];
+/// let ptr = NonNull::new(segment.as_mut_ptr().cast::<u32>()).unwrap();
+/// let mut buf = unsafe { ForeignBuffer::from_foreign_parts(ptr, 0, 4, false) };
+/// buf.try_extend_from_slice(&[1, 2, 3]).unwrap();
+/// assert_eq!(buf.header().len, 3);
+/// drop(buf);
+/// assert_eq!(unsafe { segment[2].assume_init() }, 3);
+/// ```
+pub struct ForeignBuffer<T> {
+    buf: ManuallyDrop<BoundedBuffer<T>>,
+    dealloc: bool,
+}
+
+impl<T> ForeignBuffer<T> {
+    /// Wraps `cap` slots of memory at `ptr`, the first `len` of which hold
+    /// initialized elements. The buffer takes ownership of those elements.
+    ///
+    /// With `dealloc` set, the allocation is freed on drop like a
+    /// [`BoundedBuffer`]'s. Without it, the memory is never freed and stays
+    /// valid to use once the buffer is gone.
+    ///
+    /// # Safety
+    ///
+    /// - `ptr` must be aligned to `align_of::<T>()`. Memory from `malloc` or
+    ///   `mmap` is aligned enough for primitive types at its start, but not
+    ///   necessarily at an arbitrary offset into it.
+    /// - `ptr` must be valid for reads and writes of `cap` elements for as
+    ///   long as the buffer is alive, and nothing else may access that memory
+    ///   in the meantime.
+    /// - `len` must be at most `cap`, and the first `len` elements must be
+    ///   initialized.
+    /// - With `dealloc` set, the memory must meet the requirements of
+    ///   [`BoundedBuffer::from_raw_parts`]: allocated by the global allocator
+    ///   with the layout `Layout::array::<T>(cap)`.
+    pub unsafe fn from_foreign_parts(
+        ptr: NonNull<T>,
+        len: usize,
+        cap: usize,
+        dealloc: bool,
+    ) -> Self {
+        Self {
+            buf: ManuallyDrop::new(BoundedBuffer::from_raw_parts(ptr, len, cap)),
+            dealloc,
+        }
+    }
+
+    /// Returns whether dropping the buffer frees its allocation.
+    pub fn owns_allocation(&self) -> bool {
+        self.dealloc
+    }
+
+    /// Returns the wrapped buffer.
+    pub fn as_buffer(&self) -> &BoundedBuffer<T> {
+        &self.buf
+    }
+
+    /// Returns a header describing the buffer to foreign code.
+    pub fn header(&self) -> BufferHeader {
+        self.buf.header()
+    }
+
+    /// Returns the number of elements the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.buf.cap
+    }
+
+    /// Returns a pointer to the first slot that can be written through for
+    /// all `capacity()` slots.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.buf.as_mut_ptr()
+    }
+
+    /// Appends `elem` if there is room, returning whether it was appended.
+    pub fn try_push(&mut self, elem: T) -> bool {
+        self.buf.try_push(elem)
+    }
+
+    /// Appends clones of all of `src`, or fails without writing anything if
+    /// they do not all fit.
+    pub fn try_extend_from_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        self.buf.try_extend_from_slice(src)
+    }
+
+    /// Removes and returns the last element.
+    pub fn pop(&mut self) -> Option<T> {
+        self.buf.pop()
+    }
+
+    /// Drops the elements from `new_len` on. Does nothing if `new_len` is not
+    /// less than the length.
+    pub fn truncate(&mut self, new_len: usize) {
+        self.buf.truncate(new_len);
+    }
+
+    /// Drops every element.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Returns the uninitialized slots past the length, e.g. for foreign code
+    /// to fill before [`set_len`](Self::set_len).
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        self.buf.spare_capacity_mut()
+    }
+
+    /// Sets the length without dropping or initializing anything.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`BoundedBuffer::set_len`].
+    #[track_caller]
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        self.buf.set_len(new_len);
+    }
+
+    /// Turns the wrapper into a plain buffer if it owns its allocation, or
+    /// gives it back otherwise.
+    pub fn into_buffer(self) -> Result<BoundedBuffer<T>, Self> {
+        if !self.dealloc {
+            return Err(self);
+        }
+        let mut this = ManuallyDrop::new(self);
+        unsafe { Ok(ManuallyDrop::take(&mut this.buf)) }
+    }
+
+    /// Gives up the elements and the memory without dropping or freeing
+    /// anything, returning the pointer, length and capacity.
+    pub fn into_raw_parts(self) -> (NonNull<T>, usize, usize) {
+        let this = ManuallyDrop::new(self);
+        (this.buf.ptr, this.buf.len, this.buf.cap)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ForeignBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForeignBuffer")
+            .field("buf", &*self.buf)
+            .field("dealloc", &self.dealloc)
+            .finish()
+    }
+}
+
+impl<T> Deref for ForeignBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.buf
+    }
+}
+
+impl<T> DerefMut for ForeignBuffer<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.buf
+    }
+}
+
+impl<T> Drop for ForeignBuffer<T> {
+    fn drop(&mut self) {
+        if self.dealloc {
+            unsafe { ManuallyDrop::drop(&mut self.buf) }
+        } else {
+            unsafe { ptr::drop_in_place(self.buf.as_mut_slice()) }
+        }
+    }
+}
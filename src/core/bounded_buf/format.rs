@@ -0,0 +1,84 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+    str::{self, Utf8Error},
+};
+
+use super::BoundedBuffer;
+use crate::core::error::CapacityError;
+
+impl BoundedBuffer<u8> {
+    /// Returns the bytes as a `str` if they are valid UTF-8.
+    ///
+    /// To keep the checked string around, convert the buffer with
+    /// [`BoundedString::from_utf8`](crate::BoundedString::from_utf8) instead.
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(self)
+    }
+
+    /// Returns the bytes as a `str`, replacing invalid sequences with
+    /// `U+FFFD`. Only allocates if there is something to replace.
+    pub fn as_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self)
+    }
+
+    /// Appends the bytes of `s`, or fails without writing anything if they
+    /// do not all fit. A code point is never split across the capacity.
+    pub fn push_str(&mut self, s: &str) -> Result<(), CapacityError> {
+        self.try_extend_from_slice(s.as_bytes())
+    }
+
+    /// Appends the UTF-8 encoding of `c`, or gives `c` back without writing
+    /// anything if it does not fit.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let mut buf = BoundedBuffer::new(4);
+    /// buf.push_char('a').unwrap();
+    /// assert_eq!(buf.push_char('😀').map_err(|e| e.element()), Err('😀'));
+    /// buf.push_char('é').unwrap();
+    /// assert_eq!(buf.as_str(), Ok("aé"));
+    /// ```
+    pub fn push_char(&mut self, c: char) -> Result<(), CapacityError<char>> {
+        let mut bytes = [0; 4];
+        self.push_str(c.encode_utf8(&mut bytes))
+            .map_err(|_| CapacityError::new(c))
+    }
+
+    /// Appends formatted text and returns how many bytes it took, or fails
+    /// and leaves the buffer as it was if the text does not fit.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let mut buf = BoundedBuffer::new(16);
+    /// let (sym, px) = ("ES", 5012);
+    /// assert_eq!(buf.push_fmt(format_args!("{sym}:{px}")), Ok(7));
+    /// assert!(buf.push_fmt(format_args!("|{sym}:{px}|{sym}")).is_err());
+    /// assert_eq!(buf, *b"ES:5012");
+    /// ```
+    pub fn push_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<usize, CapacityError> {
+        let start = self.len;
+        if self.write_fmt(args).is_err() {
+            self.truncate(start);
+            return Err(CapacityError::new(()));
+        }
+        Ok(self.len - start)
+    }
+}
+
+/// Appends formatted text without allocating.
+///
+/// Each piece the formatter hands over is copied whole or not at all, so on
+/// overflow the buffer keeps the pieces that fit before the failing one and
+/// nothing past the capacity. Use [`push_fmt`](BoundedBuffer::push_fmt) to
+/// undo the partial write instead.
+///
+/// This and [`io::Write`](std::io::Write) both provide `write_fmt`, so only
+/// import the one `write!` should use.
+impl Write for BoundedBuffer<u8> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s).map_err(|_| fmt::Error)
+    }
+}
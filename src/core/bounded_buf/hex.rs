@@ -0,0 +1,128 @@
+use std::fmt::{self, Write};
+
+use super::BoundedBuffer;
+
+impl BoundedBuffer<u8> {
+    /// Returns an adapter that displays the bytes like `hexdump -C`: an
+    /// offset, the bytes in hex and the bytes as ASCII on each line.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let buf = BoundedBuffer::from_vec(b"GET / HTTP/1.1\r\n".to_vec());
+    /// assert_eq!(
+    ///     buf.hex_dump().bytes_per_line(8).to_string(),
+    ///     "00000000  47 45 54 20 2f 20 48 54  |GET / HT|\n\
+    ///      00000008  54 50 2f 31 2e 31 0d 0a  |TP/1.1..|",
+    /// );
+    /// ```
+    pub fn hex_dump(&self) -> HexDump<'_> {
+        HexDump {
+            bytes: self,
+            bytes_per_line: 16,
+            max_lines: None,
+        }
+    }
+}
+
+/// Displays bytes in the classic offset / hex / ASCII layout, created by
+/// [`BoundedBuffer::hex_dump`].
+///
+/// Each line starts with the offset of its first byte as eight hex digits,
+/// followed by the bytes in lowercase hex with an extra space after every
+/// eighth, and then the bytes between `|` bars with everything outside
+/// printable ASCII shown as `.`. The last line is padded so its ASCII column
+/// lines up. Lines are separated by `\n` with none after the last, and an
+/// empty buffer displays as nothing.
+#[derive(Debug, Clone, Copy)]
+pub struct HexDump<'a> {
+    bytes: &'a [u8],
+    bytes_per_line: usize,
+    max_lines: Option<usize>,
+}
+
+impl HexDump<'_> {
+    /// Sets how many bytes go on each line. The default is 16.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    #[track_caller]
+    pub fn bytes_per_line(mut self, n: usize) -> Self {
+        assert!(n > 0, "bytes per line must be positive");
+        self.bytes_per_line = n;
+        self
+    }
+
+    /// Stops after `n` lines, ending with a line that counts the bytes left
+    /// out instead.
+    pub fn max_lines(mut self, n: usize) -> Self {
+        self.max_lines = Some(n);
+        self
+    }
+}
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per_line = self.bytes_per_line;
+        let shown = match self.max_lines {
+            Some(lines) => usize::min(self.bytes.len(), lines.saturating_mul(per_line)),
+            None => self.bytes.len(),
+        };
+        for (i, line) in self.bytes[..shown].chunks(per_line).enumerate() {
+            if i > 0 {
+                f.write_char('\n')?;
+            }
+            write!(f, "{:08x} ", i * per_line)?;
+            for col in 0..per_line {
+                if col % 8 == 0 {
+                    f.write_char(' ')?;
+                }
+                match line.get(col) {
+                    Some(byte) => write!(f, "{byte:02x} ")?,
+                    None => f.write_str("   ")?,
+                }
+            }
+            f.write_str(" |")?;
+            for &byte in line {
+                let c = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                f.write_char(c)?;
+            }
+            f.write_char('|')?;
+        }
+        let elided = self.bytes.len() - shown;
+        if elided > 0 {
+            if shown > 0 {
+                f.write_char('\n')?;
+            }
+            write!(f, "... {elided} more bytes")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the bytes as one run of lowercase hex digits, two per byte, for
+/// inline logging. `{:#x}` adds a `0x` prefix.
+impl fmt::LowerHex for BoundedBuffer<u8> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        self.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Formats the bytes as one run of uppercase hex digits, two per byte, for
+/// inline logging. `{:#X}` adds a `0x` prefix.
+impl fmt::UpperHex for BoundedBuffer<u8> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        self.iter().try_for_each(|byte| write!(f, "{byte:02X}"))
+    }
+}
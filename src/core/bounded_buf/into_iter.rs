@@ -0,0 +1,85 @@
+use std::{fmt, iter::FusedIterator, marker::PhantomData, ptr, ptr::NonNull, slice};
+
+use super::BoundedBuffer;
+
+/// An iterator that moves the elements out of a [`BoundedBuffer`], created
+/// by its [`IntoIterator`] impl.
+///
+/// It owns the allocation: dropping it drops the elements that were not
+/// yielded and frees the allocation. Leaking it leaks both.
+pub struct IntoIter<T> {
+    pub(super) ptr: NonNull<T>,
+    pub(super) cap: usize,
+    // Elements in `start..end` have not been yielded yet.
+    pub(super) start: usize,
+    pub(super) end: usize,
+    pub(super) _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for IntoIter<T> {}
+unsafe impl<T: Sync> Sync for IntoIter<T> {}
+
+impl<T> IntoIter<T> {
+    /// Returns the elements that have not been yielded yet.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr().add(self.start), self.end - self.start) }
+    }
+
+    /// Returns the elements that have not been yielded yet, mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            slice::from_raw_parts_mut(self.ptr.as_ptr().add(self.start), self.end - self.start)
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for IntoIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IntoIter").field(&self.as_slice()).finish()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        let elem = unsafe { ptr::read(self.ptr.as_ptr().add(self.start)) };
+        self.start += 1;
+        Some(elem)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        unsafe { Some(ptr::read(self.ptr.as_ptr().add(self.end))) }
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // Frees the allocation even if one of the destructors panics.
+            let _dealloc = BoundedBuffer::from_raw_parts(self.ptr, 0, self.cap);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                self.ptr.as_ptr().add(self.start),
+                self.end - self.start,
+            ));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    io::{self, ErrorKind, IoSlice, IoSliceMut, Read, Write},
+    ptr, slice,
+};
+
+use super::BoundedBuffer;
+use crate::core::error::CapacityError;
+
+// The most spare capacity zeroed for a single read. Zeroing is needed
+// because `Read::read` takes initialized memory, and `Read::read_buf`, which
+// would not need it, is not stable yet.
+const READ_WINDOW: usize = 8 * 1024;
+
+impl BoundedBuffer<u8> {
+    /// Reads once from `r` straight into the spare capacity and appends the
+    /// bytes read, returning how many there were.
+    ///
+    /// Only the part of the spare capacity handed to the reader is zeroed
+    /// first, at most 8 KiB, rather than all of it. Returns `Ok(0)` without
+    /// calling the reader if the buffer is full, so `Ok(0)` only means end
+    /// of file while there is spare capacity.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let mut buf = BoundedBuffer::new(4);
+    /// let mut src = &b"abcdef"[..];
+    /// assert_eq!(buf.read_from(&mut src).unwrap(), 4);
+    /// assert_eq!(buf.read_from(&mut src).unwrap(), 0);
+    /// assert_eq!(buf, *b"abcd");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reader claims to have read more bytes than it was
+    /// given room for.
+    #[track_caller]
+    pub fn read_from<R: Read + ?Sized>(&mut self, r: &mut R) -> io::Result<usize> {
+        self.read_window(r, self.cap - self.len)
+    }
+
+    /// Reads exactly `n` bytes from `r` into the spare capacity, retrying
+    /// reads that are interrupted.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] without reading if `n` is more
+    /// than the spare capacity, and with [`ErrorKind::UnexpectedEof`] if `r`
+    /// ends early. On any error the bytes read before it stay appended.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`read_from`](Self::read_from).
+    #[track_caller]
+    pub fn read_exact_from<R: Read + ?Sized>(&mut self, r: &mut R, n: usize) -> io::Result<()> {
+        if n > self.cap - self.len {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                CapacityError::new(()),
+            ));
+        }
+        let mut left = n;
+        while left > 0 {
+            match self.read_window(r, left) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => left -= read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads from `r` until the buffer is full or `r` ends, retrying reads
+    /// that are interrupted, and returns how many bytes were appended.
+    ///
+    /// On error the bytes read before it stay appended.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`read_from`](Self::read_from).
+    #[track_caller]
+    pub fn read_to_capacity<R: Read + ?Sized>(&mut self, r: &mut R) -> io::Result<usize> {
+        let start = self.len;
+        while self.len < self.cap {
+            match self.read_from(r) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(self.len - start)
+    }
+
+    /// Returns the bytes as an [`IoSlice`] for vectored writes.
+    pub fn as_io_slice(&self) -> IoSlice<'_> {
+        IoSlice::new(self.as_slice())
+    }
+
+    /// Zeroes the spare capacity and returns it as an [`IoSliceMut`] for
+    /// scatter reads.
+    ///
+    /// The buffer does not know how much the reader filled in; call
+    /// [`set_len`](Self::set_len) afterwards to keep those bytes. The whole
+    /// spare capacity is initialized by then, so any length up to the
+    /// capacity is sound.
+    ///
+    /// ```
+    /// use std::io::Read;
+    ///
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let (mut head, mut body) = (BoundedBuffer::new(2), BoundedBuffer::new(8));
+    /// let mut src = &b"h1payload"[..];
+    /// let n = src
+    ///     .read_vectored(&mut [head.spare_as_io_slice_mut(), body.spare_as_io_slice_mut()])
+    ///     .unwrap();
+    /// assert_eq!(n, 9);
+    /// // SAFETY: both spare regions were zeroed and the reader filled 9 bytes.
+    /// unsafe {
+    ///     head.set_len(2);
+    ///     body.set_len(7);
+    /// }
+    /// assert_eq!((&head[..], &body[..]), (&b"h1"[..], &b"payload"[..]));
+    /// ```
+    pub fn spare_as_io_slice_mut(&mut self) -> IoSliceMut<'_> {
+        let n = self.cap - self.len;
+        unsafe {
+            let dst = self.slot(self.len);
+            ptr::write_bytes(dst, 0, n);
+            IoSliceMut::new(slice::from_raw_parts_mut(dst, n))
+        }
+    }
+
+    /// Writes all the bytes of `bufs`, in order, to `w` with vectored
+    /// writes, without concatenating them first.
+    ///
+    /// Partial writes resume where the writer stopped, possibly in the
+    /// middle of a buffer, and interrupted writes are retried. Fails with
+    /// [`ErrorKind::WriteZero`] if the writer accepts nothing.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let header = BoundedBuffer::from_vec(b"len=5;".to_vec());
+    /// let payload = BoundedBuffer::from_vec(b"hello".to_vec());
+    /// let mut out = Vec::new();
+    /// BoundedBuffer::write_all_vectored_to(&[&header, &payload], &mut out).unwrap();
+    /// assert_eq!(out, b"len=5;hello");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the writer claims to have written more bytes than it was
+    /// given.
+    #[track_caller]
+    pub fn write_all_vectored_to<W: Write + ?Sized>(bufs: &[&Self], w: &mut W) -> io::Result<()> {
+        // The most slices handed to a single write.
+        const MAX_SLICES: usize = 64;
+
+        // Position of the next unwritten byte.
+        let (mut index, mut offset) = (0, 0);
+        loop {
+            while index < bufs.len() && offset == bufs[index].len {
+                index += 1;
+                offset = 0;
+            }
+            if index == bufs.len() {
+                return Ok(());
+            }
+
+            let mut slices = [IoSlice::new(&[]); MAX_SLICES];
+            slices[0] = IoSlice::new(&bufs[index][offset..]);
+            let (mut count, mut given) = (1, slices[0].len());
+            for buf in bufs[index + 1..].iter().filter(|buf| !buf.is_empty()) {
+                if count == MAX_SLICES {
+                    break;
+                }
+                slices[count] = buf.as_io_slice();
+                count += 1;
+                given += buf.len;
+            }
+
+            let mut written = match w.write_vectored(&slices[..count]) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                Ok(n) => n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            assert!(
+                written <= given,
+                "writer wrote more bytes than it was given"
+            );
+            while written > 0 {
+                let left = bufs[index].len - offset;
+                if written < left {
+                    offset += written;
+                    break;
+                }
+                written -= left;
+                index += 1;
+                offset = 0;
+            }
+        }
+    }
+
+    // Zeroes up to `max` bytes of spare capacity and reads into them.
+    #[track_caller]
+    fn read_window<R: Read + ?Sized>(&mut self, r: &mut R, max: usize) -> io::Result<usize> {
+        let n = usize::min(max, READ_WINDOW);
+        if n == 0 {
+            return Ok(0);
+        }
+        let window = unsafe {
+            let dst = self.slot(self.len);
+            ptr::write_bytes(dst, 0, n);
+            slice::from_raw_parts_mut(dst, n)
+        };
+        let read = r.read(window)?;
+        assert!(
+            read <= n,
+            "reader returned more bytes than it was given room for"
+        );
+        self.len += read;
+        Ok(read)
+    }
+}
+
+/// Appends the written bytes, never past the capacity.
+///
+/// [`write`](Write::write) and [`write_vectored`](Write::write_vectored)
+/// copy as much as fits and return the count, so a full buffer reports
+/// `Ok(0)`. [`write_all`](Write::write_all) either copies everything or
+/// fails with [`ErrorKind::WriteZero`] without writing anything. Flushing
+/// does nothing.
+///
+/// `&mut BoundedBuffer<u8>` is a writer too, through std's impl for
+/// `&mut W`.
+///
+/// ```
+/// use std::io::Write;
+///
+/// use fixed_buf::BoundedBuffer;
+///
+/// let mut buf = BoundedBuffer::new(16);
+/// write!(buf, "px={}", 101).unwrap();
+/// assert_eq!(buf, *b"px=101");
+/// ```
+impl Write for BoundedBuffer<u8> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.extend_from_slice_truncating(buf))
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let mut written = 0;
+        for buf in bufs {
+            let n = self.extend_from_slice_truncating(buf);
+            written += n;
+            if n < buf.len() {
+                break;
+            }
+        }
+        Ok(written)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.try_extend_from_slice(buf)
+            .map_err(|err| io::Error::new(ErrorKind::WriteZero, err))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
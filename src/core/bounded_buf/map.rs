@@ -0,0 +1,92 @@
+use std::{alloc::Layout, convert::Infallible, marker::PhantomData, mem, ptr, ptr::NonNull};
+
+use super::BoundedBuffer;
+
+impl<T> BoundedBuffer<T> {
+    /// Converts every element with `f`, keeping the length and capacity.
+    ///
+    /// When `T` and `U` have the same size and alignment the elements are
+    /// converted in place and the allocation is reused; otherwise a new
+    /// buffer is allocated. If `f` panics, every element is dropped exactly
+    /// once and the allocation is freed.
+    ///
+    /// # Panics
+    ///
+    /// Panics like [`new`](Self::new) if a new allocation is needed and
+    /// fails.
+    #[track_caller]
+    pub fn map<U, F>(self, mut f: F) -> BoundedBuffer<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        match self.try_map(|elem| Ok::<_, Infallible>(f(elem))) {
+            Ok(buf) => buf,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like [`map`](Self::map), but stops at the first error `f` returns,
+    /// dropping every element converted or not and returning the error.
+    #[track_caller]
+    pub fn try_map<U, E, F>(self, mut f: F) -> Result<BoundedBuffer<U>, E>
+    where
+        F: FnMut(T) -> Result<U, E>,
+    {
+        if Layout::new::<T>() != Layout::new::<U>() {
+            let mut this = self;
+            let mut out = BoundedBuffer::new(this.cap);
+            for elem in this.drain(..) {
+                unsafe {
+                    out.push_unchecked(f(elem)?);
+                }
+            }
+            return Ok(out);
+        }
+
+        let (ptr, len, cap) = self.into_raw_parts();
+        let mut guard = MapInPlace::<T, U> {
+            ptr,
+            len,
+            cap,
+            mapped: 0,
+            _marker: PhantomData,
+        };
+        while guard.mapped < len {
+            unsafe {
+                let slot = ptr.as_ptr().add(guard.mapped);
+                let elem = f(ptr::read(slot))?;
+                ptr::write(slot as *mut U, elem);
+            }
+            guard.mapped += 1;
+        }
+
+        mem::forget(guard);
+        unsafe { Ok(BoundedBuffer::from_raw_parts(ptr.cast(), len, cap)) }
+    }
+}
+
+// Owns the allocation while it holds a mix of element types: `U` values
+// below `mapped`, the `T` at `mapped` already moved out to the closure, and
+// `T` values above it. Only dropped if the closure fails or panics.
+struct MapInPlace<T, U> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+    mapped: usize,
+    _marker: PhantomData<(T, U)>,
+}
+
+impl<T, U> Drop for MapInPlace<T, U> {
+    fn drop(&mut self) {
+        unsafe {
+            // Frees the allocation last, even if a destructor panics.
+            let _dealloc = BoundedBuffer::from_raw_parts(self.ptr, 0, self.cap);
+            let base = self.ptr.as_ptr();
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(base as *mut U, self.mapped));
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                base.add(self.mapped + 1),
+                self.len - self.mapped - 1,
+            ));
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::{fmt, iter::FusedIterator};
+
+use super::BoundedBuffer;
+
+/// An iterator that pops elements off the end of a [`BoundedBuffer`] while
+/// a predicate holds, created by [`BoundedBuffer::pop_while`].
+///
+/// It stops for good at the first element the predicate rejects, which stays
+/// in the buffer.
+pub struct PopWhile<'a, T, F> {
+    pub(super) buf: &'a mut BoundedBuffer<T>,
+    pub(super) pred: F,
+    pub(super) done: bool,
+}
+
+impl<T, F> fmt::Debug for PopWhile<'_, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PopWhile").finish_non_exhaustive()
+    }
+}
+
+impl<T, F> Iterator for PopWhile<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.done {
+            return None;
+        }
+        let elem = self.buf.pop_if(&mut self.pred);
+        self.done = elem.is_none();
+        elem
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (0, Some(self.buf.len()))
+        }
+    }
+}
+
+impl<T, F> FusedIterator for PopWhile<'_, T, F> where F: FnMut(&mut T) -> bool {}
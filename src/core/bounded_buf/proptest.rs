@@ -0,0 +1,89 @@
+use proptest::{
+    arbitrary::{any_with, Arbitrary},
+    collection::{self, SizeRange, VecValueTree},
+    strategy::{NewTree, Strategy, ValueTree},
+    test_runner::TestRunner,
+};
+
+use super::BoundedBuffer;
+
+/// Returns a strategy for buffers with a capacity in `cap` and elements
+/// drawn from `elem`.
+///
+/// The capacity is picked first and the length uniformly up to it. While
+/// shrinking the capacity stays put: elements are removed first, then the
+/// remaining ones are shrunk, so the length never exceeds the capacity.
+///
+/// ```
+/// use fixed_buf::core::bounded_buf::bounded_buffer;
+/// use proptest::{prelude::*, strategy::ValueTree, test_runner::TestRunner};
+///
+/// let strategy = bounded_buffer(0u8..10, 1..8);
+/// let mut runner = TestRunner::deterministic();
+/// let buf = strategy.new_tree(&mut runner).unwrap().current();
+/// assert!((1..8).contains(&buf.capacity()));
+/// assert!(buf.len() <= buf.capacity());
+/// assert!(buf.iter().all(|&x| x < 10));
+/// ```
+pub fn bounded_buffer<S: Strategy>(elem: S, cap: impl Into<SizeRange>) -> BoundedBufferStrategy<S> {
+    BoundedBufferStrategy {
+        elem,
+        cap: cap.into(),
+    }
+}
+
+/// The strategy returned by [`bounded_buffer`].
+#[derive(Clone, Debug)]
+#[must_use = "strategies do nothing unless used"]
+pub struct BoundedBufferStrategy<S> {
+    elem: S,
+    cap: SizeRange,
+}
+
+impl<S: Strategy> Strategy for BoundedBufferStrategy<S> {
+    type Tree = BoundedBufferValueTree<S::Tree>;
+    type Value = BoundedBuffer<S::Value>;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let (lo, hi) = self.cap.start_end_incl();
+        let cap = (lo..=hi).new_tree(runner)?.current();
+        let elems = collection::vec(&self.elem, 0..=cap).new_tree(runner)?;
+        Ok(BoundedBufferValueTree { elems, cap })
+    }
+}
+
+/// The value tree of [`BoundedBufferStrategy`].
+#[derive(Clone, Debug)]
+pub struct BoundedBufferValueTree<T: ValueTree> {
+    elems: VecValueTree<T>,
+    cap: usize,
+}
+
+impl<T: ValueTree> ValueTree for BoundedBufferValueTree<T> {
+    type Value = BoundedBuffer<T::Value>;
+
+    fn current(&self) -> BoundedBuffer<T::Value> {
+        let mut buf = BoundedBuffer::new(self.cap);
+        buf.extend(self.elems.current());
+        buf
+    }
+
+    fn simplify(&mut self) -> bool {
+        self.elems.simplify()
+    }
+
+    fn complicate(&mut self) -> bool {
+        self.elems.complicate()
+    }
+}
+
+/// Buffers with a capacity in the given range, `0..100` by default, and
+/// arbitrary elements. See [`bounded_buffer`].
+impl<T: Arbitrary> Arbitrary for BoundedBuffer<T> {
+    type Parameters = (SizeRange, T::Parameters);
+    type Strategy = BoundedBufferStrategy<T::Strategy>;
+
+    fn arbitrary_with((cap, args): Self::Parameters) -> Self::Strategy {
+        bounded_buffer(any_with::<T>(args), cap)
+    }
+}
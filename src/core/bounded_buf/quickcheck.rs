@@ -0,0 +1,27 @@
+use quickcheck::{Arbitrary, Gen};
+
+use super::BoundedBuffer;
+
+/// Buffers with a capacity up to the generator's size and a length up to
+/// the capacity.
+///
+/// Shrinking keeps the capacity and shrinks the elements like `Vec<T>`
+/// does, removing elements before shrinking the ones left.
+impl<T: Arbitrary> Arbitrary for BoundedBuffer<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let cap = usize::arbitrary(g) % (g.size() + 1);
+        let len = usize::arbitrary(g) % (cap + 1);
+        let mut buf = Self::new(cap);
+        buf.extend((0..len).map(|_| T::arbitrary(g)));
+        buf
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let cap = self.cap;
+        Box::new(self.to_vec().shrink().map(move |elems| {
+            let mut buf = Self::new(cap);
+            buf.extend(elems);
+            buf
+        }))
+    }
+}
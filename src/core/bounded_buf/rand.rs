@@ -0,0 +1,88 @@
+use std::ptr;
+
+use rand::{
+    distr::{Distribution, StandardUniform},
+    seq::{IndexedRandom, SliceRandom},
+    Fill, Rng, RngExt,
+};
+
+use super::BoundedBuffer;
+use crate::core::pod::Pod;
+
+impl<T> BoundedBuffer<T> {
+    /// Fills the spare capacity with random values in bulk.
+    ///
+    /// The spare capacity is zeroed and then filled with one
+    /// [`Rng::fill`](RngExt::fill) call, which for integers is a single
+    /// `fill_bytes` over the whole region.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    /// use rand::{rngs::StdRng, SeedableRng};
+    ///
+    /// let mut rng = StdRng::seed_from_u64(7);
+    /// let mut noise = BoundedBuffer::<u8>::new(64);
+    /// noise.fill_random_to_capacity(&mut rng);
+    /// assert_eq!(noise.len(), 64);
+    /// ```
+    pub fn fill_random_to_capacity<R: Rng + ?Sized>(&mut self, rng: &mut R)
+    where
+        T: Fill + Pod,
+    {
+        let (len, n) = (self.len, self.cap - self.len);
+        unsafe {
+            let dst = self.slot(len);
+            ptr::write_bytes(dst, 0, n);
+            rng.fill(std::slice::from_raw_parts_mut(dst, n));
+        }
+        self.len = self.cap;
+    }
+
+    /// Fills the spare capacity with values sampled from `distr`, writing
+    /// each straight into its slot.
+    pub fn fill_random_to_capacity_with<R, D>(&mut self, rng: &mut R, distr: D)
+    where
+        R: Rng + ?Sized,
+        D: Distribution<T>,
+    {
+        unsafe {
+            self.extend_with_fn(self.cap - self.len, || distr.sample(rng));
+        }
+    }
+
+    /// Appends a random value if there is room, returning whether it was
+    /// appended. Nothing is drawn from `rng` when the buffer is full.
+    pub fn try_push_random<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool
+    where
+        StandardUniform: Distribution<T>,
+    {
+        if self.len == self.cap {
+            return false;
+        }
+        unsafe {
+            self.push_unchecked(rng.random());
+        }
+        true
+    }
+
+    /// Returns a uniformly chosen element, or `None` if the buffer is empty.
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&T> {
+        self.as_slice().choose(rng)
+    }
+
+    /// Shuffles the elements in place.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.as_mut_slice().shuffle(rng);
+    }
+
+    /// Moves `n` uniformly chosen elements, in random order, to the end and
+    /// returns them as the first slice, with the rest as the second. `n` is
+    /// clamped to the length. Takes `O(n)` time.
+    pub fn partial_shuffle<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        n: usize,
+    ) -> (&mut [T], &mut [T]) {
+        self.as_mut_slice().partial_shuffle(rng, n)
+    }
+}
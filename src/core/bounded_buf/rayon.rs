@@ -0,0 +1,105 @@
+use rayon::{
+    iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator},
+    slice, vec,
+};
+
+use super::BoundedBuffer;
+use crate::core::error::CapacityError;
+
+impl<T: Send> BoundedBuffer<T> {
+    /// Appends every element of the parallel iterator, or fails without
+    /// appending any if they do not all fit.
+    ///
+    /// The elements are first collected on the worker threads into one
+    /// `Vec` per split, then moved in after a single capacity check, so on
+    /// failure they have all been produced and are dropped. Returns how many
+    /// elements were appended.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    /// use rayon::prelude::*;
+    ///
+    /// let mut buf = BoundedBuffer::new(8);
+    /// assert_eq!(buf.try_par_extend((0..6).into_par_iter().map(|i| i * i)), Ok(6));
+    /// assert!(buf.try_par_extend((0..3).into_par_iter()).is_err());
+    /// assert_eq!(buf, [0, 1, 4, 9, 16, 25]);
+    /// ```
+    pub fn try_par_extend<I>(&mut self, par_iter: I) -> Result<usize, CapacityError>
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        let parts = par_iter.into_par_iter().collect_vec_list();
+        let count = parts
+            .iter()
+            .try_fold(0usize, |sum, part| sum.checked_add(part.len()));
+        let count = match count {
+            Some(count) if count <= self.cap - self.len => count,
+            _ => return Err(CapacityError::new(())),
+        };
+
+        for part in parts {
+            self.append(&mut BoundedBuffer::from_vec(part));
+        }
+        Ok(count)
+    }
+}
+
+/// Collects into a `Vec` and adopts its allocation, like `FromIterator`.
+impl<T: Send> FromParallelIterator<T> for BoundedBuffer<T> {
+    fn from_par_iter<I: IntoParallelIterator<Item = T>>(par_iter: I) -> Self {
+        Self::from_vec(Vec::from_par_iter(par_iter))
+    }
+}
+
+/// Appends every element of the parallel iterator.
+///
+/// # Panics
+///
+/// Panics if the elements do not all fit, appending none of them. Use
+/// [`BoundedBuffer::try_par_extend`] to handle that case instead.
+impl<T: Send> ParallelExtend<T> for BoundedBuffer<T> {
+    #[track_caller]
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, par_iter: I) {
+        if self.try_par_extend(par_iter).is_err() {
+            panic!("extended past capacity (is {})", self.cap);
+        }
+    }
+}
+
+/// Copies every element of the parallel iterator, with the same overflow
+/// behavior as `ParallelExtend<T>`.
+impl<'a, T: Copy + Send + Sync + 'a> ParallelExtend<&'a T> for BoundedBuffer<T> {
+    #[track_caller]
+    fn par_extend<I: IntoParallelIterator<Item = &'a T>>(&mut self, par_iter: I) {
+        self.par_extend(par_iter.into_par_iter().copied());
+    }
+}
+
+/// Moves the elements out in parallel by handing the allocation to a `Vec`,
+/// so elements not yet yielded are dropped even if a closure panics.
+impl<T: Send> IntoParallelIterator for BoundedBuffer<T> {
+    type Item = T;
+    type Iter = vec::IntoIter<T>;
+
+    fn into_par_iter(self) -> vec::IntoIter<T> {
+        self.into_vec().into_par_iter()
+    }
+}
+
+impl<'a, T: Sync> IntoParallelIterator for &'a BoundedBuffer<T> {
+    type Item = &'a T;
+    type Iter = slice::Iter<'a, T>;
+
+    fn into_par_iter(self) -> slice::Iter<'a, T> {
+        self.as_slice().into_par_iter()
+    }
+}
+
+impl<'a, T: Send> IntoParallelIterator for &'a mut BoundedBuffer<T> {
+    type Item = &'a mut T;
+    type Iter = slice::IterMut<'a, T>;
+
+    fn into_par_iter(self) -> slice::IterMut<'a, T> {
+        self.as_mut_slice().into_par_iter()
+    }
+}
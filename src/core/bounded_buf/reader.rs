@@ -0,0 +1,75 @@
+use std::io::{self, BufRead, Read};
+
+use super::BoundedBuffer;
+
+impl BoundedBuffer<u8> {
+    /// Returns a reader over the bytes, starting at the front.
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    ///
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let buf = BoundedBuffer::from_vec(b"bid 101\nask 102\n".to_vec());
+    /// let lines: Vec<String> = buf.reader().lines().map(Result::unwrap).collect();
+    /// assert_eq!(lines, ["bid 101", "ask 102"]);
+    /// ```
+    pub fn reader(&self) -> BoundedReader<'_> {
+        BoundedReader { buf: self, pos: 0 }
+    }
+}
+
+/// A reader over the bytes of a [`BoundedBuffer<u8>`], created by
+/// [`BoundedBuffer::reader`].
+///
+/// It only tracks a position: [`fill_buf`](BufRead::fill_buf) hands out the
+/// unread bytes in place, and reading never copies more than the caller
+/// asks for.
+#[derive(Clone, Debug)]
+pub struct BoundedReader<'a> {
+    pub(super) buf: &'a BoundedBuffer<u8>,
+    pub(super) pos: usize,
+}
+
+impl BoundedReader<'_> {
+    /// Returns how many bytes have been read.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns how many bytes are left to read.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+impl Read for BoundedReader<'_> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(out)?;
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
+        self.fill_buf()?.read_exact(out)?;
+        self.pos += out.len();
+        Ok(())
+    }
+
+    fn read_to_end(&mut self, out: &mut Vec<u8>) -> io::Result<usize> {
+        let n = self.fill_buf()?.read_to_end(out)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<'a> BufRead for BoundedReader<'a> {
+    fn fill_buf(&mut self) -> io::Result<&'a [u8]> {
+        Ok(&self.buf.as_slice()[self.pos..])
+    }
+
+    /// Marks `amt` bytes as read, clamped to the bytes that are left.
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt.min(self.remaining());
+    }
+}
@@ -0,0 +1,72 @@
+use rkyv::{
+    rancor::{Fallible, Source},
+    ser::{Allocator, Writer},
+    vec::{ArchivedVec, VecResolver},
+    Archive, Deserialize, Place, Serialize,
+};
+
+use super::BoundedBuffer;
+
+/// Archives the elements as an [`ArchivedVec`], the same archived form as
+/// `Vec<T>`, so they can be read in place from the archive bytes.
+///
+/// `ArchivedVec` implements `CheckBytes`, so `rkyv::access` rejects an
+/// archive whose length or offset would reach outside its bytes before any
+/// element is read.
+///
+/// ```
+/// use fixed_buf::BoundedBuffer;
+/// use rkyv::{rancor::Error, vec::ArchivedVec, Archived};
+///
+/// let buf = BoundedBuffer::from_fn(3, |i| i as u32 * 10);
+/// let bytes = rkyv::to_bytes::<Error>(&buf).unwrap();
+/// let archived = rkyv::access::<ArchivedVec<Archived<u32>>, Error>(&bytes).unwrap();
+/// assert_eq!(archived[2], 20);
+///
+/// let back: BoundedBuffer<u32> = rkyv::deserialize::<_, Error>(archived).unwrap();
+/// assert_eq!(back, buf);
+/// ```
+impl<T: Archive> Archive for BoundedBuffer<T> {
+    type Archived = ArchivedVec<T::Archived>;
+    type Resolver = VecResolver;
+
+    fn resolve(&self, resolver: VecResolver, out: Place<Self::Archived>) {
+        ArchivedVec::resolve_from_slice(self.as_slice(), resolver, out);
+    }
+}
+
+impl<T, S> Serialize<S> for BoundedBuffer<T>
+where
+    T: Serialize<S>,
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<VecResolver, S::Error> {
+        ArchivedVec::<T::Archived>::serialize_from_slice(self.as_slice(), serializer)
+    }
+}
+
+/// Deserializes into a buffer whose capacity equals the archived length.
+impl<T, D> Deserialize<BoundedBuffer<T>, D> for ArchivedVec<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+    D::Error: Source,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<BoundedBuffer<T>, D::Error> {
+        let mut buf = BoundedBuffer::try_new(self.len()).map_err(D::Error::new)?;
+        for elem in self.iter() {
+            let elem = elem.deserialize(deserializer)?;
+            unsafe {
+                buf.push_unchecked(elem);
+            }
+        }
+        Ok(buf)
+    }
+}
+
+impl<T: PartialEq<U>, U> PartialEq<BoundedBuffer<U>> for ArchivedVec<T> {
+    fn eq(&self, other: &BoundedBuffer<U>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
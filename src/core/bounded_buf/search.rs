@@ -0,0 +1,112 @@
+use super::BoundedBuffer;
+
+impl BoundedBuffer<u8> {
+    /// Returns the index of the first occurrence of `b`.
+    ///
+    /// Uses the `memchr` crate when the `memchr` feature is enabled, and a
+    /// word-at-a-time scan otherwise.
+    pub fn find_byte(&self, b: u8) -> Option<usize> {
+        #[cfg(feature = "memchr")]
+        {
+            memchr::memchr(b, self)
+        }
+        #[cfg(not(feature = "memchr"))]
+        {
+            swar::find(b, self)
+        }
+    }
+
+    /// Returns the index of the last occurrence of `b`.
+    pub fn rfind_byte(&self, b: u8) -> Option<usize> {
+        #[cfg(feature = "memchr")]
+        {
+            memchr::memrchr(b, self)
+        }
+        #[cfg(not(feature = "memchr"))]
+        {
+            swar::rfind(b, self)
+        }
+    }
+
+    /// Returns the index of the first occurrence of `needle`. An empty
+    /// needle matches at index 0.
+    pub fn find_bytes(&self, needle: &[u8]) -> Option<usize> {
+        #[cfg(feature = "memchr")]
+        {
+            memchr::memmem::find(self, needle)
+        }
+        #[cfg(not(feature = "memchr"))]
+        {
+            swar::find_bytes(needle, self)
+        }
+    }
+}
+
+// Portable fallbacks that test a whole `usize` worth of bytes per step.
+#[cfg(not(feature = "memchr"))]
+mod swar {
+    const WORD: usize = size_of::<usize>();
+    const LO: usize = usize::from_ne_bytes([0x01; WORD]);
+    const HI: usize = usize::from_ne_bytes([0x80; WORD]);
+
+    // Whether any byte of `word` equals `b`. This can report a false
+    // positive for bytes above a real match, but never misses one, so the
+    // callers confirm hits byte by byte.
+    fn contains(word: usize, b: u8) -> bool {
+        let x = word ^ (LO * b as usize);
+        x.wrapping_sub(LO) & !x & HI != 0
+    }
+
+    fn load(chunk: &[u8]) -> usize {
+        usize::from_ne_bytes(chunk.try_into().unwrap())
+    }
+
+    pub(super) fn find(b: u8, hay: &[u8]) -> Option<usize> {
+        let chunks = hay.chunks_exact(WORD);
+        let tail = chunks.remainder();
+        for (i, chunk) in chunks.enumerate() {
+            if contains(load(chunk), b) {
+                if let Some(pos) = chunk.iter().position(|&x| x == b) {
+                    return Some(i * WORD + pos);
+                }
+            }
+        }
+        let base = hay.len() - tail.len();
+        tail.iter().position(|&x| x == b).map(|pos| base + pos)
+    }
+
+    pub(super) fn rfind(b: u8, hay: &[u8]) -> Option<usize> {
+        let chunks = hay.rchunks_exact(WORD);
+        let head = chunks.remainder();
+        for (i, chunk) in chunks.enumerate() {
+            if contains(load(chunk), b) {
+                if let Some(pos) = chunk.iter().rposition(|&x| x == b) {
+                    return Some(hay.len() - (i + 1) * WORD + pos);
+                }
+            }
+        }
+        head.iter().rposition(|&x| x == b)
+    }
+
+    pub(super) fn find_bytes(needle: &[u8], hay: &[u8]) -> Option<usize> {
+        let Some((&last, rest)) = needle.split_last() else {
+            return Some(0);
+        };
+        if needle.len() > hay.len() {
+            return None;
+        }
+
+        // Scan for the needle's final byte: delimiters usually end in their
+        // rarest byte (`\r\n`, `\n\n`), which keeps false candidates rare.
+        let mut end = rest.len();
+        while let Some(pos) = find(last, &hay[end..]) {
+            let at = end + pos;
+            let start = at - rest.len();
+            if hay[start..at] == *rest {
+                return Some(start);
+            }
+            end = at + 1;
+        }
+        None
+    }
+}
@@ -0,0 +1,106 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{DeserializeSeed, Error, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{BoundedBuffer, MAX_PREALLOC_BYTES};
+use crate::core::error::CapacityError;
+
+/// Serializes the elements as a sequence, the same way `Vec<T>` does.
+impl<T: Serialize> Serialize for BoundedBuffer<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+
+/// Deserializes any sequence into a buffer whose capacity equals its length.
+///
+/// The sequence's length hint is only trusted up to about 1 MiB of memory
+/// (bytes, not elements); past that, or without a hint, the buffer is grown
+/// by moving to a larger allocation as elements arrive, and moved once more
+/// at the end to trim the capacity to the length.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for BoundedBuffer<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(BufferVisitor(PhantomData))
+    }
+}
+
+struct BufferVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for BufferVisitor<T> {
+    type Value = BoundedBuffer<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let max_prealloc = MAX_PREALLOC_BYTES / size_of::<T>().max(1);
+        let hint = seq.size_hint().unwrap_or(0).min(max_prealloc);
+        let mut buf = BoundedBuffer::try_new(hint).map_err(A::Error::custom)?;
+
+        while let Some(elem) = seq.next_element()? {
+            if buf.len() == buf.capacity() {
+                let new_cap = buf.capacity().saturating_mul(2).max(4);
+                buf.grow_to(new_cap).map_err(A::Error::custom)?;
+            }
+            unsafe {
+                buf.push_unchecked(elem);
+            }
+        }
+        buf.shrink_to(buf.len());
+        Ok(buf)
+    }
+}
+
+/// Deserializes a sequence into an existing buffer, replacing its contents
+/// without allocating.
+///
+/// The old elements are dropped first. If the sequence has more elements
+/// than the buffer's capacity, deserialization fails with a custom error
+/// wrapping [`CapacityError`]; the buffer then holds the elements that fit.
+///
+/// ```
+/// use fixed_buf::{core::bounded_buf::BoundedBufferSeed, BoundedBuffer};
+/// use serde::de::DeserializeSeed;
+///
+/// let mut buf = BoundedBuffer::<u32>::new(4);
+/// for frame in ["[1,2]", "[3,4,5]"] {
+///     let mut de = serde_json::Deserializer::from_str(frame);
+///     BoundedBufferSeed(&mut buf).deserialize(&mut de).unwrap();
+/// }
+/// assert_eq!(buf, [3, 4, 5]);
+/// ```
+pub struct BoundedBufferSeed<'a, T>(pub &'a mut BoundedBuffer<T>);
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for BoundedBufferSeed<'_, T> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for BoundedBufferSeed<'_, T> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a sequence of at most {} elements", self.0.capacity())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let buf = self.0;
+        buf.clear();
+        while let Some(elem) = seq.next_element()? {
+            if buf.len() == buf.capacity() {
+                return Err(A::Error::custom(CapacityError::new(())));
+            }
+            unsafe {
+                buf.push_unchecked(elem);
+            }
+        }
+        Ok(())
+    }
+}
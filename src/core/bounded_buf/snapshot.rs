@@ -0,0 +1,120 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    mem, ptr, slice,
+};
+
+use super::BoundedBuffer;
+use crate::core::pod::Pod;
+
+// Layout of the header, all in the writer's native byte order:
+//
+//   magic      8 bytes, `MAGIC`
+//   byte order 4 bytes, `BYTE_ORDER`, reads back differently on a machine
+//              of the other endianness
+//   elem size  4 bytes
+//   len        8 bytes, number of elements that follow
+const MAGIC: [u8; 8] = *b"FXBUFSN1";
+const BYTE_ORDER: u32 = 0x0102_0304;
+const HEADER_LEN: usize = 24;
+
+impl<T: Pod> BoundedBuffer<T> {
+    /// Writes the elements to `w` as a snapshot that
+    /// [`read_snapshot`](Self::read_snapshot) can restore.
+    ///
+    /// The elements are written as their in-memory bytes after a 24-byte
+    /// header recording the element size, the byte order and the length, so
+    /// a snapshot can only be read back on a machine of the same endianness
+    /// into the same element type. The capacity is not recorded.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    ///
+    /// let buf = BoundedBuffer::from_vec(vec![1.5f64, -2.0]);
+    /// let mut file = Vec::new();
+    /// buf.write_snapshot(&mut file).unwrap();
+    /// assert_eq!(file.len(), 24 + 16);
+    ///
+    /// let restored = BoundedBuffer::<f64>::read_snapshot(&mut &file[..], 8).unwrap();
+    /// assert_eq!(restored, buf);
+    /// assert_eq!(restored.capacity(), 8);
+    /// ```
+    pub fn write_snapshot<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        let mut header = [0; HEADER_LEN];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&BYTE_ORDER.to_ne_bytes());
+        header[12..16].copy_from_slice(&(mem::size_of::<T>() as u32).to_ne_bytes());
+        header[16..].copy_from_slice(&(self.len as u64).to_ne_bytes());
+        w.write_all(&header)?;
+        // `Pod` types have no padding, so every byte is initialized.
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                self.ptr.as_ptr() as *const u8,
+                mem::size_of_val(self.as_slice()),
+            )
+        };
+        w.write_all(bytes)
+    }
+
+    /// Reads a snapshot written by [`write_snapshot`](Self::write_snapshot)
+    /// into a new buffer with capacity `max_cap`.
+    ///
+    /// The header is checked before anything is allocated, so a corrupt or
+    /// hostile length cannot make the allocation larger than `max_cap`
+    /// elements.
+    ///
+    /// # Errors
+    ///
+    /// - [`ErrorKind::InvalidData`] if the input is not a snapshot, was
+    ///   written on a machine of the other endianness or for elements of a
+    ///   different size, or holds more than `max_cap` elements.
+    /// - [`ErrorKind::UnexpectedEof`] if the input ends early.
+    /// - [`ErrorKind::OutOfMemory`] if a buffer of `max_cap` elements cannot
+    ///   be allocated.
+    /// - Any error from `r`.
+    pub fn read_snapshot<R: Read + ?Sized>(r: &mut R, max_cap: usize) -> io::Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        r.read_exact(&mut header)?;
+        let field = |at: usize| u32::from_ne_bytes(header[at..at + 4].try_into().unwrap());
+        if header[..8] != MAGIC {
+            return Err(invalid_data("not a buffer snapshot".to_owned()));
+        }
+        if field(8) != BYTE_ORDER {
+            return Err(invalid_data(
+                "snapshot was written with the other byte order".to_owned(),
+            ));
+        }
+        let elem_size = field(12);
+        if elem_size as usize != mem::size_of::<T>() {
+            return Err(invalid_data(format!(
+                "snapshot elements are {elem_size} bytes, expected {}",
+                mem::size_of::<T>()
+            )));
+        }
+        let len = u64::from_ne_bytes(header[16..].try_into().unwrap());
+        let len = match usize::try_from(len) {
+            Ok(len) if len <= max_cap => len,
+            _ => {
+                return Err(invalid_data(format!(
+                    "snapshot holds {len} elements, more than the {max_cap} allowed"
+                )))
+            }
+        };
+
+        let mut buf =
+            Self::try_new(max_cap).map_err(|err| io::Error::new(ErrorKind::OutOfMemory, err))?;
+        let bytes = unsafe {
+            let dst = buf.ptr.as_ptr() as *mut u8;
+            let n = len * mem::size_of::<T>();
+            ptr::write_bytes(dst, 0, n);
+            slice::from_raw_parts_mut(dst, n)
+        };
+        r.read_exact(bytes)?;
+        // Every bit pattern is a valid `T`.
+        unsafe { buf.set_len(len) };
+        Ok(buf)
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,87 @@
+use std::{fmt, iter::FusedIterator, ptr};
+
+use super::Drain;
+
+/// An iterator over the elements removed by [`BoundedBuffer::splice`].
+///
+/// The replacement elements are written when the `Splice` is dropped, after
+/// any removed elements that were not yielded have been dropped. The tail is
+/// moved once, straight to its final position. Leaking the `Splice` behaves
+/// like leaking a [`Drain`]: the buffer is left shortened to the start of the
+/// range.
+///
+/// [`BoundedBuffer::splice`]: super::BoundedBuffer::splice
+pub struct Splice<'a, I: Iterator + 'a> {
+    pub(super) drain: Drain<'a, I::Item>,
+    pub(super) replace_with: I,
+    pub(super) replace_len: usize,
+}
+
+impl<I> fmt::Debug for Splice<'_, I>
+where
+    I: Iterator + fmt::Debug,
+    I::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Splice")
+            .field("drain", &self.drain)
+            .field("replace_with", &self.replace_with)
+            .finish()
+    }
+}
+
+impl<I: Iterator> Iterator for Splice<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.drain.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.drain.size_hint()
+    }
+}
+
+impl<I: Iterator> DoubleEndedIterator for Splice<'_, I> {
+    fn next_back(&mut self) -> Option<I::Item> {
+        self.drain.next_back()
+    }
+}
+
+impl<I: Iterator> ExactSizeIterator for Splice<'_, I> {}
+
+impl<I: Iterator> FusedIterator for Splice<'_, I> {}
+
+impl<I: Iterator> Drop for Splice<'_, I> {
+    fn drop(&mut self) {
+        self.drain.by_ref().for_each(drop);
+
+        // The drained range is now a gap at `buf.len`. Move the tail past the
+        // room the replacement needs, then fill the gap one element at a time
+        // so `buf.len` always covers exactly what was written. If the
+        // replacement panics or yields fewer elements than it promised, the
+        // `Drain` moves the tail back down to `buf.len` when it is dropped.
+        unsafe {
+            let buf = self.drain.buf.as_mut();
+            let new_tail_start = buf.len + self.replace_len;
+            if self.drain.tail_len > 0 && new_tail_start != self.drain.tail_start {
+                ptr::copy(
+                    buf.slot(self.drain.tail_start),
+                    buf.slot(new_tail_start),
+                    self.drain.tail_len,
+                );
+            }
+            self.drain.tail_start = new_tail_start;
+
+            for _ in 0..self.replace_len {
+                match self.replace_with.next() {
+                    Some(elem) => {
+                        buf.slot(buf.len).write(elem);
+                        buf.len += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    future, io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::BoundedBuffer;
+
+impl BoundedBuffer<u8> {
+    /// Polls `reader` to read straight into the spare capacity and appends
+    /// the bytes read, returning how many there were.
+    ///
+    /// Nothing is copied or zeroed: the reader writes into the uninitialized
+    /// tail and the length is advanced over what it filled. Returns `Ok(0)`
+    /// without polling the reader if the buffer is full, so `Ok(0)` only
+    /// means end of stream while there is spare capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reader swaps out the `ReadBuf` it is handed.
+    pub fn poll_read_from<R: AsyncRead + ?Sized>(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: Pin<&mut R>,
+    ) -> Poll<io::Result<usize>> {
+        if self.len == self.cap {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = {
+            let mut read_buf = ReadBuf::uninit(self.spare_capacity_mut());
+            let ptr = read_buf.filled().as_ptr();
+            ready!(reader.poll_read(cx, &mut read_buf))?;
+            assert_eq!(ptr, read_buf.filled().as_ptr(), "`ReadBuf` was swapped out");
+            read_buf.filled().len()
+        };
+        self.len += n;
+        Poll::Ready(Ok(n))
+    }
+
+    /// Reads once from `reader` into the spare capacity, like
+    /// [`poll_read_from`](Self::poll_read_from).
+    ///
+    /// ```
+    /// use fixed_buf::BoundedBuffer;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let (mut client, mut server) = tokio::io::duplex(64);
+    /// client.write_all(b"8=FIX.4.4").await.unwrap();
+    ///
+    /// let mut buf = BoundedBuffer::new(32);
+    /// assert_eq!(buf.read_from_async(&mut server).await.unwrap(), 9);
+    /// assert_eq!(buf, *b"8=FIX.4.4");
+    /// # });
+    /// ```
+    pub async fn read_from_async<R: AsyncRead + Unpin + ?Sized>(
+        &mut self,
+        reader: &mut R,
+    ) -> io::Result<usize> {
+        future::poll_fn(|cx| self.poll_read_from(cx, Pin::new(&mut *reader))).await
+    }
+}
+
+/// Appends the written bytes, never past the capacity, like the
+/// [`io::Write`] impl: writes copy as much as fits and a full buffer
+/// reports `Ok(0)`, which `write_all` turns into [`io::ErrorKind::WriteZero`].
+/// It never returns `Pending`.
+impl AsyncWrite for BoundedBuffer<u8> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(self.get_mut().extend_from_slice_truncating(buf)))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::BoundedBuffer;
+use crate::core::error::CapacityError;
+
+/// Zeroizes every element, drops them, and then zeroes the whole
+/// allocation, spare capacity included.
+///
+/// Zeroing the spare capacity also wipes stale copies left behind by
+/// earlier `pop`s, `remove`s or truncations. It cannot reach copies left by
+/// moving to another allocation with [`grow_to`](BoundedBuffer::grow_to) or
+/// [`shrink_to`](BoundedBuffer::shrink_to).
+impl<T: Zeroize> Zeroize for BoundedBuffer<T> {
+    fn zeroize(&mut self) {
+        self.iter_mut().zeroize();
+        self.clear();
+        self.spare_capacity_mut().zeroize();
+    }
+}
+
+impl<T: ZeroizeOnDrop> ZeroizeOnDrop for BoundedBuffer<T> {}
+
+/// A [`BoundedBuffer`] for secrets that never leaves stale copies in its
+/// spare capacity.
+///
+/// Every slot vacated by [`pop`](Self::pop), [`remove`](Self::remove),
+/// [`truncate`](Self::truncate) and friends is zeroed right away, and
+/// dropping the buffer zeroizes it as a whole. It dereferences to the
+/// elements as a slice but not to the buffer, so the length only changes
+/// through these methods. `Debug` does not print the elements.
+///
+/// ```
+/// use fixed_buf::{core::bounded_buf::ZeroizingBuffer, BoundedBuffer};
+///
+/// let mut key = ZeroizingBuffer::new(BoundedBuffer::<u8>::new(32));
+/// key.try_extend_from_slice(b"hunter2").unwrap();
+/// assert_eq!(key.pop(), Some(b'2'));
+/// assert_eq!(&key[..], b"hunter");
+/// assert_eq!(format!("{key:?}"), "ZeroizingBuffer { len: 6, cap: 32 }");
+/// ```
+pub struct ZeroizingBuffer<T: Zeroize> {
+    buf: BoundedBuffer<T>,
+}
+
+impl<T: Zeroize> ZeroizingBuffer<T> {
+    /// Wraps `buf`, zeroing its spare capacity first.
+    pub fn new(mut buf: BoundedBuffer<T>) -> Self {
+        buf.spare_capacity_mut().zeroize();
+        Self { buf }
+    }
+
+    /// Returns the wrapped buffer.
+    pub fn as_buffer(&self) -> &BoundedBuffer<T> {
+        &self.buf
+    }
+
+    /// Returns the number of elements the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.buf.cap
+    }
+
+    /// Appends `elem` if there is room, returning whether it was appended.
+    pub fn try_push(&mut self, elem: T) -> bool {
+        self.buf.try_push(elem)
+    }
+
+    /// Appends clones of all of `src`, or fails without writing anything if
+    /// they do not all fit.
+    pub fn try_extend_from_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        self.buf.try_extend_from_slice(src)
+    }
+
+    /// Removes and returns the last element, zeroing its slot.
+    pub fn pop(&mut self) -> Option<T> {
+        let elem = self.buf.pop()?;
+        self.wipe_vacated(1);
+        Some(elem)
+    }
+
+    /// Removes and returns the element at `index`, shifting the ones after
+    /// it down and zeroing the slot this frees at the end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[track_caller]
+    pub fn remove(&mut self, index: usize) -> T {
+        let elem = self.buf.remove(index);
+        self.wipe_vacated(1);
+        elem
+    }
+
+    /// Removes and returns the element at `index`, replacing it with the
+    /// last element and zeroing the last slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[track_caller]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let elem = self.buf.swap_remove(index);
+        self.wipe_vacated(1);
+        elem
+    }
+
+    /// Zeroizes and drops the elements from `new_len` on, then zeroes their
+    /// slots. Does nothing if `new_len` is not less than the length.
+    pub fn truncate(&mut self, new_len: usize) {
+        let len = self.buf.len;
+        if new_len >= len {
+            return;
+        }
+        self.buf[new_len..].iter_mut().zeroize();
+        self.buf.truncate(new_len);
+        self.wipe_vacated(len - new_len);
+    }
+
+    /// Zeroizes and drops every element, then zeroes their slots.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    // Zeroes the first `n` slots past the end.
+    fn wipe_vacated(&mut self, n: usize) {
+        self.buf.spare_capacity_mut()[..n].zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for ZeroizingBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZeroizingBuffer")
+            .field("len", &self.buf.len)
+            .field("cap", &self.buf.cap)
+            .finish()
+    }
+}
+
+impl<T: Zeroize> Deref for ZeroizingBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.buf
+    }
+}
+
+impl<T: Zeroize> DerefMut for ZeroizingBuffer<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.buf
+    }
+}
+
+impl<T: Zeroize> Drop for ZeroizingBuffer<T> {
+    fn drop(&mut self) {
+        self.buf.zeroize();
+    }
+}
+
+impl<T: Zeroize> ZeroizeOnDrop for ZeroizingBuffer<T> {}
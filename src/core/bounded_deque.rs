@@ -0,0 +1,519 @@
+use std::{
+    fmt,
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{Index, IndexMut, Range, RangeBounds},
+    ptr,
+    ptr::NonNull,
+};
+
+pub use crate::core::bounded_ring::Iter;
+use crate::core::{
+    bounded_buf::slice_range,
+    bounded_ring::BoundedRing,
+    error::{CapacityError, TryNewError},
+};
+
+/// A heap-allocated double-ended queue with a capacity fixed at
+/// construction: a `VecDeque` that never reallocates.
+///
+/// Elements can be pushed and popped at both ends in O(1) and accessed by
+/// index, counting from the front. Pushing into a full deque fails and gives
+/// the element back.
+///
+/// ```
+/// use fixed_buf::BoundedDeque;
+///
+/// let mut deque = BoundedDeque::new(3);
+/// deque.try_push_back(2).unwrap();
+/// deque.try_push_front(1).unwrap();
+/// deque.try_push_back(3).unwrap();
+/// assert!(deque.try_push_front(0).is_err());
+/// assert_eq!(deque[0], 1);
+/// assert_eq!(deque.pop_back(), Some(3));
+/// assert_eq!(deque.iter().copied().collect::<Vec<_>>(), [1, 2]);
+/// ```
+pub struct BoundedDeque<T> {
+    ring: BoundedRing<T>,
+}
+
+impl<T> BoundedDeque<T> {
+    /// Creates an empty deque that holds at most `cap` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like
+    /// [`BoundedBuffer::new`](crate::BoundedBuffer::new).
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        Self {
+            ring: BoundedRing::new(cap),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        BoundedRing::try_new(cap).map(|ring| Self { ring })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.ring.is_full()
+    }
+
+    /// Appends `elem` at the back, or gives it back if the deque is full.
+    pub fn try_push_back(&mut self, elem: T) -> Result<(), CapacityError<T>> {
+        self.ring.try_push_back(elem)
+    }
+
+    /// Prepends `elem` at the front, or gives it back if the deque is full.
+    pub fn try_push_front(&mut self, elem: T) -> Result<(), CapacityError<T>> {
+        let ring = &mut self.ring;
+        if ring.is_full() {
+            return Err(CapacityError::new(elem));
+        }
+        let head = ring.wrap(ring.head + ring.capacity() - 1);
+        unsafe {
+            ring.slot(head).write(elem);
+        }
+        ring.head = head;
+        ring.len += 1;
+        Ok(())
+    }
+
+    /// Removes and returns the front element.
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.ring.pop_front()
+    }
+
+    /// Removes and returns the back element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.ring.pop_back()
+    }
+
+    /// Returns the element `index` places from the front.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.ring.get(index)
+    }
+
+    /// Returns the element `index` places from the front, mutably.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.ring.get_mut(index)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.ring.front()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.ring.back()
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.ring.get_mut(0)
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        let last = self.len().checked_sub(1)?;
+        self.ring.get_mut(last)
+    }
+
+    /// Drops every element.
+    pub fn clear(&mut self) {
+        self.ring.clear();
+    }
+
+    /// Returns the elements as two slices that, one after the other, hold
+    /// them front to back. The second is empty unless the elements wrap
+    /// around the end of the allocation.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        self.ring.as_slices()
+    }
+
+    /// Like [`as_slices`](Self::as_slices), but mutable.
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        self.ring.as_mut_slices()
+    }
+
+    /// Moves the elements so they all sit in one slice, front to back, and
+    /// returns it.
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        self.ring.make_contiguous()
+    }
+
+    /// Returns an iterator over the elements, front to back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.ring.iter()
+    }
+
+    /// Removes `range`, counted from the front, and returns its elements as
+    /// an iterator. See [`Drain`] for what happens to the deque when the
+    /// iterator is dropped or leaked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing does.
+    #[track_caller]
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        let Range { start, end } = slice_range(range, self.len());
+        let tail_len = self.len() - end;
+        self.ring.len = start;
+        Drain {
+            front: start,
+            back: end,
+            drain_start: start,
+            drain_end: end,
+            tail_len,
+            ring: NonNull::from(&mut self.ring),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Clone> Clone for BoundedDeque<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ring: self.ring.clone(),
+        }
+    }
+}
+
+/// Formats the elements front to back as a list. The capacity is not shown.
+impl<T: fmt::Debug> fmt::Debug for BoundedDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.ring, f)
+    }
+}
+
+impl<T> Index<usize> for BoundedDeque<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, index: usize) -> &T {
+        let len = self.len();
+        match self.get(index) {
+            Some(elem) => elem,
+            None => panic!("index out of bounds: the len is {len} but the index is {index}"),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for BoundedDeque<T> {
+    #[track_caller]
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = self.len();
+        match self.get_mut(index) {
+            Some(elem) => elem,
+            None => panic!("index out of bounds: the len is {len} but the index is {index}"),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BoundedDeque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// A draining iterator over a range of a [`BoundedDeque`], created by
+/// [`BoundedDeque::drain`].
+///
+/// The deque is shortened to the start of the range as soon as the `Drain`
+/// is created. Dropping it drops any elements that were not yielded and
+/// closes the gap by moving whichever side of the range is shorter; leaking
+/// it leaves the deque at that shorter length, with the drained range and
+/// the tail leaked.
+pub struct Drain<'a, T> {
+    // Positions counted from the ring's head, which does not move until
+    // the drain is dropped. `front..back` have not been yielded yet.
+    front: usize,
+    back: usize,
+    drain_start: usize,
+    drain_end: usize,
+    tail_len: usize,
+    ring: NonNull<BoundedRing<T>>,
+    _marker: PhantomData<&'a mut BoundedRing<T>>,
+}
+
+unsafe impl<T: Send> Send for Drain<'_, T> {}
+unsafe impl<T: Sync> Sync for Drain<'_, T> {}
+
+impl<T> Drain<'_, T> {
+    unsafe fn logical(&self, index: usize) -> *mut T {
+        let ring = self.ring.as_ref();
+        ring.slot(ring.wrap(ring.head + index))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Drain<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let remaining = (self.front..self.back).map(|i| unsafe { &*self.logical(i) });
+        f.debug_tuple("Drain")
+            .field(&remaining.collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None;
+        }
+        let elem = unsafe { self.logical(self.front).read() };
+        self.front += 1;
+        Some(elem)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        unsafe { Some(self.logical(self.back).read()) }
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        // Closes the gap even if dropping one of the remaining elements
+        // panics.
+        struct CloseGap<'r, 'a, T>(&'r mut Drain<'a, T>);
+
+        impl<T> Drop for CloseGap<'_, '_, T> {
+            fn drop(&mut self) {
+                let drain = &mut *self.0;
+                let head_len = drain.drain_start;
+                let gap = drain.drain_end - drain.drain_start;
+                unsafe {
+                    if gap == 0 {
+                        // Nothing was removed, so nothing moves.
+                    } else if drain.tail_len <= head_len {
+                        for i in 0..drain.tail_len {
+                            let src = drain.logical(drain.drain_end + i);
+                            ptr::copy_nonoverlapping(src, drain.logical(head_len + i), 1);
+                        }
+                    } else {
+                        for i in (0..head_len).rev() {
+                            ptr::copy_nonoverlapping(drain.logical(i), drain.logical(i + gap), 1);
+                        }
+                        let ring = drain.ring.as_mut();
+                        ring.head = ring.wrap(ring.head + gap);
+                    }
+                    drain.ring.as_mut().len = head_len + drain.tail_len;
+                }
+            }
+        }
+
+        let guard = CloseGap(self);
+        guard.0.by_ref().for_each(drop);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BoundedDeque;
+    use crate::core::test_util::{xorshift, DropCount, Drops};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_deque_matches_vec_deque() {
+        let steps = if cfg!(miri) { 400 } else { 50_000 };
+        for cap in [1, 2, 3, 4, 7, 8, 13] {
+            let mut deque = BoundedDeque::new(cap);
+            let mut model = VecDeque::new();
+            let mut state = 0x2545_f491_4f6c_dd1d ^ cap as u64;
+            for i in 0..steps {
+                let r = xorshift(&mut state);
+                match r % 9 {
+                    0 | 1 => {
+                        let pushed = deque.try_push_back(i);
+                        assert_eq!(pushed.is_ok(), model.len() < cap);
+                        if pushed.is_ok() {
+                            model.push_back(i);
+                        }
+                    }
+                    2 | 3 => {
+                        let pushed = deque.try_push_front(i);
+                        assert_eq!(pushed.is_ok(), model.len() < cap);
+                        if pushed.is_ok() {
+                            model.push_front(i);
+                        }
+                    }
+                    4 => assert_eq!(deque.pop_front(), model.pop_front()),
+                    5 => assert_eq!(deque.pop_back(), model.pop_back()),
+                    6 => {
+                        let index = (r >> 8) as usize % (cap + 1);
+                        assert_eq!(deque.get(index), model.get(index));
+                        if let Some(elem) = deque.get_mut(index) {
+                            *elem += 1;
+                            model[index] += 1;
+                        }
+                    }
+                    7 => {
+                        let len = model.len();
+                        let a = (r >> 8) as usize % (len + 1);
+                        let b = (r >> 24) as usize % (len + 1);
+                        let (start, end) = (a.min(b), a.max(b));
+                        let mut drain = deque.drain(start..end);
+                        let mut model_drain = model.drain(start..end);
+                        // Only consume part of the range sometimes.
+                        if r & (1 << 40) != 0 {
+                            assert_eq!(drain.next(), model_drain.next());
+                            assert_eq!(drain.next_back(), model_drain.next_back());
+                        } else {
+                            assert!(drain.eq(model_drain.by_ref()));
+                        }
+                    }
+                    _ => {
+                        let (a, b) = deque.as_slices();
+                        assert_eq!([a, b].concat(), model.iter().copied().collect::<Vec<_>>());
+                    }
+                }
+                assert_eq!(deque.len(), model.len());
+                assert_eq!(deque.front(), model.front());
+                assert_eq!(deque.back(), model.back());
+                assert!(deque.iter().eq(model.iter()));
+                assert!(deque.iter().rev().eq(model.iter().rev()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_deque_wrapped_drop() {
+        let drops = Drops::default();
+        let mut deque = BoundedDeque::new(5);
+        for _ in 0..3 {
+            deque.try_push_back(DropCount(drops.clone())).unwrap();
+        }
+        for _ in 0..2 {
+            deque.try_push_front(DropCount(drops.clone())).unwrap();
+        }
+        let (a, b) = deque.as_slices();
+        assert_eq!((a.len(), b.len()), (2, 3));
+        assert!(deque.try_push_front(DropCount(drops.clone())).is_err());
+        assert_eq!(drops.get(), 1);
+        drop(deque);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    fn test_deque_drain() {
+        let build = || {
+            let mut deque = BoundedDeque::new(8);
+            for i in (0..4).rev() {
+                deque.try_push_front(i).unwrap();
+            }
+            for i in 4..8 {
+                deque.try_push_back(i).unwrap();
+            }
+            deque
+        };
+        let contents = |deque: &BoundedDeque<i32>| deque.iter().copied().collect::<Vec<_>>();
+
+        // Closes the gap from the back ...
+        let mut deque = build();
+        assert_eq!(deque.drain(5..7).collect::<Vec<_>>(), [5, 6]);
+        assert_eq!(contents(&deque), [0, 1, 2, 3, 4, 7]);
+        // ... and from the front.
+        let mut deque = build();
+        assert_eq!(deque.drain(1..3).rev().collect::<Vec<_>>(), [2, 1]);
+        assert_eq!(contents(&deque), [0, 3, 4, 5, 6, 7]);
+        deque.try_push_front(-1).unwrap();
+        deque.try_push_back(8).unwrap();
+        assert_eq!(contents(&deque), [-1, 0, 3, 4, 5, 6, 7, 8]);
+
+        let mut deque = build();
+        let mut drain = deque.drain(2..);
+        assert_eq!(drain.len(), 6);
+        assert_eq!(format!("{drain:?}"), "Drain([2, 3, 4, 5, 6, 7])");
+        drain.next();
+        drop(drain);
+        assert_eq!(contents(&deque), [0, 1]);
+
+        let mut deque = build();
+        assert_eq!(deque.drain(..).count(), 8);
+        assert!(deque.is_empty());
+
+        // Leaking the iterator leaves only the part before the range.
+        let mut deque = build();
+        std::mem::forget(deque.drain(3..5));
+        assert_eq!(contents(&deque), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_deque_drain_drop_counts() {
+        let drops = Drops::default();
+        let mut deque = BoundedDeque::new(6);
+        for _ in 0..3 {
+            deque.try_push_front(DropCount(drops.clone())).unwrap();
+            deque.try_push_back(DropCount(drops.clone())).unwrap();
+        }
+        let mut drain = deque.drain(1..5);
+        drop(drain.next());
+        assert_eq!(drops.get(), 1);
+        drop(drain);
+        assert_eq!(drops.get(), 4);
+        assert_eq!(deque.len(), 2);
+        drop(deque);
+        assert_eq!(drops.get(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "range end index 4 out of range for slice of length 3")]
+    fn test_deque_drain_out_of_bounds() {
+        let mut deque = BoundedDeque::new(4);
+        for i in 0..3 {
+            deque.try_push_back(i).unwrap();
+        }
+        deque.drain(1..4);
+    }
+
+    #[test]
+    fn test_deque_index() {
+        let mut deque = BoundedDeque::new(4);
+        deque.try_push_back(2).unwrap();
+        deque.try_push_front(1).unwrap();
+        deque[1] *= 10;
+        *deque.front_mut().unwrap() += 1;
+        *deque.back_mut().unwrap() += 1;
+        assert_eq!((deque[0], deque[1]), (2, 21));
+        assert_eq!(format!("{deque:?}"), "[2, 21]");
+        assert_eq!(deque.clone().iter().count(), 2);
+        assert_eq!(deque.make_contiguous(), [2, 21]);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the len is 1 but the index is 1")]
+    fn test_deque_index_out_of_bounds() {
+        let mut deque = BoundedDeque::new(4);
+        deque.try_push_back(0).unwrap();
+        let _ = deque[1];
+    }
+}
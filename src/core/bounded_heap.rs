@@ -0,0 +1,670 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    iter::FusedIterator,
+    mem::{self, ManuallyDrop},
+    ptr, slice,
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    error::{CapacityError, TryNewError},
+};
+
+/// A max-heap priority queue with a capacity fixed at construction.
+///
+/// Pushing into a full heap fails instead of growing it; the combined
+/// operations [`push_pop`](Self::push_pop), [`pop_push`](Self::pop_push) and
+/// [`push_evict_min`](Self::push_evict_min) keep a full heap full while
+/// moving each element at most once.
+///
+/// ```
+/// use fixed_buf::BoundedHeap;
+///
+/// let mut heap = BoundedHeap::new(3);
+/// heap.try_push(3).unwrap();
+/// heap.try_push(7).unwrap();
+/// heap.try_push(5).unwrap();
+/// assert_eq!(heap.try_push(1).unwrap_err().element(), 1);
+/// assert_eq!(heap.peek(), Some(&7));
+///
+/// // Keep the three smallest: a larger element is handed straight back.
+/// assert_eq!(heap.push_pop(9), 9);
+/// assert_eq!(heap.push_pop(4), 7);
+/// assert_eq!(heap.into_sorted_buffer().as_slice(), [3, 4, 5]);
+/// ```
+pub struct BoundedHeap<T> {
+    data: BoundedBuffer<T>,
+}
+
+impl<T: Ord> BoundedHeap<T> {
+    /// Creates a heap that holds at most `cap` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        Self {
+            data: BoundedBuffer::new(cap),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        Self::try_new_unordered(cap)
+    }
+
+    /// Returns the greatest element.
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    /// Pushes `item`, or gives it back if the heap is full.
+    pub fn try_push(&mut self, item: T) -> Result<(), CapacityError<T>> {
+        self.try_push_by(item, &mut T::cmp)
+    }
+
+    /// Removes and returns the greatest element.
+    pub fn pop(&mut self) -> Option<T> {
+        let mut item = self.data.pop()?;
+        if !self.data.is_empty() {
+            mem::swap(&mut item, &mut self.data[0]);
+            // SAFETY: the heap is non-empty.
+            unsafe { self.sift_down(0, self.data.len()) };
+        }
+        Some(item)
+    }
+
+    /// Pushes `item` and then pops the greatest element, faster than doing
+    /// the two separately. Works on a full heap too, which it leaves full.
+    ///
+    /// If `item` is at least as great as every element, it is returned
+    /// without touching the heap.
+    pub fn push_pop(&mut self, item: T) -> T {
+        self.push_pop_by(item, &mut T::cmp)
+    }
+
+    /// Pops the greatest element and then pushes `item`, faster than doing
+    /// the two separately. Works on a full heap too, which it leaves full.
+    ///
+    /// On an empty heap, `item` is pushed and `None` returned, unless the
+    /// capacity is zero, in which case `item` is handed back.
+    pub fn pop_push(&mut self, mut item: T) -> Option<T> {
+        match self.data.first_mut() {
+            Some(top) => {
+                mem::swap(top, &mut item);
+                // SAFETY: the heap is non-empty.
+                unsafe { self.sift_down(0, self.data.len()) };
+                Some(item)
+            }
+            None => self.try_push(item).err().map(CapacityError::element),
+        }
+    }
+
+    /// Pushes `item`, first evicting the least element if the heap is full,
+    /// and returns the evicted element. If `item` is itself no greater than
+    /// the least element, it is returned instead and the heap is unchanged.
+    ///
+    /// This keeps the greatest `capacity()` elements seen. Finding the least
+    /// element of a max-heap means scanning its leaves, half of the
+    /// elements, so with a large capacity prefer a heap of
+    /// [`Reverse`](std::cmp::Reverse)d elements and
+    /// [`push_pop`](Self::push_pop), which is logarithmic.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedHeap;
+    ///
+    /// let mut best = BoundedHeap::new(2);
+    /// for score in [40, 90, 10, 70] {
+    ///     best.push_evict_min(score);
+    /// }
+    /// assert_eq!(best.into_sorted_buffer().as_slice(), [70, 90]);
+    /// ```
+    pub fn push_evict_min(&mut self, item: T) -> Option<T> {
+        let item = match self.try_push(item) {
+            Ok(()) => return None,
+            Err(err) => err.element(),
+        };
+        let len = self.data.len();
+        // Every element in the first half has a child, so is greater than
+        // something; the least one is among the rest.
+        let Some(min) = (len / 2..len).min_by(|&a, &b| self.data[a].cmp(&self.data[b])) else {
+            // Zero capacity.
+            return Some(item);
+        };
+        if item <= self.data[min] {
+            return Some(item);
+        }
+        let evicted = mem::replace(&mut self.data[min], item);
+        // SAFETY: `min` is in bounds.
+        unsafe { self.sift_up(min) };
+        Some(evicted)
+    }
+
+    /// Returns the elements in ascending order.
+    pub fn into_sorted_buffer(self) -> BoundedBuffer<T> {
+        self.into_sorted_buffer_by(&mut T::cmp)
+    }
+
+    /// Returns an iterator that pops the elements in descending order.
+    /// Elements it doesn't get to are dropped with it.
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T> {
+        DrainSorted { heap: self }
+    }
+
+    // SAFETY: `pos` must be in bounds.
+    unsafe fn sift_up(&mut self, pos: usize) {
+        self.sift_up_by(pos, &mut T::cmp);
+    }
+
+    // SAFETY: `pos < end <= len` must hold.
+    unsafe fn sift_down(&mut self, pos: usize, end: usize) {
+        self.sift_down_by(pos, end, &mut T::cmp);
+    }
+}
+
+// The heap operations under an ordering given by `compare` rather than
+// `Ord`, for collectors such as `TopK` whose ordering is only known at the
+// call site. Every call on one heap must use the same ordering.
+impl<T> BoundedHeap<T> {
+    pub(crate) fn try_new_unordered(cap: usize) -> Result<Self, TryNewError> {
+        Ok(Self {
+            data: BoundedBuffer::try_new(cap)?,
+        })
+    }
+
+    pub(crate) fn try_push_by<F>(
+        &mut self,
+        item: T,
+        compare: &mut F,
+    ) -> Result<(), CapacityError<T>>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        if self.data.len() == self.data.capacity() {
+            return Err(CapacityError::new(item));
+        }
+        let pushed = self.data.try_push(item);
+        debug_assert!(pushed);
+        let last = self.data.len() - 1;
+        // SAFETY: `last` is in bounds.
+        unsafe { self.sift_up_by(last, compare) };
+        Ok(())
+    }
+
+    pub(crate) fn push_pop_by<F>(&mut self, mut item: T, compare: &mut F) -> T
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        match self.data.first_mut() {
+            Some(top) if compare(top, &item).is_gt() => {
+                mem::swap(top, &mut item);
+                // SAFETY: the heap is non-empty.
+                unsafe { self.sift_down_by(0, self.data.len(), compare) };
+                item
+            }
+            _ => item,
+        }
+    }
+
+    pub(crate) fn into_sorted_buffer_by<F>(mut self, compare: &mut F) -> BoundedBuffer<T>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut end = self.data.len();
+        while end > 1 {
+            end -= 1;
+            self.data.swap(0, end);
+            // SAFETY: `0 < end <= len`.
+            unsafe { self.sift_down_by(0, end, compare) };
+        }
+        self.data
+    }
+
+    // Moves the element at `pos` up until its parent is at least as great.
+    //
+    // SAFETY: `pos` must be in bounds.
+    unsafe fn sift_up_by<F>(&mut self, pos: usize, compare: &mut F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut hole = Hole::new(&mut self.data, pos);
+        while hole.pos > 0 {
+            let parent = (hole.pos - 1) / 2;
+            if compare(hole.element(), hole.get(parent)).is_le() {
+                break;
+            }
+            hole.move_to(parent);
+        }
+    }
+
+    // Moves the element at `pos` down until its children, if any, below
+    // `end` are no greater.
+    //
+    // SAFETY: `pos < end <= len` must hold.
+    unsafe fn sift_down_by<F>(&mut self, pos: usize, end: usize, compare: &mut F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut hole = Hole::new(&mut self.data[..end], pos);
+        let mut child = 2 * hole.pos + 1;
+        // Loop while both children are in range; `child <= end - 2` can't
+        // overflow, unlike `child + 1 < end`.
+        while child <= end.saturating_sub(2) {
+            // Take the greater of the two children.
+            child += compare(hole.get(child), hole.get(child + 1)).is_le() as usize;
+            if compare(hole.element(), hole.get(child)).is_ge() {
+                return;
+            }
+            hole.move_to(child);
+            child = 2 * hole.pos + 1;
+        }
+        if child == end - 1 && compare(hole.element(), hole.get(child)).is_lt() {
+            hole.move_to(child);
+        }
+    }
+}
+
+impl<T> BoundedHeap<T> {
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// Returns the elements in heap order, which is unspecified apart from
+    /// the greatest one coming first.
+    pub fn as_slice(&self) -> &[T] {
+        self.data.as_slice()
+    }
+
+    /// Returns an iterator over the elements in heap order.
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    /// Returns the underlying buffer, in heap order.
+    pub fn into_inner(self) -> BoundedBuffer<T> {
+        self.data
+    }
+}
+
+/// Builds a heap from the elements of `buf` in linear time, keeping its
+/// capacity.
+impl<T: Ord> From<BoundedBuffer<T>> for BoundedHeap<T> {
+    fn from(buf: BoundedBuffer<T>) -> Self {
+        let mut heap = Self { data: buf };
+        let len = heap.data.len();
+        for pos in (0..len / 2).rev() {
+            // SAFETY: `pos < len / 2 <= len`.
+            unsafe { heap.sift_down(pos, len) };
+        }
+        heap
+    }
+}
+
+impl<T: Ord + Clone> Clone for BoundedHeap<T> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BoundedHeap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.data.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BoundedHeap<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator popping the elements of a [`BoundedHeap`] in descending
+/// order, returned by [`BoundedHeap::drain_sorted`].
+pub struct DrainSorted<'a, T: Ord> {
+    heap: &'a mut BoundedHeap<T>,
+}
+
+impl<T: Ord> Iterator for DrainSorted<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.heap.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.heap.len();
+        (len, Some(len))
+    }
+}
+
+impl<T: Ord> ExactSizeIterator for DrainSorted<'_, T> {}
+impl<T: Ord> FusedIterator for DrainSorted<'_, T> {}
+
+impl<T: Ord> Drop for DrainSorted<'_, T> {
+    fn drop(&mut self) {
+        self.heap.clear();
+    }
+}
+
+impl<T: Ord + fmt::Debug> fmt::Debug for DrainSorted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DrainSorted").field(&self.heap).finish()
+    }
+}
+
+// A slice with one element moved out into `elt`, leaving a hole at `pos`.
+// Sifting moves other elements into the hole rather than swapping, and the
+// element is written back into wherever the hole ends up when this is
+// dropped, even if a comparison panics.
+struct Hole<'a, T> {
+    data: &'a mut [T],
+    elt: ManuallyDrop<T>,
+    pos: usize,
+}
+
+impl<'a, T> Hole<'a, T> {
+    // SAFETY: `pos` must be in bounds.
+    unsafe fn new(data: &'a mut [T], pos: usize) -> Self {
+        debug_assert!(pos < data.len());
+        let elt = ptr::read(data.get_unchecked(pos));
+        Hole {
+            data,
+            elt: ManuallyDrop::new(elt),
+            pos,
+        }
+    }
+
+    fn element(&self) -> &T {
+        &self.elt
+    }
+
+    // SAFETY: `index` must be in bounds and not the hole.
+    unsafe fn get(&self, index: usize) -> &T {
+        debug_assert!(index != self.pos && index < self.data.len());
+        self.data.get_unchecked(index)
+    }
+
+    // Moves the element at `index` into the hole, leaving the hole at
+    // `index`.
+    //
+    // SAFETY: `index` must be in bounds and not the hole.
+    unsafe fn move_to(&mut self, index: usize) {
+        debug_assert!(index != self.pos && index < self.data.len());
+        let ptr = self.data.as_mut_ptr();
+        ptr::copy_nonoverlapping(ptr.add(index), ptr.add(self.pos), 1);
+        self.pos = index;
+    }
+}
+
+impl<T> Drop for Hole<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: the hole is in bounds and holds no element.
+        unsafe {
+            let pos = self.pos;
+            ptr::copy_nonoverlapping(&*self.elt, self.data.get_unchecked_mut(pos), 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cmp::Reverse,
+        collections::BinaryHeap,
+        panic::{self, AssertUnwindSafe},
+        rc::Rc,
+    };
+
+    use super::BoundedHeap;
+    use crate::core::bounded_buf::BoundedBuffer;
+    use crate::core::test_util::{xorshift, DropCount, Drops};
+
+    // Ordered by `key` alone; counts its drop through `_drop`.
+    #[derive(Debug)]
+    struct Keyed {
+        key: u32,
+        _drop: DropCount,
+    }
+
+    impl PartialEq for Keyed {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Keyed {}
+
+    impl PartialOrd for Keyed {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Keyed {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    #[test]
+    fn test_push_pop_order() {
+        let mut heap = BoundedHeap::new(5);
+        for x in [3, 1, 4, 1, 5] {
+            heap.try_push(x).unwrap();
+        }
+        assert!(heap.try_push(9).is_err());
+        assert_eq!(heap.len(), 5);
+        assert_eq!(heap.peek(), Some(&5));
+        let popped: Vec<_> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(popped, [5, 4, 3, 1, 1]);
+        assert_eq!(heap.peek(), None);
+    }
+
+    #[test]
+    fn test_combined_operations() {
+        let mut heap = BoundedHeap::new(3);
+        assert_eq!(heap.push_pop(1), 1);
+        assert!(heap.is_empty());
+        assert_eq!(heap.pop_push(2), None);
+        heap.try_push(8).unwrap();
+        heap.try_push(5).unwrap();
+        assert_eq!(heap.pop_push(9), Some(8));
+        assert_eq!(heap.pop_push(1), Some(9));
+        assert_eq!(heap.push_pop(3), 5);
+        assert_eq!(heap.into_sorted_buffer().as_slice(), [1, 2, 3]);
+
+        let mut empty = BoundedHeap::new(0);
+        assert_eq!(empty.pop_push(1), Some(1));
+        assert_eq!(empty.push_evict_min(1), Some(1));
+    }
+
+    #[test]
+    fn test_push_evict_min_keeps_best() {
+        let mut best = BoundedHeap::new(4);
+        let mut state = 7;
+        let mut all = Vec::new();
+        for _ in 0..500 {
+            let x = xorshift(&mut state) % 1000;
+            all.push(x);
+            if let Some(evicted) = best.push_evict_min(x) {
+                // Anything evicted is no greater than what is kept.
+                assert!(best.iter().all(|&kept| kept >= evicted));
+            }
+        }
+        all.sort_unstable();
+        assert_eq!(best.into_sorted_buffer().as_slice(), &all[all.len() - 4..]);
+    }
+
+    #[test]
+    fn test_from_buffer_heapifies() {
+        let mut buf = BoundedBuffer::new(10);
+        buf.try_extend([5, 9, 1, 7, 3, 8]).unwrap();
+        let heap = BoundedHeap::from(buf);
+        assert_eq!(heap.capacity(), 10);
+        assert_eq!(heap.peek(), Some(&9));
+        assert_eq!(
+            heap.clone().into_sorted_buffer().as_slice(),
+            [1, 3, 5, 7, 8, 9]
+        );
+        assert_eq!(heap.into_inner().len(), 6);
+    }
+
+    #[test]
+    fn test_drain_sorted() {
+        let mut heap = BoundedHeap::from({
+            let mut buf = BoundedBuffer::new(6);
+            buf.try_extend([2, 6, 4, 3, 5, 1]).unwrap();
+            buf
+        });
+        let mut drain = heap.drain_sorted();
+        assert_eq!(drain.len(), 6);
+        assert_eq!(drain.next(), Some(6));
+        assert_eq!(drain.next(), Some(5));
+        drop(drain);
+        assert!(heap.is_empty());
+        heap.try_push(7).unwrap();
+        assert!(heap.drain_sorted().eq([7]));
+    }
+
+    #[test]
+    fn test_drop_counts() {
+        let drops = Drops::default();
+        let item = |key| Keyed {
+            key,
+            _drop: DropCount(drops.clone()),
+        };
+        let mut heap = BoundedHeap::new(3);
+        for key in [5, 3, 8] {
+            heap.try_push(item(key)).unwrap();
+        }
+        // A rejected element comes back rather than being dropped.
+        let back = heap.try_push(item(1)).unwrap_err().element();
+        assert_eq!((back.key, drops.get()), (1, 0));
+        drop(back);
+        assert_eq!(heap.push_evict_min(item(2)).unwrap().key, 2);
+        assert_eq!(drops.get(), 2);
+        let evicted = heap.push_evict_min(item(9)).unwrap();
+        assert_eq!((evicted.key, drops.get()), (3, 2));
+        drop(evicted);
+        drop(heap.push_pop(item(4)));
+        drop(heap.pop_push(item(6)));
+        assert_eq!(drops.get(), 5);
+        let sorted = heap.into_sorted_buffer();
+        assert_eq!(sorted.iter().map(|d| d.key).collect::<Vec<_>>(), [4, 5, 6]);
+        drop(sorted);
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn test_panicking_ord_keeps_elements() {
+        struct Bomb {
+            _token: Rc<()>,
+            key: u32,
+        }
+        impl PartialEq for Bomb {
+            fn eq(&self, other: &Self) -> bool {
+                self.key == other.key
+            }
+        }
+        impl Eq for Bomb {}
+        impl PartialOrd for Bomb {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Bomb {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                assert!(self.key != 13 && other.key != 13, "boom");
+                self.key.cmp(&other.key)
+            }
+        }
+
+        let token = Rc::new(());
+        let mut heap = BoundedHeap::new(8);
+        for key in [1, 2, 3, 4, 5] {
+            heap.try_push(Bomb {
+                _token: token.clone(),
+                key,
+            })
+            .unwrap();
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            heap.try_push(Bomb {
+                _token: token.clone(),
+                key: 13,
+            })
+            .unwrap();
+        }));
+        assert!(result.is_err());
+        // Nothing was lost or duplicated.
+        assert_eq!(heap.len(), 6);
+        assert_eq!(Rc::strong_count(&token), 7);
+        drop(heap);
+        assert_eq!(Rc::strong_count(&token), 1);
+    }
+
+    #[test]
+    fn test_random_ops_match_binary_heap() {
+        let rounds = if cfg!(miri) { 300 } else { 100_000 };
+        let cap = 16;
+        let mut heap = BoundedHeap::new(cap);
+        let mut model = BinaryHeap::new();
+        let mut state = 0xDEAD_BEEF;
+        for _ in 0..rounds {
+            let r = xorshift(&mut state);
+            let x = (r >> 16) % 64;
+            match r % 5 {
+                0 | 1 => {
+                    let pushed = heap.try_push(x).is_ok();
+                    assert_eq!(pushed, model.len() < cap);
+                    if pushed {
+                        model.push(x);
+                    }
+                }
+                2 => assert_eq!(heap.pop(), model.pop()),
+                3 => {
+                    model.push(x);
+                    assert_eq!(heap.push_pop(x), model.pop().unwrap());
+                }
+                _ => {
+                    let expected = model.pop();
+                    model.push(x);
+                    assert_eq!(heap.pop_push(x), expected);
+                }
+            }
+            assert_eq!(heap.len(), model.len());
+            assert_eq!(heap.peek(), model.peek());
+        }
+        let sorted = heap.into_sorted_buffer();
+        assert_eq!(sorted.as_slice(), model.into_sorted_vec());
+    }
+
+    #[test]
+    fn test_reverse_gives_min_heap() {
+        let mut heap = BoundedHeap::new(3);
+        for x in [5, 1, 9, 3, 7] {
+            heap.push_evict_min(Reverse(x));
+        }
+        let kept: Vec<_> = heap.drain_sorted().map(|Reverse(x)| x).collect();
+        assert_eq!(kept, [1, 3, 5]);
+    }
+}
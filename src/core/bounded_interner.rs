@@ -0,0 +1,398 @@
+use std::{fmt, iter::FusedIterator, ops::Range, str};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    error::{InternError, TryNewError},
+};
+
+// Marks an unused slot in the index. Symbols are always below this because
+// the symbol capacity is capped under it.
+const EMPTY: u32 = u32::MAX;
+
+/// A handle to a string interned in a [`BoundedInterner`]. Symbols are
+/// handed out densely in insertion order starting from zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the symbol's position in insertion order.
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A string interner whose memory is allocated once at construction: a byte
+/// arena holding the strings back to back, a table of where each one ends,
+/// and an open-addressed index for deduplication. Interning never
+/// allocates, and resolved `&str`s stay valid for as long as the interner
+/// is borrowed.
+///
+/// The index has at least twice as many slots as there are symbols, so
+/// lookups probe only a few entries on average.
+///
+/// ```
+/// use fixed_buf::{BoundedInterner, InternError};
+///
+/// let mut tickers = BoundedInterner::new(4, 16);
+/// let aapl = tickers.intern("AAPL").unwrap();
+/// let msft = tickers.intern("MSFT").unwrap();
+/// assert_eq!(tickers.intern("AAPL"), Ok(aapl));
+/// assert_eq!(tickers.resolve(msft), "MSFT");
+/// assert_eq!(tickers.get("MSFT"), Some(msft));
+/// assert_eq!(tickers.intern("GOOGLE-CLASS-A"), Err(InternError::ArenaFull));
+/// assert_eq!(tickers.len(), 2);
+/// ```
+pub struct BoundedInterner {
+    arena: BoundedBuffer<u8>,
+    // The end offset in the arena of each symbol's string; a symbol's string
+    // starts where the previous one ends.
+    ends: BoundedBuffer<usize>,
+    // Symbol numbers or `EMPTY`, always completely filled. Its capacity is a
+    // power of two.
+    index: BoundedBuffer<u32>,
+}
+
+impl BoundedInterner {
+    /// Creates an interner that holds at most `max_symbols` strings totalling
+    /// at most `arena_bytes` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_symbols` is not below `u32::MAX` or an allocation
+    /// fails.
+    #[track_caller]
+    pub fn new(max_symbols: usize, arena_bytes: usize) -> Self {
+        match Self::try_new(max_symbols, arena_bytes) {
+            Ok(interner) => interner,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(max_symbols: usize, arena_bytes: usize) -> Result<Self, TryNewError> {
+        if max_symbols >= EMPTY as usize {
+            return Err(TryNewError::CapacityOverflow);
+        }
+        let slots = max_symbols
+            .checked_mul(2)
+            .and_then(usize::checked_next_power_of_two)
+            .ok_or(TryNewError::CapacityOverflow)?
+            .max(1);
+        let mut index = BoundedBuffer::try_new(slots)?;
+        index.fill_to_capacity(EMPTY);
+        Ok(Self {
+            arena: BoundedBuffer::try_new(arena_bytes)?,
+            ends: BoundedBuffer::try_new(max_symbols)?,
+            index,
+        })
+    }
+
+    /// Returns the number of interned strings.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Returns the maximum number of strings.
+    pub fn capacity(&self) -> usize {
+        self.ends.capacity()
+    }
+
+    /// Returns the number of arena bytes in use.
+    pub fn arena_len(&self) -> usize {
+        self.arena.len()
+    }
+
+    pub fn arena_capacity(&self) -> usize {
+        self.arena.capacity()
+    }
+
+    /// Returns the symbol for `s`, interning it first if it is new.
+    ///
+    /// Fails without changing anything if `s` is new and either every
+    /// symbol is taken or the arena cannot hold it.
+    pub fn intern(&mut self, s: &str) -> Result<Symbol, InternError> {
+        let slot = match self.find(s) {
+            Ok(sym) => return Ok(sym),
+            Err(slot) => slot,
+        };
+        if self.ends.len() == self.ends.capacity() {
+            return Err(InternError::SlotsFull);
+        }
+        self.arena.push_str(s).map_err(|_| InternError::ArenaFull)?;
+        let sym = self.ends.len() as u32;
+        let pushed = self.ends.try_push(self.arena.len());
+        debug_assert!(pushed);
+        self.index[slot] = sym;
+        Ok(Symbol(sym))
+    }
+
+    /// Returns the symbol for `s` if it has been interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.find(s).ok()
+    }
+
+    /// Returns the string for `sym`.
+    ///
+    /// Symbols are plain indices, so one from another interner is not
+    /// detected as such: if it is in range here, this returns whichever
+    /// string was interned at that index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sym` is out of range, i.e. no symbol with its index has
+    /// been handed out by this interner.
+    #[track_caller]
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        match self.try_resolve(sym) {
+            Some(s) => s,
+            None => panic!(
+                "symbol {} out of range for an interner of {} strings",
+                sym.0,
+                self.len()
+            ),
+        }
+    }
+
+    /// Returns the string for `sym`, or `None` if no such symbol has been
+    /// handed out.
+    pub fn try_resolve(&self, sym: Symbol) -> Option<&str> {
+        let range = self.span(sym.index())?;
+        // SAFETY: the arena is a concatenation of `&str`s and `span` returns
+        // the bounds of one of them.
+        Some(unsafe { str::from_utf8_unchecked(&self.arena[range]) })
+    }
+
+    /// Returns an iterator over the symbols and their strings in insertion
+    /// order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            interner: self,
+            next: 0,
+            end: self.len(),
+        }
+    }
+
+    /// Forgets every string, keeping the memory for reuse. Previously issued
+    /// symbols will resolve to new strings.
+    pub fn clear(&mut self) {
+        self.arena.clear();
+        self.ends.clear();
+        self.index.fill(EMPTY);
+    }
+
+    fn span(&self, i: usize) -> Option<Range<usize>> {
+        let end = *self.ends.get(i)?;
+        let start = match i {
+            0 => 0,
+            _ => self.ends[i - 1],
+        };
+        Some(start..end)
+    }
+
+    // Returns the symbol for `s`, or the empty index slot where it belongs.
+    fn find(&self, s: &str) -> Result<Symbol, usize> {
+        let mask = self.index.len() - 1;
+        let mut slot = hash(s) as usize & mask;
+        loop {
+            let sym = self.index[slot];
+            if sym == EMPTY {
+                return Err(slot);
+            }
+            let range = self.span(sym as usize).unwrap();
+            if &self.arena[range] == s.as_bytes() {
+                return Ok(Symbol(sym));
+            }
+            slot = (slot + 1) & mask;
+        }
+    }
+}
+
+// FNV-1a. Interned names are short and come from the program itself, so a
+// keyed hash isn't worth the extra state.
+fn hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl fmt::Debug for BoundedInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(sym, s)| (sym.0, s)))
+            .finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a BoundedInterner {
+    type Item = (Symbol, &'a str);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of a [`BoundedInterner`].
+///
+/// Created by [`BoundedInterner::iter`].
+#[derive(Clone)]
+pub struct Iter<'a> {
+    interner: &'a BoundedInterner,
+    next: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (Symbol, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.end {
+            return None;
+        }
+        let sym = Symbol(self.next as u32);
+        self.next += 1;
+        Some((sym, self.interner.resolve(sym)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.end - self.next;
+        (n, Some(n))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.next == self.end {
+            return None;
+        }
+        self.end -= 1;
+        let sym = Symbol(self.end as u32);
+        Some((sym, self.interner.resolve(sym)))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod test {
+    use super::{BoundedInterner, Symbol};
+    use crate::core::error::{InternError, TryNewError};
+
+    #[test]
+    fn test_duplicates_share_a_symbol() {
+        let mut interner = BoundedInterner::new(8, 64);
+        let a = interner.intern("alpha").unwrap();
+        let b = interner.intern("beta").unwrap();
+        let empty = interner.intern("").unwrap();
+        assert_eq!((a.as_u32(), b.as_u32(), empty.as_u32()), (0, 1, 2));
+
+        let used = interner.arena_len();
+        for _ in 0..3 {
+            assert_eq!(interner.intern("alpha"), Ok(a));
+            assert_eq!(interner.intern("beta"), Ok(b));
+            assert_eq!(interner.intern(""), Ok(empty));
+        }
+        assert_eq!(interner.len(), 3);
+        assert_eq!(interner.arena_len(), used);
+        assert_eq!(interner.resolve(empty), "");
+        // Prefixes and extensions of interned strings are distinct.
+        assert_ne!(interner.intern("alph").unwrap(), a);
+        assert_ne!(interner.intern("alphas").unwrap(), a);
+    }
+
+    #[test]
+    fn test_arena_exhaustion() {
+        let mut interner = BoundedInterner::new(8, 10);
+        interner.intern("héllo").unwrap();
+        assert_eq!(interner.arena_len(), 6);
+        assert_eq!(interner.intern("world"), Err(InternError::ArenaFull));
+        // A failed intern leaves no trace and smaller strings still fit.
+        assert_eq!(interner.len(), 1);
+        assert_eq!(interner.get("world"), None);
+        let sym = interner.intern("wrld").unwrap();
+        assert_eq!(sym.index(), 1);
+        assert_eq!(interner.arena_len(), 10);
+        // Existing strings and the empty string need no arena space.
+        assert!(interner.intern("héllo").is_ok());
+        assert!(interner.intern("").is_ok());
+        assert_eq!(interner.intern("!"), Err(InternError::ArenaFull));
+    }
+
+    #[test]
+    fn test_slots_exhaustion() {
+        let mut interner = BoundedInterner::new(2, 64);
+        interner.intern("a").unwrap();
+        interner.intern("b").unwrap();
+        assert_eq!(interner.intern("c"), Err(InternError::SlotsFull));
+        assert_eq!(interner.intern("a").unwrap().index(), 0);
+
+        let mut none = BoundedInterner::new(0, 64);
+        assert_eq!(none.intern("a"), Err(InternError::SlotsFull));
+        assert_eq!(none.get("a"), None);
+    }
+
+    #[test]
+    fn test_resolve_after_many_inserts() {
+        let n = if cfg!(miri) { 200 } else { 10_000 };
+        let mut interner = BoundedInterner::new(n, n * 8);
+        let names: Vec<String> = (0..n).map(|i| format!("tag{i:x}")).collect();
+        let syms: Vec<Symbol> = names.iter().map(|s| interner.intern(s).unwrap()).collect();
+        assert_eq!(interner.len(), n);
+
+        for (name, &sym) in names.iter().zip(&syms) {
+            assert_eq!(interner.resolve(sym), name);
+            assert_eq!(interner.get(name), Some(sym));
+        }
+        for (i, (sym, s)) in interner.iter().enumerate() {
+            assert_eq!(sym, syms[i]);
+            assert_eq!(s, names[i]);
+        }
+        assert_eq!(interner.iter().len(), n);
+        assert_eq!(interner.iter().next_back().unwrap().1, names[n - 1]);
+        assert_eq!(interner.intern("one too many"), Err(InternError::SlotsFull));
+    }
+
+    #[test]
+    fn test_clear_and_reuse() {
+        let mut interner = BoundedInterner::new(2, 8);
+        interner.intern("AAPL").unwrap();
+        interner.intern("MSFT").unwrap();
+        interner.clear();
+        assert!(interner.is_empty());
+        assert_eq!(interner.get("AAPL"), None);
+        let sym = interner.intern("MSFT").unwrap();
+        assert_eq!(sym.index(), 0);
+        assert_eq!(interner.try_resolve(Symbol(1)), None);
+        assert_eq!(format!("{interner:?}"), r#"{0: "MSFT"}"#);
+    }
+
+    #[test]
+    #[should_panic(expected = "symbol 3 out of range for an interner of 1 strings")]
+    fn test_resolve_foreign_symbol() {
+        let mut big = BoundedInterner::new(8, 32);
+        let sym = ["a", "b", "c", "d"].map(|s| big.intern(s).unwrap())[3];
+        let mut small = BoundedInterner::new(8, 32);
+        small.intern("a").unwrap();
+        small.resolve(sym);
+    }
+
+    #[test]
+    fn test_try_new_limits() {
+        assert_eq!(
+            BoundedInterner::try_new(u32::MAX as usize, 0).unwrap_err(),
+            TryNewError::CapacityOverflow
+        );
+        assert!(BoundedInterner::try_new(usize::MAX / 2, 0).is_err());
+        assert!(BoundedInterner::try_new(4, usize::MAX).is_err());
+    }
+}
@@ -0,0 +1,825 @@
+use std::{fmt, iter::FusedIterator};
+
+use crate::core::{
+    error::{CapacityError, TryNewError},
+    slab::{Key, RawSlotsMut, Slab},
+};
+
+// Ends a chain of links. Node indices are always below it because a slab's
+// capacity is capped at `u32::MAX`.
+const NONE: u32 = u32::MAX;
+
+/// A handle to an element of a [`BoundedLinkedList`], returned when it is
+/// inserted.
+///
+/// It wraps the slab [`Key`] of the element's node, so once the element is
+/// removed the token stops working, even after its node is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Token(Key);
+
+impl Token {
+    /// Returns the index of the node the token points to.
+    pub const fn index(self) -> usize {
+        self.0.index()
+    }
+
+    pub const fn generation(self) -> u32 {
+        self.0.generation()
+    }
+}
+
+struct Node<T> {
+    prev: u32,
+    next: u32,
+    value: T,
+}
+
+/// A doubly linked list whose nodes live in a fixed [`Slab`] and link to
+/// each other by slot index.
+///
+/// Inserting at either end, and removing any element given its [`Token`],
+/// are O(1) and never allocate. The slab reuses the nodes of removed
+/// elements and retires those whose generation would wrap around, so a
+/// stale token never reaches a later element.
+///
+/// ```
+/// use fixed_buf::BoundedLinkedList;
+///
+/// // Resting orders at one price level, oldest first.
+/// let mut level = BoundedLinkedList::new(4);
+/// let a = level.push_back(("a", 100)).unwrap();
+/// let b = level.push_back(("b", 50)).unwrap();
+/// let c = level.push_back(("c", 75)).unwrap();
+///
+/// // "b" is cancelled; nothing else moves.
+/// assert_eq!(level.remove(b), Some(("b", 50)));
+/// assert_eq!(level.remove(b), None);
+/// assert!(level.iter().map(|(id, _)| *id).eq(["a", "c"]));
+///
+/// // "a" is filled from the front.
+/// assert_eq!(level.pop_front(), Some(("a", 100)));
+/// assert!(!level.contains(a));
+/// assert_eq!(level.get(c), Some(&("c", 75)));
+/// ```
+pub struct BoundedLinkedList<T> {
+    nodes: Slab<Node<T>>,
+    head: u32,
+    tail: u32,
+}
+
+impl<T> BoundedLinkedList<T> {
+    /// Creates a list that holds at most `cap` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` exceeds `u32::MAX` or the allocation fails.
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        match Self::try_new(cap) {
+            Ok(list) => list,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        Ok(Self {
+            nodes: Slab::try_new(cap)?,
+            head: NONE,
+            tail: NONE,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Returns how many more elements fit.
+    pub fn remaining_capacity(&self) -> usize {
+        self.nodes.remaining_capacity()
+    }
+
+    pub fn push_back(&mut self, value: T) -> Result<Token, CapacityError<T>> {
+        self.insert_between(self.tail, NONE, value)
+    }
+
+    pub fn push_front(&mut self, value: T) -> Result<Token, CapacityError<T>> {
+        self.insert_between(NONE, self.head, value)
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        (self.head != NONE).then(|| self.unlink_at(self.head))
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        (self.tail != NONE).then(|| self.unlink_at(self.tail))
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.value(self.head)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.value(self.tail)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.value_mut(self.head)
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.value_mut(self.tail)
+    }
+
+    /// Returns the token of the first element.
+    pub fn front_token(&self) -> Option<Token> {
+        self.token(self.head)
+    }
+
+    /// Returns the token of the last element.
+    pub fn back_token(&self) -> Option<Token> {
+        self.token(self.tail)
+    }
+
+    pub fn contains(&self, token: Token) -> bool {
+        self.nodes.contains(token.0)
+    }
+
+    /// Returns the element for `token`, or `None` if it has been removed.
+    pub fn get(&self, token: Token) -> Option<&T> {
+        self.nodes.get(token.0).map(|node| &node.value)
+    }
+
+    pub fn get_mut(&mut self, token: Token) -> Option<&mut T> {
+        self.nodes.get_mut(token.0).map(|node| &mut node.value)
+    }
+
+    /// Removes and returns the element for `token`, or returns `None` if it
+    /// has already been removed. The elements around it keep their order.
+    pub fn remove(&mut self, token: Token) -> Option<T> {
+        let node = self.nodes.remove(token.0)?;
+        Some(self.unlink(node))
+    }
+
+    /// Removes every element. Tokens to them are invalid afterwards.
+    pub fn clear(&mut self) {
+        while self.pop_back().is_some() {}
+    }
+
+    /// Returns an iterator over the elements, front to back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            list: self,
+            front: self.head,
+            back: self.tail,
+            len: self.len(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            front: self.head,
+            back: self.tail,
+            len: self.len(),
+            nodes: self.nodes.raw_slots_mut(),
+        }
+    }
+
+    /// Returns an iterator over the tokens and elements, front to back.
+    pub fn iter_tokens(&self) -> impl Iterator<Item = (Token, &T)> + '_ {
+        let mut indices = self.iter();
+        std::iter::from_fn(move || {
+            let index = indices.front;
+            let value = indices.next()?;
+            Some((self.token(index)?, value))
+        })
+    }
+
+    /// Returns a cursor at the first element, or at the "ghost" position if
+    /// the list is empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            index: self.head,
+            list: self,
+        }
+    }
+
+    /// Returns a cursor at the last element, or at the "ghost" position if
+    /// the list is empty.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            index: self.tail,
+            list: self,
+        }
+    }
+
+    /// Returns a cursor at the element for `token`, or `None` if it has been
+    /// removed.
+    pub fn cursor_mut(&mut self, token: Token) -> Option<CursorMut<'_, T>> {
+        self.contains(token).then(|| CursorMut {
+            list: self,
+            index: token.0.index() as u32,
+        })
+    }
+
+    fn token(&self, index: u32) -> Option<Token> {
+        self.nodes.key_at(index).map(Token)
+    }
+
+    fn value(&self, index: u32) -> Option<&T> {
+        self.nodes.get_at(index).map(|node| &node.value)
+    }
+
+    fn value_mut(&mut self, index: u32) -> Option<&mut T> {
+        self.nodes.get_at_mut(index).map(|node| &mut node.value)
+    }
+
+    // Follows a link, which always leads to an occupied node.
+    fn node(&self, index: u32) -> &Node<T> {
+        self.nodes.get_at(index).expect("link to a vacant node")
+    }
+
+    fn node_mut(&mut self, index: u32) -> &mut Node<T> {
+        self.nodes.get_at_mut(index).expect("link to a vacant node")
+    }
+
+    fn insert_between(
+        &mut self,
+        prev: u32,
+        next: u32,
+        value: T,
+    ) -> Result<Token, CapacityError<T>> {
+        let key = match self.nodes.insert(Node { prev, next, value }) {
+            Ok(key) => key,
+            Err(err) => return Err(CapacityError::new(err.element().value)),
+        };
+        let index = key.index() as u32;
+        match prev {
+            NONE => self.head = index,
+            prev => self.node_mut(prev).next = index,
+        }
+        match next {
+            NONE => self.tail = index,
+            next => self.node_mut(next).prev = index,
+        }
+        Ok(Token(key))
+    }
+
+    // Takes the element at `index` out of the list.
+    fn unlink_at(&mut self, index: u32) -> T {
+        let key = self.nodes.key_at(index).expect("link to a vacant node");
+        let node = self.nodes.remove(key).expect("link to a vacant node");
+        self.unlink(node)
+    }
+
+    // Closes the gap a node just removed from the slab leaves behind.
+    fn unlink(&mut self, node: Node<T>) -> T {
+        let Node { prev, next, value } = node;
+        match prev {
+            NONE => self.head = next,
+            prev => self.node_mut(prev).next = next,
+        }
+        match next {
+            NONE => self.tail = prev,
+            next => self.node_mut(next).prev = prev,
+        }
+        value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BoundedLinkedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for BoundedLinkedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for BoundedLinkedList<T> {}
+
+impl<'a, T> IntoIterator for &'a BoundedLinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut BoundedLinkedList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the elements of a [`BoundedLinkedList`], in list order.
+pub struct Iter<'a, T> {
+    list: &'a BoundedLinkedList<T>,
+    front: u32,
+    back: u32,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.list.node(self.front);
+        self.front = node.next;
+        self.len -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.list.node(self.back);
+        self.back = node.prev;
+        self.len -= 1;
+        Some(&node.value)
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self { ..*self }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Iter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// A mutable iterator over the elements of a [`BoundedLinkedList`], in list
+/// order.
+pub struct IterMut<'a, T> {
+    nodes: RawSlotsMut<'a, Node<T>>,
+    front: u32,
+    back: u32,
+    len: usize,
+}
+
+// It hands out `&mut T`s, like `slice::IterMut`.
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: each node is visited once from either end, since `len`
+        // stops the two ends from crossing.
+        let (_, node) = unsafe { self.nodes.get_mut(self.front) }.expect("link to a vacant node");
+        self.front = node.next;
+        self.len -= 1;
+        Some(&mut node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: as in `next`.
+        let (_, node) = unsafe { self.nodes.get_mut(self.back) }.expect("link to a vacant node");
+        self.back = node.prev;
+        self.len -= 1;
+        Some(&mut node.value)
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+impl<T> fmt::Debug for IterMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IterMut")
+            .field("remaining", &self.len)
+            .finish()
+    }
+}
+
+/// A position in a [`BoundedLinkedList`] from which elements can be
+/// inspected, inserted, removed and spliced in.
+///
+/// Besides the elements, a cursor can point at a "ghost" position past the
+/// back and before the front, as with `std`'s linked list cursors. Moving
+/// past either end lands on it, and moving on from it wraps around.
+///
+/// ```
+/// use fixed_buf::BoundedLinkedList;
+///
+/// let mut list = BoundedLinkedList::new(8);
+/// let two = list.push_back(2).unwrap();
+/// list.push_back(5).unwrap();
+///
+/// let mut cursor = list.cursor_mut(two).unwrap();
+/// cursor.insert_after(3).unwrap();
+/// cursor.move_next();
+/// cursor.insert_after(4).unwrap();
+///
+/// let mut more = BoundedLinkedList::new(2);
+/// more.push_back(0).unwrap();
+/// more.push_back(1).unwrap();
+/// list.cursor_front_mut().splice_before(&mut more).unwrap();
+/// assert!(more.is_empty());
+/// assert!(list.iter().copied().eq(0..6));
+/// ```
+pub struct CursorMut<'a, T> {
+    list: &'a mut BoundedLinkedList<T>,
+    // `NONE` at the ghost position.
+    index: u32,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// Returns the element at the cursor, or `None` at the ghost position.
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.list.value_mut(self.index)
+    }
+
+    /// Returns the token of the element at the cursor.
+    pub fn token(&self) -> Option<Token> {
+        if self.index == NONE {
+            return None;
+        }
+        self.list.token(self.index)
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = self.next_index();
+        self.list.value_mut(next)
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        let prev = self.prev_index();
+        self.list.value_mut(prev)
+    }
+
+    pub fn move_next(&mut self) {
+        self.index = self.next_index();
+    }
+
+    pub fn move_prev(&mut self) {
+        self.index = self.prev_index();
+    }
+
+    /// Inserts `value` after the element at the cursor, or at the front if
+    /// the cursor is at the ghost position. The cursor doesn't move.
+    pub fn insert_after(&mut self, value: T) -> Result<Token, CapacityError<T>> {
+        let next = self.next_index();
+        self.list.insert_between(self.index, next, value)
+    }
+
+    /// Inserts `value` before the element at the cursor, or at the back if
+    /// the cursor is at the ghost position. The cursor doesn't move.
+    pub fn insert_before(&mut self, value: T) -> Result<Token, CapacityError<T>> {
+        let prev = self.prev_index();
+        self.list.insert_between(prev, self.index, value)
+    }
+
+    /// Removes and returns the element at the cursor, moving the cursor to
+    /// the next one. Does nothing at the ghost position.
+    pub fn remove_current(&mut self) -> Option<T> {
+        if self.index == NONE {
+            return None;
+        }
+        let next = self.next_index();
+        let value = self.list.unlink_at(self.index);
+        self.index = next;
+        Some(value)
+    }
+
+    /// Moves every element of `other` in after the element at the cursor, in
+    /// order, leaving `other` empty. At the ghost position they go at the
+    /// front. The cursor doesn't move.
+    ///
+    /// The elements move into this list's nodes, so they get new tokens;
+    /// their tokens from `other` stop working. If they don't all fit,
+    /// nothing is moved.
+    pub fn splice_after(&mut self, other: &mut BoundedLinkedList<T>) -> Result<(), CapacityError> {
+        if other.len() > self.list.remaining_capacity() {
+            return Err(CapacityError::new(()));
+        }
+        let mut prev = self.index;
+        let next = self.next_index();
+        while let Some(value) = other.pop_front() {
+            match self.list.insert_between(prev, next, value) {
+                Ok(token) => prev = token.index() as u32,
+                Err(_) => unreachable!("capacity was checked up front"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`splice_after`](Self::splice_after), but moves the elements in
+    /// before the element at the cursor, or at the back at the ghost
+    /// position.
+    pub fn splice_before(&mut self, other: &mut BoundedLinkedList<T>) -> Result<(), CapacityError> {
+        if other.len() > self.list.remaining_capacity() {
+            return Err(CapacityError::new(()));
+        }
+        let mut next = self.index;
+        let prev = self.prev_index();
+        while let Some(value) = other.pop_back() {
+            match self.list.insert_between(prev, next, value) {
+                Ok(token) => next = token.index() as u32,
+                Err(_) => unreachable!("capacity was checked up front"),
+            }
+        }
+        Ok(())
+    }
+
+    fn next_index(&self) -> u32 {
+        match self.index {
+            NONE => self.list.head,
+            index => self.list.node(index).next,
+        }
+    }
+
+    fn prev_index(&self) -> u32 {
+        match self.index {
+            NONE => self.list.tail,
+            index => self.list.node(index).prev,
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CursorMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CursorMut")
+            .field(&self.list.value(self.index))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::VecDeque, rc::Rc};
+
+    use super::{BoundedLinkedList, Token};
+    use crate::core::test_util::xorshift;
+
+    fn collect<T: Clone>(list: &BoundedLinkedList<T>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_push_pop_both_ends() {
+        let mut list = BoundedLinkedList::new(3);
+        list.push_back(2).unwrap();
+        list.push_front(1).unwrap();
+        list.push_back(3).unwrap();
+        assert_eq!(list.push_front(0).unwrap_err().element(), 0);
+        assert_eq!(collect(&list), [1, 2, 3]);
+        assert!(list.iter().rev().copied().eq([3, 2, 1]));
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&3)));
+        *list.back_mut().unwrap() = 30;
+        assert_eq!(list.pop_back(), Some(30));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+        assert!(list.is_empty());
+        assert_eq!(format!("{list:?}"), "[]");
+    }
+
+    #[test]
+    fn test_remove_from_middle() {
+        let mut list = BoundedLinkedList::new(5);
+        let tokens: Vec<Token> = (0..5).map(|i| list.push_back(i).unwrap()).collect();
+        assert_eq!(list.remove(tokens[2]), Some(2));
+        assert_eq!(list.remove(tokens[0]), Some(0));
+        assert_eq!(list.remove(tokens[4]), Some(4));
+        assert_eq!(collect(&list), [1, 3]);
+        assert_eq!(list.front_token(), Some(tokens[1]));
+        assert_eq!(list.back_token(), Some(tokens[3]));
+
+        // The freed nodes are reused under new generations.
+        let reused = list.push_front(9).unwrap();
+        assert_eq!(reused.index(), tokens[4].index());
+        assert_eq!(reused.generation(), 1);
+        assert_eq!(list.remove(tokens[4]), None);
+        assert_eq!(list.get(tokens[4]), None);
+        assert_eq!(list.get(reused), Some(&9));
+        assert_eq!(collect(&list), [9, 1, 3]);
+        assert_eq!(list.remaining_capacity(), 2);
+    }
+
+    #[test]
+    fn test_iter_mut_and_tokens() {
+        let mut list = BoundedLinkedList::new(4);
+        let tokens: Vec<Token> = (1..=4).map(|i| list.push_back(i).unwrap()).collect();
+        let mut iter = list.iter_mut();
+        *iter.next().unwrap() *= 10;
+        *iter.next_back().unwrap() *= 10;
+        assert_eq!(iter.len(), 2);
+        for v in &mut list {
+            *v += 1;
+        }
+        assert_eq!(collect(&list), [11, 3, 4, 41]);
+        let pairs: Vec<_> = list.iter_tokens().map(|(t, &v)| (t, v)).collect();
+        assert_eq!(
+            pairs,
+            [
+                (tokens[0], 11),
+                (tokens[1], 3),
+                (tokens[2], 4),
+                (tokens[3], 41)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cursor_walks_through_ghost() {
+        let mut list = BoundedLinkedList::new(4);
+        for i in 0..3 {
+            list.push_back(i).unwrap();
+        }
+        let mut cursor = list.cursor_back_mut();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.token(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 0));
+        assert_eq!(cursor.peek_prev(), Some(&mut 2));
+        // At the ghost, inserting after means at the front.
+        cursor.insert_after(-1).unwrap();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut -1));
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(collect(&list), [-1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cursor_remove_and_insert() {
+        let mut list = BoundedLinkedList::new(5);
+        let tokens: Vec<Token> = (0..4).map(|i| list.push_back(i).unwrap()).collect();
+        let mut cursor = list.cursor_mut(tokens[1]).unwrap();
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.current(), Some(&mut 2));
+        cursor.insert_before(10).unwrap();
+        cursor.insert_after(20).unwrap();
+        assert_eq!(cursor.insert_after(30).unwrap_err().element(), 30);
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(3));
+        assert_eq!(cursor.remove_current(), None);
+        assert_eq!(collect(&list), [0, 10, 2, 20]);
+        assert!(list.cursor_mut(tokens[1]).is_none());
+        assert!(list.cursor_mut(tokens[3]).is_none());
+    }
+
+    #[test]
+    fn test_splice() {
+        let mut list = BoundedLinkedList::new(6);
+        let one = list.push_back(1).unwrap();
+        list.push_back(5).unwrap();
+        let mut mid = BoundedLinkedList::new(3);
+        let other = mid.push_back(2).unwrap();
+        mid.push_back(3).unwrap();
+        mid.push_back(4).unwrap();
+        list.cursor_mut(one)
+            .unwrap()
+            .splice_after(&mut mid)
+            .unwrap();
+        assert!(mid.is_empty());
+        assert!(!mid.contains(other));
+        assert_eq!(collect(&list), [1, 2, 3, 4, 5]);
+
+        // Only one more fits, so splicing two moves nothing.
+        mid.push_back(6).unwrap();
+        mid.push_back(7).unwrap();
+        assert!(list.cursor_back_mut().splice_after(&mut mid).is_err());
+        assert_eq!(mid.len(), 2);
+        mid.pop_front();
+        let mut ghost = list.cursor_back_mut();
+        ghost.move_next();
+        ghost.splice_before(&mut mid).unwrap();
+        assert_eq!(collect(&list), [1, 2, 3, 4, 5, 7]);
+    }
+
+    #[test]
+    fn test_drops_elements() {
+        let token = Rc::new(());
+        let mut list = BoundedLinkedList::new(4);
+        let handles: Vec<Token> = (0..4)
+            .map(|_| list.push_back(token.clone()).unwrap())
+            .collect();
+        drop(list.remove(handles[1]));
+        assert_eq!(Rc::strong_count(&token), 4);
+        drop(list);
+        assert_eq!(Rc::strong_count(&token), 1);
+    }
+
+    #[test]
+    fn test_exhausted_generation_retires_node() {
+        let mut list = BoundedLinkedList::new(2);
+        list.push_back(0).unwrap();
+        list.nodes.set_generation(0, u32::MAX);
+        let last = list.front_token().unwrap();
+        assert_eq!(list.remove(last), Some(0));
+        assert_eq!(list.remaining_capacity(), 1);
+        assert_eq!(list.push_back(1).unwrap().index(), 1);
+        assert!(list.push_back(2).is_err());
+        assert_eq!(list.get(last), None);
+    }
+
+    // The reference model keeps every element ever inserted, in list order,
+    // with removed ones left behind as tombstones.
+    #[test]
+    fn test_random_ops_match_tombstone_model() {
+        let rounds = if cfg!(miri) { 400 } else { 100_000 };
+        let cap = 32;
+        let mut list = BoundedLinkedList::new(cap);
+        let mut model: VecDeque<(Token, Option<u64>)> = VecDeque::new();
+        let mut live = 0;
+        let mut state = 0x2545_F491_4F6C_DD1D;
+        for round in 0..rounds {
+            let r = xorshift(&mut state);
+            match r % 6 {
+                0 | 1 => match list.push_back(round) {
+                    Ok(token) => {
+                        model.push_back((token, Some(round)));
+                        live += 1;
+                    }
+                    Err(_) => assert_eq!(live, cap),
+                },
+                2 => match list.push_front(round) {
+                    Ok(token) => {
+                        model.push_front((token, Some(round)));
+                        live += 1;
+                    }
+                    Err(_) => assert_eq!(live, cap),
+                },
+                3 | 4 if !model.is_empty() => {
+                    // Removes a live element or tries a stale token.
+                    let i = (r >> 8) as usize % model.len();
+                    let (token, value) = &mut model[i];
+                    assert_eq!(list.remove(*token), value.take());
+                    live = model.iter().filter(|(_, v)| v.is_some()).count();
+                }
+                _ => {
+                    let expected = model.iter_mut().find_map(|(_, v)| v.take());
+                    assert_eq!(list.pop_front(), expected);
+                    if expected.is_some() {
+                        live -= 1;
+                    }
+                }
+            }
+            // Keep the tombstones from piling up forever.
+            while model.front().is_some_and(|(_, v)| v.is_none()) && model.len() > 4 * cap {
+                model.pop_front();
+            }
+            assert_eq!(list.len(), live);
+            if round % 64 == 0 {
+                let expected: Vec<u64> = model.iter().filter_map(|(_, v)| *v).collect();
+                assert_eq!(collect(&list), expected);
+                let back: Vec<u64> = list.iter().rev().copied().collect();
+                assert!(back.iter().rev().eq(expected.iter()));
+                for (token, value) in &model {
+                    assert_eq!(list.get(*token), value.as_ref());
+                }
+            }
+        }
+    }
+}
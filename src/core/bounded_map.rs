@@ -0,0 +1,878 @@
+use std::{
+    borrow::Borrow,
+    fmt,
+    iter::FusedIterator,
+    ops::{Bound, Index, Range, RangeBounds},
+    slice,
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    error::{CapacityError, TryNewError},
+};
+
+/// An ordered map with a capacity fixed at construction, stored as sorted
+/// arrays and searched by bisection.
+///
+/// Keys and values live in two parallel buffers rather than one buffer of
+/// pairs, so a lookup's binary search only touches keys and packs as many
+/// of them into each cache line as it can. Lookups are O(log n). Inserting
+/// and removing shift the later entries over, so they are O(n) moves, which
+/// for the small maps this is meant for usually beats chasing the pointers
+/// of a `BTreeMap`.
+///
+/// Inserting a new key into a full map fails and gives the key and value
+/// back. Replacing the value of a key already present always succeeds.
+///
+/// ```
+/// use fixed_buf::BoundedMap;
+///
+/// let mut ports = BoundedMap::new(3);
+/// ports.try_insert("https", 443).unwrap();
+/// ports.try_insert("ssh", 22).unwrap();
+/// ports.try_insert("http", 80).unwrap();
+/// assert!(ports.try_insert("dns", 53).is_err());
+/// assert_eq!(ports.try_insert("ssh", 2222), Ok(Some(22)));
+/// assert_eq!(ports["http"], 80);
+/// let h: Vec<_> = ports.range("h".."i").map(|(k, _)| *k).collect();
+/// assert_eq!(h, ["http", "https"]);
+/// ```
+#[derive(Clone)]
+pub struct BoundedMap<K, V> {
+    // Strictly increasing. `values[i]` belongs to `keys[i]`.
+    keys: BoundedBuffer<K>,
+    values: BoundedBuffer<V>,
+}
+
+impl<K, V> BoundedMap<K, V> {
+    /// Creates an empty map that holds at most `cap` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        match Self::try_new(cap) {
+            Ok(map) => map,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        Ok(Self {
+            keys: BoundedBuffer::try_new(cap)?,
+            values: BoundedBuffer::try_new(cap)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.keys.capacity()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.keys.len() == self.keys.capacity()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.values.clear();
+    }
+
+    /// Returns the keys in ascending order, as a slice.
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// Returns the values in the order of their keys, as a slice.
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    /// Like [`values`](Self::values), but mutable.
+    pub fn values_mut(&mut self) -> &mut [V] {
+        &mut self.values
+    }
+
+    /// Returns the entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        Some((self.keys.first()?, self.values.first()?))
+    }
+
+    /// Returns the entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        Some((self.keys.last()?, self.values.last()?))
+    }
+
+    /// Returns an iterator over the entries, in ascending key order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            keys: self.keys.iter(),
+            values: self.values.iter(),
+        }
+    }
+
+    /// Like [`iter`](Self::iter), but the values are mutable.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            keys: self.keys.iter(),
+            values: self.values.iter_mut(),
+        }
+    }
+
+    /// Keeps only the entries for which `f` returns `true`, in one pass.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        // Kept entries are swapped down to `..write`, so `write..read` holds
+        // the rejected ones. Dropping those even if `f` panics keeps the
+        // keys sorted.
+        struct Guard<'a, K, V> {
+            map: &'a mut BoundedMap<K, V>,
+            write: usize,
+            read: usize,
+        }
+
+        impl<K, V> Drop for Guard<'_, K, V> {
+            fn drop(&mut self) {
+                self.map.keys.drain(self.write..self.read);
+                self.map.values.drain(self.write..self.read);
+            }
+        }
+
+        let len = self.len();
+        let mut guard = Guard {
+            map: self,
+            write: 0,
+            read: 0,
+        };
+        while guard.read < len {
+            let (read, map) = (guard.read, &mut *guard.map);
+            if f(&map.keys[read], &mut map.values[read]) {
+                map.keys.swap(guard.write, read);
+                map.values.swap(guard.write, read);
+                guard.write += 1;
+            }
+            guard.read += 1;
+        }
+    }
+}
+
+impl<K: Ord, V> BoundedMap<K, V> {
+    /// Returns the index of `key`, or where it would be inserted.
+    fn search<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.keys.binary_search_by(|k| k.borrow().cmp(key))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.search(key).is_ok()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.search(key).ok().map(|i| &self.values[i])
+    }
+
+    /// Returns the stored key equal to `key` along with its value.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.search(key)
+            .ok()
+            .map(|i| (&self.keys[i], &self.values[i]))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.search(key).ok().map(|i| &mut self.values[i])
+    }
+
+    /// Inserts `value` under `key`, returning the value it replaces, if any.
+    ///
+    /// If `key` is already present, its value is replaced and the stored key
+    /// is kept, even if the map is full. Otherwise the entry needs a new
+    /// slot, and a full map gives `key` and `value` back.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, CapacityError<(K, V)>> {
+        match self.search(&key) {
+            Ok(i) => Ok(Some(std::mem::replace(&mut self.values[i], value))),
+            Err(i) => {
+                if self.is_full() {
+                    return Err(CapacityError::new((key, value)));
+                }
+                self.insert_at(i, key, value);
+                Ok(None)
+            }
+        }
+    }
+
+    fn insert_at(&mut self, index: usize, key: K, value: V) {
+        // The buffers have the same capacity and always the same length, so
+        // either both have room or neither does.
+        let inserted = self.keys.try_insert(index, key);
+        debug_assert!(inserted);
+        let inserted = self.values.try_insert(index, value);
+        debug_assert!(inserted);
+    }
+
+    /// Removes `key`, returning its value if it was present.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes `key`, returning the stored key and its value if it was
+    /// present.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let i = self.search(key).ok()?;
+        Some((self.keys.remove(i), self.values.remove(i)))
+    }
+
+    /// Returns the entry for `key`, to read, update or fill in place with a
+    /// single search.
+    ///
+    /// ```
+    /// use fixed_buf::BoundedMap;
+    ///
+    /// let mut counts = BoundedMap::new(2);
+    /// for word in ["a", "b", "a", "c"] {
+    ///     match counts.entry(word).and_modify(|n| *n += 1).or_insert(1) {
+    ///         Ok(_) => {}
+    ///         Err(err) => assert_eq!(err.element(), ("c", 1)),
+    ///     }
+    /// }
+    /// assert_eq!(counts.iter().collect::<Vec<_>>(), [(&"a", &2), (&"b", &1)]);
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        match self.search(&key) {
+            Ok(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            Err(index) => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                index,
+            }),
+        }
+    }
+
+    /// Returns an iterator over the entries with keys in `range`, in
+    /// ascending key order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range starts after it ends, or starts and ends at the
+    /// same excluded key, like [`BTreeMap::range`](std::collections::BTreeMap::range).
+    #[track_caller]
+    pub fn range<Q, R>(&self, range: R) -> Iter<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let Range { start, end } = self.range_indices(range);
+        Iter {
+            keys: self.keys[start..end].iter(),
+            values: self.values[start..end].iter(),
+        }
+    }
+
+    /// Like [`range`](Self::range), but the values are mutable.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`range`](Self::range).
+    #[track_caller]
+    pub fn range_mut<Q, R>(&mut self, range: R) -> IterMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let Range { start, end } = self.range_indices(range);
+        IterMut {
+            keys: self.keys[start..end].iter(),
+            values: self.values[start..end].iter_mut(),
+        }
+    }
+
+    #[track_caller]
+    fn range_indices<Q, R>(&self, range: R) -> Range<usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(s), Bound::Excluded(e)) if s == e => {
+                panic!("range start and end are equal and excluded in BoundedMap")
+            }
+            (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e))
+                if s > e =>
+            {
+                panic!("range start is greater than range end in BoundedMap")
+            }
+            _ => {}
+        }
+        let start = match range.start_bound() {
+            Bound::Included(s) => self.keys.partition_point(|k| k.borrow() < s),
+            Bound::Excluded(s) => self.keys.partition_point(|k| k.borrow() <= s),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => self.keys.partition_point(|k| k.borrow() <= e),
+            Bound::Excluded(e) => self.keys.partition_point(|k| k.borrow() < e),
+            Bound::Unbounded => self.len(),
+        };
+        // Only an empty range can end before it starts, e.g. `(Excluded(3),
+        // Included(3))`.
+        start..end.max(start)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for BoundedMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: PartialEq, V: PartialEq> PartialEq for BoundedMap<K, V> {
+    /// Compares the entries, ignoring capacity.
+    fn eq(&self, other: &Self) -> bool {
+        self.keys() == other.keys() && self.values() == other.values()
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for BoundedMap<K, V> {}
+
+impl<K, Q, V> Index<&Q> for BoundedMap<K, V>
+where
+    K: Borrow<Q> + Ord,
+    Q: Ord + ?Sized,
+{
+    type Output = V;
+
+    /// # Panics
+    ///
+    /// Panics if `key` is not in the map.
+    #[track_caller]
+    fn index(&self, key: &Q) -> &V {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("key not found in BoundedMap"),
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a BoundedMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut BoundedMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// A view into a single entry of a [`BoundedMap`], returned by
+/// [`BoundedMap::entry`].
+///
+/// Filling a vacant entry needs a free slot, so the inserting methods return
+/// a [`CapacityError`] when the map is full, instead of the plain reference
+/// `BTreeMap`'s entries give.
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Runs `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+
+    /// Returns the value, inserting `default` first if the entry is vacant.
+    /// A full map gives the key and `default` back.
+    pub fn or_insert(self, default: V) -> Result<&'a mut V, CapacityError<(K, V)>> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.try_insert(default),
+        }
+    }
+
+    /// Like [`or_insert`](Self::or_insert), but only calls `default` when
+    /// there is room for its result. A full map gives the key back.
+    pub fn or_insert_with<F: FnOnce() -> V>(
+        self,
+        default: F,
+    ) -> Result<&'a mut V, CapacityError<K>> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) if entry.map.is_full() => Err(CapacityError::new(entry.key)),
+            Entry::Vacant(entry) => match entry.try_insert(default()) {
+                Ok(value) => Ok(value),
+                Err(_) => unreachable!(),
+            },
+        }
+    }
+
+    /// Like [`or_insert_with`](Self::or_insert_with), with `V::default()`.
+    pub fn or_default(self) -> Result<&'a mut V, CapacityError<K>>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Entry<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Occupied(entry) => f.debug_tuple("Entry").field(entry).finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
+    }
+}
+
+/// An entry of a [`BoundedMap`] whose key is present.
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut BoundedMap<K, V>,
+    index: usize,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    /// Returns the stored key.
+    pub fn key(&self) -> &K {
+        &self.map.keys[self.index]
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map.values[self.index]
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.values[self.index]
+    }
+
+    /// Turns the entry into a reference to its value that lives as long as
+    /// the map borrow.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.values[self.index]
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        (
+            self.map.keys.remove(self.index),
+            self.map.values.remove(self.index),
+        )
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for OccupiedEntry<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", self.key())
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+/// An entry of a [`BoundedMap`] whose key is absent.
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut BoundedMap<K, V>,
+    key: K,
+    // Where the key goes to keep the keys sorted.
+    index: usize,
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts `value` under the entry's key, or gives both back if the map
+    /// is full.
+    pub fn try_insert(self, value: V) -> Result<&'a mut V, CapacityError<(K, V)>> {
+        let map = self.map;
+        if map.is_full() {
+            return Err(CapacityError::new((self.key, value)));
+        }
+        map.insert_at(self.index, self.key, value);
+        Ok(&mut map.values[self.index])
+    }
+}
+
+impl<K: fmt::Debug, V> fmt::Debug for VacantEntry<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VacantEntry").field(&self.key).finish()
+    }
+}
+
+/// An iterator over the entries of a [`BoundedMap`], in ascending key order.
+pub struct Iter<'a, K, V> {
+    keys: slice::Iter<'a, K>,
+    values: slice::Iter<'a, V>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next()?, self.values.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next_back()?, self.values.next_back()?))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            keys: self.keys.clone(),
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Iter<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// An iterator over the entries of a [`BoundedMap`] with mutable values.
+pub struct IterMut<'a, K, V> {
+    keys: slice::Iter<'a, K>,
+    values: slice::IterMut<'a, V>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next()?, self.values.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.keys.next_back()?, self.values.next_back()?))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+impl<K: fmt::Debug, V> fmt::Debug for IterMut<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IterMut")
+            .field("remaining", &self.keys.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, ops::Bound, rc::Rc};
+
+    use super::{BoundedMap, Entry};
+    use crate::core::test_util::xorshift;
+
+    fn bound(r: u64) -> Bound<u16> {
+        let key = (r >> 8) as u16 % 40;
+        match r % 3 {
+            0 => Bound::Unbounded,
+            1 => Bound::Included(key),
+            _ => Bound::Excluded(key),
+        }
+    }
+
+    fn is_valid(range: &(Bound<u16>, Bound<u16>)) -> bool {
+        match *range {
+            (Bound::Excluded(s), Bound::Excluded(e)) => s < e,
+            (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) => {
+                s <= e
+            }
+            _ => true,
+        }
+    }
+
+    #[test]
+    fn test_matches_btree_map() {
+        const CAP: usize = 24;
+        let rounds = if cfg!(miri) { 300 } else { 50_000 };
+        let mut map = BoundedMap::new(CAP);
+        let mut model = BTreeMap::new();
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for round in 0..rounds {
+            let r = xorshift(&mut state);
+            let key = (r >> 32) as u16 % 40;
+            let full = model.len() == CAP && !model.contains_key(&key);
+            match r % 8 {
+                0 | 1 => match map.try_insert(key, round) {
+                    Ok(old) => assert_eq!(old, model.insert(key, round)),
+                    Err(err) => {
+                        assert!(full);
+                        assert_eq!(err.element(), (key, round));
+                    }
+                },
+                2 => assert_eq!(map.remove(&key), model.remove(&key)),
+                3 => {
+                    let result = map.entry(key).and_modify(|v| *v += 1).or_insert(round);
+                    match result {
+                        Ok(v) => assert_eq!(
+                            *v,
+                            *model.entry(key).and_modify(|v| *v += 1).or_insert(round)
+                        ),
+                        Err(_) => assert!(full),
+                    }
+                }
+                4 => match map.entry(key) {
+                    Entry::Occupied(entry) => {
+                        assert_eq!(entry.remove_entry(), model.remove_entry(&key).unwrap());
+                    }
+                    Entry::Vacant(entry) => {
+                        assert!(!model.contains_key(&key));
+                        assert_eq!(*entry.key(), key);
+                    }
+                },
+                5 => {
+                    let mut called = false;
+                    match map.entry(key).or_insert_with(|| {
+                        called = true;
+                        round
+                    }) {
+                        Ok(v) => assert_eq!(*v, *model.entry(key).or_insert(round)),
+                        Err(err) => {
+                            assert!(full);
+                            assert_eq!(err.element(), key);
+                        }
+                    }
+                    assert_eq!(called, !full && model[&key] == round);
+                }
+                _ => {
+                    let range = (bound(r >> 20), bound(r >> 40));
+                    if is_valid(&range) {
+                        assert!(map.range(range).eq(model.range(range)));
+                        assert!(map.range(range).rev().eq(model.range(range).rev()));
+                        assert_eq!(map.range(range).len(), model.range(range).count());
+                    }
+                }
+            }
+            assert_eq!(map.get(&key), model.get(&key));
+            assert_eq!(map.len(), model.len());
+        }
+        assert!(map.iter().eq(model.iter()));
+        assert_eq!(map.first_key_value(), model.first_key_value());
+        assert_eq!(map.last_key_value(), model.last_key_value());
+    }
+
+    #[test]
+    fn test_every_range_shape() {
+        let mut map = BoundedMap::new(8);
+        let mut model = BTreeMap::new();
+        for k in [1u16, 3, 4, 8, 9] {
+            map.try_insert(k, k * 10).unwrap();
+            model.insert(k, k * 10);
+        }
+        let bounds = |k| [Bound::Included(k), Bound::Excluded(k)];
+        for s in 0..11 {
+            for e in 0..11 {
+                let mut shapes = vec![(Bound::Unbounded, Bound::Unbounded)];
+                shapes.extend(bounds(s).map(|b| (b, Bound::Unbounded)));
+                shapes.extend(bounds(e).map(|b| (Bound::Unbounded, b)));
+                for start in bounds(s) {
+                    shapes.extend(bounds(e).map(|end| (start, end)));
+                }
+                for range in shapes.into_iter().filter(is_valid) {
+                    assert!(map.range(range).eq(model.range(range)), "{range:?}");
+                    for (_, v) in map.range_mut(range) {
+                        *v += 1;
+                    }
+                    for (_, v) in model.range_mut(range) {
+                        *v += 1;
+                    }
+                }
+            }
+        }
+        assert!(map.iter().eq(model.iter()));
+        // Bounds can be a borrowed form of the key.
+        let strings: BoundedMap<String, ()> =
+            ["ant", "bee", "cat"]
+                .into_iter()
+                .fold(BoundedMap::new(3), |mut map, k| {
+                    map.try_insert(k.to_string(), ()).unwrap();
+                    map
+                });
+        let keys: Vec<_> = strings
+            .range::<str, _>((Bound::Included("b"), Bound::Included("cat")))
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, ["bee", "cat"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "range start is greater than range end in BoundedMap")]
+    fn test_range_start_after_end() {
+        let map = BoundedMap::<u8, ()>::new(1);
+        let _ = map.range((Bound::Included(3), Bound::Excluded(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "range start and end are equal and excluded in BoundedMap")]
+    fn test_range_equal_excluded() {
+        let map = BoundedMap::<u8, ()>::new(1);
+        let _ = map.range((Bound::Excluded(3), Bound::Excluded(3)));
+    }
+
+    #[test]
+    fn test_entry_on_full_map() {
+        let mut map = BoundedMap::new(2);
+        map.try_insert(1, "one").unwrap();
+        map.try_insert(2, "two").unwrap();
+        // Occupied entries and replacements need no new slot.
+        assert_eq!(map.try_insert(2, "TWO"), Ok(Some("two")));
+        *map.entry(1).or_insert("x").unwrap() = "ONE";
+        assert_eq!(
+            map.entry(3).or_insert("three").unwrap_err().element(),
+            (3, "three")
+        );
+        assert_eq!(
+            map.entry(3)
+                .or_insert_with(|| unreachable!())
+                .unwrap_err()
+                .element(),
+            3
+        );
+        match map.entry(0) {
+            Entry::Vacant(entry) => {
+                assert_eq!(format!("{entry:?}"), "VacantEntry(0)");
+                assert_eq!(entry.try_insert("zero").unwrap_err().element(), (0, "zero"));
+            }
+            Entry::Occupied(_) => unreachable!(),
+        }
+        match map.entry(2) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert("2"), "TWO");
+                assert_eq!(entry.remove(), "2");
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(*map.entry(5).or_default().unwrap(), "");
+        assert_eq!(format!("{map:?}"), r#"{1: "ONE", 5: ""}"#);
+        assert_eq!(map.keys(), [1, 5]);
+    }
+
+    #[test]
+    fn test_retain_keeps_order_on_panic() {
+        let mut map = BoundedMap::new(8);
+        for k in 0..8 {
+            map.try_insert(k, k).unwrap();
+        }
+        map.retain(|&k, v| {
+            *v *= 10;
+            k % 3 != 0
+        });
+        assert_eq!(map.keys(), [1, 2, 4, 5, 7]);
+        assert_eq!(map.values(), [10, 20, 40, 50, 70]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            map.retain(|&k, _| if k == 5 { panic!() } else { k != 2 })
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.keys(), [1, 4, 5, 7]);
+        assert_eq!(map[&7], 70);
+    }
+
+    #[test]
+    fn test_drops_entries() {
+        let token = Rc::new(());
+        let mut map = BoundedMap::new(4);
+        for k in 0..4 {
+            map.try_insert(k, token.clone()).unwrap();
+        }
+        assert!(map.try_insert(9, token.clone()).is_err());
+        let clone = map.clone();
+        assert_eq!(Rc::strong_count(&token), 9);
+        assert!(map == clone);
+        drop(clone);
+        map.try_insert(0, token.clone()).unwrap();
+        drop(map.remove(&1));
+        map.retain(|&k, _| k != 2);
+        assert_eq!(Rc::strong_count(&token), 3);
+        drop(map);
+        assert_eq!(Rc::strong_count(&token), 1);
+    }
+}
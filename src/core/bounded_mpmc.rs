@@ -0,0 +1,346 @@
+use std::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    cache_padded::CachePadded,
+    error::{CapacityError, TryNewError},
+};
+
+/// A lock-free multi-producer multi-consumer queue with a capacity fixed at
+/// construction, using Dmitry Vyukov's bounded MPMC algorithm.
+///
+/// Every slot carries a sequence number that tells producers and consumers
+/// whose turn it is, so each push and pop is one compare-and-swap on a
+/// shared position plus uncontended accesses to its slot. The queue is used
+/// through `&self`; share it between threads with an `Arc` or scoped
+/// threads.
+///
+/// ```
+/// use std::thread;
+/// use fixed_buf::BoundedMpmc;
+///
+/// let queue = BoundedMpmc::new(64);
+/// thread::scope(|s| {
+///     for t in 0..4 {
+///         let queue = &queue;
+///         s.spawn(move || {
+///             for i in 0..16 {
+///                 queue.try_push(t * 16 + i).unwrap();
+///             }
+///         });
+///     }
+/// });
+/// let mut items: Vec<_> = std::iter::from_fn(|| queue.try_pop()).collect();
+/// items.sort();
+/// assert!(items.into_iter().eq(0..64));
+/// ```
+pub struct BoundedMpmc<T> {
+    slots: BoundedBuffer<Slot<T>>,
+    mask: usize,
+    // The positions of the next push and pop. They count up without
+    // wrapping to the capacity; `pos & mask` is the slot.
+    enqueue_pos: CachePadded<AtomicUsize>,
+    dequeue_pos: CachePadded<AtomicUsize>,
+}
+
+struct Slot<T> {
+    // Equals the position of the push that may fill this slot, or that
+    // position plus one once it is filled and ready to pop.
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// Elements move between threads but are only ever accessed by the one thread
+// that won the slot, so `T: Send` is enough for both.
+unsafe impl<T: Send> Send for BoundedMpmc<T> {}
+unsafe impl<T: Send> Sync for BoundedMpmc<T> {}
+
+impl<T> BoundedMpmc<T> {
+    /// Creates an empty queue that holds at least `cap` elements. The
+    /// capacity is rounded up to a power of two, and to at least 2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rounded capacity overflows or the allocation fails,
+    /// like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        match Self::try_new(cap) {
+            Ok(queue) => queue,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        let cap = cap
+            .max(2)
+            .checked_next_power_of_two()
+            .ok_or(TryNewError::CapacityOverflow)?;
+        let mut slots = BoundedBuffer::try_new(cap)?;
+        for i in 0..cap {
+            let pushed = slots.try_push(Slot {
+                seq: AtomicUsize::new(i),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            });
+            debug_assert!(pushed);
+        }
+        Ok(Self {
+            slots,
+            mask: cap - 1,
+            enqueue_pos: CachePadded(AtomicUsize::new(0)),
+            dequeue_pos: CachePadded(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of queued elements. Other threads may be pushing
+    /// and popping concurrently, so this is only a snapshot.
+    pub fn len(&self) -> usize {
+        // Loading the pop position first means the push position can't be
+        // behind it; pops between the loads can make it run more than a
+        // full lap ahead, hence the clamp.
+        let head = self.dequeue_pos.load(Ordering::Acquire);
+        let tail = self.enqueue_pos.load(Ordering::Acquire);
+        tail.wrapping_sub(head).min(self.capacity())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends `elem`, or gives it back if the queue is full.
+    pub fn try_push(&self, elem: T) -> Result<(), CapacityError<T>> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos) as isize;
+            if diff == 0 {
+                match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: winning the exchange gives this thread the
+                        // empty slot until `seq` is published below.
+                        unsafe { (*slot.value.get()).write(elem) };
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                // The slot still holds the element from one lap ago.
+                return Err(CapacityError::new(elem));
+            } else {
+                pos = self.enqueue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Removes and returns the oldest element, or `None` if the queue is
+    /// empty.
+    pub fn try_pop(&self) -> Option<T> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos.wrapping_add(1)) as isize;
+            if diff == 0 {
+                match self.dequeue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: winning the exchange gives this thread the
+                        // filled slot until `seq` hands it to the next lap.
+                        let elem = unsafe { (*slot.value.get()).assume_init_read() };
+                        slot.seq
+                            .store(pos.wrapping_add(self.mask + 1), Ordering::Release);
+                        return Some(elem);
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                // No push has filled this slot yet.
+                return None;
+            } else {
+                pos = self.dequeue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl<T> Drop for BoundedMpmc<T> {
+    fn drop(&mut self) {
+        if std::mem::needs_drop::<T>() {
+            while self.try_pop().is_some() {}
+        }
+    }
+}
+
+impl<T> fmt::Debug for BoundedMpmc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedMpmc")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::BoundedMpmc;
+    use crate::core::test_util::{DropCount, Drops};
+
+    #[test]
+    fn test_capacity_rounding() {
+        for (requested, cap) in [(0, 2), (1, 2), (2, 2), (3, 4), (5, 8), (64, 64)] {
+            assert_eq!(BoundedMpmc::<u8>::new(requested).capacity(), cap);
+        }
+        assert!(BoundedMpmc::<u8>::try_new(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_fifo_and_full() {
+        let queue = BoundedMpmc::new(4);
+        for lap in 0..5 {
+            for i in 0..4 {
+                queue.try_push(lap * 4 + i).unwrap();
+            }
+            assert_eq!(queue.try_push(100).unwrap_err().element(), 100);
+            assert_eq!(queue.len(), 4);
+            for i in 0..4 {
+                assert_eq!(queue.try_pop(), Some(lap * 4 + i));
+            }
+            assert_eq!(queue.try_pop(), None);
+            assert!(queue.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_drop_unconsumed() {
+        let drops = Drops::default();
+        let queue = BoundedMpmc::new(8);
+        for _ in 0..6 {
+            assert!(queue.try_push(DropCount(drops.clone())).is_ok());
+        }
+        drop(queue.try_pop());
+        drop(queue.try_pop());
+        for _ in 0..3 {
+            assert!(queue.try_push(DropCount(drops.clone())).is_ok());
+        }
+        assert_eq!(drops.get(), 2);
+        drop(queue);
+        assert_eq!(drops.get(), 9);
+    }
+
+    #[test]
+    fn test_stress_no_loss_no_duplication() {
+        let (producers, consumers) = (4, 4);
+        let per_producer = if cfg!(miri) { 50 } else { 100_000 };
+        let total = producers * per_producer;
+        let queue = BoundedMpmc::new(16);
+        let popped = AtomicUsize::new(0);
+
+        let mut seen: Vec<usize> = thread::scope(|s| {
+            for p in 0..producers {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..per_producer {
+                        let mut item = p * per_producer + i;
+                        while let Err(err) = queue.try_push(item) {
+                            item = err.element();
+                            thread::yield_now();
+                        }
+                    }
+                });
+            }
+            let workers: Vec<_> = (0..consumers)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut got = Vec::new();
+                        let mut last = vec![None; producers];
+                        while popped.load(Ordering::Relaxed) < total {
+                            match queue.try_pop() {
+                                Some(item) => {
+                                    popped.fetch_add(1, Ordering::Relaxed);
+                                    // Each consumer sees a given producer's
+                                    // items in the order they were pushed.
+                                    let p = item / per_producer;
+                                    assert!(last[p] < Some(item));
+                                    last[p] = Some(item);
+                                    got.push(item);
+                                }
+                                None => thread::yield_now(),
+                            }
+                        }
+                        got
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().unwrap())
+                .collect()
+        });
+
+        seen.sort_unstable();
+        assert_eq!(seen.len(), total);
+        assert!(seen.into_iter().eq(0..total));
+        assert_eq!(queue.try_pop(), None);
+    }
+
+    #[test]
+    fn test_stress_drop_with_items_in_flight() {
+        let per_thread = if cfg!(miri) { 20 } else { 10_000 };
+        let drops = Drops::default();
+        let queue = BoundedMpmc::new(64);
+        let pushed = AtomicUsize::new(0);
+        let popped = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| {
+                    for _ in 0..per_thread {
+                        if queue.try_push(DropCount(drops.clone())).is_ok() {
+                            pushed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+                s.spawn(|| {
+                    for _ in 0..per_thread / 2 {
+                        if queue.try_pop().is_some() {
+                            popped.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+
+        let left = pushed.load(Ordering::Relaxed) - popped.load(Ordering::Relaxed);
+        assert_eq!(queue.len(), left);
+        drop(queue);
+        // Rejected pushes, popped items and leftovers each dropped once.
+        assert_eq!(drops.get(), 3 * per_thread);
+        assert_eq!(drops.handles(), 1);
+    }
+}
@@ -0,0 +1,492 @@
+use std::{fmt, iter::FusedIterator, mem::MaybeUninit, ptr, slice};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    error::{CapacityError, TryNewError},
+};
+
+/// A heap-allocated ring buffer with a capacity fixed at construction.
+/// Elements are pushed at the back and popped from the front in O(1), and
+/// the buffer never reallocates.
+///
+/// When full, [`try_push_back`](Self::try_push_back) fails while
+/// [`push_back_overwrite`](Self::push_back_overwrite) evicts the oldest
+/// element instead. Capacities that are powers of two wrap indices with a
+/// mask rather than a comparison.
+///
+/// ```
+/// use fixed_buf::BoundedRing;
+///
+/// let mut ring = BoundedRing::new(3);
+/// for i in 1..=3 {
+///     ring.try_push_back(i).unwrap();
+/// }
+/// assert!(ring.try_push_back(4).is_err());
+/// assert_eq!(ring.push_back_overwrite(4), Some(1));
+/// assert_eq!(ring.pop_front(), Some(2));
+/// assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [3, 4]);
+/// ```
+pub struct BoundedRing<T> {
+    // Owns the allocation only; its length stays zero and the ring tracks
+    // which slots are initialized itself.
+    buf: BoundedBuffer<T>,
+    // The slot of the front element, always less than the capacity unless
+    // the capacity is zero.
+    pub(crate) head: usize,
+    pub(crate) len: usize,
+    pow2: bool,
+}
+
+impl<T> BoundedRing<T> {
+    /// Creates an empty ring that holds at most `cap` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        Self::from_buffer(BoundedBuffer::new(cap))
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        BoundedBuffer::try_new(cap).map(Self::from_buffer)
+    }
+
+    fn from_buffer(buf: BoundedBuffer<T>) -> Self {
+        Self {
+            pow2: buf.capacity().is_power_of_two(),
+            buf,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    // Maps `head + offset`, for any `offset` up to the capacity, to a slot.
+    #[inline]
+    pub(crate) fn wrap(&self, index: usize) -> usize {
+        let cap = self.capacity();
+        if self.pow2 {
+            index & (cap - 1)
+        } else if index >= cap {
+            index - cap
+        } else {
+            index
+        }
+    }
+
+    #[inline]
+    pub(crate) unsafe fn slot(&self, index: usize) -> *mut T {
+        self.buf.slot(index)
+    }
+
+    /// Appends `elem` at the back, or gives it back if the ring is full.
+    pub fn try_push_back(&mut self, elem: T) -> Result<(), CapacityError<T>> {
+        if self.is_full() {
+            return Err(CapacityError::new(elem));
+        }
+        unsafe {
+            self.slot(self.wrap(self.head + self.len)).write(elem);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends `elem` at the back, first evicting and returning the front
+    /// element if the ring is full. With a capacity of zero, `elem` itself
+    /// comes straight back.
+    pub fn push_back_overwrite(&mut self, elem: T) -> Option<T> {
+        if !self.is_full() {
+            let pushed = self.try_push_back(elem);
+            debug_assert!(pushed.is_ok());
+            return None;
+        }
+        if self.len == 0 {
+            return Some(elem);
+        }
+        unsafe {
+            let slot = self.slot(self.head);
+            let evicted = slot.read();
+            slot.write(elem);
+            self.head = self.wrap(self.head + 1);
+            Some(evicted)
+        }
+    }
+
+    /// Removes and returns the front element.
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let elem = unsafe { self.slot(self.head).read() };
+        self.head = self.wrap(self.head + 1);
+        self.len -= 1;
+        Some(elem)
+    }
+
+    /// Removes and returns the back element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(self.slot(self.wrap(self.head + self.len)).read()) }
+    }
+
+    /// Returns the element `index` places from the front.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        unsafe { Some(&*self.slot(self.wrap(self.head + index))) }
+    }
+
+    /// Returns the element `index` places from the front, mutably.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        unsafe { Some(&mut *self.slot(self.wrap(self.head + index))) }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    /// Drops every element.
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+        self.head = 0;
+    }
+
+    // The two runs of initialized slots, front first, as raw parts.
+    fn slice_ranges(&self) -> ((usize, usize), (usize, usize)) {
+        let first = usize::min(self.len, self.capacity() - self.head);
+        ((self.head, first), (0, self.len - first))
+    }
+
+    /// Returns the elements as two slices that, one after the other, hold
+    /// them front to back. The second is empty unless the elements wrap
+    /// around the end of the allocation.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let ((a, a_len), (b, b_len)) = self.slice_ranges();
+        unsafe {
+            (
+                slice::from_raw_parts(self.slot(a), a_len),
+                slice::from_raw_parts(self.slot(b), b_len),
+            )
+        }
+    }
+
+    /// Like [`as_slices`](Self::as_slices), but mutable.
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let ((a, a_len), (b, b_len)) = self.slice_ranges();
+        unsafe {
+            (
+                slice::from_raw_parts_mut(self.slot(a), a_len),
+                slice::from_raw_parts_mut(self.slot(b), b_len),
+            )
+        }
+    }
+
+    /// Moves the elements so they all sit in one slice, front to back, and
+    /// returns it. Takes O(capacity) time if they wrapped around and O(1)
+    /// otherwise.
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        if self.head + self.len > self.capacity() {
+            let cap = self.capacity();
+            unsafe {
+                let all = slice::from_raw_parts_mut(self.slot(0) as *mut MaybeUninit<T>, cap);
+                all.rotate_left(self.head);
+            }
+            self.head = 0;
+        }
+        self.as_mut_slices().0
+    }
+
+    /// Returns an iterator over the elements, front to back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (a, b) = self.as_slices();
+        Iter {
+            a: a.iter(),
+            b: b.iter(),
+        }
+    }
+}
+
+impl<T: Clone> Clone for BoundedRing<T> {
+    fn clone(&self) -> Self {
+        let mut ring = Self::new(self.capacity());
+        for elem in self {
+            let pushed = ring.try_push_back(elem.clone());
+            debug_assert!(pushed.is_ok());
+        }
+        ring
+    }
+}
+
+/// Formats the elements front to back as a list. The capacity is not shown.
+impl<T: fmt::Debug> fmt::Debug for BoundedRing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T> Drop for BoundedRing<T> {
+    fn drop(&mut self) {
+        // Drops the second run even if a destructor in the first panics.
+        struct Dropper<T>(*mut [T]);
+
+        impl<T> Drop for Dropper<T> {
+            fn drop(&mut self) {
+                unsafe { ptr::drop_in_place(self.0) }
+            }
+        }
+
+        let (front, back) = self.as_mut_slices();
+        let (front, back) = (front as *mut [T], back as *mut [T]);
+        let _back = Dropper(back);
+        unsafe { ptr::drop_in_place(front) }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BoundedRing<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of a [`BoundedRing`], front to back,
+/// created by [`BoundedRing::iter`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a, T> {
+    a: slice::Iter<'a, T>,
+    b: slice::Iter<'a, T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.a.next().or_else(|| self.b.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.a.len() + self.b.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.b.next_back().or_else(|| self.a.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::BoundedRing;
+    use crate::core::error::CapacityError;
+    use crate::core::test_util::{xorshift, DropCount, Drops};
+    use std::collections::VecDeque;
+
+    fn contents<T: Clone>(ring: &BoundedRing<T>) -> Vec<T> {
+        ring.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_ring_matches_vec_deque() {
+        let steps = if cfg!(miri) { 300 } else { 20_000 };
+        for cap in [1, 2, 3, 5, 8, 16] {
+            let mut ring = BoundedRing::new(cap);
+            let mut model = VecDeque::new();
+            let mut state = 0x9e37_79b9_7f4a_7c15 ^ cap as u64;
+            for i in 0..steps {
+                match xorshift(&mut state) % 6 {
+                    0 | 1 => {
+                        let pushed = ring.try_push_back(i);
+                        if model.len() < cap {
+                            model.push_back(i);
+                            assert!(pushed.is_ok());
+                        } else {
+                            assert_eq!(pushed.unwrap_err().element(), i);
+                        }
+                    }
+                    2 => {
+                        let evicted = if model.len() == cap {
+                            model.pop_front()
+                        } else {
+                            None
+                        };
+                        model.push_back(i);
+                        assert_eq!(ring.push_back_overwrite(i), evicted);
+                    }
+                    3 => assert_eq!(ring.pop_front(), model.pop_front()),
+                    4 => assert_eq!(ring.pop_back(), model.pop_back()),
+                    _ => {
+                        let (a, b) = ring.as_slices();
+                        let (ma, mb) = model.as_slices();
+                        assert_eq!([a, b].concat(), [ma, mb].concat());
+                        assert!(b.is_empty() || a.len() + ring.head == cap);
+                    }
+                }
+                assert_eq!(ring.len(), model.len());
+                assert_eq!(ring.is_full(), model.len() == cap);
+                assert_eq!(ring.front(), model.front());
+                assert_eq!(ring.back(), model.back());
+                assert!(ring.head < cap);
+            }
+            assert_eq!(contents(&ring), Vec::from(model));
+        }
+    }
+
+    #[test]
+    fn test_ring_iter_across_wrap() {
+        let mut ring = BoundedRing::new(5);
+        for i in 0..5 {
+            ring.try_push_back(i).unwrap();
+        }
+        ring.pop_front();
+        ring.pop_front();
+        ring.try_push_back(5).unwrap();
+        ring.try_push_back(6).unwrap();
+        assert_eq!(ring.as_slices(), (&[2, 3, 4][..], &[5, 6][..]));
+
+        let mut iter = ring.iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next_back(), Some(&6));
+        assert_eq!(iter.next_back(), Some(&5));
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        assert_eq!(
+            ring.iter().rev().copied().collect::<Vec<_>>(),
+            [6, 5, 4, 3, 2]
+        );
+        assert_eq!((&ring).into_iter().count(), 5);
+        assert_eq!(ring.get(3), Some(&5));
+        assert_eq!(ring.get(5), None);
+        *ring.get_mut(4).unwrap() = 60;
+        assert_eq!(format!("{ring:?}"), "[2, 3, 4, 5, 60]");
+        assert_eq!(contents(&ring.clone()), [2, 3, 4, 5, 60]);
+    }
+
+    #[test]
+    fn test_ring_make_contiguous() {
+        for cap in [4, 7] {
+            for shift in 0..cap {
+                let mut ring = BoundedRing::new(cap);
+                for i in 0..shift {
+                    ring.try_push_back(i).unwrap();
+                }
+                for _ in 0..shift {
+                    ring.pop_front();
+                }
+                for i in 0..cap - 1 {
+                    ring.try_push_back(i).unwrap();
+                }
+                let expected: Vec<_> = (0..cap - 1).collect();
+                assert_eq!(ring.make_contiguous(), &expected[..]);
+                assert!(ring.as_slices().1.is_empty());
+                ring.try_push_back(cap - 1).unwrap();
+                assert_eq!(contents(&ring), (0..cap).collect::<Vec<_>>());
+            }
+        }
+
+        let mut ring = BoundedRing::<u8>::new(0);
+        assert_eq!(ring.make_contiguous(), &[] as &[u8]);
+    }
+
+    #[test]
+    fn test_ring_drop_counts() {
+        let drops = Drops::default();
+        let mut ring = BoundedRing::new(3);
+        for _ in 0..3 {
+            ring.try_push_back(DropCount(drops.clone())).unwrap();
+        }
+        let rejected = ring.try_push_back(DropCount(drops.clone())).unwrap_err();
+        drop(rejected.element());
+        assert_eq!(drops.get(), 1);
+
+        // Every overwrite evicts exactly one element, and it is handed back
+        // rather than dropped in place.
+        for round in 0..10 {
+            let evicted = ring.push_back_overwrite(DropCount(drops.clone()));
+            assert_eq!(drops.get(), 1 + round);
+            drop(evicted);
+            assert_eq!(drops.get(), 2 + round);
+        }
+        assert_eq!(ring.len(), 3);
+        ring.pop_front();
+        assert_eq!(drops.get(), 12);
+
+        // Dropping a wrapped ring drops both runs.
+        ring.try_push_back(DropCount(drops.clone())).unwrap();
+        assert!(!ring.as_slices().1.is_empty());
+        drop(ring);
+        assert_eq!(drops.get(), 15);
+
+        let mut ring = BoundedRing::new(4);
+        for _ in 0..4 {
+            ring.try_push_back(DropCount(drops.clone())).unwrap();
+        }
+        ring.clear();
+        assert_eq!(drops.get(), 19);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn test_ring_zero_capacity_and_zsts() {
+        let mut ring = BoundedRing::new(0);
+        assert!(ring.is_full());
+        assert_eq!(ring.try_push_back(1), Err(CapacityError::new(1)));
+        assert_eq!(ring.push_back_overwrite(2), Some(2));
+        assert_eq!(ring.pop_front(), None);
+        assert_eq!(ring.as_slices(), (&[][..], &[][..]));
+
+        let mut ring = BoundedRing::new(3);
+        for _ in 0..3 {
+            ring.try_push_back(()).unwrap();
+        }
+        assert_eq!(ring.push_back_overwrite(()), Some(()));
+        assert_eq!(ring.iter().count(), 3);
+        assert_eq!(ring.make_contiguous().len(), 3);
+    }
+
+    #[test]
+    fn test_ring_try_new() {
+        assert!(BoundedRing::<u64>::try_new(usize::MAX).is_err());
+        assert_eq!(BoundedRing::<u64>::try_new(6).unwrap().capacity(), 6);
+    }
+}
@@ -0,0 +1,494 @@
+use std::{
+    borrow::Borrow,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+    str,
+    str::Utf8Error,
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    error::{CapacityError, TryNewError},
+};
+
+/// A UTF-8 string with a byte capacity fixed at construction, backed by a
+/// [`BoundedBuffer<u8>`].
+///
+/// Every operation keeps the contents valid UTF-8: pushes either append the
+/// whole string or character or fail without writing anything, so a code
+/// point is never split at the capacity limit.
+///
+/// ```
+/// use fixed_buf::BoundedString;
+///
+/// let mut s = BoundedString::new(6);
+/// s.try_push_str("né").unwrap();
+/// s.try_push('€').unwrap();
+/// assert_eq!(s.try_push('😀').unwrap_err().element(), '😀');
+/// assert_eq!(s, "né€");
+/// assert_eq!((s.len(), s.capacity()), (6, 6));
+/// assert_eq!(s.pop(), Some('€'));
+/// ```
+pub struct BoundedString {
+    buf: BoundedBuffer<u8>,
+}
+
+impl BoundedString {
+    /// Creates an empty string that holds at most `cap` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        Self {
+            buf: BoundedBuffer::new(cap),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        BoundedBuffer::try_new(cap).map(|buf| Self { buf })
+    }
+
+    /// Creates a string with capacity `cap` holding a copy of `s`, or fails
+    /// if `s` is longer than `cap` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn from_str_with_capacity(s: &str, cap: usize) -> Result<Self, CapacityError> {
+        if s.len() > cap {
+            return Err(CapacityError::new(()));
+        }
+        let mut string = Self::new(cap);
+        string.buf.push_str(s)?;
+        Ok(string)
+    }
+
+    /// Takes over `buf` if its bytes are valid UTF-8, or hands it back along
+    /// with the error.
+    pub fn from_utf8(buf: BoundedBuffer<u8>) -> Result<Self, (Utf8Error, BoundedBuffer<u8>)> {
+        match str::from_utf8(&buf) {
+            Ok(_) => Ok(Self { buf }),
+            Err(err) => Err((err, buf)),
+        }
+    }
+
+    /// Takes over `buf` without checking its bytes.
+    ///
+    /// # Safety
+    ///
+    /// The initialized bytes of `buf` must be valid UTF-8.
+    pub unsafe fn from_utf8_unchecked(buf: BoundedBuffer<u8>) -> Self {
+        Self { buf }
+    }
+
+    /// Returns the underlying bytes, giving up the UTF-8 guarantee.
+    pub fn into_bytes(self) -> BoundedBuffer<u8> {
+        self.buf
+    }
+
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(&self.buf) }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the length in bytes.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the capacity in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Returns how many more bytes fit.
+    pub fn remaining_capacity(&self) -> usize {
+        self.buf.capacity() - self.buf.len()
+    }
+
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { str::from_utf8_unchecked_mut(&mut self.buf) }
+    }
+
+    /// Appends `s`, or fails without writing anything if it does not fit.
+    pub fn try_push_str(&mut self, s: &str) -> Result<(), CapacityError> {
+        self.buf.push_str(s)
+    }
+
+    /// Appends `c`, or gives it back without writing anything if its
+    /// encoding does not fit.
+    pub fn try_push(&mut self, c: char) -> Result<(), CapacityError<char>> {
+        self.buf.push_char(c)
+    }
+
+    /// Removes and returns the last character.
+    pub fn pop(&mut self) -> Option<char> {
+        let c = self.chars().next_back()?;
+        self.buf.truncate(self.len() - c.len_utf8());
+        Some(c)
+    }
+
+    /// Shortens the string to `new_len` bytes. Does nothing if `new_len` is
+    /// not less than the length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_len` does not lie on a character boundary, like
+    /// `String::truncate`.
+    #[track_caller]
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len >= self.len() {
+            return;
+        }
+        assert!(
+            self.is_char_boundary(new_len),
+            "new_len does not lie on a char boundary"
+        );
+        self.buf.truncate(new_len);
+    }
+
+    /// Shortens the string to at most `max_len` bytes, cutting at the last
+    /// character boundary at or below it. Returns the new length.
+    pub fn truncate_floor(&mut self, max_len: usize) -> usize {
+        if max_len < self.len() {
+            let new_len = (0..=max_len)
+                .rev()
+                .find(|&i| self.is_char_boundary(i))
+                .unwrap_or(0);
+            self.buf.truncate(new_len);
+        }
+        self.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+impl Clone for BoundedString {
+    fn clone(&self) -> Self {
+        Self {
+            buf: self.buf.clone(),
+        }
+    }
+}
+
+impl Deref for BoundedString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl DerefMut for BoundedString {
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl AsRef<str> for BoundedString {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl AsRef<[u8]> for BoundedString {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<str> for BoundedString {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+/// Appends formatted text. Each piece the formatter hands over is pushed
+/// whole or not at all, so on overflow the string keeps the pieces that fit
+/// before the failing one and is still valid UTF-8.
+impl fmt::Write for BoundedString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.try_push_str(s).map_err(|_| fmt::Error)
+    }
+
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.try_push(c).map_err(|_| fmt::Error)
+    }
+}
+
+impl fmt::Debug for BoundedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for BoundedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq for BoundedString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for BoundedString {}
+
+impl PartialOrd for BoundedString {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BoundedString {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+/// Hashes like the `str` it holds, so `Borrow<str>` lookups work.
+impl Hash for BoundedString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl PartialEq<str> for BoundedString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for BoundedString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<BoundedString> for str {
+    fn eq(&self, other: &BoundedString) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<BoundedString> for &str {
+    fn eq(&self, other: &BoundedString) -> bool {
+        *self == other.as_str()
+    }
+}
+
+/// Copies the string into a new `BoundedString` with exactly enough
+/// capacity to hold it.
+impl From<&str> for BoundedString {
+    #[track_caller]
+    fn from(s: &str) -> Self {
+        let mut string = Self::new(s.len());
+        let pushed = string.try_push_str(s);
+        debug_assert!(pushed.is_ok());
+        string
+    }
+}
+
+/// Checks that the bytes are valid UTF-8, like
+/// [`from_utf8`](BoundedString::from_utf8).
+impl TryFrom<BoundedBuffer<u8>> for BoundedString {
+    type Error = (Utf8Error, BoundedBuffer<u8>);
+
+    fn try_from(buf: BoundedBuffer<u8>) -> Result<Self, Self::Error> {
+        Self::from_utf8(buf)
+    }
+}
+
+impl From<BoundedString> for BoundedBuffer<u8> {
+    fn from(s: BoundedString) -> Self {
+        s.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, fmt::Write};
+
+    use super::BoundedString;
+    use crate::core::bounded_buf::BoundedBuffer;
+
+    #[test]
+    fn test_push_multibyte_at_capacity() {
+        // 'é' is 2 bytes, '€' 3 and '😀' 4.
+        for (c, width) in [('a', 1), ('é', 2), ('€', 3), ('😀', 4)] {
+            for cap in 0..8 {
+                let mut s = BoundedString::new(cap);
+                let mut pushed = 0;
+                while s.try_push(c).is_ok() {
+                    pushed += 1;
+                }
+                assert_eq!(pushed, cap / width);
+                assert_eq!(s.len(), pushed * width);
+                assert_eq!(s.remaining_capacity(), cap % width);
+                assert_eq!(s.try_push(c).unwrap_err().element(), c);
+                assert!(s.chars().all(|x| x == c));
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_str_all_or_nothing() {
+        let mut s = BoundedString::new(7);
+        s.try_push_str("ab").unwrap();
+        // Five bytes remain; "c😀" needs exactly five and "😀😀" would need eight.
+        assert!(s.try_push_str("😀😀").is_err());
+        assert_eq!(s, "ab");
+        s.try_push_str("c😀").unwrap();
+        assert_eq!(s, "abc😀");
+        assert_eq!(s.remaining_capacity(), 0);
+        assert!(s.try_push_str("").is_ok());
+        assert!(s.try_push_str("d").is_err());
+    }
+
+    #[test]
+    fn test_combining_characters() {
+        // "e" followed by U+0301 COMBINING ACUTE ACCENT: two chars, three bytes.
+        let decomposed = "e\u{301}";
+        let mut s = BoundedString::new(4);
+        s.try_push_str(decomposed).unwrap();
+        // The accent alone is a 2-byte char and one byte is left.
+        assert!(s.try_push('\u{301}').is_err());
+        s.try_push('x').unwrap();
+        assert_eq!(s, "e\u{301}x");
+
+        assert_eq!(s.pop(), Some('x'));
+        // Popping removes the combining mark on its own, leaving the base.
+        assert_eq!(s.pop(), Some('\u{301}'));
+        assert_eq!(s, "e");
+        assert_eq!(s.pop(), Some('e'));
+        assert_eq!(s.pop(), None);
+    }
+
+    #[test]
+    fn test_pop_returns_chars_in_reverse() {
+        let text = "aé€😀e\u{301}";
+        let mut s = BoundedString::from(text);
+        let mut popped = Vec::new();
+        while let Some(c) = s.pop() {
+            popped.push(c);
+        }
+        popped.reverse();
+        assert_eq!(popped, text.chars().collect::<Vec<_>>());
+        assert!(s.is_empty());
+        assert_eq!(s.capacity(), text.len());
+    }
+
+    #[test]
+    fn test_truncate_on_boundaries() {
+        let text = "a€😀b";
+        for (i, _) in text.char_indices() {
+            let mut s = BoundedString::from(text);
+            s.truncate(i);
+            assert_eq!(s, &text[..i]);
+        }
+        let mut s = BoundedString::from(text);
+        s.truncate(100);
+        assert_eq!(s, text);
+    }
+
+    #[test]
+    #[should_panic(expected = "new_len does not lie on a char boundary")]
+    fn test_truncate_inside_char() {
+        let mut s = BoundedString::from("a€");
+        s.truncate(2);
+    }
+
+    #[test]
+    fn test_truncate_floor() {
+        let text = "a€😀b";
+        let expected = [0, 1, 1, 1, 4, 4, 4, 4, 8, 9];
+        for (max_len, &want) in expected.iter().enumerate() {
+            let mut s = BoundedString::from(text);
+            assert_eq!(s.truncate_floor(max_len), want);
+            assert_eq!(s, &text[..want]);
+        }
+    }
+
+    #[test]
+    fn test_fmt_write() {
+        let mut s = BoundedString::new(8);
+        let euro = '€';
+        write!(s, "{}-{euro}", 12).unwrap();
+        assert_eq!(s, "12-€");
+        // Pieces are pushed whole: "34" fits, the 4-byte emoji does not.
+        let emoji = '😀';
+        assert!(write!(s, "{}{emoji}", 34).is_err());
+        assert_eq!(s, "12-€34");
+        assert!(std::str::from_utf8(s.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_conversions_round_trip() {
+        let text = "grüße, 世界 👋";
+        let s = BoundedString::from(text);
+        assert_eq!(s.capacity(), text.len());
+        assert_eq!(s.as_str(), text);
+
+        let buf: BoundedBuffer<u8> = s.into();
+        assert_eq!(&buf[..], text.as_bytes());
+        let s = BoundedString::try_from(buf).unwrap();
+        assert_eq!(s, text);
+        assert_eq!(text, s);
+
+        let mut buf = BoundedBuffer::new(4);
+        buf.try_extend_from_slice(&[b'a', 0xE2, 0x82]).unwrap();
+        let (err, buf) = BoundedString::try_from(buf).unwrap_err();
+        assert_eq!(err.valid_up_to(), 1);
+        assert_eq!(buf.len(), 3);
+
+        let s = BoundedString::from_str_with_capacity("héllo", 16).unwrap();
+        assert_eq!((s.len(), s.capacity()), (6, 16));
+        assert!(BoundedString::from_str_with_capacity("héllo", 5).is_err());
+    }
+
+    #[test]
+    fn test_traits() {
+        let a = BoundedString::from("€uro");
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        assert_eq!(format!("{a}"), "€uro");
+        assert_eq!(format!("{a:?}"), "\"€uro\"");
+        assert_eq!(format!("{a:>6}"), "  €uro");
+
+        b.make_ascii_uppercase();
+        assert_eq!(b, "€URO");
+        assert!(b < a);
+
+        let set: HashSet<BoundedString> = [a, b].into_iter().collect();
+        assert!(set.contains("€uro"));
+        assert!(set.contains("€URO"));
+        assert!(!set.contains("euro"));
+    }
+
+    #[test]
+    fn test_clear_keeps_capacity() {
+        let mut s = BoundedString::new(5);
+        s.try_push_str("日").unwrap();
+        s.clear();
+        assert!(s.is_empty());
+        s.try_push_str("éé").unwrap();
+        assert_eq!(s.remaining_capacity(), 1);
+        assert!(BoundedString::try_new(usize::MAX).is_err());
+    }
+}
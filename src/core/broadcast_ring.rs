@@ -0,0 +1,510 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError, RwLock,
+    },
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    cache_padded::CachePadded,
+    error::{CapacityError, TryNewError, TryNextError},
+};
+
+/// What [`BroadcastRing::publish`] does when the slowest reader is a whole
+/// ring behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Fail the publish and hand the event back, so no reader ever misses
+    /// one.
+    Reject,
+    /// Overwrite the oldest event. Readers that had not read it yet report
+    /// [`TryNextError::Lagged`].
+    Overwrite,
+}
+
+/// A fixed ring of events that every [`Reader`] sees in full, each at its
+/// own pace.
+///
+/// Events are numbered from zero as they are published and each reader keeps
+/// the number of the next one it wants. Readers clone events out of the ring
+/// rather than removing them, so an event stays until the writer comes back
+/// around to its slot. Each slot has its own lock, so readers only contend
+/// with the writer, and only on the slot it is writing.
+///
+/// Publishes are serialized internally; the ring is meant for one writer,
+/// and several will take turns.
+///
+/// ```
+/// use fixed_buf::{BroadcastRing, OverflowPolicy, TryNextError};
+///
+/// let ring = BroadcastRing::new(2, OverflowPolicy::Overwrite);
+/// let mut fast = ring.subscribe();
+/// let mut slow = ring.subscribe();
+/// for i in 0..3 {
+///     ring.publish(i).unwrap();
+///     assert_eq!(fast.try_next(), Ok(i));
+/// }
+/// assert_eq!(slow.try_next(), Err(TryNextError::Lagged { skipped: 1 }));
+/// assert_eq!(slow.try_next(), Ok(1));
+/// assert_eq!(slow.try_next(), Ok(2));
+/// assert_eq!(slow.try_next(), Err(TryNextError::Empty));
+/// ```
+pub struct BroadcastRing<T> {
+    slots: BoundedBuffer<RwLock<Slot<T>>>,
+    // The number of events published so far.
+    tail: CachePadded<AtomicU64>,
+    policy: OverflowPolicy,
+    // The lowest cursor among the readers as last computed, so a rejecting
+    // writer only walks `cursors` when the ring looks full. Also serializes
+    // publishes.
+    writer: Mutex<u64>,
+    // The cursors of live readers, consulted by the rejecting policy.
+    cursors: Mutex<Vec<Arc<AtomicU64>>>,
+}
+
+struct Slot<T> {
+    // The number of the event in `value`.
+    seq: u64,
+    value: Option<T>,
+}
+
+impl<T> BroadcastRing<T> {
+    /// Creates an empty ring that holds the last `cap` events.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero or the allocation fails, like
+    /// [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize, policy: OverflowPolicy) -> Self {
+        match Self::try_new(cap, policy) {
+            Ok(ring) => ring,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking
+    /// if the allocation fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[track_caller]
+    pub fn try_new(cap: usize, policy: OverflowPolicy) -> Result<Self, TryNewError> {
+        assert!(cap > 0, "broadcast ring capacity must be positive");
+        let mut slots = BoundedBuffer::try_new(cap)?;
+        slots.fill_with_to_capacity(|| {
+            RwLock::new(Slot {
+                seq: 0,
+                value: None,
+            })
+        });
+        Ok(Self {
+            slots,
+            tail: CachePadded(AtomicU64::new(0)),
+            policy,
+            writer: Mutex::new(0),
+            cursors: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Returns the number of events published so far, which is also the
+    /// number the next one will get.
+    pub fn published(&self) -> u64 {
+        self.tail.load(Ordering::Acquire)
+    }
+
+    /// Returns a reader that starts at the next event to be published.
+    pub fn subscribe(&self) -> Reader<'_, T> {
+        let mut cursors = self.cursors.lock().unwrap_or_else(PoisonError::into_inner);
+        // Read the tail under the lock so a rejecting writer either sees
+        // this cursor or published before it was taken.
+        let cursor = self.published();
+        let shared = Arc::new(AtomicU64::new(cursor));
+        cursors.push(shared.clone());
+        Reader {
+            ring: self,
+            cursor,
+            shared,
+        }
+    }
+
+    /// Returns the number of live readers.
+    pub fn reader_count(&self) -> usize {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Appends `event` for every reader. Under the
+    /// [`Reject`](OverflowPolicy::Reject) policy, fails and gives the event
+    /// back if the slowest reader has not yet read the event it would
+    /// overwrite.
+    pub fn publish(&self, event: T) -> Result<(), CapacityError<T>> {
+        let mut min_cursor = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let seq = self.tail.load(Ordering::Relaxed);
+        let cap = self.capacity() as u64;
+        if self.policy == OverflowPolicy::Reject && seq - *min_cursor >= cap {
+            *min_cursor = self.min_cursor(seq);
+            if seq - *min_cursor >= cap {
+                return Err(CapacityError::new(event));
+            }
+        }
+        let slot = &self.slots[(seq % cap) as usize];
+        let old = {
+            let mut slot = slot.write().unwrap_or_else(PoisonError::into_inner);
+            slot.seq = seq;
+            slot.value.replace(event)
+        };
+        self.tail.store(seq + 1, Ordering::Release);
+        drop(min_cursor);
+        // The overwritten event is dropped outside every lock.
+        drop(old);
+        Ok(())
+    }
+
+    fn min_cursor(&self, tail: u64) -> u64 {
+        let cursors = self.cursors.lock().unwrap_or_else(PoisonError::into_inner);
+        cursors
+            .iter()
+            .map(|c| c.load(Ordering::Acquire))
+            .min()
+            .unwrap_or(tail)
+    }
+}
+
+impl<T> fmt::Debug for BroadcastRing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BroadcastRing")
+            .field("published", &self.published())
+            .field("capacity", &self.capacity())
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A cursor into a [`BroadcastRing`], created by
+/// [`BroadcastRing::subscribe`].
+pub struct Reader<'a, T> {
+    ring: &'a BroadcastRing<T>,
+    // The number of the next event to read.
+    cursor: u64,
+    // `cursor` as seen by a rejecting writer.
+    shared: Arc<AtomicU64>,
+}
+
+impl<T: Clone> Reader<'_, T> {
+    /// Returns a clone of the next event.
+    ///
+    /// If the writer has overwritten events this reader had not read yet,
+    /// reports how many with [`TryNextError::Lagged`] and moves on to the
+    /// oldest event still in the ring, which the next call returns.
+    pub fn try_next(&mut self) -> Result<T, TryNextError> {
+        let cap = self.ring.capacity() as u64;
+        let slot = &self.ring.slots[(self.cursor % cap) as usize];
+        let slot = slot.read().unwrap_or_else(PoisonError::into_inner);
+        match &slot.value {
+            Some(value) if slot.seq == self.cursor => {
+                let value = value.clone();
+                drop(slot);
+                self.advance(self.cursor + 1);
+                Ok(value)
+            }
+            Some(_) if slot.seq > self.cursor => {
+                let seq = slot.seq;
+                drop(slot);
+                // The slot was rewritten, so events before `tail - cap` are
+                // gone. The writer bumps the tail only after filling the
+                // slot, so count the event seen here too. Anything
+                // overwritten after the tail is loaded is reported by a
+                // later call.
+                let tail = self.ring.published().max(seq + 1);
+                let oldest = tail - cap;
+                let skipped = oldest - self.cursor;
+                self.advance(oldest);
+                Err(TryNextError::Lagged { skipped })
+            }
+            // Still holds the event from a lap ago, or nothing yet.
+            _ => Err(TryNextError::Empty),
+        }
+    }
+}
+
+impl<T> Reader<'_, T> {
+    /// Returns the number of the next event this reader will read.
+    pub fn position(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Returns how many published events this reader has not read,
+    /// including any that have already been overwritten.
+    pub fn pending(&self) -> u64 {
+        self.ring.published() - self.cursor
+    }
+
+    fn advance(&mut self, cursor: u64) {
+        self.cursor = cursor;
+        self.shared.store(cursor, Ordering::Release);
+    }
+}
+
+impl<T> Clone for Reader<'_, T> {
+    /// Returns a new reader at the same position.
+    fn clone(&self) -> Self {
+        let shared = Arc::new(AtomicU64::new(self.cursor));
+        self.ring
+            .cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(shared.clone());
+        Self {
+            ring: self.ring,
+            cursor: self.cursor,
+            shared,
+        }
+    }
+}
+
+impl<T> Drop for Reader<'_, T> {
+    fn drop(&mut self) {
+        let mut cursors = self
+            .ring
+            .cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(i) = cursors.iter().position(|c| Arc::ptr_eq(c, &self.shared)) {
+            cursors.swap_remove(i);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Reader<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reader")
+            .field("position", &self.cursor)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use super::{BroadcastRing, OverflowPolicy};
+    use crate::core::error::TryNextError;
+    use crate::core::test_util::xorshift;
+
+    #[test]
+    fn test_every_reader_sees_every_event() {
+        let ring = BroadcastRing::new(4, OverflowPolicy::Reject);
+        let mut a = ring.subscribe();
+        ring.publish("x".to_owned()).unwrap();
+        let mut b = ring.subscribe();
+        ring.publish("y".to_owned()).unwrap();
+
+        assert_eq!(a.try_next().as_deref(), Ok("x"));
+        assert_eq!(a.try_next().as_deref(), Ok("y"));
+        assert_eq!(a.try_next(), Err(TryNextError::Empty));
+        // A late subscriber only sees what comes after it.
+        assert_eq!(b.try_next().as_deref(), Ok("y"));
+        assert_eq!(b.try_next(), Err(TryNextError::Empty));
+        assert_eq!((a.position(), b.pending()), (2, 0));
+        assert_eq!(ring.published(), 2);
+    }
+
+    #[test]
+    fn test_reject_waits_for_slowest_reader() {
+        let ring = BroadcastRing::new(3, OverflowPolicy::Reject);
+        // Nothing to protect without readers.
+        for i in 0..10 {
+            ring.publish(i).unwrap();
+        }
+        let mut fast = ring.subscribe();
+        let mut slow = ring.subscribe();
+        for i in 10..13 {
+            ring.publish(i).unwrap();
+        }
+        assert_eq!(ring.publish(13).unwrap_err().element(), 13);
+        for i in 10..13 {
+            assert_eq!(fast.try_next(), Ok(i));
+        }
+        // The fast reader alone doesn't free any room.
+        assert!(ring.publish(13).is_err());
+        assert_eq!(slow.try_next(), Ok(10));
+        ring.publish(13).unwrap();
+        assert!(ring.publish(14).is_err());
+        // Dropping the slow reader stops it holding the writer back.
+        drop(slow);
+        assert_eq!(ring.reader_count(), 1);
+        ring.publish(14).unwrap();
+        assert_eq!(fast.try_next(), Ok(13));
+        assert_eq!(fast.try_next(), Ok(14));
+    }
+
+    #[test]
+    fn test_overwrite_lag_accounting() {
+        let ring = BroadcastRing::new(4, OverflowPolicy::Overwrite);
+        let mut reader = ring.subscribe();
+        let mut clone = reader.clone();
+        for i in 0..11 {
+            ring.publish(i).unwrap();
+        }
+        assert_eq!(reader.pending(), 11);
+        assert_eq!(reader.try_next(), Err(TryNextError::Lagged { skipped: 7 }));
+        for i in 7..11 {
+            assert_eq!(reader.try_next(), Ok(i));
+        }
+        assert_eq!(reader.try_next(), Err(TryNextError::Empty));
+
+        // Other readers keep their own cursors.
+        assert_eq!(clone.try_next(), Err(TryNextError::Lagged { skipped: 7 }));
+        for i in 7..11 {
+            assert_eq!(clone.try_next(), Ok(i));
+        }
+        // Falling behind by exactly the capacity loses nothing.
+        for i in 11..15 {
+            ring.publish(i).unwrap();
+        }
+        for i in 11..15 {
+            assert_eq!(clone.try_next(), Ok(i));
+        }
+        // One more than that loses the oldest.
+        for i in 15..20 {
+            ring.publish(i).unwrap();
+        }
+        assert_eq!(clone.try_next(), Err(TryNextError::Lagged { skipped: 1 }));
+        assert_eq!(clone.try_next(), Ok(16));
+    }
+
+    #[test]
+    fn test_overwritten_events_dropped_once() {
+        let event = Arc::new(());
+        let ring = BroadcastRing::new(2, OverflowPolicy::Overwrite);
+        let mut reader = ring.subscribe();
+        for _ in 0..5 {
+            ring.publish(event.clone()).unwrap();
+        }
+        assert_eq!(Arc::strong_count(&event), 3);
+        let read = reader.try_next();
+        assert!(read.is_err());
+        let read = reader.try_next().unwrap();
+        assert_eq!(Arc::strong_count(&event), 4);
+        drop(read);
+        drop(reader);
+        drop(ring);
+        assert_eq!(Arc::strong_count(&event), 1);
+    }
+
+    #[test]
+    fn test_slow_readers_lag_exactly() {
+        let events = if cfg!(miri) { 200 } else { 200_000 };
+        let ring = BroadcastRing::new(16, OverflowPolicy::Overwrite);
+        let done = AtomicBool::new(false);
+        thread::scope(|s| {
+            let readers: Vec<_> = (0..3u64)
+                .map(|r| {
+                    let mut reader = ring.subscribe();
+                    let (ring, done) = (&ring, &done);
+                    s.spawn(move || {
+                        let mut rng = 0x9E37_79B9_7F4A_7C15 ^ (r + 1);
+                        let (mut expected, mut received, mut skipped) = (0u64, 0u64, 0u64);
+                        loop {
+                            match reader.try_next() {
+                                Ok(event) => {
+                                    assert_eq!(event, expected);
+                                    expected += 1;
+                                    received += 1;
+                                }
+                                Err(TryNextError::Lagged { skipped: n }) => {
+                                    assert!(n > 0);
+                                    expected += n;
+                                    skipped += n;
+                                }
+                                Err(TryNextError::Empty) => {
+                                    if done.load(Ordering::Acquire)
+                                        && reader.position() == ring.published()
+                                    {
+                                        break;
+                                    }
+                                }
+                            }
+                            // Readers dawdle at different rates.
+                            if xorshift(&mut rng).is_multiple_of(r * 4 + 1) {
+                                thread::yield_now();
+                            }
+                        }
+                        (received, skipped)
+                    })
+                })
+                .collect();
+            for i in 0..events {
+                ring.publish(i).unwrap();
+            }
+            done.store(true, Ordering::Release);
+            for reader in readers {
+                let (received, skipped) = reader.join().unwrap();
+                assert_eq!(received + skipped, events);
+            }
+        });
+    }
+
+    #[test]
+    fn test_reject_never_loses_events_across_threads() {
+        let events = if cfg!(miri) { 100 } else { 50_000 };
+        let ring = BroadcastRing::new(8, OverflowPolicy::Reject);
+        thread::scope(|s| {
+            let readers: Vec<_> = (0..3)
+                .map(|_| {
+                    let mut reader = ring.subscribe();
+                    s.spawn(move || {
+                        for expected in 0..events {
+                            loop {
+                                match reader.try_next() {
+                                    Ok(event) => {
+                                        assert_eq!(event, expected);
+                                        break;
+                                    }
+                                    Err(TryNextError::Empty) => thread::yield_now(),
+                                    Err(err) => panic!("{err}"),
+                                }
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for i in 0..events {
+                let mut event = i;
+                while let Err(err) = ring.publish(event) {
+                    event = err.element();
+                    thread::yield_now();
+                }
+            }
+            for reader in readers {
+                reader.join().unwrap();
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "broadcast ring capacity must be positive")]
+    fn test_zero_capacity() {
+        BroadcastRing::<u8>::new(0, OverflowPolicy::Overwrite);
+    }
+}
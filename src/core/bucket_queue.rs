@@ -0,0 +1,402 @@
+use std::fmt;
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    bounded_deque::BoundedDeque,
+    error::{CapacityError, TryNewError},
+};
+
+const BITS: usize = u64::BITS as usize;
+
+/// A priority queue for small integer priorities, `0..LEVELS`, that pops the
+/// highest priority first and keeps items of equal priority in FIFO order.
+///
+/// Each level is its own [`BoundedDeque`] with a capacity fixed at
+/// construction, and a 256-bit mask records which levels are non-empty. So
+/// `try_push`, `pop` and `pop_at` are all O(1), and neither ever compares
+/// items: finding the highest non-empty level looks at no more than four
+/// words of the mask. `LEVELS` can be at most 256.
+///
+/// Giving every level its own space means a burst at one priority can never
+/// take space from another, so low-priority work cannot starve
+/// high-priority pushes. The cost is that a level can be full while others
+/// still have room. A shared arena would avoid that, but there one noisy
+/// level could fill the whole queue. [`with_capacities`](Self::with_capacities)
+/// sizes each level separately.
+///
+/// ```
+/// use fixed_buf::BucketQueue;
+///
+/// let mut queue: BucketQueue<&str, 256> = BucketQueue::new(4);
+/// queue.try_push(1, "log").unwrap();
+/// queue.try_push(200, "irq").unwrap();
+/// queue.try_push(1, "flush").unwrap();
+/// assert_eq!(queue.pop(), Some((200, "irq")));
+/// assert_eq!(queue.pop(), Some((1, "log")));
+/// assert_eq!(queue.pop(), Some((1, "flush")));
+/// assert_eq!(queue.pop(), None);
+/// ```
+pub struct BucketQueue<T, const LEVELS: usize> {
+    levels: BoundedBuffer<BoundedDeque<T>>,
+    // Bit `p % 64` of word `p / 64` is set while level `p` is non-empty.
+    mask: [u64; 4],
+    len: usize,
+}
+
+impl<T, const LEVELS: usize> BucketQueue<T, LEVELS> {
+    /// Creates an empty queue where every level holds at most `cap` items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        match Self::try_new(cap) {
+            Ok(queue) => queue,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        Self::try_with_capacities([cap; LEVELS])
+    }
+
+    /// Creates an empty queue where level `p` holds at most `caps[p]` items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn with_capacities(caps: [usize; LEVELS]) -> Self {
+        match Self::try_with_capacities(caps) {
+            Ok(queue) => queue,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`with_capacities`](Self::with_capacities), but returns an error
+    /// instead of panicking.
+    pub fn try_with_capacities(caps: [usize; LEVELS]) -> Result<Self, TryNewError> {
+        const { assert!(LEVELS <= 256, "BucketQueue supports at most 256 levels") };
+        let mut levels = BoundedBuffer::try_new(LEVELS)?;
+        for cap in caps {
+            let pushed = levels.try_push(BoundedDeque::try_new(cap)?);
+            debug_assert!(pushed);
+        }
+        Ok(Self {
+            levels,
+            mask: [0; 4],
+            len: 0,
+        })
+    }
+
+    /// Returns the total number of items across all levels.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of items at `level`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not below `LEVELS`.
+    #[track_caller]
+    pub fn len_at(&self, level: u8) -> usize {
+        self.level(level).len()
+    }
+
+    /// Returns how many items `level` can hold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not below `LEVELS`.
+    #[track_caller]
+    pub fn capacity_at(&self, level: u8) -> usize {
+        self.level(level).capacity()
+    }
+
+    /// Appends `item` behind any others at `priority`, or gives it back if
+    /// that level is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `priority` is not below `LEVELS`.
+    #[track_caller]
+    pub fn try_push(&mut self, priority: u8, item: T) -> Result<(), CapacityError<T>> {
+        self.level_mut(priority).try_push_back(item)?;
+        self.mask[priority as usize / BITS] |= 1 << (priority as usize % BITS);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the highest priority that has items, if any.
+    pub fn highest_priority(&self) -> Option<u8> {
+        self.mask.iter().enumerate().rev().find_map(|(w, &word)| {
+            (word != 0).then(|| (w * BITS + (BITS - 1 - word.leading_zeros() as usize)) as u8)
+        })
+    }
+
+    /// Returns the item [`pop`](Self::pop) would remove next, with its
+    /// priority.
+    pub fn peek(&self) -> Option<(u8, &T)> {
+        let priority = self.highest_priority()?;
+        self.levels[priority as usize]
+            .front()
+            .map(|item| (priority, item))
+    }
+
+    /// Removes the oldest item at the highest non-empty priority, and returns
+    /// it with its priority.
+    pub fn pop(&mut self) -> Option<(u8, T)> {
+        let priority = self.highest_priority()?;
+        self.pop_at(priority).map(|item| (priority, item))
+    }
+
+    /// Removes the oldest item at `level`, ignoring every other level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not below `LEVELS`.
+    #[track_caller]
+    pub fn pop_at(&mut self, level: u8) -> Option<T> {
+        let deque = self.level_mut(level);
+        let item = deque.pop_front()?;
+        if deque.is_empty() {
+            self.mask[level as usize / BITS] &= !(1 << (level as usize % BITS));
+        }
+        self.len -= 1;
+        Some(item)
+    }
+
+    /// Removes every item, keeping each level's capacity.
+    pub fn clear(&mut self) {
+        for deque in self.levels.iter_mut() {
+            deque.clear();
+        }
+        self.mask = [0; 4];
+        self.len = 0;
+    }
+
+    /// Returns an iterator over the items with their priorities, in the order
+    /// [`pop`](Self::pop) would remove them.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &T)> {
+        self.levels
+            .iter()
+            .enumerate()
+            .rev()
+            .flat_map(|(p, deque)| deque.iter().map(move |item| (p as u8, item)))
+    }
+
+    #[track_caller]
+    fn level(&self, level: u8) -> &BoundedDeque<T> {
+        match self.levels.get(level as usize) {
+            Some(deque) => deque,
+            None => panic!("priority {level} out of range for {LEVELS} levels"),
+        }
+    }
+
+    #[track_caller]
+    fn level_mut(&mut self, level: u8) -> &mut BoundedDeque<T> {
+        match self.levels.get_mut(level as usize) {
+            Some(deque) => deque,
+            None => panic!("priority {level} out of range for {LEVELS} levels"),
+        }
+    }
+}
+
+impl<T: Clone, const LEVELS: usize> Clone for BucketQueue<T, LEVELS> {
+    fn clone(&self) -> Self {
+        Self {
+            levels: self.levels.clone(),
+            mask: self.mask,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: fmt::Debug, const LEVELS: usize> fmt::Debug for BucketQueue<T, LEVELS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use super::BucketQueue;
+    use crate::core::test_util::xorshift;
+
+    // A reference model: every queued item in push order. Popping takes the
+    // first item of the highest priority present.
+    #[derive(Default)]
+    struct Model {
+        items: Vec<(u8, usize)>,
+    }
+
+    impl Model {
+        fn pop_at(&mut self, level: u8) -> Option<usize> {
+            let pos = self.items.iter().position(|&(p, _)| p == level)?;
+            Some(self.items.remove(pos).1)
+        }
+
+        fn pop(&mut self) -> Option<(u8, usize)> {
+            let top = self.items.iter().map(|&(p, _)| p).max()?;
+            self.pop_at(top).map(|item| (top, item))
+        }
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    enum Op {
+        Push(u8),
+        Pop,
+        PopAt(u8),
+    }
+
+    const CAP: usize = 2;
+    const OPS: [Op; 7] = [
+        Op::Push(0),
+        Op::Push(1),
+        Op::Push(2),
+        Op::Pop,
+        Op::PopAt(0),
+        Op::PopAt(1),
+        Op::PopAt(2),
+    ];
+
+    fn run(ops: &[Op]) {
+        let mut queue = BucketQueue::<usize, 3>::new(CAP);
+        let mut model = Model::default();
+        for (seq, &op) in ops.iter().enumerate() {
+            match op {
+                Op::Push(p) => {
+                    let full = model.items.iter().filter(|&&(q, _)| q == p).count() == CAP;
+                    match queue.try_push(p, seq) {
+                        Ok(()) => model.items.push((p, seq)),
+                        Err(err) => {
+                            assert!(full, "{ops:?}");
+                            assert_eq!(err.element(), seq);
+                        }
+                    }
+                }
+                Op::Pop => assert_eq!(queue.pop(), model.pop(), "{ops:?}"),
+                Op::PopAt(p) => assert_eq!(queue.pop_at(p), model.pop_at(p), "{ops:?}"),
+            }
+            assert_eq!(queue.len(), model.items.len());
+            let mut expected = model.items.clone();
+            expected.sort_by_key(|&(p, seq)| (std::cmp::Reverse(p), seq));
+            assert!(queue
+                .iter()
+                .map(|(p, &seq)| (p, seq))
+                .eq(expected.iter().copied()));
+            assert_eq!(
+                queue.peek().map(|(p, &seq)| (p, seq)),
+                expected.first().copied()
+            );
+        }
+    }
+
+    // Every sequence of pushes and pops up to `depth` long, over three levels
+    // small enough to fill, checked step by step against the model.
+    #[test]
+    fn test_fifo_within_priority_exhaustive() {
+        let depth = if cfg!(miri) { 3 } else { 6 };
+        let mut ops = Vec::new();
+        fn walk(ops: &mut Vec<Op>, depth: usize) {
+            run(ops);
+            if ops.len() == depth {
+                return;
+            }
+            for op in OPS {
+                ops.push(op);
+                walk(ops, depth);
+                ops.pop();
+            }
+        }
+        walk(&mut ops, depth);
+    }
+
+    #[test]
+    fn test_full_range_matches_model() {
+        let rounds = if cfg!(miri) { 200 } else { 50_000 };
+        let mut queue = BucketQueue::<usize, 256>::new(8);
+        let mut model = Model::default();
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        for seq in 0..rounds {
+            let r = xorshift(&mut state);
+            // Cluster the priorities so levels fill up and share words of the
+            // mask.
+            let p = [0, 1, 63, 64, 127, 128, 254, 255][(r >> 8) as usize % 8];
+            if !r.is_multiple_of(3) {
+                let full = model.items.iter().filter(|&&(q, _)| q == p).count() == 8;
+                assert_eq!(queue.try_push(p, seq).is_err(), full);
+                if !full {
+                    model.items.push((p, seq));
+                }
+            } else if r.is_multiple_of(2) {
+                assert_eq!(queue.pop(), model.pop());
+            } else {
+                assert_eq!(queue.pop_at(p), model.pop_at(p));
+            }
+            assert_eq!(
+                queue.highest_priority(),
+                model.items.iter().map(|&(p, _)| p).max()
+            );
+        }
+        while let Some(popped) = model.pop() {
+            assert_eq!(queue.pop(), Some(popped));
+        }
+        assert!(queue.is_empty());
+        assert_eq!(queue.highest_priority(), None);
+    }
+
+    #[test]
+    fn test_per_level_capacities() {
+        let mut queue = BucketQueue::<u8, 3>::with_capacities([0, 1, 3]);
+        assert_eq!(queue.capacity_at(2), 3);
+        assert_eq!(queue.try_push(0, 0).unwrap_err().element(), 0);
+        queue.try_push(1, 1).unwrap();
+        assert!(queue.try_push(1, 1).is_err());
+        for i in 0..3 {
+            queue.try_push(2, i).unwrap();
+        }
+        assert!(queue.try_push(2, 3).is_err());
+        assert_eq!((queue.len(), queue.len_at(2)), (4, 3));
+        assert_eq!(format!("{queue:?}"), "[(2, 0), (2, 1), (2, 2), (1, 1)]");
+        queue.clear();
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+        queue.try_push(1, 9).unwrap();
+        assert_eq!(queue.pop(), Some((1, 9)));
+    }
+
+    #[test]
+    #[should_panic(expected = "priority 4 out of range for 4 levels")]
+    fn test_priority_out_of_range() {
+        let mut queue = BucketQueue::<(), 4>::new(1);
+        let _ = queue.try_push(4, ());
+    }
+
+    #[test]
+    fn test_drops_queued_items() {
+        let token = Rc::new(());
+        let mut queue = BucketQueue::<Rc<()>, 2>::new(2);
+        for p in [0, 1, 1] {
+            queue.try_push(p, token.clone()).unwrap();
+        }
+        let clone = queue.clone();
+        assert_eq!(Rc::strong_count(&token), 7);
+        drop(queue.pop());
+        drop(clone);
+        queue.clear();
+        assert_eq!(Rc::strong_count(&token), 1);
+        queue.try_push(0, token.clone()).unwrap();
+        drop(queue);
+        assert_eq!(Rc::strong_count(&token), 1);
+    }
+}
@@ -0,0 +1,22 @@
+use std::ops::{Deref, DerefMut};
+
+/// Aligns and pads a value to 128 bytes so that it never shares a cache line
+/// with neighbouring fields. 128 rather than 64 because recent x86 and ARM
+/// cores prefetch cache lines in adjacent pairs.
+#[derive(Default)]
+#[repr(align(128))]
+pub(crate) struct CachePadded<T>(pub(crate) T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
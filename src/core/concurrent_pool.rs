@@ -0,0 +1,354 @@
+use std::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use crate::core::{bounded_buf::BoundedBuffer, error::TryNewError};
+
+const BITS: usize = u64::BITS as usize;
+
+type ResetHook<T> = Box<dyn Fn(&mut T) + Send + Sync>;
+
+/// A fixed set of objects, all constructed up front, that threads check out
+/// and return concurrently without locks.
+///
+/// Which objects are free is tracked in a bitmap of atomic words, one bit
+/// per object. Acquiring clears a set bit with a single `fetch_and`, and
+/// releasing sets it again with a `fetch_or`, so neither ever blocks, and
+/// contention is spread over the words rather than funnelled through one
+/// head pointer. Nothing is allocated after construction.
+///
+/// ```
+/// use fixed_buf::ConcurrentPool;
+///
+/// let pool = ConcurrentPool::new_with_reset(2, || Vec::with_capacity(64), Vec::clear);
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             if let Some(mut buf) = pool.acquire() {
+///                 buf.extend_from_slice(b"request");
+///             }
+///         });
+///     }
+/// });
+/// let buf = pool.acquire().unwrap();
+/// assert!(buf.is_empty());
+/// assert!(buf.capacity() >= 64);
+/// ```
+pub struct ConcurrentPool<T> {
+    objects: BoundedBuffer<UnsafeCell<T>>,
+    // Bit `i % 64` of word `i / 64` is set while object `i` is free.
+    free: BoundedBuffer<AtomicU64>,
+    // The word the last acquire found a free object in, where the next one
+    // starts looking.
+    hint: AtomicUsize,
+    reset: Option<ResetHook<T>>,
+}
+
+// Each object is reachable through at most one guard at a time, and moves
+// between threads with it, like the value in a `Mutex`.
+unsafe impl<T: Send> Send for ConcurrentPool<T> {}
+unsafe impl<T: Send> Sync for ConcurrentPool<T> {}
+
+impl<T> ConcurrentPool<T> {
+    /// Creates a pool of `cap` objects, each constructed by `init`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new_with<F: FnMut() -> T>(cap: usize, init: F) -> Self {
+        match Self::try_new_with(cap, init) {
+            Ok(pool) => pool,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new_with`](Self::new_with), but returns an error instead of
+    /// panicking.
+    pub fn try_new_with<F: FnMut() -> T>(cap: usize, mut init: F) -> Result<Self, TryNewError> {
+        let mut objects = BoundedBuffer::try_new(cap)?;
+        let mut free = BoundedBuffer::try_new(cap.div_ceil(BITS))?;
+        objects.fill_with_to_capacity(|| UnsafeCell::new(init()));
+        let mut remaining = cap;
+        free.fill_with_to_capacity(|| {
+            let bits = remaining.min(BITS);
+            remaining -= bits;
+            AtomicU64::new(u64::MAX >> (BITS - bits))
+        });
+        Ok(Self {
+            objects,
+            free,
+            hint: AtomicUsize::new(0),
+            reset: None,
+        })
+    }
+
+    /// Like [`new_with`](Self::new_with), but also runs `reset` on each
+    /// object as it is released, so the next guard starts from a clean
+    /// state.
+    ///
+    /// If `reset` panics, the object is never released and the pool stays
+    /// one object smaller.
+    #[track_caller]
+    pub fn new_with_reset<F, R>(cap: usize, init: F, reset: R) -> Self
+    where
+        F: FnMut() -> T,
+        R: Fn(&mut T) + Send + Sync + 'static,
+    {
+        let mut pool = Self::new_with(cap, init);
+        pool.reset = Some(Box::new(reset));
+        pool
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.objects.capacity()
+    }
+
+    /// Returns the number of objects not currently acquired. Other threads
+    /// may be acquiring and releasing concurrently, so this is only a
+    /// snapshot.
+    pub fn available(&self) -> usize {
+        self.free
+            .iter()
+            .map(|word| word.load(Ordering::Relaxed).count_ones() as usize)
+            .sum()
+    }
+
+    /// Checks out a free object, or returns `None` if all of them are in use.
+    /// Never blocks.
+    pub fn acquire(&self) -> Option<PoolGuard<'_, T>> {
+        let words = self.free.len();
+        let start = self.hint.load(Ordering::Relaxed);
+        for offset in 0..words {
+            let w = (start + offset) % words;
+            let word = &self.free[w];
+            let mut bits = word.load(Ordering::Relaxed);
+            while bits != 0 {
+                let bit = 1 << bits.trailing_zeros();
+                // Pairs with the release in `PoolGuard::drop`, so this thread
+                // sees the object as the last holder left it.
+                let prev = word.fetch_and(!bit, Ordering::Acquire);
+                if prev & bit != 0 {
+                    if w != start {
+                        self.hint.store(w, Ordering::Relaxed);
+                    }
+                    return Some(PoolGuard {
+                        pool: self,
+                        index: w * BITS + bit.trailing_zeros() as usize,
+                        _marker: PhantomData,
+                    });
+                }
+                // Another thread took that one first; try the rest.
+                bits = prev & !bit;
+            }
+        }
+        None
+    }
+
+    /// Returns mutable references to all the objects. No guards can be
+    /// alive while the pool is mutably borrowed.
+    pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = &mut T> {
+        self.objects.iter_mut().map(UnsafeCell::get_mut)
+    }
+}
+
+impl<T> fmt::Debug for ConcurrentPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrentPool")
+            .field("available", &self.available())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+/// An object checked out of a [`ConcurrentPool`]. It goes back to the pool
+/// when the guard is dropped.
+///
+/// Like `&mut T`, a guard can only be shared between threads if `T` can:
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<fixed_buf::PoolGuard<'static, std::cell::Cell<u8>>>();
+/// ```
+pub struct PoolGuard<'a, T> {
+    pool: &'a ConcurrentPool<T>,
+    index: usize,
+    // Lends out the object like `&'a mut T` does, so the guard is only
+    // `Sync` if `T` is.
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T> PoolGuard<'_, T> {
+    /// Returns the position of the object in the pool, below its capacity.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T> Deref for PoolGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the cleared bit gives this guard sole access to the object.
+        unsafe { &*self.pool.objects[self.index].get() }
+    }
+}
+
+impl<T> DerefMut for PoolGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: as in `deref`.
+        unsafe { &mut *self.pool.objects[self.index].get() }
+    }
+}
+
+impl<T> Drop for PoolGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(reset) = &self.pool.reset {
+            reset(self);
+        }
+        let bit = 1 << (self.index % BITS);
+        self.pool.free[self.index / BITS].fetch_or(bit, Ordering::Release);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PoolGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolGuard")
+            .field("index", &self.index)
+            .field("value", &**self)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::ConcurrentPool;
+
+    #[test]
+    fn test_acquire_until_exhausted() {
+        let counter = AtomicUsize::new(0);
+        let pool = ConcurrentPool::new_with(70, || counter.fetch_add(1, Ordering::Relaxed));
+        assert_eq!(pool.available(), 70);
+        let guards: Vec<_> = (0..70).map(|_| pool.acquire().unwrap()).collect();
+        assert!(pool.acquire().is_none());
+        assert_eq!(pool.available(), 0);
+        let mut indices: Vec<_> = guards.iter().map(|g| g.index()).collect();
+        indices.sort();
+        assert!(indices.into_iter().eq(0..70));
+        // Each guard holds the object built for its own slot.
+        assert!(guards.iter().all(|g| **g == g.index()));
+        drop(guards);
+        assert_eq!(pool.available(), 70);
+    }
+
+    #[test]
+    fn test_release_makes_object_available_again() {
+        let mut pool = ConcurrentPool::new_with(1, || 0u32);
+        let mut guard = pool.acquire().unwrap();
+        *guard += 5;
+        assert!(pool.acquire().is_none());
+        drop(guard);
+        // Without a reset hook, the object keeps its state.
+        assert_eq!(*pool.acquire().unwrap(), 5);
+        assert_eq!(pool.iter_mut().map(|x| *x).collect::<Vec<_>>(), [5]);
+    }
+
+    #[test]
+    fn test_reset_hook_runs_on_release() {
+        let pool = ConcurrentPool::new_with_reset(2, String::new, |s: &mut String| s.clear());
+        let mut a = pool.acquire().unwrap();
+        a.push_str("dirty");
+        let index = a.index();
+        drop(a);
+        let mut guards = [pool.acquire().unwrap(), pool.acquire().unwrap()];
+        guards.sort_by_key(|g| g.index());
+        assert_eq!(*guards[index], "");
+        assert_eq!(
+            format!("{:?}", guards[0]),
+            r#"PoolGuard { index: 0, value: "" }"#
+        );
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let pool = ConcurrentPool::new_with(0, || ());
+        assert!(pool.acquire().is_none());
+        assert_eq!(pool.available(), 0);
+    }
+
+    #[test]
+    fn test_drops_every_object_once() {
+        let token = std::rc::Rc::new(());
+        let pool = ConcurrentPool::new_with(3, || token.clone());
+        let guard = pool.acquire().unwrap();
+        assert_eq!(std::rc::Rc::strong_count(&token), 4);
+        drop(guard);
+        drop(pool);
+        assert_eq!(std::rc::Rc::strong_count(&token), 1);
+    }
+
+    struct Conn {
+        holder: usize,
+        uses: u64,
+    }
+
+    // Threads acquire and release as fast as they can. An object handed out
+    // twice at once would trip its `in_use` flag or see another thread's
+    // id.
+    fn stress(cap: usize, threads: usize, rounds: usize) {
+        let in_use: Vec<AtomicBool> = (0..cap).map(|_| AtomicBool::new(false)).collect();
+        let pool = ConcurrentPool::new_with_reset(
+            cap,
+            || Conn {
+                holder: usize::MAX,
+                uses: 0,
+            },
+            |conn: &mut Conn| conn.holder = usize::MAX,
+        );
+        let misses = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for id in 0..threads {
+                let (pool, in_use, misses) = (&pool, &in_use, &misses);
+                s.spawn(move || {
+                    for _ in 0..rounds {
+                        let Some(mut conn) = pool.acquire() else {
+                            misses.fetch_add(1, Ordering::Relaxed);
+                            thread::yield_now();
+                            continue;
+                        };
+                        assert!(!in_use[conn.index()].swap(true, Ordering::Relaxed));
+                        assert_eq!(conn.holder, usize::MAX);
+                        conn.holder = id;
+                        conn.uses += 1;
+                        thread::yield_now();
+                        assert_eq!(conn.holder, id);
+                        in_use[conn.index()].store(false, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        let mut pool = pool;
+        let uses: u64 = pool.iter_mut().map(|conn| conn.uses).sum();
+        assert_eq!(
+            uses as usize + misses.load(Ordering::Relaxed),
+            threads * rounds
+        );
+        assert_eq!(pool.available(), cap);
+    }
+
+    #[test]
+    fn test_stress_no_double_handout() {
+        let rounds = if cfg!(miri) { 50 } else { 20_000 };
+        stress(4, 8, rounds);
+        stress(130, 8, rounds);
+    }
+}
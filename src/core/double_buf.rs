@@ -0,0 +1,255 @@
+use std::{collections::VecDeque, fmt};
+
+use crate::core::{
+    borrowed_buf::BorrowedBuffer, bounded_buf::BoundedBuffer, bounded_deque::BoundedDeque,
+    bounded_ring::BoundedRing, bounded_str::BoundedString, error::TryNewError,
+    inline_buf::InlineBuffer, small_buf::SmallBoundedBuffer,
+};
+
+/// A front value that is read and a back value that is built, swapped in
+/// one step once the back is ready.
+///
+/// Swapping flips which of the two is the front rather than moving them, so
+/// it is free no matter how large they are. Both are only reachable through
+/// borrows of the `DoubleBuffer`, so the back can't be written while a
+/// reference to the front is alive, and neither can be reached at all while
+/// the back is being written, unless through [`split_mut`](Self::split_mut),
+/// which hands out both halves at once and can never alias them.
+///
+/// ```
+/// use fixed_buf::DoubleBuffer;
+///
+/// let mut frames = DoubleBuffer::with_capacity(4);
+/// frames.back_mut().try_extend_from_slice(&[1, 2, 3]).unwrap();
+/// assert!(frames.front().is_empty());
+/// frames.swap_and_clear();
+/// assert_eq!(frames.front().as_slice(), [1, 2, 3]);
+/// assert!(frames.back().is_empty());
+///
+/// // Build the next frame from the current one.
+/// let (front, back) = frames.split_mut();
+/// back.try_extend(front.iter().map(|x| x * 10)).unwrap();
+/// frames.swap();
+/// assert_eq!(frames.front().as_slice(), [10, 20, 30]);
+/// ```
+///
+/// Holding on to the front while writing the back doesn't compile:
+///
+/// ```compile_fail
+/// use fixed_buf::DoubleBuffer;
+///
+/// let mut frames = DoubleBuffer::<fixed_buf::BoundedBuffer<u8>>::with_capacity(4);
+/// let front = frames.front();
+/// frames.back_mut().try_push(1);
+/// println!("{}", front.len());
+/// ```
+///
+/// and nor does reading anything while a write is in progress:
+///
+/// ```compile_fail
+/// use fixed_buf::DoubleBuffer;
+///
+/// let mut frames = DoubleBuffer::<fixed_buf::BoundedBuffer<u8>>::with_capacity(4);
+/// let back = frames.back_mut();
+/// println!("{}", frames.front().len());
+/// back.try_push(1);
+/// ```
+#[derive(Clone, Default)]
+pub struct DoubleBuffer<T> {
+    halves: [T; 2],
+    // The index of the front half in `halves`.
+    front: usize,
+}
+
+impl<E> DoubleBuffer<BoundedBuffer<E>> {
+    /// Creates a double buffer of two empty [`BoundedBuffer`]s that each
+    /// hold at most `cap` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn with_capacity(cap: usize) -> Self {
+        Self::new(BoundedBuffer::new(cap), BoundedBuffer::new(cap))
+    }
+
+    /// Like [`with_capacity`](Self::with_capacity), but returns an error
+    /// instead of panicking.
+    pub fn try_with_capacity(cap: usize) -> Result<Self, TryNewError> {
+        Ok(Self::new(
+            BoundedBuffer::try_new(cap)?,
+            BoundedBuffer::try_new(cap)?,
+        ))
+    }
+}
+
+impl<T> DoubleBuffer<T> {
+    pub const fn new(front: T, back: T) -> Self {
+        Self {
+            halves: [front, back],
+            front: 0,
+        }
+    }
+
+    pub fn front(&self) -> &T {
+        &self.halves[self.front]
+    }
+
+    pub fn back(&self) -> &T {
+        &self.halves[1 - self.front]
+    }
+
+    pub fn back_mut(&mut self) -> &mut T {
+        &mut self.halves[1 - self.front]
+    }
+
+    /// Returns the front for reading and the back for writing at the same
+    /// time.
+    pub fn split_mut(&mut self) -> (&T, &mut T) {
+        let [a, b] = &mut self.halves;
+        match self.front {
+            0 => (a, b),
+            _ => (b, a),
+        }
+    }
+
+    /// Makes the back the front and the front the back.
+    pub fn swap(&mut self) {
+        self.front = 1 - self.front;
+    }
+
+    /// Swaps, then clears the new back so building the next value starts
+    /// from empty.
+    pub fn swap_and_clear(&mut self)
+    where
+        T: Clear,
+    {
+        self.swap();
+        self.back_mut().clear();
+    }
+
+    /// Returns the front and the back.
+    pub fn into_inner(self) -> (T, T) {
+        let [a, b] = self.halves;
+        match self.front {
+            0 => (a, b),
+            _ => (b, a),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for DoubleBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DoubleBuffer")
+            .field("front", self.front())
+            .field("back", self.back())
+            .finish()
+    }
+}
+
+/// Containers that can be emptied in place, keeping their storage, as used
+/// by [`DoubleBuffer::swap_and_clear`].
+pub trait Clear {
+    fn clear(&mut self);
+}
+
+macro_rules! impl_clear {
+    ($([$($generics:tt)*] $t:ty),* $(,)?) => {
+        $(impl<$($generics)*> Clear for $t {
+            fn clear(&mut self) {
+                <$t>::clear(self);
+            }
+        })*
+    };
+}
+
+impl_clear!(
+    [T] BoundedBuffer<T>,
+    [T] BoundedRing<T>,
+    [T] BoundedDeque<T>,
+    [T, const N: usize] InlineBuffer<T, N>,
+    [T, const N: usize] SmallBoundedBuffer<T, N>,
+    ['a, T] BorrowedBuffer<'a, T>,
+    [] BoundedString,
+    [T] Vec<T>,
+    [T] VecDeque<T>,
+    [] String,
+);
+
+#[cfg(test)]
+mod test {
+    use super::DoubleBuffer;
+    use crate::core::{bounded_buf::BoundedBuffer, bounded_str::BoundedString};
+
+    #[test]
+    fn test_swap_alternates() {
+        let mut buf = DoubleBuffer::new(1, 2);
+        assert_eq!((*buf.front(), *buf.back()), (1, 2));
+        buf.swap();
+        assert_eq!((*buf.front(), *buf.back()), (2, 1));
+        *buf.back_mut() = 3;
+        buf.swap();
+        assert_eq!((*buf.front(), *buf.back()), (3, 2));
+        assert_eq!(buf.into_inner(), (3, 2));
+    }
+
+    #[test]
+    fn test_swap_and_clear_keeps_capacity() {
+        let mut buf = DoubleBuffer::with_capacity(3);
+        let back_ptr = buf.back().as_ptr();
+        buf.back_mut().try_extend_from_slice(&[1u32, 2, 3]).unwrap();
+        buf.swap_and_clear();
+        // Swapping moved nothing: the built elements are where they were.
+        assert_eq!(buf.front().as_ptr(), back_ptr);
+        assert_eq!(buf.front().as_slice(), [1, 2, 3]);
+        assert!(buf.back().is_empty());
+        assert_eq!(buf.back().capacity(), 3);
+
+        buf.back_mut().try_push(4);
+        buf.swap_and_clear();
+        assert_eq!(buf.front().as_slice(), [4]);
+        assert!(buf.back().is_empty());
+        assert_eq!(buf.back().as_ptr(), back_ptr);
+    }
+
+    #[test]
+    fn test_split_mut_tracks_front() {
+        let mut buf = DoubleBuffer::new(BoundedBuffer::new(4), BoundedBuffer::new(4));
+        buf.back_mut().try_push(1u8);
+        buf.swap();
+        for _ in 0..3 {
+            let (front, back) = buf.split_mut();
+            back.clear();
+            back.try_extend(front.iter().map(|x| x + 1)).unwrap();
+            buf.swap();
+        }
+        assert_eq!(buf.front().as_slice(), [4]);
+        assert_eq!(buf.back().as_slice(), [3]);
+    }
+
+    #[test]
+    fn test_arbitrary_payloads() {
+        let mut text = DoubleBuffer::new(BoundedString::new(8), BoundedString::new(8));
+        text.back_mut().try_push_str("frame").unwrap();
+        text.swap_and_clear();
+        assert_eq!(*text.front(), "frame");
+        assert_eq!(
+            format!("{text:?}"),
+            r#"DoubleBuffer { front: "frame", back: "" }"#
+        );
+
+        let mut grid: DoubleBuffer<[[u8; 3]; 2]> = DoubleBuffer::default();
+        grid.back_mut()[1][2] = 9;
+        grid.swap();
+        assert_eq!(grid.front()[1], [0, 0, 9]);
+        let copy = grid.clone();
+        assert_eq!(copy.front(), grid.front());
+    }
+
+    #[test]
+    fn test_try_with_capacity() {
+        assert!(DoubleBuffer::<BoundedBuffer<u64>>::try_with_capacity(usize::MAX).is_err());
+        let buf = DoubleBuffer::<BoundedBuffer<u64>>::try_with_capacity(2).unwrap();
+        assert_eq!((buf.front().capacity(), buf.back().capacity()), (2, 2));
+    }
+}
@@ -0,0 +1,302 @@
+use std::{alloc::Layout, error::Error, fmt};
+
+use crate::core::bounded_buf::BoundedBuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryNewError {
+    /// The requested capacity does not fit in `isize::MAX` bytes.
+    CapacityOverflow,
+    /// The allocator returned null for this layout.
+    AllocError { layout: Layout },
+}
+
+impl fmt::Display for TryNewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CapacityOverflow => f.write_str("capacity overflow"),
+            Self::AllocError { layout } => {
+                write!(f, "could not allocate {} bytes", layout.size())
+            }
+        }
+    }
+}
+
+impl Error for TryNewError {}
+
+/// The buffer did not have room for the operation. Carries back the value
+/// that did not fit, if the operation took one by value.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError<T = ()> {
+    elem: T,
+}
+
+impl<T> CapacityError<T> {
+    pub const fn new(elem: T) -> Self {
+        Self { elem }
+    }
+
+    pub fn element(self) -> T {
+        self.elem
+    }
+
+    /// Discards the carried value.
+    pub fn simplify(self) -> CapacityError {
+        CapacityError::new(())
+    }
+}
+
+// Doesn't require `T: Debug` so errors carrying closures or other opaque
+// values can still be unwrapped.
+impl<T> fmt::Debug for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CapacityError")
+    }
+}
+
+impl<T> fmt::Display for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("insufficient capacity")
+    }
+}
+
+impl<T> Error for CapacityError<T> {}
+
+/// The buffer's allocation cannot hold the target element type. Carries the
+/// buffer back unchanged.
+pub struct CastError<T> {
+    buf: BoundedBuffer<T>,
+}
+
+impl<T> CastError<T> {
+    pub const fn new(buf: BoundedBuffer<T>) -> Self {
+        Self { buf }
+    }
+
+    pub fn into_inner(self) -> BoundedBuffer<T> {
+        self.buf
+    }
+}
+
+impl<T> fmt::Debug for CastError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CastError")
+    }
+}
+
+impl<T> fmt::Display for CastError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("allocation layout is incompatible with the target type")
+    }
+}
+
+impl<T> Error for CastError<T> {}
+
+/// A [`BoundedInterner`](crate::BoundedInterner) had no room for a new
+/// string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InternError {
+    /// Every symbol has been handed out.
+    SlotsFull,
+    /// The string does not fit in the remaining arena bytes.
+    ArenaFull,
+}
+
+impl fmt::Display for InternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlotsFull => f.write_str("no symbols left in interner"),
+            Self::ArenaFull => f.write_str("interner arena is full"),
+        }
+    }
+}
+
+impl Error for InternError {}
+
+/// The receiving half of a channel was dropped. Carries back the value that
+/// could not be sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> SendError<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SendError")
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+/// A non-blocking send could not complete. Carries back the value.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is at capacity.
+    Full(T),
+    /// The receiving half was dropped.
+    Disconnected(T),
+}
+
+impl<T> TrySendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(elem) | Self::Disconnected(elem) => elem,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("sending on a full channel"),
+            Self::Disconnected(_) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(err: SendError<T>) -> Self {
+        Self::Disconnected(err.0)
+    }
+}
+
+/// A send with a timeout could not complete. Carries back the value.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SendTimeoutError<T> {
+    /// The channel stayed at capacity until the timeout.
+    Timeout(T),
+    /// The receiving half was dropped.
+    Disconnected(T),
+}
+
+impl<T> SendTimeoutError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Timeout(elem) | Self::Disconnected(elem) => elem,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(_) => f.write_str("Timeout(..)"),
+            Self::Disconnected(_) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(_) => f.write_str("timed out waiting to send on a full channel"),
+            Self::Disconnected(_) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for SendTimeoutError<T> {}
+
+/// Every sending half of a channel was dropped and nothing is left to
+/// receive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("receiving on a closed channel")
+    }
+}
+
+impl Error for RecvError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// Nothing is queued right now.
+    Empty,
+    /// Every sending half was dropped and nothing is left to receive.
+    Disconnected,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("receiving on an empty channel"),
+            Self::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+impl From<RecvError> for TryRecvError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    /// Nothing arrived before the timeout.
+    Timeout,
+    /// Every sending half was dropped and nothing is left to receive.
+    Disconnected,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("timed out waiting on an empty channel"),
+            Self::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for RecvTimeoutError {}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+/// A [`Reader`](crate::core::broadcast_ring::Reader) had nothing to return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryNextError {
+    /// The reader has seen every published event.
+    Empty,
+    /// The writer overwrote `skipped` events before the reader got to them.
+    /// The reader has moved past them to the oldest event still held.
+    Lagged { skipped: u64 },
+}
+
+impl fmt::Display for TryNextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("no new events"),
+            Self::Lagged { skipped } => write!(f, "reader lagged and skipped {skipped} events"),
+        }
+    }
+}
+
+impl Error for TryNextError {}
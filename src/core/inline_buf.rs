@@ -0,0 +1,535 @@
+use std::{
+    borrow::{Borrow, BorrowMut},
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr, slice,
+};
+
+use crate::core::{error::CapacityError, raw_buf::RawBuf};
+
+/// A buffer of at most `N` elements stored inline, without any heap
+/// allocation.
+///
+/// It offers the core of [`BoundedBuffer`](crate::BoundedBuffer)'s API and
+/// dereferences to a slice the same way, so code written against `&[T]` or
+/// `&mut [T]` takes either. [`new`](Self::new) is a `const fn`, so a buffer
+/// can be built in a `static` or `const`.
+///
+/// ```
+/// use fixed_buf::InlineBuffer;
+///
+/// let mut buf = InlineBuffer::<String, 2>::new();
+/// assert!(buf.try_push("a".to_owned()));
+/// assert!(buf.try_push("b".to_owned()));
+/// assert!(!buf.try_push("c".to_owned()));
+/// assert_eq!(buf.remove(0), "a");
+/// assert_eq!(buf.as_slice(), ["b"]);
+/// ```
+pub struct InlineBuffer<T, const N: usize> {
+    // The first `len` slots are initialized.
+    data: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> InlineBuffer<T, N> {
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        Self {
+            data: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.data.as_ptr() as *const T
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr() as *mut T
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    /// Returns the uninitialized slots past `len()`. Write into them and then
+    /// call [`set_len`](Self::set_len) to make the written elements part of
+    /// the buffer.
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        &mut self.data[self.len..]
+    }
+
+    /// Sets the length of the buffer without dropping or initializing
+    /// anything.
+    ///
+    /// # Safety
+    ///
+    /// `new_len` must be at most `N`, and every element below `new_len`
+    /// must be initialized.
+    #[track_caller]
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= N, "length exceeds capacity");
+        self.len = new_len;
+    }
+
+    fn raw(&mut self) -> RawBuf<'_, T> {
+        // SAFETY: `data` has `N` slots and the first `len` are initialized.
+        unsafe { RawBuf::new(self.data.as_mut_ptr().cast(), &mut self.len, N) }
+    }
+
+    pub fn try_push(&mut self, elem: T) -> bool {
+        self.raw().try_push(elem)
+    }
+
+    pub fn try_insert(&mut self, index: usize, elem: T) -> bool {
+        self.raw().try_insert(index, elem)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.raw().pop()
+    }
+
+    #[track_caller]
+    pub fn remove(&mut self, index: usize) -> T {
+        self.raw().remove(index)
+    }
+
+    pub fn try_remove(&mut self, index: usize) -> Option<T> {
+        self.raw().try_remove(index)
+    }
+
+    /// Removes and returns the element at `index` in O(1) by moving the last
+    /// element into its place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len()`.
+    #[track_caller]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        self.raw().swap_remove(index)
+    }
+
+    /// Drops the elements from `new_len` on. Does nothing if `new_len` is not
+    /// less than the length.
+    pub fn truncate(&mut self, new_len: usize) {
+        self.raw().truncate(new_len)
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Appends clones of all of `src`, or fails without writing anything if
+    /// they do not all fit.
+    pub fn try_extend_from_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        self.raw().try_extend_from_slice(src)
+    }
+}
+
+impl<T, const N: usize> Default for InlineBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for InlineBuffer<T, N> {
+    fn clone(&self) -> Self {
+        let mut buf = Self::new();
+        for elem in self {
+            buf.data[buf.len].write(elem.clone());
+            buf.len += 1;
+        }
+        buf
+    }
+}
+
+impl<T, const N: usize> Drop for InlineBuffer<T, N> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+/// Formats the initialized elements as a list, the same way `Vec` does. The
+/// capacity is not shown.
+impl<T: fmt::Debug, const N: usize> fmt::Debug for InlineBuffer<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+impl<T, U, const N: usize, const M: usize> PartialEq<InlineBuffer<U, M>> for InlineBuffer<T, N>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &InlineBuffer<U, M>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize> PartialEq<[U]> for InlineBuffer<T, N> {
+    fn eq(&self, other: &[U]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize, const M: usize> PartialEq<[U; M]> for InlineBuffer<T, N> {
+    fn eq(&self, other: &[U; M]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for InlineBuffer<T, N> {}
+
+impl<T: PartialOrd, const N: usize> PartialOrd for InlineBuffer<T, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.as_slice().partial_cmp(other.as_slice())
+    }
+}
+
+impl<T: Ord, const N: usize> Ord for InlineBuffer<T, N> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl<T: Hash, const N: usize> Hash for InlineBuffer<T, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for InlineBuffer<T, N> {
+    fn from(array: [T; N]) -> Self {
+        let array = ManuallyDrop::new(array);
+        Self {
+            data: unsafe { ptr::read(&*array as *const [T; N] as *const [MaybeUninit<T>; N]) },
+            len: N,
+        }
+    }
+}
+
+/// Appends every element of the iterator.
+///
+/// # Panics
+///
+/// Panics if the buffer fills up before the iterator is exhausted, keeping
+/// the elements that fit.
+impl<T, const N: usize> Extend<T> for InlineBuffer<T, N> {
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.raw().extend(iter);
+    }
+}
+
+impl<T, const N: usize> Deref for InlineBuffer<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for InlineBuffer<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for InlineBuffer<T, N> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const N: usize> AsMut<[T]> for InlineBuffer<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, const N: usize> Borrow<[T]> for InlineBuffer<T, N> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const N: usize> BorrowMut<[T]> for InlineBuffer<T, N> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, const N: usize> IntoIterator for InlineBuffer<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> IntoIter<T, N> {
+        let this = ManuallyDrop::new(self);
+        IntoIter {
+            data: unsafe { ptr::read(&this.data) },
+            start: 0,
+            end: this.len,
+        }
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a InlineBuffer<T, N> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut InlineBuffer<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+/// An iterator that moves the elements out of an [`InlineBuffer`], created
+/// by its [`IntoIterator`] impl. Dropping it drops the elements that were
+/// not yielded.
+pub struct IntoIter<T, const N: usize> {
+    data: [MaybeUninit<T>; N],
+    // Elements in `start..end` have not been yielded yet.
+    start: usize,
+    end: usize,
+}
+
+impl<T, const N: usize> IntoIter<T, N> {
+    /// Returns the elements that have not been yielded yet.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe {
+            slice::from_raw_parts(
+                (self.data.as_ptr() as *const T).add(self.start),
+                self.end - self.start,
+            )
+        }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for IntoIter<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IntoIter").field(&self.as_slice()).finish()
+    }
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.start += 1;
+        unsafe { Some(self.data[self.start - 1].assume_init_read()) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        unsafe { Some(self.data[self.end].assume_init_read()) }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> FusedIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                (self.data.as_mut_ptr() as *mut T).add(self.start),
+                self.end - self.start,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::InlineBuffer;
+    use crate::core::error::CapacityError;
+    use crate::core::test_util::{DropCount, Drops};
+
+    static EMPTY: InlineBuffer<u8, 64> = InlineBuffer::new();
+
+    const SCRATCH: InlineBuffer<u32, 4> = InlineBuffer::new();
+
+    #[test]
+    fn test_inline_in_static() {
+        assert!(EMPTY.is_empty());
+        assert_eq!(EMPTY.capacity(), 64);
+        assert_eq!(EMPTY.as_slice(), &[] as &[u8]);
+        let mut buf = SCRATCH;
+        assert!(buf.try_push(1));
+        assert_eq!(buf, [1]);
+        assert_eq!(
+            std::mem::size_of::<InlineBuffer<u8, 64>>(),
+            64 + std::mem::size_of::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_inline_push_pop_remove() {
+        let mut buf = InlineBuffer::<i32, 4>::new();
+        assert_eq!(buf.pop(), None);
+        for i in 0..4 {
+            assert!(buf.try_push(i));
+        }
+        assert!(buf.is_full());
+        assert!(!buf.try_push(4));
+        assert!(!buf.try_insert(0, 4));
+        assert_eq!(buf.remove(1), 1);
+        assert_eq!(buf.try_remove(3), None);
+        assert!(buf.try_insert(0, 9));
+        assert_eq!(buf, [9, 0, 2, 3]);
+        assert_eq!(buf.swap_remove(0), 9);
+        assert_eq!(buf, [3, 0, 2]);
+        assert!(!buf.try_insert(4, 5));
+        assert!(buf.try_insert(3, 5));
+        assert_eq!(buf.pop(), Some(5));
+        buf.sort();
+        assert_eq!(buf.as_slice(), [0, 2, 3]);
+        buf.truncate(1);
+        assert_eq!(buf.len(), 1);
+        assert_eq!(
+            buf.try_extend_from_slice(&[1, 2, 3, 4]),
+            Err(CapacityError::new(()))
+        );
+        buf.try_extend_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3]);
+        buf.clear();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_inline_remove_out_of_bounds() {
+        let mut buf = InlineBuffer::<u8, 2>::new();
+        buf.try_push(1);
+        buf.remove(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "extended past capacity (is 2)")]
+    fn test_inline_extend_past_capacity() {
+        let mut buf = InlineBuffer::<u8, 2>::new();
+        buf.extend([1, 2, 3]);
+    }
+
+    #[test]
+    fn test_inline_drops() {
+        let drops = Drops::default();
+        let mut buf = InlineBuffer::<_, 5>::new();
+        for _ in 0..5 {
+            assert!(buf.try_push(DropCount(drops.clone())));
+        }
+        assert!(!buf.try_push(DropCount(drops.clone())));
+        assert_eq!(drops.get(), 1);
+        drop(buf.pop());
+        drop(buf.remove(0));
+        drop(buf.swap_remove(0));
+        assert_eq!(drops.get(), 4);
+        let clone = buf.clone();
+        buf.truncate(1);
+        assert_eq!(drops.get(), 5);
+        drop(buf);
+        drop(clone);
+        assert_eq!(drops.get(), 8);
+
+        let mut buf = InlineBuffer::<_, 4>::new();
+        for _ in 0..4 {
+            buf.try_push(DropCount(drops.clone()));
+        }
+        let mut iter = buf.into_iter();
+        drop(iter.next());
+        drop(iter.next_back());
+        assert_eq!(iter.len(), 2);
+        assert_eq!(drops.get(), 10);
+        drop(iter);
+        assert_eq!(drops.get(), 12);
+    }
+
+    #[test]
+    fn test_inline_iteration_and_slices() {
+        let mut buf = InlineBuffer::from([3, 1, 2]);
+        assert!(buf.is_full());
+        for elem in &mut buf {
+            *elem *= 10;
+        }
+        assert_eq!((&buf).into_iter().sum::<i32>(), 60);
+        assert_eq!(buf.iter().max(), Some(&30));
+        fn takes_slice(s: &[i32]) -> usize {
+            s.len()
+        }
+        assert_eq!(takes_slice(&buf), 3);
+        assert_eq!(format!("{buf:?}"), "[30, 10, 20]");
+        assert_eq!(buf.into_iter().rev().collect::<Vec<_>>(), [20, 10, 30]);
+
+        let strings = InlineBuffer::from(["a".to_owned(), "b".to_owned()]);
+        let iter = strings.into_iter();
+        assert_eq!(format!("{iter:?}"), "IntoIter([\"a\", \"b\"])");
+
+        let mut buf = InlineBuffer::<u8, 4>::default();
+        buf.spare_capacity_mut()[0].write(7);
+        unsafe { buf.set_len(1) };
+        assert_eq!(buf, [7]);
+        let bigger = InlineBuffer::from([8, 0, 0, 0]);
+        assert!(buf < bigger);
+        assert_eq!(buf, InlineBuffer::<u8, 1>::from([7]));
+    }
+
+    #[test]
+    fn test_inline_zsts() {
+        let mut buf = InlineBuffer::<(), 3>::new();
+        while buf.try_push(()) {}
+        assert_eq!(buf.len(), 3);
+        assert_eq!(buf.into_iter().count(), 3);
+
+        let mut buf = InlineBuffer::<u8, 0>::new();
+        assert!(buf.is_full());
+        assert!(!buf.try_push(1));
+    }
+}
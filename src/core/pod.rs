@@ -0,0 +1,19 @@
+/// Plain-old-data element types that can be reinterpreted as one another by
+/// [`BoundedBuffer::try_cast`](crate::BoundedBuffer::try_cast).
+///
+/// # Safety
+///
+/// Implementors must be inhabited by every bit pattern of their size, have
+/// no padding bytes, and hold no pointers or other values whose validity
+/// depends on more than their bits.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),* $(,)?) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
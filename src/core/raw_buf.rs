@@ -0,0 +1,208 @@
+use std::ptr;
+
+use crate::core::error::CapacityError;
+
+/// The element operations of a buffer over `cap` slots at `ptr`, of which
+/// the first `*len` are initialized.
+///
+/// [`BoundedBuffer`](crate::BoundedBuffer),
+/// [`InlineBuffer`](crate::InlineBuffer) and
+/// [`BorrowedBuffer`](crate::BorrowedBuffer) only differ in where their
+/// slots live, so all three borrow one of these to push, insert and remove.
+pub(crate) struct RawBuf<'a, T> {
+    ptr: *mut T,
+    len: &'a mut usize,
+    cap: usize,
+}
+
+impl<'a, T> RawBuf<'a, T> {
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads and writes of `cap` elements for `'a`,
+    /// `*len` must be at most `cap`, and the first `*len` slots must be
+    /// initialized.
+    #[inline]
+    pub(crate) unsafe fn new(ptr: *mut T, len: &'a mut usize, cap: usize) -> Self {
+        debug_assert!(*len <= cap, "length exceeds capacity");
+        Self { ptr, len, cap }
+    }
+
+    #[inline]
+    pub(crate) fn try_push(&mut self, elem: T) -> bool {
+        if *self.len == self.cap {
+            return false;
+        }
+        unsafe { self.push_unchecked(elem) };
+        true
+    }
+
+    /// # Safety
+    ///
+    /// `*len` must be less than `cap`.
+    #[inline]
+    #[track_caller]
+    pub(crate) unsafe fn push_unchecked(&mut self, elem: T) {
+        debug_assert!(*self.len < self.cap, "buffer is full");
+        self.ptr.add(*self.len).write(elem);
+        *self.len += 1;
+    }
+
+    pub(crate) fn try_insert(&mut self, index: usize, elem: T) -> bool {
+        if *self.len == self.cap || index > *self.len {
+            return false;
+        }
+        unsafe { self.insert_unchecked(index, elem) };
+        true
+    }
+
+    /// # Safety
+    ///
+    /// `*len` must be less than `cap` and `index` at most `*len`.
+    #[track_caller]
+    pub(crate) unsafe fn insert_unchecked(&mut self, index: usize, elem: T) {
+        debug_assert!(*self.len < self.cap, "buffer is full");
+        debug_assert!(index <= *self.len, "index out of bounds");
+        let slot = self.ptr.add(index);
+        ptr::copy(slot, slot.add(1), *self.len - index);
+        slot.write(elem);
+        *self.len += 1;
+    }
+
+    #[inline]
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        if *self.len == 0 {
+            return None;
+        }
+        *self.len -= 1;
+        unsafe { Some(self.ptr.add(*self.len).read()) }
+    }
+
+    #[track_caller]
+    pub(crate) fn remove(&mut self, index: usize) -> T {
+        assert!(index < *self.len, "index out of bounds");
+        unsafe { self.remove_unchecked(index) }
+    }
+
+    pub(crate) fn try_remove(&mut self, index: usize) -> Option<T> {
+        if index >= *self.len {
+            return None;
+        }
+        unsafe { Some(self.remove_unchecked(index)) }
+    }
+
+    /// # Safety
+    ///
+    /// `index` must be less than `*len`.
+    #[track_caller]
+    pub(crate) unsafe fn remove_unchecked(&mut self, index: usize) -> T {
+        debug_assert!(index < *self.len, "index out of bounds");
+        *self.len -= 1;
+        let slot = self.ptr.add(index);
+        let elem = slot.read();
+        ptr::copy(slot.add(1), slot, *self.len - index);
+        elem
+    }
+
+    #[track_caller]
+    pub(crate) fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < *self.len, "index out of bounds");
+        unsafe { self.swap_remove_unchecked(index) }
+    }
+
+    pub(crate) fn try_swap_remove(&mut self, index: usize) -> Option<T> {
+        if index >= *self.len {
+            return None;
+        }
+        unsafe { Some(self.swap_remove_unchecked(index)) }
+    }
+
+    // Reads the removed element out before the last one is moved over it; a
+    // self-copy when `index` is the last slot is a no-op, so nothing is read
+    // twice.
+    //
+    // SAFETY: `index` must be less than `*len`.
+    pub(crate) unsafe fn swap_remove_unchecked(&mut self, index: usize) -> T {
+        debug_assert!(index < *self.len, "index out of bounds");
+        *self.len -= 1;
+        let elem = self.ptr.add(index).read();
+        ptr::copy(self.ptr.add(*self.len), self.ptr.add(index), 1);
+        elem
+    }
+
+    pub(crate) fn truncate(&mut self, new_len: usize) {
+        if new_len >= *self.len {
+            return;
+        }
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.ptr.add(new_len) }, *self.len - new_len);
+        // Shortened first so a panicking destructor leaks the rest of the
+        // tail rather than dropping anything twice.
+        *self.len = new_len;
+        unsafe { ptr::drop_in_place(tail) }
+    }
+
+    pub(crate) fn try_extend_from_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        if src.len() > self.cap - *self.len {
+            return Err(CapacityError::new(()));
+        }
+        unsafe { self.extend_from_slice_unchecked(src) };
+        Ok(())
+    }
+
+    // The length is only written back once at the end (or on unwind), which
+    // lets the loop lower to a single `memcpy` when `clone` is a plain copy.
+    // Stable Rust can't pick a `copy_nonoverlapping` path for `T: Copy` from
+    // behind a `T: Clone` bound, so that is left to the optimizer here.
+    //
+    // SAFETY: `src` must fit in the spare capacity.
+    pub(crate) unsafe fn extend_from_slice_unchecked(&mut self, src: &[T])
+    where
+        T: Clone,
+    {
+        debug_assert!(src.len() <= self.cap - *self.len);
+        let dst = self.ptr.add(*self.len);
+        let mut len = SetLenOnDrop::new(self.len);
+        for (i, elem) in src.iter().enumerate() {
+            dst.add(i).write(elem.clone());
+            len.increment();
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            if !self.try_push(elem) {
+                panic!("extended past capacity (is {})", self.cap);
+            }
+        }
+    }
+}
+
+// Tracks a length in a local and writes it back when dropped, so a panic
+// part way through a bulk write still leaves the buffer length covering
+// exactly the elements written so far.
+pub(crate) struct SetLenOnDrop<'a> {
+    len: &'a mut usize,
+    local_len: usize,
+}
+
+impl<'a> SetLenOnDrop<'a> {
+    pub(crate) fn new(len: &'a mut usize) -> Self {
+        let local_len = *len;
+        Self { len, local_len }
+    }
+
+    #[inline]
+    pub(crate) fn increment(&mut self) {
+        self.local_len += 1;
+    }
+}
+
+impl Drop for SetLenOnDrop<'_> {
+    fn drop(&mut self) {
+        *self.len = self.local_len;
+    }
+}
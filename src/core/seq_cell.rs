@@ -0,0 +1,290 @@
+use std::{
+    fmt, hint,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr,
+    sync::atomic::{self, AtomicU64, AtomicUsize, Ordering},
+};
+
+use crate::core::{bounded_buf::BoundedBuffer, cache_padded::CachePadded, pod::Pod};
+
+const WORD: usize = mem::size_of::<u64>();
+const LINE_WORDS: usize = mem::size_of::<CachePadded<[AtomicU64; 1]>>() / WORD;
+
+type Line = CachePadded<[AtomicU64; LINE_WORDS]>;
+
+/// A seqlock: a cell holding a small [`Pod`] value that one thread writes
+/// and any number of threads read, without locks.
+///
+/// A write bumps a sequence counter to odd, stores the value and bumps it
+/// back to even. A read copies the value out and retries if the counter was
+/// odd or changed meanwhile, so it never returns a mix of two writes.
+/// Readers never block the writer; a writer that keeps writing can starve
+/// readers, which suits values updated far less often than the time it
+/// takes to copy them.
+///
+/// The value is stored in cache-line-aligned words allocated once at
+/// construction, and both sides copy it one `AtomicU64` at a time. Copying
+/// shared memory with plain or volatile accesses would be a data race, and
+/// with it undefined behaviour, whenever a read overlaps a write. The
+/// atomic copies make the overlap well defined; the sequence check then
+/// throws the torn copy away. This is why the element type must be
+/// [`Pod`] rather than just `Copy`: padding bytes are uninitialized and
+/// can't be loaded into a `u64`.
+///
+/// Concurrent writes are serialized by the counter, so several writers are
+/// safe too; they just spin while one of them is mid-write.
+///
+/// ```
+/// use fixed_buf::SeqCell;
+///
+/// let quote = SeqCell::new([100.25f64, 100.5]);
+/// std::thread::scope(|s| {
+///     s.spawn(|| quote.write([100.5, 100.75]));
+///     let [bid, ask] = quote.read();
+///     assert!(ask - bid == 0.25);
+/// });
+/// assert_eq!(quote.read(), [100.5, 100.75]);
+/// assert_eq!(quote.version(), 1);
+/// ```
+pub struct SeqCell<T> {
+    // Even while the value is stable, odd while a write is in progress.
+    seq: CachePadded<AtomicUsize>,
+    lines: BoundedBuffer<Line>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Pod> SeqCell<T> {
+    /// Creates a cell holding `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        let words = mem::size_of::<T>().div_ceil(WORD);
+        let mut lines = BoundedBuffer::new(words.div_ceil(LINE_WORDS));
+        lines.fill_with_to_capacity(|| CachePadded(std::array::from_fn(|_| AtomicU64::new(0))));
+        let cell = Self {
+            seq: CachePadded(AtomicUsize::new(0)),
+            lines,
+            _marker: PhantomData,
+        };
+        cell.store(&value);
+        cell
+    }
+
+    /// Returns the number of completed writes.
+    pub fn version(&self) -> usize {
+        self.seq.load(Ordering::Acquire) / 2
+    }
+
+    /// Replaces the value. Readers see either the old value or the new one.
+    pub fn write(&self, value: T) {
+        let mut seq = self.seq.load(Ordering::Relaxed);
+        loop {
+            if seq % 2 == 1 {
+                hint::spin_loop();
+                seq = self.seq.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.seq.compare_exchange_weak(
+                seq,
+                seq.wrapping_add(1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+        // Keeps the word stores below from being reordered before the odd
+        // counter becomes visible.
+        atomic::fence(Ordering::Release);
+        self.store(&value);
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Returns a copy of the value, retrying while a write is in progress.
+    pub fn read(&self) -> T {
+        loop {
+            if let Some(value) = self.try_read() {
+                return value;
+            }
+            hint::spin_loop();
+        }
+    }
+
+    /// Returns a copy of the value, or `None` if a write was in progress
+    /// at any point during the copy.
+    pub fn try_read(&self) -> Option<T> {
+        let before = self.seq.load(Ordering::Acquire);
+        if before % 2 == 1 {
+            return None;
+        }
+        let value = self.load();
+        // Keeps the word loads above from being reordered after the second
+        // counter load.
+        atomic::fence(Ordering::Acquire);
+        let after = self.seq.load(Ordering::Relaxed);
+        (before == after).then_some(value)
+    }
+
+    fn word(&self, i: usize) -> &AtomicU64 {
+        &self.lines[i / LINE_WORDS][i % LINE_WORDS]
+    }
+
+    fn store(&self, value: &T) {
+        let src = (value as *const T).cast::<u8>();
+        let size = mem::size_of::<T>();
+        for (i, offset) in (0..size).step_by(WORD).enumerate() {
+            let mut bytes = [0u8; WORD];
+            let n = WORD.min(size - offset);
+            // SAFETY: `T: Pod` has no padding, so all of its bytes are
+            // initialized.
+            unsafe { ptr::copy_nonoverlapping(src.add(offset), bytes.as_mut_ptr(), n) };
+            self.word(i)
+                .store(u64::from_ne_bytes(bytes), Ordering::Relaxed);
+        }
+    }
+
+    fn load(&self) -> T {
+        let mut out = MaybeUninit::<T>::uninit();
+        let dst = out.as_mut_ptr().cast::<u8>();
+        let size = mem::size_of::<T>();
+        for (i, offset) in (0..size).step_by(WORD).enumerate() {
+            let bytes = self.word(i).load(Ordering::Relaxed).to_ne_bytes();
+            let n = WORD.min(size - offset);
+            unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), dst.add(offset), n) };
+        }
+        // SAFETY: every byte was written above, and `T: Pod` is valid for
+        // any bytes, even those of a torn copy the caller will discard.
+        unsafe { out.assume_init() }
+    }
+}
+
+impl<T: Pod + fmt::Debug> fmt::Debug for SeqCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SeqCell").field(&self.read()).finish()
+    }
+}
+
+impl<T: Pod + Default> Default for SeqCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        mem,
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+    };
+
+    use super::SeqCell;
+
+    #[test]
+    fn test_round_trip_odd_sizes() {
+        let cell = SeqCell::new(0u8);
+        cell.write(0xAB);
+        assert_eq!(cell.read(), 0xAB);
+
+        let bytes: [u8; 13] = std::array::from_fn(|i| i as u8 * 7);
+        let cell = SeqCell::new([0u8; 13]);
+        cell.write(bytes);
+        assert_eq!(cell.read(), bytes);
+
+        // Spans several cache lines and isn't a whole number of words.
+        let big: [u32; 75] = std::array::from_fn(|i| i as u32 * 0x0101_0101);
+        let cell = SeqCell::new(big);
+        assert_eq!(cell.read(), big);
+        assert_eq!(cell.version(), 0);
+        cell.write([1; 75]);
+        assert_eq!(cell.read(), [1; 75]);
+        assert_eq!(cell.version(), 1);
+        assert_eq!(cell.lines.capacity(), (75 * 4usize).div_ceil(128));
+
+        let unit = SeqCell::new([0u64; 0]);
+        unit.write([]);
+        assert_eq!(unit.read(), [0u64; 0]);
+        assert_eq!(unit.lines.capacity(), 0);
+    }
+
+    #[test]
+    fn test_storage_is_line_aligned() {
+        let cell = SeqCell::new([7u64; 40]);
+        assert_eq!(cell.lines.as_ptr() as usize % 128, 0);
+        assert_eq!(mem::align_of_val(&cell.seq), 128);
+    }
+
+    #[test]
+    fn test_try_read_during_write() {
+        let cell = SeqCell::new(5u32);
+        cell.seq.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(cell.try_read(), None);
+        cell.seq.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(cell.try_read(), Some(5));
+        assert_eq!(format!("{cell:?}"), "SeqCell(5)");
+    }
+
+    // Every word of a snapshot holds the same value, so a read that mixed
+    // two writes would show two different words.
+    fn check_consistent(snapshot: &[u64; 48]) -> u64 {
+        let first = snapshot[0];
+        assert!(
+            snapshot.iter().all(|&w| w == first),
+            "torn read: {snapshot:?}"
+        );
+        first
+    }
+
+    #[test]
+    fn test_readers_never_see_mixed_snapshot() {
+        let writes = if cfg!(miri) { 100 } else { 50_000 };
+        let cell = SeqCell::new([0u64; 48]);
+        let done = AtomicBool::new(false);
+        thread::scope(|s| {
+            for _ in 0..3 {
+                s.spawn(|| {
+                    let mut last = 0;
+                    while !done.load(Ordering::Relaxed) {
+                        let version = check_consistent(&cell.read());
+                        // A single writer's values only move forward.
+                        assert!(version >= last);
+                        last = version;
+                    }
+                });
+            }
+            for i in 1..=writes {
+                cell.write([i; 48]);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(check_consistent(&cell.read()), writes);
+        assert_eq!(cell.version(), writes as usize);
+    }
+
+    #[test]
+    fn test_concurrent_writers_serialize() {
+        let writes = if cfg!(miri) { 20 } else { 10_000 };
+        let cell = SeqCell::new([0u64; 48]);
+        thread::scope(|s| {
+            for w in 0..3u64 {
+                let cell = &cell;
+                s.spawn(move || {
+                    for i in 0..writes {
+                        cell.write([w << 32 | i; 48]);
+                    }
+                });
+            }
+            s.spawn(|| {
+                for _ in 0..writes {
+                    check_consistent(&cell.read());
+                }
+            });
+        });
+        assert_eq!(cell.version(), 3 * writes as usize);
+    }
+}
@@ -0,0 +1,730 @@
+use std::{
+    fmt,
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem,
+    ops::{Index, IndexMut},
+    slice,
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    error::{CapacityError, TryNewError},
+};
+
+// Ends the free list. Slot indices are always below it because the capacity
+// is capped at `u32::MAX`.
+const NONE: u32 = u32::MAX;
+
+/// A handle to an element of a [`Slab`].
+///
+/// Besides the slot it points to, a key carries the generation of the
+/// element it was created for. Removing an element bumps its slot's
+/// generation, so keys to it stop working even after the slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key {
+    index: u32,
+    generation: u32,
+}
+
+impl Key {
+    /// Returns the index of the slot the key points to.
+    pub const fn index(self) -> usize {
+        self.index as usize
+    }
+
+    pub const fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    state: State<T>,
+}
+
+enum State<T> {
+    // `dense` is where the slot's index sits in `Slab::dense`.
+    Occupied { value: T, dense: u32 },
+    Vacant { next_free: u32 },
+}
+
+/// A fixed-capacity slab: elements addressed by [`Key`]s that stay valid
+/// while other elements come and go, and are rejected once their own
+/// element is removed.
+///
+/// Slots are allocated once at construction and never move. Vacant slots
+/// are chained into a free list through the slots themselves, so inserting
+/// and removing are O(1). A separate table of the occupied slots makes
+/// iteration O(len) rather than O(capacity); it visits elements in no
+/// particular order.
+///
+/// A slot whose generation would wrap around is retired instead of reused,
+/// so a stale key can never match a later element, at the cost of one slot
+/// per 2<sup>32</sup> removals from it.
+///
+/// ```
+/// use fixed_buf::Slab;
+///
+/// let mut sessions = Slab::new(2);
+/// let alice = sessions.insert("alice").unwrap();
+/// let bob = sessions.insert("bob").unwrap();
+/// assert_eq!(sessions.insert("carol").unwrap_err().element(), "carol");
+///
+/// assert_eq!(sessions.remove(alice), Some("alice"));
+/// let carol = sessions.insert("carol").unwrap();
+/// // Carol reuses Alice's slot, but Alice's key doesn't reach her.
+/// assert_eq!(carol.index(), alice.index());
+/// assert_eq!(sessions.get(alice), None);
+/// assert_eq!(sessions[carol], "carol");
+/// assert_eq!(sessions[bob], "bob");
+/// ```
+pub struct Slab<T> {
+    slots: BoundedBuffer<Slot<T>>,
+    // Indices of the occupied slots, in no particular order.
+    dense: BoundedBuffer<u32>,
+    // The first vacant slot below `slots.len()`, or `NONE`.
+    free: u32,
+    // Vacant slots that are never reused.
+    retired: usize,
+}
+
+impl<T> Slab<T> {
+    /// Creates a slab that holds at most `cap` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` exceeds `u32::MAX` or an allocation fails.
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        match Self::try_new(cap) {
+            Ok(slab) => slab,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        if cap > u32::MAX as usize {
+            return Err(TryNewError::CapacityOverflow);
+        }
+        Ok(Self {
+            slots: BoundedBuffer::try_new(cap)?,
+            dense: BoundedBuffer::try_new(cap)?,
+            free: NONE,
+            retired: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    /// Returns how many more elements fit. Retired slots don't count.
+    pub fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.len() - self.retired
+    }
+
+    /// Inserts `value` and returns its key, or gives it back if every slot is
+    /// taken.
+    pub fn insert(&mut self, value: T) -> Result<Key, CapacityError<T>> {
+        match self.vacant_entry() {
+            Some(entry) => {
+                let key = entry.key();
+                entry.insert(value);
+                Ok(key)
+            }
+            None => Err(CapacityError::new(value)),
+        }
+    }
+
+    /// Returns a handle to the slot the next insertion will use, or `None` if
+    /// every slot is taken.
+    ///
+    /// The key is known before the element is inserted, so the element can
+    /// hold its own key.
+    ///
+    /// ```
+    /// use fixed_buf::{Key, Slab};
+    ///
+    /// struct Node {
+    ///     this: Key,
+    /// }
+    ///
+    /// let mut nodes = Slab::new(4);
+    /// let entry = nodes.vacant_entry().unwrap();
+    /// let this = entry.key();
+    /// entry.insert(Node { this });
+    /// assert_eq!(nodes[this].this, this);
+    /// ```
+    pub fn vacant_entry(&mut self) -> Option<VacantEntry<'_, T>> {
+        let key = if self.free != NONE {
+            let slot = &self.slots[self.free as usize];
+            Key {
+                index: self.free,
+                generation: slot.generation,
+            }
+        } else if self.slots.len() < self.slots.capacity() {
+            Key {
+                index: self.slots.len() as u32,
+                generation: 0,
+            }
+        } else {
+            return None;
+        };
+        Some(VacantEntry { slab: self, key })
+    }
+
+    pub fn contains(&self, key: Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the element for `key`, or `None` if it has been removed.
+    pub fn get(&self, key: Key) -> Option<&T> {
+        match self.slots.get(key.index as usize)? {
+            Slot {
+                generation,
+                state: State::Occupied { value, .. },
+            } if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        match self.slots.get_mut(key.index as usize)? {
+            Slot {
+                generation,
+                state: State::Occupied { value, .. },
+            } if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the key of the element in slot `index`, if it is occupied.
+    pub(crate) fn key_at(&self, index: u32) -> Option<Key> {
+        match self.slots.get(index as usize)? {
+            Slot {
+                generation,
+                state: State::Occupied { .. },
+            } => Some(Key {
+                index,
+                generation: *generation,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Like [`get`](Self::get), but by slot index alone, for structures that
+    /// link their elements to each other by index.
+    pub(crate) fn get_at(&self, index: u32) -> Option<&T> {
+        match &self.slots.get(index as usize)?.state {
+            State::Occupied { value, .. } => Some(value),
+            State::Vacant { .. } => None,
+        }
+    }
+
+    pub(crate) fn get_at_mut(&mut self, index: u32) -> Option<&mut T> {
+        match &mut self.slots.get_mut(index as usize)?.state {
+            State::Occupied { value, .. } => Some(value),
+            State::Vacant { .. } => None,
+        }
+    }
+
+    /// Returns a view through which elements of different slots can be
+    /// borrowed mutably at the same time.
+    pub(crate) fn raw_slots_mut(&mut self) -> RawSlotsMut<'_, T> {
+        RawSlotsMut::new(&mut self.slots)
+    }
+
+    /// Removes and returns the element for `key`, or returns `None` if it
+    /// has already been removed. Either way, `key` is invalid afterwards.
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        self.get(key)?;
+        let next_free = self.free;
+        let slot = &mut self.slots[key.index as usize];
+        let (value, dense) = match mem::replace(&mut slot.state, State::Vacant { next_free }) {
+            State::Occupied { value, dense } => (value, dense),
+            State::Vacant { .. } => unreachable!(),
+        };
+        match slot.generation.checked_add(1) {
+            Some(generation) => {
+                slot.generation = generation;
+                self.free = key.index;
+            }
+            // Retire the slot: it stays vacant and off the free list.
+            None => {
+                slot.state = State::Vacant { next_free: NONE };
+                self.retired += 1;
+            }
+        }
+        self.dense.swap_remove(dense as usize);
+        if let Some(&moved) = self.dense.get(dense as usize) {
+            if let State::Occupied { dense: pos, .. } = &mut self.slots[moved as usize].state {
+                *pos = dense;
+            }
+        }
+        Some(value)
+    }
+
+    /// Removes every element `f` returns `false` for.
+    pub fn retain<F: FnMut(Key, &mut T) -> bool>(&mut self, mut f: F) {
+        let mut i = 0;
+        while i < self.dense.len() {
+            let index = self.dense[i];
+            let slot = &mut self.slots[index as usize];
+            let key = Key {
+                index,
+                generation: slot.generation,
+            };
+            let keep = match &mut slot.state {
+                State::Occupied { value, .. } => f(key, value),
+                State::Vacant { .. } => unreachable!(),
+            };
+            if keep {
+                i += 1;
+            } else {
+                // Moves the last occupied slot into position `i`.
+                self.remove(key);
+            }
+        }
+    }
+
+    /// Removes every element. Keys to them are invalid afterwards.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    /// Returns an iterator over the keys and elements, in no particular
+    /// order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            dense: self.dense.iter(),
+            slots: self.slots.as_slice(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            dense: self.dense.iter(),
+            slots: RawSlotsMut::new(&mut self.slots),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_generation(&mut self, index: u32, generation: u32) {
+        self.slots[index as usize].generation = generation;
+    }
+}
+
+impl<T> Index<Key> for Slab<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn index(&self, key: Key) -> &T {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("invalid slab key {key:?}"),
+        }
+    }
+}
+
+impl<T> IndexMut<Key> for Slab<T> {
+    #[track_caller]
+    fn index_mut(&mut self, key: Key) -> &mut T {
+        match self.get_mut(key) {
+            Some(value) => value,
+            None => panic!("invalid slab key {key:?}"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Slab<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Slab<T> {
+    type Item = (Key, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Slab<T> {
+    type Item = (Key, &'a mut T);
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// A vacant slot of a [`Slab`], returned by [`Slab::vacant_entry`].
+pub struct VacantEntry<'a, T> {
+    slab: &'a mut Slab<T>,
+    key: Key,
+}
+
+impl<'a, T> VacantEntry<'a, T> {
+    /// Returns the key the element will have once inserted.
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    pub fn insert(self, value: T) -> &'a mut T {
+        let slab = self.slab;
+        let dense = slab.dense.len() as u32;
+        let pushed = slab.dense.try_push(self.key.index);
+        debug_assert!(pushed);
+        let state = State::Occupied { value, dense };
+        let index = self.key.index as usize;
+        if index == slab.slots.len() {
+            let pushed = slab.slots.try_push(Slot {
+                generation: 0,
+                state,
+            });
+            debug_assert!(pushed);
+        } else {
+            match mem::replace(&mut slab.slots[index].state, state) {
+                State::Vacant { next_free } => slab.free = next_free,
+                State::Occupied { .. } => unreachable!(),
+            }
+        }
+        match &mut slab.slots[index].state {
+            State::Occupied { value, .. } => value,
+            State::Vacant { .. } => unreachable!(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for VacantEntry<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VacantEntry").field(&self.key).finish()
+    }
+}
+
+/// An iterator over the keys and elements of a [`Slab`].
+pub struct Iter<'a, T> {
+    dense: slice::Iter<'a, u32>,
+    slots: &'a [Slot<T>],
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (Key, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = *self.dense.next()?;
+        let slot = &self.slots[index as usize];
+        match &slot.state {
+            State::Occupied { value, .. } => Some((
+                Key {
+                    index,
+                    generation: slot.generation,
+                },
+                value,
+            )),
+            State::Vacant { .. } => unreachable!(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.dense.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            dense: self.dense.clone(),
+            slots: self.slots,
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Iter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// The slots of a mutably borrowed [`Slab`], as a raw pointer, so that
+/// iterators can hand out mutable references to several elements at once.
+pub(crate) struct RawSlotsMut<'a, T> {
+    slots: *mut Slot<T>,
+    len: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> RawSlotsMut<'a, T> {
+    fn new(slots: &'a mut BoundedBuffer<Slot<T>>) -> Self {
+        Self {
+            len: slots.len(),
+            slots: slots.as_mut_ptr(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the key and element in slot `index`, if it is occupied.
+    ///
+    /// # Safety
+    ///
+    /// No other reference to the element in slot `index` obtained through
+    /// this view may still be alive.
+    pub(crate) unsafe fn get_mut(&self, index: u32) -> Option<(Key, &'a mut T)> {
+        if index as usize >= self.len {
+            return None;
+        }
+        // SAFETY: the slot is in bounds, the slab is mutably borrowed for
+        // `'a`, and the caller guarantees the slot isn't borrowed already.
+        let slot = unsafe { &mut *self.slots.add(index as usize) };
+        match &mut slot.state {
+            State::Occupied { value, .. } => Some((
+                Key {
+                    index,
+                    generation: slot.generation,
+                },
+                value,
+            )),
+            State::Vacant { .. } => None,
+        }
+    }
+}
+
+/// A mutable iterator over the keys and elements of a [`Slab`].
+pub struct IterMut<'a, T> {
+    dense: slice::Iter<'a, u32>,
+    slots: RawSlotsMut<'a, T>,
+}
+
+// It hands out `&mut T`s, like `slice::IterMut`.
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (Key, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = *self.dense.next()?;
+        // SAFETY: `dense` lists each occupied slot once, so no two items
+        // point into the same slot.
+        let item = unsafe { self.slots.get_mut(index) };
+        debug_assert!(item.is_some());
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.dense.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+impl<T> fmt::Debug for IterMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IterMut")
+            .field("remaining", &self.dense.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, rc::Rc};
+
+    use super::{Key, Slab};
+    use crate::core::test_util::xorshift;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut slab = Slab::new(3);
+        let a = slab.insert('a').unwrap();
+        let b = slab.insert('b').unwrap();
+        let c = slab.insert('c').unwrap();
+        assert_eq!(slab.insert('d').unwrap_err().element(), 'd');
+        assert_eq!(slab.len(), 3);
+        *slab.get_mut(b).unwrap() = 'B';
+        assert_eq!(slab.remove(b), Some('B'));
+        assert_eq!(slab.remove(b), None);
+        assert!(!slab.contains(b));
+        assert_eq!((slab[a], slab[c]), ('a', 'c'));
+        let d = slab.insert('d').unwrap();
+        assert_eq!((d.index(), d.generation()), (b.index(), 1));
+        assert_eq!(slab.get(b), None);
+        assert_eq!(slab.get_mut(b), None);
+        assert_eq!(slab.len(), 3);
+    }
+
+    #[test]
+    fn test_free_list_is_lifo() {
+        let mut slab = Slab::new(4);
+        let keys: Vec<Key> = (0..4).map(|i| slab.insert(i).unwrap()).collect();
+        slab.remove(keys[1]);
+        slab.remove(keys[3]);
+        assert_eq!(slab.insert(10).unwrap().index(), 3);
+        assert_eq!(slab.insert(11).unwrap().index(), 1);
+        assert!(slab.vacant_entry().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid slab key")]
+    fn test_index_stale_key_panics() {
+        let mut slab = Slab::new(1);
+        let key = slab.insert(1).unwrap();
+        slab.remove(key);
+        slab.insert(2).unwrap();
+        let _ = slab[key];
+    }
+
+    #[test]
+    fn test_vacant_entry() {
+        let mut slab = Slab::new(2);
+        let first = slab.vacant_entry().unwrap().key();
+        // Not inserting leaves the slab untouched.
+        assert!(slab.is_empty());
+        let entry = slab.vacant_entry().unwrap();
+        assert_eq!(entry.key(), first);
+        *entry.insert(5) += 1;
+        assert_eq!(slab[first], 6);
+
+        slab.remove(first);
+        let entry = slab.vacant_entry().unwrap();
+        let key = entry.key();
+        assert_eq!((key.index(), key.generation()), (0, 1));
+        entry.insert(7);
+        assert_eq!(slab.get(first), None);
+        assert_eq!(slab[key], 7);
+    }
+
+    #[test]
+    fn test_iteration_follows_removals() {
+        let mut slab = Slab::new(8);
+        let keys: Vec<Key> = (0..8).map(|i| slab.insert(i).unwrap()).collect();
+        for &k in &keys[..6] {
+            slab.remove(k);
+        }
+        let mut seen: Vec<_> = slab.iter().map(|(k, &v)| (k, v)).collect();
+        seen.sort();
+        assert_eq!(seen, [(keys[6], 6), (keys[7], 7)]);
+        assert_eq!(slab.iter().len(), 2);
+
+        for (_, v) in &mut slab {
+            *v *= 10;
+        }
+        let mut values: Vec<_> = slab.iter().map(|(_, &v)| v).collect();
+        values.sort();
+        assert_eq!(values, [60, 70]);
+        assert_eq!(format!("{:?}", Slab::<u8>::new(1)), "{}");
+    }
+
+    #[test]
+    fn test_retain_and_clear() {
+        let mut slab = Slab::new(10);
+        let keys: Vec<Key> = (0..10).map(|i| slab.insert(i).unwrap()).collect();
+        slab.retain(|key, v| {
+            *v += 100;
+            key.index() % 3 == 0
+        });
+        let mut values: Vec<_> = slab.iter().map(|(_, &v)| v).collect();
+        values.sort();
+        assert_eq!(values, [100, 103, 106, 109]);
+        assert_eq!(slab.get(keys[1]), None);
+        assert_eq!(slab[keys[3]], 103);
+
+        slab.clear();
+        assert!(slab.is_empty());
+        assert!(keys.iter().all(|&k| slab.get(k).is_none()));
+        for i in 0..10 {
+            let key = slab.insert(i).unwrap();
+            assert!(!keys.contains(&key));
+        }
+    }
+
+    #[test]
+    fn test_drops_elements() {
+        let token = Rc::new(());
+        let mut slab = Slab::new(4);
+        let keys: Vec<Key> = (0..4)
+            .map(|_| slab.insert(token.clone()).unwrap())
+            .collect();
+        drop(slab.remove(keys[0]));
+        slab.retain(|k, _| k != keys[1]);
+        assert_eq!(Rc::strong_count(&token), 3);
+        drop(slab);
+        assert_eq!(Rc::strong_count(&token), 1);
+    }
+
+    #[test]
+    fn test_exhausted_generation_retires_slot() {
+        let mut slab = Slab::new(2);
+        let key = slab.insert(0).unwrap();
+        slab.set_generation(0, u32::MAX);
+        let last = Key {
+            index: 0,
+            generation: u32::MAX,
+        };
+        assert_eq!(slab.remove(last), Some(0));
+        assert_eq!(slab.remove(key), None);
+        // The retired slot is skipped; only the untouched one is left.
+        assert_eq!(slab.remaining_capacity(), 1);
+        let next = slab.insert(1).unwrap();
+        assert_eq!(next.index(), 1);
+        assert!(slab.insert(2).is_err());
+        assert_eq!(slab.get(last), None);
+    }
+
+    #[test]
+    fn test_random_churn_rejects_stale_keys() {
+        let rounds = if cfg!(miri) { 500 } else { 200_000 };
+        let cap = 64;
+        let mut slab = Slab::new(cap);
+        let mut live: HashMap<Key, u64> = HashMap::new();
+        let mut dead: Vec<Key> = Vec::new();
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        for round in 0..rounds {
+            let r = xorshift(&mut state);
+            if !r.is_multiple_of(3) && live.len() < cap {
+                let key = slab.insert(round).unwrap();
+                assert!(!live.contains_key(&key));
+                live.insert(key, round);
+            } else if let Some(&key) = live.keys().nth(r as usize % live.len().max(1)) {
+                assert_eq!(slab.remove(key), live.remove(&key));
+                dead.push(key);
+            } else {
+                assert!(slab.is_empty());
+            }
+            if live.len() == cap {
+                assert!(slab.insert(0).is_err());
+            }
+            // Spot-check a few stale keys every round.
+            for _ in 0..3 {
+                if !dead.is_empty() {
+                    let key = dead[xorshift(&mut state) as usize % dead.len()];
+                    assert_eq!(slab.get(key), None);
+                    assert_eq!(slab.remove(key), None);
+                }
+            }
+            assert_eq!(slab.len(), live.len());
+        }
+        assert!(dead.iter().all(|&k| !slab.contains(k)));
+        let mut seen: Vec<_> = slab.iter().map(|(k, &v)| (k, v)).collect();
+        let mut expected: Vec<_> = live.into_iter().collect();
+        seen.sort();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+}
@@ -0,0 +1,476 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    ptr, slice,
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    error::{CapacityError, TryNewError},
+    inline_buf::InlineBuffer,
+};
+
+/// A bounded buffer that keeps up to `INLINE` elements inline and spills to
+/// the heap once it needs more.
+///
+/// The capacity is fixed at construction like a [`BoundedBuffer`]'s. Nothing
+/// is allocated until the buffer first grows past `INLINE` elements; it then
+/// moves its elements into one allocation of the full capacity and stays
+/// there, so it spills at most once and never reallocates. Removing
+/// elements does not move it back inline.
+///
+/// While inline, the elements live inside the value itself, so moving the
+/// buffer moves them, and spilling moves them to the heap. Once spilled,
+/// element addresses stay put for as long as the buffer lives, even if the
+/// buffer itself is moved.
+///
+/// ```
+/// use fixed_buf::SmallBoundedBuffer;
+///
+/// let mut buf = SmallBoundedBuffer::<u32, 2>::new(4);
+/// assert!(buf.try_push(1));
+/// assert!(buf.try_push(2));
+/// assert!(!buf.spilled());
+/// assert!(buf.try_push(3));
+/// assert!(buf.spilled());
+/// assert!(buf.try_push(4));
+/// assert!(!buf.try_push(5));
+/// assert_eq!(buf.as_slice(), [1, 2, 3, 4]);
+/// ```
+pub struct SmallBoundedBuffer<T, const INLINE: usize> {
+    repr: Repr<T, INLINE>,
+    cap: usize,
+}
+
+enum Repr<T, const INLINE: usize> {
+    Inline(InlineBuffer<T, INLINE>),
+    Heap(BoundedBuffer<T>),
+}
+
+impl<T, const INLINE: usize> SmallBoundedBuffer<T, INLINE> {
+    /// Creates an empty buffer that holds at most `cap` elements, without
+    /// allocating.
+    pub const fn new(cap: usize) -> Self {
+        Self {
+            repr: Repr::Inline(InlineBuffer::new()),
+            cap,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.cap
+    }
+
+    /// Returns whether the elements have moved to the heap.
+    pub fn spilled(&self) -> bool {
+        matches!(self.repr, Repr::Heap(_))
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        match &self.repr {
+            Repr::Inline(buf) => buf,
+            Repr::Heap(buf) => buf,
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match &mut self.repr {
+            Repr::Inline(buf) => buf,
+            Repr::Heap(buf) => buf,
+        }
+    }
+
+    /// Moves the elements to a heap allocation of the full capacity now
+    /// rather than when the inline storage runs out. Does nothing if they
+    /// are already there.
+    ///
+    /// The elements are moved with one bulk copy once the allocation has
+    /// succeeded; on error the buffer is left untouched.
+    pub fn try_spill(&mut self) -> Result<(), TryNewError> {
+        let Repr::Inline(inline) = &mut self.repr else {
+            return Ok(());
+        };
+        let mut heap = BoundedBuffer::try_new(self.cap)?;
+        let len = inline.len();
+        unsafe {
+            ptr::copy_nonoverlapping(inline.as_ptr(), heap.as_mut_ptr(), len);
+            inline.set_len(0);
+            heap.set_len(len);
+        }
+        self.repr = Repr::Heap(heap);
+        Ok(())
+    }
+
+    // Makes room for `additional` more elements, spilling if the inline
+    // storage cannot hold them. Returns whether they fit in the capacity.
+    #[track_caller]
+    fn reserve(&mut self, additional: usize) -> bool {
+        let len = self.len();
+        if additional > self.cap - len {
+            return false;
+        }
+        if len + additional > INLINE {
+            if let Err(err) = self.try_spill() {
+                panic!("{err}");
+            }
+        }
+        true
+    }
+
+    /// Appends `elem` if there is room, spilling to the heap first if the
+    /// inline storage is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if spilling is needed and the allocation fails, like
+    /// [`BoundedBuffer::new`]. The buffer keeps its elements and `elem` is
+    /// dropped.
+    #[track_caller]
+    pub fn try_push(&mut self, elem: T) -> bool {
+        if !self.reserve(1) {
+            return false;
+        }
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.try_push(elem),
+            Repr::Heap(buf) => buf.try_push(elem),
+        }
+    }
+
+    /// Inserts `elem` at `index` if there is room and `index <= len()`,
+    /// shifting the elements after it up. Spills like
+    /// [`try_push`](Self::try_push).
+    #[track_caller]
+    pub fn try_insert(&mut self, index: usize, elem: T) -> bool {
+        if index > self.len() || !self.reserve(1) {
+            return false;
+        }
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.try_insert(index, elem),
+            Repr::Heap(buf) => buf.try_insert(index, elem),
+        }
+    }
+
+    /// Appends clones of all of `src`, or fails without writing anything if
+    /// they do not all fit. Spills like [`try_push`](Self::try_push).
+    #[track_caller]
+    pub fn try_extend_from_slice(&mut self, src: &[T]) -> Result<(), CapacityError>
+    where
+        T: Clone,
+    {
+        if !self.reserve(src.len()) {
+            return Err(CapacityError::new(()));
+        }
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.try_extend_from_slice(src),
+            Repr::Heap(buf) => buf.try_extend_from_slice(src),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.pop(),
+            Repr::Heap(buf) => buf.pop(),
+        }
+    }
+
+    #[track_caller]
+    pub fn remove(&mut self, index: usize) -> T {
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.remove(index),
+            Repr::Heap(buf) => buf.remove(index),
+        }
+    }
+
+    pub fn try_remove(&mut self, index: usize) -> Option<T> {
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.try_remove(index),
+            Repr::Heap(buf) => buf.try_remove(index),
+        }
+    }
+
+    /// Removes and returns the element at `index` in O(1) by moving the last
+    /// element into its place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len()`.
+    #[track_caller]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.swap_remove(index),
+            Repr::Heap(buf) => buf.swap_remove(index),
+        }
+    }
+
+    /// Drops the elements from `new_len` on. Does nothing if `new_len` is not
+    /// less than the length.
+    pub fn truncate(&mut self, new_len: usize) {
+        match &mut self.repr {
+            Repr::Inline(buf) => buf.truncate(new_len),
+            Repr::Heap(buf) => buf.truncate(new_len),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Converts into a [`BoundedBuffer`] of the same capacity, spilling to
+    /// the heap first if the elements are still inline.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn into_buffer(mut self) -> BoundedBuffer<T> {
+        if let Err(err) = self.try_spill() {
+            panic!("{err}");
+        }
+        match self.repr {
+            Repr::Heap(buf) => buf,
+            Repr::Inline(_) => unreachable!(),
+        }
+    }
+}
+
+impl<T: Clone, const INLINE: usize> Clone for SmallBoundedBuffer<T, INLINE> {
+    /// Clones inline if the elements fit there, even if `self` has spilled.
+    fn clone(&self) -> Self {
+        let mut buf = Self::new(self.cap);
+        let pushed = buf.try_extend_from_slice(self);
+        debug_assert!(pushed.is_ok());
+        buf
+    }
+}
+
+/// Formats the initialized elements as a list, the same way `Vec` does. The
+/// capacity is not shown.
+impl<T: fmt::Debug, const INLINE: usize> fmt::Debug for SmallBoundedBuffer<T, INLINE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+impl<T, U, const INLINE: usize, const M: usize> PartialEq<SmallBoundedBuffer<U, M>>
+    for SmallBoundedBuffer<T, INLINE>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &SmallBoundedBuffer<U, M>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, const INLINE: usize> PartialEq<[U]> for SmallBoundedBuffer<T, INLINE> {
+    fn eq(&self, other: &[U]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: PartialEq<U>, U, const INLINE: usize, const N: usize> PartialEq<[U; N]>
+    for SmallBoundedBuffer<T, INLINE>
+{
+    fn eq(&self, other: &[U; N]) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<T: Eq, const INLINE: usize> Eq for SmallBoundedBuffer<T, INLINE> {}
+
+/// Appends every element of the iterator.
+///
+/// # Panics
+///
+/// Panics if the buffer fills up before the iterator is exhausted, keeping
+/// the elements that fit.
+impl<T, const INLINE: usize> Extend<T> for SmallBoundedBuffer<T, INLINE> {
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            if !self.try_push(elem) {
+                panic!("extended past capacity (is {})", self.cap);
+            }
+        }
+    }
+}
+
+impl<T, const INLINE: usize> Deref for SmallBoundedBuffer<T, INLINE> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const INLINE: usize> DerefMut for SmallBoundedBuffer<T, INLINE> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const INLINE: usize> AsRef<[T]> for SmallBoundedBuffer<T, INLINE> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const INLINE: usize> AsMut<[T]> for SmallBoundedBuffer<T, INLINE> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<'a, T, const INLINE: usize> IntoIterator for &'a SmallBoundedBuffer<T, INLINE> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, const INLINE: usize> IntoIterator for &'a mut SmallBoundedBuffer<T, INLINE> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SmallBoundedBuffer;
+    use crate::core::error::{CapacityError, TryNewError};
+    use crate::core::test_util::{DropCount, Drops};
+
+    #[test]
+    fn test_small_spill_boundary() {
+        let mut buf = SmallBoundedBuffer::<u32, 3>::new(6);
+        for i in 0..3 {
+            assert!(buf.try_push(i));
+        }
+        assert!(!buf.spilled());
+        assert_eq!(buf.len(), 3);
+        assert!(buf.try_insert(0, 9));
+        assert!(buf.spilled());
+        assert_eq!(buf, [9, 0, 1, 2]);
+
+        // Once spilled, it stays on the heap and addresses stay put.
+        let first = buf.as_ptr();
+        buf.clear();
+        assert!(buf.spilled());
+        buf.extend([1, 2, 3, 4, 5, 6]);
+        assert_eq!(buf.as_ptr(), first);
+        let moved = buf;
+        assert_eq!(moved.as_ptr(), first);
+        assert!(moved.is_full());
+
+        let mut buf = SmallBoundedBuffer::<u32, 3>::new(6);
+        buf.try_extend_from_slice(&[1, 2, 3]).unwrap();
+        assert!(!buf.spilled());
+        assert_eq!(
+            buf.try_extend_from_slice(&[4, 5, 6, 7]),
+            Err(CapacityError::new(()))
+        );
+        // A failed extend does not spill.
+        assert!(!buf.spilled());
+        buf.try_extend_from_slice(&[4, 5, 6]).unwrap();
+        assert!(buf.spilled());
+        assert!(!buf.try_push(7));
+        assert_eq!(buf.remove(0), 1);
+        assert_eq!(buf.swap_remove(0), 2);
+        assert_eq!(buf.pop(), Some(5));
+        assert_eq!(buf.try_remove(5), None);
+        assert_eq!(buf, [6, 3, 4]);
+        assert_eq!(buf.into_buffer().capacity(), 6);
+    }
+
+    #[test]
+    fn test_small_capacity_below_inline() {
+        let mut buf = SmallBoundedBuffer::<u8, 8>::new(2);
+        assert!(buf.try_push(1));
+        assert!(buf.try_push(2));
+        assert!(!buf.try_push(3));
+        assert!(!buf.spilled());
+        let buf = buf.into_buffer();
+        assert_eq!((buf.len(), buf.capacity()), (2, 2));
+
+        let mut buf = SmallBoundedBuffer::<u8, 0>::new(1);
+        assert!(buf.try_push(1));
+        assert!(buf.spilled());
+    }
+
+    #[test]
+    fn test_small_drop_counts() {
+        let drops = Drops::default();
+        let mut buf = SmallBoundedBuffer::<_, 2>::new(4);
+        for _ in 0..2 {
+            assert!(buf.try_push(DropCount(drops.clone())));
+        }
+        // The spill moves the elements without dropping or duplicating them.
+        assert!(buf.try_push(DropCount(drops.clone())));
+        assert!(buf.spilled());
+        assert_eq!(drops.get(), 0);
+        assert!(buf.try_push(DropCount(drops.clone())));
+        assert!(!buf.try_push(DropCount(drops.clone())));
+        assert_eq!(drops.get(), 1);
+        let clone = buf.clone();
+        drop(buf.pop());
+        buf.truncate(1);
+        assert_eq!(drops.get(), 4);
+        drop(buf);
+        assert_eq!(drops.get(), 5);
+        drop(clone);
+        assert_eq!(drops.get(), 9);
+
+        // Still inline when dropped.
+        let mut buf = SmallBoundedBuffer::<_, 4>::new(8);
+        buf.extend((0..3).map(|_| DropCount(drops.clone())));
+        drop(buf);
+        assert_eq!(drops.get(), 12);
+    }
+
+    #[test]
+    fn test_small_failed_spill_keeps_elements() {
+        let mut buf = SmallBoundedBuffer::<u64, 2>::new(usize::MAX);
+        assert!(buf.try_push(1));
+        assert!(buf.try_push(2));
+        assert_eq!(buf.try_spill(), Err(TryNewError::CapacityOverflow));
+        assert!(!buf.spilled());
+        assert_eq!(buf, [1, 2]);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| buf.try_push(3)));
+        assert!(result.is_err());
+        assert_eq!(buf, [1, 2]);
+    }
+
+    #[test]
+    fn test_small_slices_and_clone() {
+        let mut buf = SmallBoundedBuffer::<String, 1>::new(3);
+        buf.extend(["b", "a", "c"].map(str::to_owned));
+        buf.sort();
+        for s in &mut buf {
+            s.push('!');
+        }
+        assert_eq!(format!("{buf:?}"), r#"["a!", "b!", "c!"]"#);
+        buf.truncate(1);
+        let clone = buf.clone();
+        assert!(buf.spilled());
+        assert!(!clone.spilled());
+        assert_eq!(clone, buf);
+        assert_eq!((&clone).into_iter().count(), 1);
+    }
+}
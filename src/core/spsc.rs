@@ -0,0 +1,434 @@
+//! A wait-free single-producer single-consumer queue over one fixed
+//! allocation.
+//!
+//! ```
+//! use std::thread;
+//!
+//! let (mut tx, mut rx) = fixed_buf::spsc::channel(4);
+//! let producer = thread::spawn(move || {
+//!     for i in 0..100 {
+//!         let mut item = i;
+//!         while let Err(err) = tx.try_push(item) {
+//!             item = err.element();
+//!             thread::yield_now();
+//!         }
+//!     }
+//! });
+//! let mut received = Vec::new();
+//! while received.len() < 100 {
+//!     match rx.try_pop() {
+//!         Some(i) => received.push(i),
+//!         None => thread::yield_now(),
+//!     }
+//! }
+//! producer.join().unwrap();
+//! assert!(received.iter().copied().eq(0..100));
+//! ```
+
+use std::{
+    cell::Cell,
+    fmt,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    cache_padded::CachePadded,
+    error::{CapacityError, TryNewError},
+};
+
+/// Creates a queue that holds at most `cap` elements and returns its two
+/// ends.
+///
+/// # Panics
+///
+/// Panics if the allocation fails, like [`BoundedBuffer::new`].
+#[track_caller]
+pub fn channel<T>(cap: usize) -> (Producer<T>, Consumer<T>) {
+    match try_channel(cap) {
+        Ok(ends) => ends,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+/// Like [`channel`], but returns an error instead of panicking.
+pub fn try_channel<T>(cap: usize) -> Result<(Producer<T>, Consumer<T>), TryNewError> {
+    // One slot always stays empty so that a full queue and an empty one
+    // have different positions.
+    let slots = cap.checked_add(1).ok_or(TryNewError::CapacityOverflow)?;
+    let shared = Arc::new(Shared {
+        head: CachePadded(AtomicUsize::new(0)),
+        tail: CachePadded(AtomicUsize::new(0)),
+        buf: BoundedBuffer::try_new(slots)?,
+    });
+    let producer = Producer {
+        shared: shared.clone(),
+        tail: 0,
+        cached_head: 0,
+        _not_sync: PhantomData,
+    };
+    let consumer = Consumer {
+        shared,
+        head: 0,
+        cached_tail: 0,
+        _not_sync: PhantomData,
+    };
+    Ok((producer, consumer))
+}
+
+struct Shared<T> {
+    // The next slot to pop, written only by the consumer.
+    head: CachePadded<AtomicUsize>,
+    // The next slot to push, written only by the producer.
+    tail: CachePadded<AtomicUsize>,
+    // Owns the allocation only; its length stays zero and the slots from
+    // `head` up to `tail`, wrapping, are the initialized ones.
+    buf: BoundedBuffer<T>,
+}
+
+// Elements are moved from the producer's thread to the consumer's and never
+// shared, so `T: Send` is enough for both.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn slots(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    fn next(&self, index: usize) -> usize {
+        if index + 1 == self.slots() {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    fn len(&self, head: usize, tail: usize) -> usize {
+        if tail >= head {
+            tail - head
+        } else {
+            self.slots() - head + tail
+        }
+    }
+
+    unsafe fn slot(&self, index: usize) -> *mut T {
+        self.buf.slot(index)
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        // Both ends are gone, so nothing else can touch the indices.
+        let mut head = *self.head.get_mut();
+        let tail = *self.tail.get_mut();
+        while head != tail {
+            unsafe { self.slot(head).drop_in_place() };
+            head = self.next(head);
+        }
+    }
+}
+
+/// The sending end of an SPSC queue, created by [`channel`].
+///
+/// It can be moved to another thread but not shared or cloned; there is only
+/// ever one producer.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<fixed_buf::spsc::Producer<u8>>();
+/// ```
+///
+/// ```compile_fail
+/// fn assert_clone<T: Clone>() {}
+/// assert_clone::<fixed_buf::spsc::Producer<u8>>();
+/// ```
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+    tail: usize,
+    // The consumer's position as last seen. It only moves forward, so the
+    // producer can trust it until the queue looks full.
+    cached_head: usize,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> Producer<T> {
+    /// Appends `elem`, or gives it back if the queue is full. Never blocks.
+    pub fn try_push(&mut self, elem: T) -> Result<(), CapacityError<T>> {
+        let next = self.shared.next(self.tail);
+        if next == self.cached_head {
+            self.cached_head = self.shared.head.load(Ordering::Acquire);
+            if next == self.cached_head {
+                return Err(CapacityError::new(elem));
+            }
+        }
+        // SAFETY: the slot at `tail` is outside the consumer's range until
+        // the store below publishes it.
+        unsafe { self.shared.slot(self.tail).write(elem) };
+        self.shared.tail.store(next, Ordering::Release);
+        self.tail = next;
+        Ok(())
+    }
+
+    /// Returns the number of queued elements. The consumer may be popping
+    /// concurrently, so this is an upper bound.
+    pub fn len(&self) -> usize {
+        let head = self.shared.head.load(Ordering::Acquire);
+        self.shared.len(head, self.tail)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.slots() - 1
+    }
+
+    /// Returns `true` if the consumer has been dropped.
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+impl<T> fmt::Debug for Producer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Producer")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+/// The receiving end of an SPSC queue, created by [`channel`].
+///
+/// Like [`Producer`], it can be moved to another thread but not shared or
+/// cloned.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<fixed_buf::spsc::Consumer<u8>>();
+/// ```
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+    head: usize,
+    // The producer's position as last seen; see `Producer::cached_head`.
+    cached_tail: usize,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> Consumer<T> {
+    /// Removes and returns the oldest element, or `None` if the queue is
+    /// empty. Never blocks.
+    pub fn try_pop(&mut self) -> Option<T> {
+        if self.head == self.cached_tail {
+            self.cached_tail = self.shared.tail.load(Ordering::Acquire);
+            if self.head == self.cached_tail {
+                return None;
+            }
+        }
+        // SAFETY: the producer published the slot at `head` and won't reuse
+        // it until the store below hands it back.
+        let elem = unsafe { self.shared.slot(self.head).read() };
+        let next = self.shared.next(self.head);
+        self.shared.head.store(next, Ordering::Release);
+        self.head = next;
+        Some(elem)
+    }
+
+    /// Returns the number of queued elements. The producer may be pushing
+    /// concurrently, so this is a lower bound.
+    pub fn len(&self) -> usize {
+        let tail = self.shared.tail.load(Ordering::Acquire);
+        self.shared.len(self.head, tail)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.slots() - 1
+    }
+
+    /// Returns `true` if the producer has been dropped. Elements it pushed
+    /// before that can still be popped.
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+impl<T> fmt::Debug for Consumer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Consumer")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::channel;
+    use crate::core::error::TryNewError;
+    use crate::core::test_util::{DropCount, Drops};
+
+    #[test]
+    fn test_push_pop_wraparound() {
+        let (mut tx, mut rx) = channel(3);
+        assert_eq!(tx.capacity(), 3);
+        assert_eq!(rx.try_pop(), None);
+        for round in 0..10 {
+            for i in 0..3 {
+                tx.try_push(round * 3 + i).unwrap();
+            }
+            assert_eq!(tx.try_push(99).unwrap_err().element(), 99);
+            assert_eq!((tx.len(), rx.len()), (3, 3));
+            for i in 0..3 {
+                assert_eq!(rx.try_pop(), Some(round * 3 + i));
+            }
+            assert!(rx.is_empty());
+        }
+        // Interleaved, keeping the queue partly full across the wrap.
+        tx.try_push(0).unwrap();
+        for i in 1..20 {
+            tx.try_push(i).unwrap();
+            assert_eq!(rx.try_pop(), Some(i - 1));
+        }
+        assert_eq!(rx.len(), 1);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let (mut tx, mut rx) = channel::<u8>(0);
+        assert!(tx.try_push(1).is_err());
+        assert_eq!(rx.try_pop(), None);
+        assert!(super::try_channel::<u8>(usize::MAX).is_err());
+        assert_eq!(
+            super::try_channel::<u64>(usize::MAX / 2).unwrap_err(),
+            TryNewError::CapacityOverflow
+        );
+    }
+
+    #[test]
+    fn test_abandoned() {
+        let (mut tx, mut rx) = channel(2);
+        tx.try_push(1).unwrap();
+        assert!(!rx.is_abandoned());
+        drop(tx);
+        assert!(rx.is_abandoned());
+        assert_eq!(rx.try_pop(), Some(1));
+
+        let (tx, rx) = channel::<u8>(2);
+        drop(rx);
+        assert!(tx.is_abandoned());
+    }
+
+    #[test]
+    fn test_drop_remaining_either_side_last() {
+        for consumer_last in [false, true] {
+            let drops = Drops::default();
+            let (mut tx, mut rx) = channel(4);
+            for _ in 0..4 {
+                assert!(tx.try_push(DropCount(drops.clone())).is_ok());
+            }
+            drop(rx.try_pop());
+            // Wrap the positions so the remaining run straddles the end.
+            assert!(tx.try_push(DropCount(drops.clone())).is_ok());
+            assert_eq!(drops.get(), 1);
+            if consumer_last {
+                drop(tx);
+                assert_eq!(drops.get(), 1);
+                drop(rx);
+            } else {
+                drop(rx);
+                assert_eq!(drops.get(), 1);
+                drop(tx);
+            }
+            assert_eq!(drops.get(), 5);
+        }
+    }
+
+    #[test]
+    fn test_stress_ordered_transfer() {
+        let n = if cfg!(miri) { 500 } else { 1_000_000 };
+        for cap in [1, 7, 64] {
+            let (mut tx, mut rx) = channel(cap);
+            let producer = thread::spawn(move || {
+                for i in 0..n {
+                    let mut item = i;
+                    while let Err(err) = tx.try_push(item) {
+                        item = err.element();
+                        thread::yield_now();
+                    }
+                }
+            });
+            let mut expected = 0;
+            while expected < n {
+                match rx.try_pop() {
+                    Some(i) => {
+                        assert_eq!(i, expected);
+                        expected += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+            producer.join().unwrap();
+            assert_eq!(rx.try_pop(), None);
+        }
+    }
+
+    #[test]
+    fn test_stress_drop_across_threads() {
+        let n = if cfg!(miri) { 100 } else { 100_000 };
+        let drops = Drops::default();
+        let (mut tx, mut rx) = channel(16);
+        let producer = {
+            let drops = drops.clone();
+            thread::spawn(move || {
+                for _ in 0..n {
+                    let mut item = DropCount(drops.clone());
+                    while let Err(err) = tx.try_push(item) {
+                        item = err.element();
+                        if tx.is_abandoned() {
+                            return;
+                        }
+                        thread::yield_now();
+                    }
+                }
+            })
+        };
+        // Take half, then walk away with the rest still queued or in flight.
+        let mut taken = 0;
+        while taken < n / 2 {
+            if rx.try_pop().is_some() {
+                taken += 1;
+            } else {
+                thread::yield_now();
+            }
+        }
+        drop(rx);
+        producer.join().unwrap();
+        // Every element the producer created was dropped exactly once: the
+        // popped ones, the leftover queued ones and the one it gave up on.
+        let created = drops.get();
+        assert!(created >= n / 2 && created <= n);
+        assert_eq!(drops.handles(), 1);
+    }
+
+    #[test]
+    fn test_zero_sized() {
+        let (mut tx, mut rx) = channel(2);
+        tx.try_push(()).unwrap();
+        tx.try_push(()).unwrap();
+        assert!(tx.try_push(()).is_err());
+        assert_eq!(rx.try_pop(), Some(()));
+        assert_eq!(rx.len(), 1);
+    }
+}
@@ -0,0 +1,527 @@
+//! A blocking multi-producer single-consumer channel with a capacity fixed
+//! at construction.
+//!
+//! Elements are queued in a [`BoundedRing`] behind a mutex, with one condvar
+//! for waiting senders and one for the waiting receiver. The ring is
+//! allocated once up front, so sending and receiving never allocate.
+//!
+//! ```
+//! use std::thread;
+//! use fixed_buf::sync_channel::sync_channel_fixed;
+//!
+//! let (tx, rx) = sync_channel_fixed(2);
+//! let producer = thread::spawn(move || {
+//!     for i in 0..10 {
+//!         tx.send(i).unwrap();
+//!     }
+//! });
+//! // Dropping the only sender ends the iteration once the queue is drained.
+//! let received: Vec<_> = rx.iter().collect();
+//! producer.join().unwrap();
+//! assert!(received.into_iter().eq(0..10));
+//! ```
+
+use std::{
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::core::{
+    bounded_ring::BoundedRing,
+    error::{
+        RecvError, RecvTimeoutError, SendError, SendTimeoutError, TryNewError, TryRecvError,
+        TrySendError,
+    },
+};
+
+/// Creates a channel that queues at most `cap` elements and returns its two
+/// halves. The [`Sender`] can be cloned to send from several threads.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero or the allocation fails, like
+/// [`BoundedBuffer::new`](crate::BoundedBuffer::new).
+#[track_caller]
+pub fn sync_channel_fixed<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    match try_sync_channel_fixed(cap) {
+        Ok(halves) => halves,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+/// Like [`sync_channel_fixed`], but returns an error instead of panicking
+/// if the allocation fails.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero.
+#[track_caller]
+pub fn try_sync_channel_fixed<T>(cap: usize) -> Result<(Sender<T>, Receiver<T>), TryNewError> {
+    assert!(cap > 0, "channel capacity must be positive");
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            ring: BoundedRing::try_new(cap)?,
+            senders: 1,
+            receiver: true,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+    });
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+    Ok((sender, Receiver { shared }))
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+struct State<T> {
+    ring: BoundedRing<T>,
+    senders: usize,
+    receiver: bool,
+}
+
+impl<T> Shared<T> {
+    // No user code runs while the lock is held, so a panic elsewhere in a
+    // thread holding it can't leave the state half-updated.
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn len(&self) -> usize {
+        self.lock().ring.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.lock().ring.capacity()
+    }
+}
+
+// `None` stands for a deadline too far away to represent, which is as good
+// as waiting forever.
+fn deadline(timeout: Duration) -> Option<Instant> {
+    Instant::now().checked_add(timeout)
+}
+
+// Waits on `cond` until `deadline`, returning `None` once it has passed.
+fn wait_until<'a, T>(
+    cond: &Condvar,
+    guard: MutexGuard<'a, State<T>>,
+    deadline: Option<Instant>,
+) -> Option<MutexGuard<'a, State<T>>> {
+    let Some(deadline) = deadline else {
+        return Some(cond.wait(guard).unwrap_or_else(PoisonError::into_inner));
+    };
+    let now = Instant::now();
+    if now >= deadline {
+        return None;
+    }
+    let (guard, _) = cond
+        .wait_timeout(guard, deadline - now)
+        .unwrap_or_else(PoisonError::into_inner);
+    Some(guard)
+}
+
+/// The sending half of a channel, created by [`sync_channel_fixed`].
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends `elem`, blocking while the channel is full. Fails with the
+    /// element if the receiver is dropped, even while blocked.
+    pub fn send(&self, elem: T) -> Result<(), SendError<T>> {
+        match self.send_until(elem, None) {
+            Ok(()) => Ok(()),
+            Err(SendTimeoutError::Disconnected(elem)) => Err(SendError(elem)),
+            Err(SendTimeoutError::Timeout(_)) => unreachable!(),
+        }
+    }
+
+    /// Sends `elem` if there is room right now.
+    pub fn try_send(&self, elem: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.lock();
+        if !state.receiver {
+            return Err(TrySendError::Disconnected(elem));
+        }
+        state
+            .ring
+            .try_push_back(elem)
+            .map_err(|err| TrySendError::Full(err.element()))?;
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Like [`send`](Self::send), but gives up once `timeout` has passed.
+    pub fn send_timeout(&self, elem: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(elem, deadline(timeout))
+    }
+
+    fn send_until(&self, elem: T, deadline: Option<Instant>) -> Result<(), SendTimeoutError<T>> {
+        let mut state = self.shared.lock();
+        let mut elem = elem;
+        loop {
+            if !state.receiver {
+                return Err(SendTimeoutError::Disconnected(elem));
+            }
+            match state.ring.try_push_back(elem) {
+                Ok(()) => break,
+                Err(err) => elem = err.element(),
+            }
+            state = match wait_until(&self.shared.not_full, state, deadline) {
+                Some(state) => state,
+                None => return Err(SendTimeoutError::Timeout(elem)),
+            };
+        }
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Returns the number of queued elements.
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a channel, created by [`sync_channel_fixed`].
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the oldest element, blocking while the channel is empty.
+    /// Fails once every sender is dropped and the queue is drained, even
+    /// while blocked.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    /// Receives the oldest element if one is queued right now.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+        match state.ring.pop_front() {
+            Some(elem) => {
+                drop(state);
+                self.shared.not_full.notify_one();
+                Ok(elem)
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Like [`recv`](Self::recv), but gives up once `timeout` has passed.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.recv_until(deadline(timeout))
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(elem) = state.ring.pop_front() {
+                drop(state);
+                self.shared.not_full.notify_one();
+                return Ok(elem);
+            }
+            if state.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            state = match wait_until(&self.shared.not_empty, state, deadline) {
+                Some(state) => state,
+                None => return Err(RecvTimeoutError::Timeout),
+            };
+        }
+    }
+
+    /// Returns an iterator that blocks for each element and ends once the
+    /// channel is disconnected and drained.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns an iterator over the elements queued right now, without
+    /// blocking.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+
+    /// Returns the number of queued elements.
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver = false;
+        self.shared.not_full.notify_all();
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// A blocking iterator over a [`Receiver`], created by [`Receiver::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+/// A non-blocking iterator over a [`Receiver`], created by
+/// [`Receiver::try_iter`].
+#[derive(Debug)]
+pub struct TryIter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::sync_channel_fixed;
+    use crate::core::error::{
+        RecvError, RecvTimeoutError, SendError, SendTimeoutError, TryRecvError, TrySendError,
+    };
+    use crate::core::test_util::{DropCount, Drops};
+
+    // Generous upper bound on oversleeping, so loaded CI machines don't
+    // flake.
+    const SLACK: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn test_try_send_recv() {
+        let (tx, rx) = sync_channel_fixed(2);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.try_send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!((tx.len(), rx.capacity()), (2, 2));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+        assert!(rx.is_empty());
+
+        drop(rx);
+        assert_eq!(tx.try_send(4), Err(TrySendError::Disconnected(4)));
+        assert_eq!(tx.send(5), Err(SendError(5)));
+    }
+
+    #[test]
+    fn test_drain_after_senders_drop() {
+        let (tx, rx) = sync_channel_fixed(4);
+        let tx2 = tx.clone();
+        tx.send(1).unwrap();
+        drop(tx);
+        tx2.send(2).unwrap();
+        assert_eq!(rx.recv(), Ok(1));
+        drop(tx2);
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(60)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_timeout_precision() {
+        let timeout = Duration::from_millis(50);
+        let (tx, rx) = sync_channel_fixed(1);
+
+        let start = Instant::now();
+        assert_eq!(rx.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+        let waited = start.elapsed();
+        assert!(waited >= timeout, "returned early after {waited:?}");
+        assert!(waited < timeout + SLACK, "overslept for {waited:?}");
+
+        tx.send(1).unwrap();
+        let start = Instant::now();
+        assert_eq!(
+            tx.send_timeout(2, timeout),
+            Err(SendTimeoutError::Timeout(2))
+        );
+        let waited = start.elapsed();
+        assert!(waited >= timeout, "returned early after {waited:?}");
+        assert!(waited < timeout + SLACK, "overslept for {waited:?}");
+
+        // A zero timeout still succeeds when no wait is needed.
+        assert_eq!(rx.recv_timeout(Duration::ZERO), Ok(1));
+        assert_eq!(tx.send_timeout(3, Duration::ZERO), Ok(()));
+        // So does one too large to turn into a deadline.
+        assert_eq!(rx.recv_timeout(Duration::MAX), Ok(3));
+    }
+
+    #[test]
+    fn test_timeout_woken_by_send() {
+        let (tx, rx) = sync_channel_fixed(1);
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.send(7).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(60)), Ok(7));
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_disconnect_while_blocked() {
+        // A receiver blocked on an empty channel wakes when the last sender
+        // goes away.
+        let (tx, rx) = sync_channel_fixed::<u32>(1);
+        let tx2 = tx.clone();
+        let receiver = thread::spawn(move || rx.recv());
+        thread::sleep(Duration::from_millis(20));
+        drop(tx);
+        thread::sleep(Duration::from_millis(20));
+        drop(tx2);
+        assert_eq!(receiver.join().unwrap(), Err(RecvError));
+
+        // Senders blocked on a full channel wake when the receiver goes away
+        // and get their elements back.
+        let (tx, rx) = sync_channel_fixed(1);
+        tx.send(0).unwrap();
+        let senders: Vec<_> = (1..=3)
+            .map(|i| {
+                let tx = tx.clone();
+                thread::spawn(move || tx.send(i))
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(20));
+        drop(rx);
+        let mut returned: Vec<_> = senders
+            .into_iter()
+            .map(|s| s.join().unwrap().unwrap_err().into_inner())
+            .collect();
+        returned.sort();
+        assert_eq!(returned, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_queued_elements_dropped_once() {
+        let drops = Drops::default();
+        let (tx, rx) = sync_channel_fixed(4);
+        for _ in 0..3 {
+            assert!(tx.send(DropCount(drops.clone())).is_ok());
+        }
+        drop(rx.recv());
+        drop(rx);
+        let err = tx.send(DropCount(drops.clone())).unwrap_err();
+        assert_eq!(drops.get(), 1);
+        drop(err);
+        drop(tx);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_throughput_smoke() {
+        let (senders, per_sender) = (4, if cfg!(miri) { 50 } else { 50_000 });
+        let (tx, rx) = sync_channel_fixed(8);
+        let handles: Vec<_> = (0..senders)
+            .map(|s| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..per_sender {
+                        tx.send(s * per_sender + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let mut count = 0;
+        let mut sum = 0u64;
+        for item in &rx {
+            count += 1;
+            sum += item as u64;
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let n = (senders * per_sender) as u64;
+        assert_eq!(count, senders * per_sender);
+        assert_eq!(sum, n * (n - 1) / 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "channel capacity must be positive")]
+    fn test_zero_capacity() {
+        sync_channel_fixed::<u8>(0);
+    }
+}
@@ -0,0 +1,40 @@
+//! Fixtures shared by the unit tests.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Small deterministic generator for randomized tests.
+pub(crate) fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Counts how many of the [`DropCount`]s sharing it have been dropped. It is
+/// atomic so that multithreaded tests can use it too.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Drops(Arc<AtomicUsize>);
+
+impl Drops {
+    pub(crate) fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many handles to the counter are alive, this one included.
+    pub(crate) fn handles(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+}
+
+/// An element that bumps its [`Drops`] counter when it is dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct DropCount(pub(crate) Drops);
+
+impl Drop for DropCount {
+    fn drop(&mut self) {
+        self.0 .0.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{cmp::Ordering, fmt, iter::FusedIterator, slice};
+
+use crate::core::{bounded_buf::BoundedBuffer, bounded_heap::BoundedHeap, error::TryNewError};
+
+/// Keeps the `k` greatest items offered to it, in memory allocated once at
+/// construction.
+///
+/// The items are kept in a [`BoundedHeap`] ordered by `(Reverse(key), seq)`,
+/// so the least of them, the one the next better item displaces, is always
+/// at the root. Offering an item that doesn't make the cut, which is what
+/// most items in a long stream do, costs one comparison against the root;
+/// only an accepted item pays the O(log k) comparisons of sifting it into
+/// place. Over `n` items in random order, about `k · ln(n / k)` are
+/// accepted, so the total is close to `n` comparisons.
+///
+/// # Ties
+///
+/// Among items that compare equal, the ones offered first are kept and rank
+/// first. An item equal to the least one kept is rejected, and of several
+/// equal items the latest one is displaced first. The result is therefore
+/// exactly the first `k` items of a stable descending sort of everything
+/// offered.
+///
+/// ```
+/// use fixed_buf::TopK;
+///
+/// let mut top = TopK::new(3);
+/// for x in [5, 1, 9, 3, 7, 9] {
+///     top.offer(x);
+/// }
+/// assert_eq!(top.min(), Some(&7));
+/// assert_eq!(top.into_sorted_descending().as_slice(), [9, 9, 7]);
+/// ```
+pub struct TopK<T> {
+    heap: BoundedHeap<Entry<T>>,
+    next_seq: u64,
+}
+
+struct Entry<T> {
+    item: T,
+    // When the item was offered, breaking ties between equal items in
+    // favour of the earlier one.
+    seq: u64,
+}
+
+// Orders entries by `(Reverse(item), seq)` under `compare`, which puts the
+// least item, and of equal ones the latest, at the root of the max-heap.
+fn rank<T, F>(mut compare: F) -> impl FnMut(&Entry<T>, &Entry<T>) -> Ordering
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    move |a, b| compare(&b.item, &a.item).then_with(|| a.seq.cmp(&b.seq))
+}
+
+impl<T> TopK<T> {
+    /// Creates a collector that keeps the `k` greatest items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(k: usize) -> Self {
+        match Self::try_new(k) {
+            Ok(top) => top,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(k: usize) -> Result<Self, TryNewError> {
+        Ok(Self {
+            heap: BoundedHeap::try_new_unordered(k)?,
+            next_seq: 0,
+        })
+    }
+
+    /// Returns how many items are kept.
+    pub fn k(&self) -> usize {
+        self.heap.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns the least of the kept items: once `k` items are kept, an
+    /// offered item must be greater than this one to get in.
+    pub fn min(&self) -> Option<&T> {
+        self.heap.as_slice().first().map(|entry| &entry.item)
+    }
+
+    /// Returns an iterator over the kept items in no particular order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            entries: self.heap.iter(),
+        }
+    }
+
+    /// Drops every kept item.
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+
+    /// Offers `item`, ordered by `T`'s `Ord`, and returns the item that
+    /// didn't make the cut as a result: the displaced one, `item` itself if
+    /// it was rejected, or `None` if it was kept without displacing any.
+    pub fn offer(&mut self, item: T) -> Option<T>
+    where
+        T: Ord,
+    {
+        self.offer_by(item, T::cmp)
+    }
+
+    /// Like [`offer`](Self::offer), but orders items by the key `f`
+    /// extracts.
+    ///
+    /// The key is recomputed for every comparison, so it should be cheap;
+    /// and since the kept items are ordered by whatever key was in use when
+    /// they were offered, every offer to one collector should use the same
+    /// key.
+    ///
+    /// ```
+    /// use fixed_buf::TopK;
+    ///
+    /// let mut busiest = TopK::new(2);
+    /// for route in [("LHR", 41), ("SFO", 57), ("NRT", 57), ("JFK", 38)] {
+    ///     busiest.offer_by_key(route, |&(_, flights)| flights);
+    /// }
+    /// // SFO and NRT tie; SFO was offered first.
+    /// let ranked = busiest.into_sorted_descending_by_key(|&(_, flights)| flights);
+    /// assert_eq!(ranked.as_slice(), [("SFO", 57), ("NRT", 57)]);
+    /// ```
+    pub fn offer_by_key<K, F>(&mut self, item: T, mut f: F) -> Option<T>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        self.offer_by(item, |a, b| f(a).cmp(&f(b)))
+    }
+
+    /// Like [`offer`](Self::offer), but orders items with `compare`, under
+    /// the same caveat as [`offer_by_key`](Self::offer_by_key).
+    pub fn offer_by<F>(&mut self, item: T, compare: F) -> Option<T>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let entry = Entry {
+            item,
+            seq: self.next_seq,
+        };
+        self.next_seq += 1;
+        let mut rank = rank(compare);
+        match self.heap.try_push_by(entry, &mut rank) {
+            Ok(()) => None,
+            // The entry is newer than everything kept, so it loses ties and
+            // only displaces the root if its item is strictly greater.
+            Err(err) => Some(self.heap.push_pop_by(err.element(), &mut rank).item),
+        }
+    }
+
+    /// Returns the kept items from greatest to least, ties in the order they
+    /// were offered, in a buffer of exactly [`len`](Self::len) items.
+    ///
+    /// This sorts by `T`'s `Ord`, as [`offer`](Self::offer) does. Items
+    /// offered under another ordering should be sorted by the same one,
+    /// with [`into_sorted_descending_by_key`](Self::into_sorted_descending_by_key)
+    /// or [`into_sorted_descending_by`](Self::into_sorted_descending_by).
+    ///
+    /// # Panics
+    ///
+    /// Panics if allocating the returned buffer fails, like
+    /// [`BoundedBuffer::new`]. The collector's own buffer is freed once the
+    /// items are moved out of it.
+    #[track_caller]
+    pub fn into_sorted_descending(self) -> BoundedBuffer<T>
+    where
+        T: Ord,
+    {
+        self.into_sorted_descending_by(T::cmp)
+    }
+
+    /// Like [`into_sorted_descending`](Self::into_sorted_descending), for
+    /// items offered with [`offer_by_key`](Self::offer_by_key).
+    #[track_caller]
+    pub fn into_sorted_descending_by_key<K, F>(self, mut f: F) -> BoundedBuffer<T>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        self.into_sorted_descending_by(|a, b| f(a).cmp(&f(b)))
+    }
+
+    /// Like [`into_sorted_descending`](Self::into_sorted_descending), for
+    /// items offered with [`offer_by`](Self::offer_by).
+    #[track_caller]
+    pub fn into_sorted_descending_by<F>(self, compare: F) -> BoundedBuffer<T>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        // Ascending by rank is descending by item, ties earliest first.
+        let entries = self.heap.into_sorted_buffer_by(&mut rank(compare));
+        let mut items = BoundedBuffer::new(entries.len());
+        items.extend(entries.into_iter().map(|entry| entry.item));
+        items
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TopK<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TopK")
+            .field("k", &self.k())
+            .field("items", &self.iter())
+            .finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a TopK<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the items kept by a [`TopK`], in no particular order.
+pub struct Iter<'a, T> {
+    entries: slice::Iter<'a, Entry<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|entry| &entry.item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Iter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, cmp::Reverse};
+
+    use super::TopK;
+    use crate::core::test_util::xorshift;
+
+    // What the collector should match: a stable descending sort by key,
+    // truncated to `k`.
+    fn oracle(items: &[(u32, usize)], k: usize) -> Vec<(u32, usize)> {
+        let mut sorted = items.to_vec();
+        sorted.sort_by_key(|&(key, _)| Reverse(key));
+        sorted.truncate(k);
+        sorted
+    }
+
+    #[test]
+    fn test_matches_sort_then_truncate() {
+        let trials = if cfg!(miri) { 3 } else { 200 };
+        let mut state = 0x1234_5678;
+        for trial in 0..trials {
+            let n = (xorshift(&mut state) % 300) as usize;
+            // Narrow key ranges on some trials to force lots of ties.
+            let range = if trial % 2 == 0 { 8 } else { 1_000_000 };
+            let items: Vec<(u32, usize)> = (0..n)
+                .map(|i| ((xorshift(&mut state) % range) as u32, i))
+                .collect();
+            for k in [0, 1, 2, 7, 64, 500] {
+                let mut top = TopK::new(k);
+                let mut rejected = 0;
+                for &item in &items {
+                    if let Some(out) = top.offer_by_key(item, |&(key, _)| key) {
+                        rejected += 1;
+                        // Whatever drops out is no better than the cut.
+                        if let Some(&(min, _)) = top.min() {
+                            assert!(out.0 <= min);
+                        }
+                    }
+                }
+                assert_eq!(top.len(), k.min(n));
+                assert_eq!(rejected, n - k.min(n));
+                let sorted = top.into_sorted_descending_by_key(|&(key, _)| key);
+                assert_eq!(sorted.as_slice(), oracle(&items, k), "n={n} k={k}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_k_zero_and_one() {
+        let mut none = TopK::new(0);
+        assert_eq!(none.offer(5), Some(5));
+        assert!(none.is_empty());
+        assert_eq!(none.min(), None);
+        assert!(none.into_sorted_descending().is_empty());
+
+        let mut one = TopK::new(1);
+        assert_eq!(one.offer(3), None);
+        assert_eq!(one.offer(2), Some(2));
+        assert_eq!(one.offer(3), Some(3));
+        assert_eq!(one.offer(4), Some(3));
+        assert_eq!(one.iter().copied().collect::<Vec<_>>(), [4]);
+    }
+
+    #[test]
+    fn test_ties_keep_earliest() {
+        let mut top = TopK::new(2);
+        for (key, name) in [(1, "a"), (1, "b"), (1, "c"), (2, "d"), (1, "e")] {
+            top.offer_by_key((key, name), |&(key, _)| key);
+        }
+        // "d" displaced the later of the two ones, "b".
+        let kept = top.into_sorted_descending_by_key(|&(key, _)| key);
+        assert_eq!(kept.as_slice(), [(2, "d"), (1, "a")]);
+    }
+
+    #[test]
+    fn test_rejection_costs_one_comparison() {
+        let comparisons = Cell::new(0);
+        let mut top = TopK::new(16);
+        let mut offer = |x: u64| {
+            top.offer_by(x, |a, b| {
+                comparisons.set(comparisons.get() + 1);
+                a.cmp(b)
+            })
+        };
+        for x in (1000..1016).rev() {
+            offer(x);
+        }
+        comparisons.set(0);
+        for x in 0..1000 {
+            assert_eq!(offer(x), Some(x));
+        }
+        assert_eq!(comparisons.get(), 1000);
+    }
+
+    #[test]
+    fn test_displaced_items_are_returned_not_dropped() {
+        let token = std::rc::Rc::new(());
+        let mut top = TopK::new(3);
+        let mut out = Vec::new();
+        for key in [4, 8, 1, 9, 2, 7] {
+            out.extend(top.offer_by_key((key, token.clone()), |(key, _)| *key));
+        }
+        let mut out_keys: Vec<_> = out.iter().map(|(key, _)| *key).collect();
+        out_keys.sort();
+        assert_eq!(out_keys, [1, 2, 4]);
+        assert_eq!(std::rc::Rc::strong_count(&token), 7);
+        drop(out);
+        assert_eq!(std::rc::Rc::strong_count(&token), 4);
+        top.clear();
+        assert_eq!(std::rc::Rc::strong_count(&token), 1);
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+use crate::core::bounded_buf::BoundedBuffer;
+
+// In `Shared::back`, the low bits hold the index of the back buffer and this
+// bit is set while it holds a value the output hasn't taken yet.
+const DIRTY: u8 = 0b100;
+const INDEX: u8 = 0b011;
+
+/// Hands the latest value from one thread to another without either side
+/// ever waiting.
+///
+/// Three copies of the value live in one allocation. The input writes into
+/// its own copy, then publishes it by swapping it with the shared back copy.
+/// The output swaps the back copy with its own when a newer one has been
+/// published, and reads from that. Each swap is a single atomic exchange,
+/// so the output always sees a complete value, and values the output was
+/// too slow to see are simply replaced.
+///
+/// ```
+/// use fixed_buf::TripleBuffer;
+///
+/// let (mut input, mut output) = TripleBuffer::new_with(0).split();
+/// assert!(!output.updated());
+/// input.write(1);
+/// input.write(2);
+/// assert!(output.updated());
+/// assert_eq!(*output.read(), 2);
+/// assert!(!output.updated());
+/// assert_eq!(*output.read(), 2);
+/// ```
+pub struct TripleBuffer<T> {
+    input: Input<T>,
+    output: Output<T>,
+}
+
+struct Shared<T> {
+    buffers: BoundedBuffer<UnsafeCell<T>>,
+    back: AtomicU8,
+}
+
+// Each copy is only ever accessed by the one side that holds its index, and
+// the exchanges on `back` hand copies over with acquire/release ordering.
+// Values move from the input's thread to the output's, so `T: Send` is
+// enough. `Input` and `Output` are `!Sync` themselves, so a copy is never
+// reachable from two threads at once.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T: Clone> TripleBuffer<T> {
+    /// Creates a triple buffer whose copies all start as `init`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new_with(init: T) -> Self {
+        let mut buffers = BoundedBuffer::new(3);
+        let pushed = buffers.try_push(UnsafeCell::new(init.clone()))
+            && buffers.try_push(UnsafeCell::new(init.clone()))
+            && buffers.try_push(UnsafeCell::new(init));
+        debug_assert!(pushed);
+        let shared = Arc::new(Shared {
+            buffers,
+            back: AtomicU8::new(1),
+        });
+        Self {
+            input: Input {
+                shared: shared.clone(),
+                index: 0,
+                _not_sync: PhantomData,
+            },
+            output: Output {
+                shared,
+                index: 2,
+                _not_sync: PhantomData,
+            },
+        }
+    }
+}
+
+impl<T> TripleBuffer<T> {
+    /// Splits the buffer into its two ends, to be moved to the writing and
+    /// reading threads.
+    pub fn split(self) -> (Input<T>, Output<T>) {
+        (self.input, self.output)
+    }
+
+    pub fn input(&mut self) -> &mut Input<T> {
+        &mut self.input
+    }
+
+    pub fn output(&mut self) -> &mut Output<T> {
+        &mut self.output
+    }
+}
+
+impl<T: Clone + Default> Default for TripleBuffer<T> {
+    fn default() -> Self {
+        Self::new_with(T::default())
+    }
+}
+
+impl<T> fmt::Debug for TripleBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TripleBuffer").finish_non_exhaustive()
+    }
+}
+
+/// The writing end of a [`TripleBuffer`].
+pub struct Input<T> {
+    shared: Arc<Shared<T>>,
+    index: u8,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> Input<T> {
+    /// Publishes `value` as the latest.
+    pub fn write(&mut self, value: T) {
+        *self.input_mut() = value;
+        self.publish();
+    }
+
+    /// Returns the copy the next [`publish`](Self::publish) will hand over,
+    /// for filling in place.
+    ///
+    /// It holds whichever older value the output last gave back, not the
+    /// most recently published one, so it must be overwritten in full.
+    pub fn input_mut(&mut self) -> &mut T {
+        // SAFETY: no one else can reach the copy at `index` until it is
+        // published.
+        unsafe { &mut *self.shared.buffers[self.index as usize].get() }
+    }
+
+    /// Publishes the copy filled through [`input_mut`](Self::input_mut).
+    pub fn publish(&mut self) {
+        let old = self.shared.back.swap(self.index | DIRTY, Ordering::AcqRel);
+        self.index = old & INDEX;
+    }
+
+    /// Returns `true` if the output has taken the last published value.
+    pub fn consumed(&self) -> bool {
+        self.shared.back.load(Ordering::Relaxed) & DIRTY == 0
+    }
+}
+
+impl<T> fmt::Debug for Input<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Input").finish_non_exhaustive()
+    }
+}
+
+/// The reading end of a [`TripleBuffer`].
+///
+/// [`peek`](Self::peek) lends out the output's copy through `&self`, so the
+/// output can't be shared between threads, or two of them could use a
+/// `Cell` in it at once:
+///
+/// ```compile_fail
+/// use std::cell::Cell;
+/// use fixed_buf::TripleBuffer;
+///
+/// let (_input, output) = TripleBuffer::new_with(Cell::new(0u32)).split();
+/// std::thread::scope(|s| {
+///     s.spawn(|| output.peek().set(1));
+///     s.spawn(|| output.peek().set(2));
+/// });
+/// ```
+pub struct Output<T> {
+    shared: Arc<Shared<T>>,
+    index: u8,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> Output<T> {
+    /// Returns `true` if a value has been published since the last
+    /// [`read`](Self::read).
+    pub fn updated(&self) -> bool {
+        self.shared.back.load(Ordering::Relaxed) & DIRTY != 0
+    }
+
+    /// Returns the latest published value, taking it over first if it is
+    /// new. Before anything is published, returns the initial value.
+    pub fn read(&mut self) -> &T {
+        self.update();
+        self.peek()
+    }
+
+    /// Like [`read`](Self::read), but allows modifying the value in place.
+    /// Changes only affect this copy and are lost on the next update.
+    pub fn read_mut(&mut self) -> &mut T {
+        self.update();
+        // SAFETY: as in `peek`.
+        unsafe { &mut *self.shared.buffers[self.index as usize].get() }
+    }
+
+    /// Returns the value taken over by the last read, without checking for
+    /// a newer one.
+    pub fn peek(&self) -> &T {
+        // SAFETY: the input never touches the copy at `index` while the
+        // output holds it.
+        unsafe { &*self.shared.buffers[self.index as usize].get() }
+    }
+
+    fn update(&mut self) {
+        if self.updated() {
+            let old = self.shared.back.swap(self.index, Ordering::AcqRel);
+            self.index = old & INDEX;
+        }
+    }
+}
+
+impl<T> fmt::Debug for Output<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Output")
+            .field("updated", &self.updated())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use super::TripleBuffer;
+
+    // A payload that can't be copied in one go and is easy to check for
+    // tearing: every part derives from the same version.
+    #[derive(Clone, Default)]
+    struct Frame {
+        version: u64,
+        samples: Vec<u64>,
+        label: String,
+    }
+
+    impl Frame {
+        fn new(version: u64) -> Self {
+            Self {
+                version,
+                samples: vec![version; (version % 7 + 1) as usize],
+                label: format!("frame {version}"),
+            }
+        }
+
+        fn check(&self) {
+            assert!(self.samples.iter().all(|&s| s == self.version));
+            assert_eq!(self.samples.len(), (self.version % 7 + 1) as usize);
+            assert_eq!(self.label, format!("frame {}", self.version));
+        }
+    }
+
+    #[test]
+    fn test_latest_value_wins() {
+        let mut buf = TripleBuffer::new_with(Frame::new(0));
+        assert_eq!(buf.output().read().version, 0);
+        for v in 1..=5 {
+            buf.input().write(Frame::new(v));
+        }
+        assert!(buf.output().updated());
+        let frame = buf.output().read();
+        frame.check();
+        assert_eq!(frame.version, 5);
+        assert!(!buf.output().updated());
+        assert!(buf.input().consumed());
+    }
+
+    #[test]
+    fn test_fill_in_place() {
+        let (mut input, mut output) = TripleBuffer::new_with(vec![0u32; 4]).split();
+        input.input_mut().iter_mut().for_each(|x| *x = 1);
+        assert!(!output.updated());
+        input.publish();
+        assert!(!input.consumed());
+        assert_eq!(*output.read(), [1; 4]);
+
+        // The input copy now holds an older value, not the published one.
+        assert_eq!(*input.input_mut(), [0; 4]);
+        input.input_mut().fill(2);
+        input.publish();
+        output.read_mut().push(3);
+        assert_eq!(*output.peek(), [2, 2, 2, 2, 3]);
+        input.write(vec![4]);
+        assert_eq!(*output.read(), [4]);
+    }
+
+    #[test]
+    fn test_values_dropped_once() {
+        let token = Arc::new(());
+        let (mut input, output) = TripleBuffer::new_with(token.clone()).split();
+        assert_eq!(Arc::strong_count(&token), 4);
+        input.write(Arc::new(()));
+        assert_eq!(Arc::strong_count(&token), 3);
+        input.write(Arc::new(()));
+        assert_eq!(Arc::strong_count(&token), 2);
+        drop(input);
+        assert_eq!(Arc::strong_count(&token), 2);
+        drop(output);
+        assert_eq!(Arc::strong_count(&token), 1);
+    }
+
+    #[test]
+    fn test_monotonic_versions_across_threads() {
+        let writes = if cfg!(miri) { 200 } else { 100_000 };
+        let (mut input, mut output) = TripleBuffer::new_with(Frame::new(0)).split();
+        let done = AtomicBool::new(false);
+        thread::scope(|s| {
+            s.spawn(|| {
+                for v in 1..=writes {
+                    if v % 2 == 0 {
+                        input.write(Frame::new(v));
+                    } else {
+                        *input.input_mut() = Frame::default();
+                        let frame = input.input_mut();
+                        frame.version = v;
+                        frame.samples = vec![v; (v % 7 + 1) as usize];
+                        frame.label = format!("frame {v}");
+                        input.publish();
+                    }
+                }
+                done.store(true, Ordering::Release);
+            });
+            let mut last = 0;
+            let mut distinct = 0;
+            loop {
+                let finished = done.load(Ordering::Acquire);
+                let frame = output.read();
+                frame.check();
+                assert!(frame.version >= last);
+                if frame.version > last {
+                    distinct += 1;
+                    last = frame.version;
+                }
+                if finished {
+                    break;
+                }
+            }
+            assert_eq!(last, writes);
+            assert!(distinct > 0);
+        });
+    }
+}
@@ -0,0 +1,679 @@
+//! A fixed-capacity Chase–Lev work-stealing deque.
+//!
+//! The owning thread pushes and pops tasks at the bottom through
+//! [`WorkStealingDeque`], most recent first, which keeps the tasks it works
+//! on hot in its cache. Any number of [`Stealer`] handles take the oldest
+//! tasks from the top. All operations are lock-free, and pushing into a full
+//! deque fails instead of growing it.
+//!
+//! ```
+//! use std::thread;
+//!
+//! use fixed_buf::{Steal, WorkStealingDeque};
+//!
+//! let mut worker = WorkStealingDeque::new(64);
+//! for task in 0..64 {
+//!     worker.try_push(task).unwrap();
+//! }
+//! let stealer = worker.stealer();
+//! let thief = thread::spawn(move || {
+//!     let mut stolen = Vec::new();
+//!     loop {
+//!         match stealer.steal() {
+//!             Steal::Success(task) => stolen.push(task),
+//!             Steal::Retry => continue,
+//!             Steal::Empty => break stolen,
+//!         }
+//!     }
+//! });
+//! let mut done = Vec::new();
+//! while let Some(task) = worker.pop() {
+//!     done.push(task);
+//! }
+//! done.extend(thief.join().unwrap());
+//! done.sort();
+//! assert!(done.into_iter().eq(0..64));
+//! ```
+
+use std::{fmt, mem::MaybeUninit, ptr};
+
+#[cfg(loom)]
+use loom::{
+    cell::UnsafeCell,
+    sync::{
+        atomic::{fence, AtomicBool, AtomicIsize, Ordering},
+        Arc,
+    },
+};
+#[cfg(not(loom))]
+use std::sync::{
+    atomic::{fence, AtomicBool, AtomicIsize, Ordering},
+    Arc,
+};
+
+use crate::core::{
+    bounded_buf::BoundedBuffer,
+    cache_padded::CachePadded,
+    error::{CapacityError, TryNewError},
+};
+
+// The subset of loom's `UnsafeCell` API used below, so the same code runs
+// under loom and without it.
+#[cfg(not(loom))]
+struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    fn new(value: T) -> Self {
+        Self(std::cell::UnsafeCell::new(value))
+    }
+
+    fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        f(self.0.get())
+    }
+
+    fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
+
+/// The outcome of a steal attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Steal<T> {
+    /// The deque was empty.
+    Empty,
+    Success(T),
+    /// Another thread took the same task first. The deque may still hold
+    /// others, so the attempt is worth repeating.
+    Retry,
+}
+
+impl<T> Steal<T> {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Steal::Empty)
+    }
+
+    pub fn is_retry(&self) -> bool {
+        matches!(self, Steal::Retry)
+    }
+
+    pub fn success(self) -> Option<T> {
+        match self {
+            Steal::Success(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+struct Shared<T> {
+    // The oldest task, advanced by stealers and by the owner taking the
+    // last task.
+    top: CachePadded<AtomicIsize>,
+    // One past the newest task, written only by the owner.
+    bottom: CachePadded<AtomicIsize>,
+    // Position `i` lives in slot `i % capacity`; those from `top` up to
+    // `bottom` are initialized.
+    slots: BoundedBuffer<Slot<T>>,
+}
+
+struct Slot<T> {
+    task: UnsafeCell<MaybeUninit<T>>,
+    // Set by the owner when it pushes the task and cleared once the task
+    // has been moved out. A stealer moves it out only after claiming it, so
+    // the flag stays set until the copy is done, and the owner must not
+    // reuse the slot before then.
+    full: AtomicBool,
+}
+
+// Each task is moved out by exactly one thread, chosen by whoever wins the
+// race on `top`, so `T: Send` is enough.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    fn slot(&self, pos: isize) -> &Slot<T> {
+        &self.slots[pos as usize % self.capacity()]
+    }
+
+    fn len(&self) -> usize {
+        let top = self.top.load(Ordering::Acquire);
+        let bottom = self.bottom.load(Ordering::Acquire);
+        (bottom.wrapping_sub(top).max(0) as usize).min(self.capacity())
+    }
+
+    fn steal(&self) -> Steal<T> {
+        let top = self.top.load(Ordering::Acquire);
+        // Pairs with the fence in `pop`: either the owner sees this
+        // stealer's claim on `top`, or this stealer sees the owner's
+        // decremented `bottom`.
+        fence(Ordering::SeqCst);
+        let bottom = self.bottom.load(Ordering::Acquire);
+        if bottom.wrapping_sub(top) <= 0 {
+            return Steal::Empty;
+        }
+        if self
+            .top
+            .compare_exchange(
+                top,
+                top.wrapping_add(1),
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            return Steal::Retry;
+        }
+        // SAFETY: the claim makes this stealer the only one to move the task
+        // out, and the owner won't reuse the slot until `full` is cleared.
+        let slot = self.slot(top);
+        let task = slot
+            .task
+            .with(|task| unsafe { ptr::read(task).assume_init() });
+        slot.full.store(false, Ordering::Release);
+        Steal::Success(task)
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        // Every handle is gone, so nothing else can touch the positions.
+        let bottom = self.bottom.load(Ordering::Relaxed);
+        let mut pos = self.top.load(Ordering::Relaxed);
+        while pos != bottom {
+            self.slot(pos)
+                .task
+                .with_mut(|task| unsafe { (*task).assume_init_drop() });
+            pos = pos.wrapping_add(1);
+        }
+    }
+}
+
+/// The owning end of a work-stealing deque.
+///
+/// There is exactly one owner. It can be moved to another thread, but
+/// pushing and popping need `&mut self`; other threads take tasks through
+/// [`stealer`](Self::stealer) handles.
+///
+/// ```compile_fail
+/// fn assert_clone<T: Clone>() {}
+/// assert_clone::<fixed_buf::WorkStealingDeque<u8>>();
+/// ```
+pub struct WorkStealingDeque<T> {
+    shared: Arc<Shared<T>>,
+    // `top` as last seen. It only moves forward, so the owner can trust it
+    // until the deque looks full.
+    cached_top: isize,
+}
+
+impl<T> WorkStealingDeque<T> {
+    /// Creates a deque that holds at most `cap` tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation fails, like [`BoundedBuffer::new`].
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        match Self::try_new(cap) {
+            Ok(deque) => deque,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`new`](Self::new), but returns an error instead of panicking.
+    pub fn try_new(cap: usize) -> Result<Self, TryNewError> {
+        if cap > isize::MAX as usize {
+            return Err(TryNewError::CapacityOverflow);
+        }
+        let mut slots = BoundedBuffer::try_new(cap)?;
+        slots.fill_with_to_capacity(|| Slot {
+            task: UnsafeCell::new(MaybeUninit::uninit()),
+            full: AtomicBool::new(false),
+        });
+        Ok(Self {
+            shared: Arc::new(Shared {
+                top: CachePadded(AtomicIsize::new(0)),
+                bottom: CachePadded(AtomicIsize::new(0)),
+                slots,
+            }),
+            cached_top: 0,
+        })
+    }
+
+    /// Returns a new handle for stealing from this deque.
+    pub fn stealer(&self) -> Stealer<T> {
+        Stealer {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Pushes `task` at the bottom, or gives it back if the deque is full.
+    ///
+    /// A task a stealer has claimed but not finished moving out still counts
+    /// as taking up room.
+    pub fn try_push(&mut self, task: T) -> Result<(), CapacityError<T>> {
+        let shared = &*self.shared;
+        let bottom = shared.bottom.load(Ordering::Relaxed);
+        if bottom.wrapping_sub(self.cached_top) as usize >= shared.capacity() {
+            self.cached_top = shared.top.load(Ordering::Acquire);
+            if bottom.wrapping_sub(self.cached_top) as usize >= shared.capacity() {
+                return Err(CapacityError::new(task));
+            }
+        }
+        let slot = shared.slot(bottom);
+        if slot.full.load(Ordering::Acquire) {
+            return Err(CapacityError::new(task));
+        }
+        // SAFETY: the slot is empty and outside the range stealers claim
+        // from until the store below publishes it.
+        slot.task.with_mut(|slot| unsafe { (*slot).write(task) });
+        slot.full.store(true, Ordering::Relaxed);
+        shared
+            .bottom
+            .store(bottom.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Pops the most recently pushed task, or returns `None` if the deque is
+    /// empty.
+    pub fn pop(&mut self) -> Option<T> {
+        let shared = &*self.shared;
+        let bottom = shared.bottom.load(Ordering::Relaxed).wrapping_sub(1);
+        // Reserve the task before looking at `top`, so a stealer that loads
+        // `bottom` after this point won't take it. The stores to `bottom`
+        // here are releases too: a stealer that loads one of them must also
+        // see the tasks pushed before it.
+        shared.bottom.store(bottom, Ordering::Release);
+        fence(Ordering::SeqCst);
+        let top = shared.top.load(Ordering::Relaxed);
+        let len = bottom.wrapping_sub(top);
+        if len < 0 {
+            shared
+                .bottom
+                .store(bottom.wrapping_add(1), Ordering::Release);
+            return None;
+        }
+        // SAFETY: the slot at `bottom` is initialized, and stealers can't
+        // claim it: either it wasn't the last task, or the claim below
+        // decides who gets it. A stealer that does only reads it too.
+        let slot = shared.slot(bottom);
+        let task = slot.task.with(|task| unsafe { ptr::read(task) });
+        if len > 0 {
+            slot.full.store(false, Ordering::Relaxed);
+            return Some(unsafe { task.assume_init() });
+        }
+        // The last task: race the stealers for it on `top`.
+        let won = shared
+            .top
+            .compare_exchange(
+                top,
+                top.wrapping_add(1),
+                Ordering::SeqCst,
+                Ordering::Relaxed,
+            )
+            .is_ok();
+        shared
+            .bottom
+            .store(bottom.wrapping_add(1), Ordering::Release);
+        if !won {
+            return None;
+        }
+        slot.full.store(false, Ordering::Relaxed);
+        Some(unsafe { task.assume_init() })
+    }
+
+    /// Returns the number of tasks in the deque. Stealers may be taking
+    /// tasks concurrently, so this is an upper bound.
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+impl<T> fmt::Debug for WorkStealingDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkStealingDeque")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+/// A handle for taking the oldest tasks from a [`WorkStealingDeque`].
+///
+/// Stealers can be cloned and shared freely between threads.
+pub struct Stealer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Stealer<T> {
+    /// Takes the oldest task.
+    pub fn steal(&self) -> Steal<T> {
+        self.shared.steal()
+    }
+
+    /// Moves about half the tasks, oldest first, into `dst`, as many as fit,
+    /// and returns how many were moved.
+    ///
+    /// The owner may be popping concurrently, so the tasks are claimed one at
+    /// a time and the batch ends early if another thread gets in the way.
+    /// Returns [`Steal::Retry`] only if not even the first task could be
+    /// taken, and `Steal::Success(0)` if `dst` is already full.
+    pub fn steal_into(&self, dst: &mut BoundedBuffer<T>) -> Steal<usize> {
+        let want = self.shared.len().div_ceil(2);
+        if want == 0 {
+            return Steal::Empty;
+        }
+        let want = want.min(dst.capacity() - dst.len());
+        let mut moved = 0;
+        while moved < want {
+            match self.shared.steal() {
+                Steal::Success(task) => {
+                    let pushed = dst.try_push(task);
+                    debug_assert!(pushed);
+                    moved += 1;
+                }
+                Steal::Empty if moved == 0 => return Steal::Empty,
+                Steal::Retry if moved == 0 => return Steal::Retry,
+                _ => break,
+            }
+        }
+        Steal::Success(moved)
+    }
+
+    /// Returns the number of tasks in the deque. Other threads may be
+    /// pushing or taking tasks concurrently, so this is only a snapshot.
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+impl<T> Clone for Stealer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Stealer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stealer")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::{
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::{Steal, WorkStealingDeque};
+    use crate::core::bounded_buf::BoundedBuffer;
+    use crate::core::test_util::{DropCount, Drops};
+
+    #[test]
+    fn test_owner_lifo_stealer_fifo() {
+        let mut deque = WorkStealingDeque::new(4);
+        let stealer = deque.stealer();
+        for i in 0..4 {
+            deque.try_push(i).unwrap();
+        }
+        assert_eq!(deque.try_push(4).unwrap_err().element(), 4);
+        assert_eq!(deque.len(), 4);
+        assert_eq!(deque.pop(), Some(3));
+        assert_eq!(stealer.steal(), Steal::Success(0));
+        assert_eq!(stealer.clone().steal(), Steal::Success(1));
+        assert_eq!(deque.pop(), Some(2));
+        assert_eq!(deque.pop(), None);
+        assert_eq!(stealer.steal(), Steal::Empty);
+        assert!(stealer.is_empty());
+    }
+
+    #[test]
+    fn test_wraps_around() {
+        let mut deque = WorkStealingDeque::new(3);
+        let stealer = deque.stealer();
+        for round in 0..10 {
+            deque.try_push(round * 2).unwrap();
+            deque.try_push(round * 2 + 1).unwrap();
+            assert_eq!(stealer.steal().success(), Some(round * 2));
+            assert_eq!(deque.len(), 1);
+            // Leave one behind every other round.
+            if round % 2 == 0 {
+                assert_eq!(deque.pop(), Some(round * 2 + 1));
+            } else {
+                assert_eq!(stealer.steal().success(), Some(round * 2 + 1));
+            }
+        }
+        assert!(deque.is_empty());
+        assert!(deque.try_push(0).is_ok());
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut deque = WorkStealingDeque::<u8>::new(0);
+        assert!(deque.try_push(1).is_err());
+        assert_eq!(deque.pop(), None);
+        assert!(deque.stealer().steal().is_empty());
+    }
+
+    #[test]
+    fn test_steal_into_takes_half() {
+        let mut deque = WorkStealingDeque::new(8);
+        let stealer = deque.stealer();
+        for i in 0..7 {
+            deque.try_push(i).unwrap();
+        }
+        let mut dst = BoundedBuffer::new(8);
+        assert_eq!(stealer.steal_into(&mut dst), Steal::Success(4));
+        assert_eq!(dst.as_slice(), [0, 1, 2, 3]);
+        assert_eq!(deque.len(), 3);
+
+        // Limited by the room left in `dst`.
+        let mut small = BoundedBuffer::new(1);
+        assert_eq!(stealer.steal_into(&mut small), Steal::Success(1));
+        assert_eq!(stealer.steal_into(&mut small), Steal::Success(0));
+        assert_eq!(small.as_slice(), [4]);
+
+        assert_eq!(deque.pop(), Some(6));
+        assert_eq!(deque.pop(), Some(5));
+        assert_eq!(stealer.steal_into(&mut dst), Steal::Empty);
+    }
+
+    #[test]
+    fn test_drops_remaining_tasks() {
+        let drops = Drops::default();
+        let mut deque = WorkStealingDeque::new(4);
+        let stealer = deque.stealer();
+        for _ in 0..4 {
+            deque.try_push(DropCount(drops.clone())).unwrap();
+        }
+        drop(deque.pop());
+        drop(stealer.steal());
+        assert_eq!(drops.get(), 2);
+        drop(deque);
+        assert_eq!(drops.get(), 2);
+        drop(stealer);
+        assert_eq!(drops.get(), 4);
+    }
+
+    // The owner keeps pushing and popping while several thieves steal, one
+    // at a time and in batches. Every task must be run exactly once.
+    fn stress(cap: usize, tasks: usize, thieves: usize) {
+        let runs: Vec<AtomicUsize> = (0..tasks).map(|_| AtomicUsize::new(0)).collect();
+        let mut deque = WorkStealingDeque::<usize>::new(cap);
+        let done = AtomicBool::new(false);
+        thread::scope(|s| {
+            for t in 0..thieves {
+                let stealer = deque.stealer();
+                let (runs, done) = (&runs, &done);
+                s.spawn(move || {
+                    let mut batch = BoundedBuffer::new(cap / 2 + 1);
+                    loop {
+                        let finished = done.load(Ordering::Acquire);
+                        if t % 2 == 0 {
+                            if let Steal::Success(task) = stealer.steal() {
+                                runs[task].fetch_add(1, Ordering::Relaxed);
+                                continue;
+                            }
+                        } else if let Steal::Success(n) = stealer.steal_into(&mut batch) {
+                            if n > 0 {
+                                for task in batch.drain(..) {
+                                    runs[task].fetch_add(1, Ordering::Relaxed);
+                                }
+                                continue;
+                            }
+                        }
+                        if finished {
+                            break;
+                        }
+                        thread::yield_now();
+                    }
+                });
+            }
+            let mut next = 0;
+            while next < tasks {
+                while next < tasks && deque.try_push(next).is_ok() {
+                    next += 1;
+                    // Pop now and then so the owner and the thieves race
+                    // on the bottom as well as the top.
+                    if next % 3 == 0 {
+                        if let Some(task) = deque.pop() {
+                            runs[task].fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+                thread::yield_now();
+            }
+            while let Some(task) = deque.pop() {
+                runs[task].fetch_add(1, Ordering::Relaxed);
+            }
+            done.store(true, Ordering::Release);
+        });
+        for (task, count) in runs.iter().enumerate() {
+            assert_eq!(count.load(Ordering::Relaxed), 1, "task {task}");
+        }
+    }
+
+    #[test]
+    fn test_stress_each_task_once() {
+        let tasks = if cfg!(miri) { 200 } else { 200_000 };
+        stress(64, tasks, 3);
+    }
+
+    #[test]
+    fn test_stress_tiny_capacity() {
+        let tasks = if cfg!(miri) { 100 } else { 50_000 };
+        stress(2, tasks, 4);
+    }
+}
+
+// Run with `RUSTFLAGS="--cfg loom" cargo test --release --lib work_steal`.
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::thread;
+
+    use super::{Steal, WorkStealingDeque};
+    use crate::core::bounded_buf::BoundedBuffer;
+
+    #[test]
+    fn test_pop_races_steal_for_last_task() {
+        loom::model(|| {
+            let mut deque = WorkStealingDeque::new(2);
+            deque.try_push(1).unwrap();
+            let stealer = deque.stealer();
+            let thief = thread::spawn(move || stealer.steal().success());
+            let popped = deque.pop();
+            let stolen = thief.join().unwrap();
+            assert!(popped.is_some() != stolen.is_some());
+        });
+    }
+
+    #[test]
+    fn test_push_pop_against_two_thieves() {
+        loom::model(|| {
+            let mut deque = WorkStealingDeque::new(4);
+            let thieves: Vec<_> = (0..2)
+                .map(|_| {
+                    let stealer = deque.stealer();
+                    thread::spawn(move || stealer.steal().success())
+                })
+                .collect();
+            deque.try_push(1).unwrap();
+            deque.try_push(2).unwrap();
+            let mut seen: Vec<i32> = deque.pop().into_iter().collect();
+            seen.extend(thieves.into_iter().filter_map(|t| t.join().unwrap()));
+            seen.extend(deque.pop());
+            seen.sort();
+            assert_eq!(seen, [1, 2]);
+        });
+    }
+
+    // With one slot, the owner refills the slot a stealer is moving a task
+    // out of as soon as the stealer claims it.
+    #[test]
+    fn test_refill_while_stealing() {
+        loom::model(|| {
+            let mut deque = WorkStealingDeque::new(1);
+            deque.try_push(1).unwrap();
+            let stealer = deque.stealer();
+            let thief = thread::spawn(move || stealer.steal().success());
+            let mut seen: Vec<i32> = Vec::new();
+            if deque.try_push(2).is_err() {
+                seen.extend(deque.pop());
+            }
+            seen.extend(thief.join().unwrap());
+            seen.extend(deque.pop());
+            seen.sort();
+            assert!(seen == [1, 2] || seen == [1], "{seen:?}");
+        });
+    }
+
+    #[test]
+    fn test_steal_into_against_pop() {
+        loom::model(|| {
+            let mut deque = WorkStealingDeque::new(4);
+            for i in 0..3 {
+                deque.try_push(i).unwrap();
+            }
+            let stealer = deque.stealer();
+            let thief = thread::spawn(move || {
+                let mut dst = BoundedBuffer::new(4);
+                if let Steal::Success(n) = stealer.steal_into(&mut dst) {
+                    assert_eq!(n, dst.len());
+                }
+                dst.iter().copied().collect::<Vec<_>>()
+            });
+            let mut seen: Vec<i32> = deque.pop().into_iter().collect();
+            seen.extend(thief.join().unwrap());
+            while let Some(task) = deque.pop() {
+                seen.push(task);
+            }
+            seen.sort();
+            assert_eq!(seen, [0, 1, 2]);
+        });
+    }
+}
@@ -1,3 +1,31 @@
 pub mod core;
 
+pub use crate::core::async_channel;
+pub use crate::core::borrowed_buf::BorrowedBuffer;
 pub use crate::core::bounded_buf::BoundedBuffer;
+pub use crate::core::bounded_deque::BoundedDeque;
+pub use crate::core::bounded_heap::BoundedHeap;
+pub use crate::core::bounded_interner::{BoundedInterner, Symbol};
+pub use crate::core::bounded_list::BoundedLinkedList;
+pub use crate::core::bounded_map::BoundedMap;
+pub use crate::core::bounded_mpmc::BoundedMpmc;
+pub use crate::core::bounded_ring::BoundedRing;
+pub use crate::core::bounded_str::BoundedString;
+pub use crate::core::broadcast_ring::{BroadcastRing, OverflowPolicy};
+pub use crate::core::bucket_queue::BucketQueue;
+pub use crate::core::concurrent_pool::{ConcurrentPool, PoolGuard};
+pub use crate::core::double_buf::{Clear, DoubleBuffer};
+pub use crate::core::error::{
+    CapacityError, CastError, InternError, RecvError, RecvTimeoutError, SendError,
+    SendTimeoutError, TryNewError, TryNextError, TryRecvError, TrySendError,
+};
+pub use crate::core::inline_buf::InlineBuffer;
+pub use crate::core::pod::Pod;
+pub use crate::core::seq_cell::SeqCell;
+pub use crate::core::slab::{Key, Slab};
+pub use crate::core::small_buf::SmallBoundedBuffer;
+pub use crate::core::spsc;
+pub use crate::core::sync_channel;
+pub use crate::core::top_k::TopK;
+pub use crate::core::triple_buf::TripleBuffer;
+pub use crate::core::work_steal::{Steal, Stealer, WorkStealingDeque};